@@ -13,19 +13,43 @@ mod sync;
 
 use did::identity::DidIdentity;
 use did::signing;
+use rag::aging;
+use rag::analytics;
+use rag::cache::{self, ContextCache};
+use rag::conflicts;
+use rag::context;
 use rag::db::RagDb;
+use rag::dedup;
 use rag::digest;
 use rag::embedding::EmbeddingEngine;
+use rag::history;
+use rag::http::{HttpPolicy, ResilientHttp};
+use rag::events::RagEvent;
 use rag::ingest;
+use rag::patterns;
+use rag::persona;
+use rag::policy::{self, DataLabels};
 use rag::knowledge;
+use rag::llm::{self, LlmClient, LlmTask};
+use rag::ontology;
+use rag::outcomes;
+use rag::prompts;
 use rag::query;
+use rag::redaction;
+use rag::relations;
+use rag::scheduler::{self, SchedulerPolicy};
 use rag::seed;
+use rag::snapshot;
+use rag::standing;
+use rag::synthesis;
+use rag::usage;
 use phone::contacts;
 use phone::call;
 use sync::sync as sync_engine;
 use std::path::PathBuf;
 use std::sync::Arc;
-use tauri::Manager;
+use tauri::{Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
 
 
 /// Shared state accessible from all IPC commands
@@ -33,6 +57,8 @@ struct AppState {
     db: Arc<RagDb>,
     embedding: Arc<EmbeddingEngine>,
     did_identity: Arc<DidIdentity>,
+    context_cache: Arc<ContextCache>,
+    http: Arc<ResilientHttp>,
 }
 
 // ── General IPC ──────────────────────────────────────────
@@ -59,6 +85,7 @@ fn ping() -> String {
 
 /// IPC: Search local knowledge base (hybrid vector + text search)
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 fn rag_search(
     state: tauri::State<'_, AppState>,
     query: String,
@@ -69,6 +96,7 @@ fn rag_search(
     knowledge_type: Option<String>,
     threshold: Option<f32>,
     limit: Option<usize>,
+    expand_relations: Option<bool>,
 ) -> Result<String, String> {
     let embedding_result = state.embedding.embed(&query)?;
 
@@ -81,6 +109,7 @@ fn rag_search(
         knowledge_type,
         threshold: threshold.unwrap_or(0.30),
         limit: limit.unwrap_or(5),
+        expand_relations: expand_relations.unwrap_or(false),
         ..Default::default()
     };
 
@@ -136,7 +165,12 @@ fn rag_dialectic_search(
 }
 
 /// IPC: Get RAG context string for LLM injection (3-pass search)
+///
+/// `max_tokens` budgets the context in tokens; `max_chars` is kept for older
+/// callers and caps characters (alone, it is the only cap). `format`: markdown
+/// (default) | xml | json.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 fn rag_get_context(
     state: tauri::State<'_, AppState>,
     query: String,
@@ -145,165 +179,1091 @@ fn rag_get_context(
     project_id: Option<String>,
     role_tag: Option<String>,
     max_chars: Option<usize>,
+    max_tokens: Option<usize>,
+    format: Option<String>,
 ) -> Result<String, String> {
-    let embedding_result = state.embedding.embed(&query)?;
+    let options = context_options(max_chars, max_tokens, format)?;
+    cached_context(&state, &query, scope, user_id, project_id, role_tag, &options, "text", |built| {
+        Ok(built.text)
+    })
+}
+
+/// IPC: Get RAG context plus its citation map ([K1] → knowledge id)
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn rag_get_cited_context(
+    state: tauri::State<'_, AppState>,
+    query: String,
+    scope: Option<String>,
+    user_id: Option<String>,
+    project_id: Option<String>,
+    role_tag: Option<String>,
+    max_tokens: Option<usize>,
+    format: Option<String>,
+) -> Result<String, String> {
+    let options = context_options(None, max_tokens, format)?;
+    cached_context(&state, &query, scope, user_id, project_id, role_tag, &options, "cited", |built| {
+        serde_json::to_string(&built).map_err(|e| format!("Serialize failed: {}", e))
+    })
+}
+
+fn context_options(
+    max_chars: Option<usize>,
+    max_tokens: Option<usize>,
+    format: Option<String>,
+) -> Result<context::ContextOptions, String> {
+    let format = match format {
+        Some(f) => context::ContextFormat::parse(&f)?,
+        None => context::ContextFormat::Markdown,
+    };
+    Ok(context::ContextOptions::with_limits(max_chars, max_tokens, format))
+}
+
+/// Shared 3-pass search + build for the context commands, backed by the semantic cache.
+#[allow(clippy::too_many_arguments)]
+fn cached_context(
+    state: &AppState,
+    query: &str,
+    scope: Option<String>,
+    user_id: Option<String>,
+    project_id: Option<String>,
+    role_tag: Option<String>,
+    options: &context::ContextOptions,
+    output: &str,
+    render: impl FnOnce(context::BuiltContext) -> Result<String, String>,
+) -> Result<String, String> {
+    let embedding_result = state.embedding.embed(query)?;
+    let scope = scope.unwrap_or_else(|| "all".to_string());
+
+    // Semantic cache: same scope + near-identical query → reuse the last context
+    let cache_key = cache::ContextKey {
+        scope: scope.clone(),
+        user_id: user_id.clone(),
+        project_id: project_id.clone(),
+        role_tag: role_tag.clone(),
+        shape: format!(
+            "{}:{}:{}:{}",
+            output,
+            options.format.as_str(),
+            options.max_tokens,
+            options.max_chars.map(|c| c.to_string()).unwrap_or_default()
+        ),
+    };
+    if let Some(cached) = state.context_cache.get(&cache_key, &embedding_result.vector) {
+        return Ok(cached);
+    }
+
+    let base = query::SearchParams {
+        query_embedding: embedding_result.vector.clone(),
+        scope,
+        user_id,
+        project_id: project_id.clone(),
+        role_tag,
+        ..Default::default()
+    };
+    let sections = context::retrieve(&state.db, &base, &context::RetrievalSettings::default())?;
+
+    // Project snapshot goes first and is paid for out of the same token/char budget
+    let count_tokens = |text: &str| state.embedding.count_tokens(text);
+    let project_snapshot = match &project_id {
+        Some(pid) => Some(snapshot::get_or_generate(&state.db, pid, snapshot::DEFAULT_TTL_SECS)?),
+        None => None,
+    };
+    let knowledge_options = match &project_snapshot {
+        Some(snap) => snapshot::knowledge_options(snap, &options, &count_tokens),
+        None => options.clone(),
+    };
+
+    // Build within the token budget
+    let mut built = context::build_context(&sections, &knowledge_options, &count_tokens);
+    if let Some(snap) = &project_snapshot {
+        snapshot::prepend(&mut built, snap, &options, &count_tokens);
+    }
+
+    let output = render(built)?;
+    state
+        .context_cache
+        .put(cache_key, embedding_result.vector, output.clone());
+    Ok(output)
+}
+
+/// IPC: rag_get_context cache statistics (entries, hits, misses, invalidations)
+#[tauri::command]
+fn rag_context_cache_stats(state: tauri::State<'_, AppState>) -> Result<String, String> {
+    serde_json::to_string(&state.context_cache.stats())
+        .map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Ingest knowledge item into local DB (with DID author tagging)
+#[tauri::command]
+fn rag_ingest(
+    state: tauri::State<'_, AppState>,
+    content: String,
+    knowledge_type: String,
+    source_type: String,
+    scope: Option<String>,
+    scope_layer: Option<String>,
+    role_tag: Option<String>,
+    dialectic_tag: Option<String>,
+    confidence: Option<f64>,
+    user_id: Option<String>,
+    project_id: Option<String>,
+    source_id: Option<String>,
+    source_context: Option<String>,
+) -> Result<String, String> {
+    let embedding_result = state.embedding.embed(&content)?;
+
+    // Tag with DID author
+    let did_author = state.did_identity.get_did().ok();
+
+    let item = knowledge::KnowledgeItem {
+        id: String::new(),
+        content,
+        summary: None,
+        knowledge_type,
+        source_type,
+        scope: scope.unwrap_or_else(|| "personal".to_string()),
+        scope_layer,
+        role_tag,
+        dialectic_tag,
+        confidence: confidence.unwrap_or(0.5),
+        relevance_score: 0.5,
+        usage_count: 0,
+        decision_maker: None,
+        outcome: None,
+        financial_impact_krw: None,
+        source_id,
+        source_context,
+        user_id,
+        project_id,
+        did_author,
+        is_active: true,
+        expires_at: None,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        updated_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    let id = knowledge::create_knowledge_item(&state.db, &item, &embedding_result.vector)?;
+
+    Ok(serde_json::json!({
+        "id": id,
+        "is_pseudo_embedding": embedding_result.is_pseudo,
+        "did_author": item.did_author,
+    })
+    .to_string())
+}
+
+/// IPC: Merge near-duplicate knowledge already stored (oldest item survives)
+#[tauri::command]
+fn rag_dedupe(
+    state: tauri::State<'_, AppState>,
+    project_id: Option<String>,
+    threshold: Option<f32>,
+    dry_run: Option<bool>,
+) -> Result<String, String> {
+    let report = dedup::dedupe_existing(
+        &state.db,
+        project_id.as_deref(),
+        threshold.unwrap_or(dedup::DEFAULT_DUPLICATE_THRESHOLD),
+        dry_run.unwrap_or(false),
+    )?;
+    serde_json::to_string(&report).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Get a single knowledge item (active or not)
+#[tauri::command]
+fn rag_get_knowledge(state: tauri::State<'_, AppState>, id: String) -> Result<String, String> {
+    let item = knowledge::get_knowledge_item(&state.db, &id)?;
+    serde_json::to_string(&item).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Edit a knowledge item (content changes are re-embedded; versioned)
+#[tauri::command]
+fn rag_update_knowledge(
+    state: tauri::State<'_, AppState>,
+    id: String,
+    update: knowledge::KnowledgeUpdate,
+    changed_by: Option<String>,
+) -> Result<String, String> {
+    let item = knowledge::update_knowledge_item(
+        &state.db,
+        &state.embedding,
+        &id,
+        &update,
+        changed_by.as_deref(),
+    )?;
+    serde_json::to_string(&item).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Soft-delete a knowledge item
+#[tauri::command]
+fn rag_deactivate_knowledge(
+    state: tauri::State<'_, AppState>,
+    id: String,
+    changed_by: Option<String>,
+) -> Result<(), String> {
+    knowledge::deactivate_knowledge_item(&state.db, &id, changed_by.as_deref())
+}
+
+/// IPC: Restore a soft-deleted knowledge item
+#[tauri::command]
+fn rag_restore_knowledge(
+    state: tauri::State<'_, AppState>,
+    id: String,
+    changed_by: Option<String>,
+) -> Result<(), String> {
+    knowledge::restore_knowledge_item(&state.db, &id, changed_by.as_deref())
+}
+
+/// IPC: Permanently delete a knowledge item (embeddings + history included)
+#[tauri::command]
+fn rag_delete_knowledge(state: tauri::State<'_, AppState>, id: String) -> Result<(), String> {
+    knowledge::delete_knowledge_item(&state.db, &id)
+}
+
+/// IPC: Version history of a knowledge item (newest first)
+#[tauri::command]
+fn rag_knowledge_history(state: tauri::State<'_, AppState>, id: String) -> Result<String, String> {
+    let versions = history::list_versions(&state.db, &id)?;
+    serde_json::to_string(&versions).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Revert a knowledge item to an earlier version
+#[tauri::command]
+fn rag_revert_knowledge(
+    state: tauri::State<'_, AppState>,
+    id: String,
+    version: i64,
+    changed_by: Option<String>,
+) -> Result<String, String> {
+    let item = history::revert_to_version(
+        &state.db,
+        &state.embedding,
+        &id,
+        version,
+        changed_by.as_deref(),
+    )?;
+    serde_json::to_string(&item).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Link two knowledge items (supersedes, contradicts, supports, derived_from, duplicate_of)
+#[tauri::command]
+fn rag_link_knowledge(
+    state: tauri::State<'_, AppState>,
+    source_id: String,
+    target_id: String,
+    relation_type: String,
+    note: Option<String>,
+    created_by: Option<String>,
+) -> Result<String, String> {
+    let relation = relations::link(
+        &state.db,
+        &source_id,
+        &target_id,
+        relations::RelationType::parse(&relation_type)?,
+        note.as_deref(),
+        created_by.as_deref(),
+    )?;
+    serde_json::to_string(&relation).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Remove a relation
+#[tauri::command]
+fn rag_unlink_knowledge(state: tauri::State<'_, AppState>, relation_id: String) -> Result<bool, String> {
+    relations::unlink(&state.db, &relation_id)
+}
+
+/// IPC: Relations touching a knowledge item (both directions)
+#[tauri::command]
+fn rag_knowledge_relations(state: tauri::State<'_, AppState>, id: String) -> Result<String, String> {
+    let results = relations::list_relations(&state.db, &id)?;
+    serde_json::to_string(&results).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Traverse the relations graph from an item
+#[tauri::command]
+fn rag_traverse_relations(
+    state: tauri::State<'_, AppState>,
+    id: String,
+    relation_types: Option<Vec<String>>,
+    max_depth: Option<usize>,
+) -> Result<String, String> {
+    let types = relation_types
+        .unwrap_or_default()
+        .iter()
+        .map(|t| relations::RelationType::parse(t))
+        .collect::<Result<Vec<_>, _>>()?;
+    let results = relations::traverse(
+        &state.db,
+        &id,
+        &types,
+        max_depth.unwrap_or(relations::DEFAULT_MAX_DEPTH),
+    )?;
+    serde_json::to_string(&results).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Batch contradiction scan (one project or all); `llm_review` (default: when an
+/// `api_key` is given) adds the LLM review under each project's outbound policy,
+/// cancellable via `request_id`
+#[tauri::command]
+async fn rag_detect_conflicts(
+    state: tauri::State<'_, AppState>,
+    project_id: Option<String>,
+    threshold: Option<f32>,
+    api_key: Option<String>,
+    llm_review: Option<bool>,
+    request_id: Option<String>,
+) -> Result<String, String> {
+    let threshold = threshold.unwrap_or(conflicts::DEFAULT_CONFLICT_SIMILARITY);
+    let results = if llm_review.unwrap_or(api_key.is_some()) {
+        let call = state.http.begin(request_id.as_deref());
+        let make_llm = |project: &str, labels: DataLabels| -> Result<LlmClient, String> {
+            Ok(LlmClient::for_task(&state.db, &state.http, LlmTask::Conflict, api_key.as_deref())?
+                .with_cancel(call.token())
+                .with_usage("detect_conflicts", Some(project), None)
+                .with_data(labels))
+        };
+        conflicts::detect_with_llm(&state.db, project_id.as_deref(), threshold, make_llm).await?
+    } else {
+        conflicts::detect(&state.db, project_id.as_deref(), threshold)?
+    };
+    serde_json::to_string(&results).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Recorded conflicts (status: open / resolved / dismissed)
+#[tauri::command]
+fn rag_list_conflicts(
+    state: tauri::State<'_, AppState>,
+    project_id: Option<String>,
+    status: Option<String>,
+) -> Result<String, String> {
+    let results = conflicts::list_conflicts(&state.db, project_id.as_deref(), status.as_deref())?;
+    serde_json::to_string(&results).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Resolve, dismiss or reopen a conflict
+#[tauri::command]
+fn rag_resolve_conflict(
+    state: tauri::State<'_, AppState>,
+    id: String,
+    status: String,
+    note: Option<String>,
+) -> Result<String, String> {
+    let conflict = conflicts::set_status(&state.db, &id, &status, note.as_deref())?;
+    serde_json::to_string(&conflict).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Move a decision to another outcome (pending / escalated / confirmed / rejected)
+#[tauri::command]
+fn rag_set_decision_outcome(
+    state: tauri::State<'_, AppState>,
+    id: String,
+    outcome: String,
+    reason: Option<String>,
+    changed_by: Option<String>,
+) -> Result<String, String> {
+    let change = outcomes::transition(
+        &state.db,
+        &state.embedding,
+        &id,
+        &outcome,
+        reason.as_deref(),
+        changed_by.as_deref(),
+    )?;
+    serde_json::to_string(&change).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Outcome audit trail of a decision
+#[tauri::command]
+fn rag_decision_outcome_history(
+    state: tauri::State<'_, AppState>,
+    id: String,
+) -> Result<String, String> {
+    let log = outcomes::outcome_history(&state.db, &id)?;
+    serde_json::to_string(&log).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Decisions by outcome (default pending + escalated), longest-waiting first
+#[tauri::command]
+fn rag_decisions_by_outcome(
+    state: tauri::State<'_, AppState>,
+    filter: Option<outcomes::DecisionFilter>,
+) -> Result<String, String> {
+    let results = outcomes::list_decisions(&state.db, &filter.unwrap_or_default())?;
+    serde_json::to_string(&results).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Fire reminders for decisions pending or escalated longer than `days`
+#[tauri::command]
+fn rag_run_decision_reminders(
+    state: tauri::State<'_, AppState>,
+    days: Option<f64>,
+) -> Result<String, String> {
+    let due = outcomes::run_reminders(&state.db, days.unwrap_or(outcomes::DEFAULT_REMINDER_DAYS))?;
+    serde_json::to_string(&due).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: LLM provider + model per task (digest, extraction, conflict, synthesis, persona)
+#[tauri::command]
+fn rag_llm_settings(state: tauri::State<'_, AppState>) -> Result<String, String> {
+    let settings = llm::list_settings(&state.db)?;
+    serde_json::to_string(&settings).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Choose the provider (anthropic / openai_compatible / mock) and model for a task
+#[tauri::command]
+fn rag_set_llm_settings(
+    state: tauri::State<'_, AppState>,
+    task: String,
+    provider: String,
+    model: String,
+    base_url: Option<String>,
+    api_key_env: Option<String>,
+) -> Result<String, String> {
+    let settings = llm::set_settings(
+        &state.db,
+        LlmTask::parse(&task)?,
+        llm::ProviderKind::parse(&provider)?,
+        &model,
+        base_url.as_deref(),
+        api_key_env.as_deref(),
+    )?;
+    serde_json::to_string(&settings).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Restore the default provider for a task
+#[tauri::command]
+fn rag_reset_llm_settings(state: tauri::State<'_, AppState>, task: String) -> Result<bool, String> {
+    llm::reset_settings(&state.db, LlmTask::parse(&task)?)
+}
+
+/// IPC: Cancel an in-flight LLM call started with this `request_id`
+#[tauri::command]
+fn rag_cancel_llm(state: tauri::State<'_, AppState>, request_id: String) -> bool {
+    state.http.cancel(&request_id)
+}
+
+/// IPC: Circuit breaker state of LLM hosts that failed recently
+#[tauri::command]
+fn rag_llm_circuits(state: tauri::State<'_, AppState>) -> Result<String, String> {
+    serde_json::to_string(&state.http.circuits()).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: LLM usage grouped by day, project or feature (task), optionally since a date
+#[tauri::command]
+fn rag_llm_usage(
+    state: tauri::State<'_, AppState>,
+    group_by: String,
+    since: Option<String>,
+    project_id: Option<String>,
+) -> Result<String, String> {
+    let buckets = usage::summarize(
+        &state.db,
+        usage::UsageGroup::parse(&group_by)?,
+        since.as_deref(),
+        project_id.as_deref(),
+    )?;
+    serde_json::to_string(&buckets).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Monthly LLM budgets with this month's spend (one project or all)
+#[tauri::command]
+fn rag_llm_budgets(state: tauri::State<'_, AppState>, project_id: Option<String>) -> Result<String, String> {
+    let budgets = usage::budgets(&state.db, project_id.as_deref())?;
+    serde_json::to_string(&budgets).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Set a project's monthly LLM budget in USD (mode: warn / block)
+#[tauri::command]
+fn rag_set_llm_budget(
+    state: tauri::State<'_, AppState>,
+    project_id: String,
+    monthly_limit_usd: f64,
+    mode: String,
+) -> Result<String, String> {
+    let budget = usage::set_budget(&state.db, &project_id, monthly_limit_usd, usage::BudgetMode::parse(&mode)?)?;
+    serde_json::to_string(&budget).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Remove a project's LLM budget
+#[tauri::command]
+fn rag_remove_llm_budget(state: tauri::State<'_, AppState>, project_id: String) -> Result<bool, String> {
+    usage::remove_budget(&state.db, &project_id)
+}
+
+/// IPC: Model prices (USD per million tokens), built-in and custom
+#[tauri::command]
+fn rag_llm_prices(state: tauri::State<'_, AppState>) -> Result<String, String> {
+    let prices = usage::list_prices(&state.db)?;
+    serde_json::to_string(&prices).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Set the price of a model; omit both prices to remove the custom price
+#[tauri::command]
+fn rag_set_llm_price(
+    state: tauri::State<'_, AppState>,
+    model: String,
+    input_per_mtok: Option<f64>,
+    output_per_mtok: Option<f64>,
+) -> Result<(), String> {
+    let price = match (input_per_mtok, output_per_mtok) {
+        (Some(input), Some(output)) => Some((input, output)),
+        (None, None) => None,
+        _ => return Err("Give both input_per_mtok and output_per_mtok, or neither".to_string()),
+    };
+    usage::set_price(&state.db, &model, price)
+}
+
+/// IPC: PII redaction settings of a project (defaults when none are stored)
+#[tauri::command]
+fn rag_redaction_settings(state: tauri::State<'_, AppState>, project_id: Option<String>) -> Result<String, String> {
+    let settings = redaction::get_settings(&state.db, project_id.as_deref())?;
+    serde_json::to_string(&settings).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Set a project's PII redaction (kinds: phone / rrn / email / bank_account / client_name / person / user_id)
+#[tauri::command]
+fn rag_set_redaction_settings(
+    state: tauri::State<'_, AppState>,
+    project_id: String,
+    enabled: bool,
+    kinds: Vec<String>,
+    client_names: Vec<String>,
+) -> Result<String, String> {
+    let kinds = kinds
+        .iter()
+        .map(|k| redaction::PiiKind::parse(k))
+        .collect::<Result<Vec<_>, _>>()?;
+    let settings = redaction::set_settings(&state.db, &project_id, enabled, &kinds, &client_names)?;
+    serde_json::to_string(&settings).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Reset a project's PII redaction to the defaults
+#[tauri::command]
+fn rag_reset_redaction_settings(state: tauri::State<'_, AppState>, project_id: String) -> Result<bool, String> {
+    redaction::reset_settings(&state.db, &project_id)
+}
+
+/// IPC: What a prompt would look like after redaction (nothing is sent)
+#[tauri::command]
+fn rag_redaction_preview(
+    state: tauri::State<'_, AppState>,
+    text: String,
+    project_id: Option<String>,
+) -> Result<String, String> {
+    let preview = redaction::preview(&state.db, project_id.as_deref(), &text)?;
+    serde_json::to_string(&preview).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Recent redacting LLM calls, newest first (counts per kind only)
+#[tauri::command]
+fn rag_redaction_audit(
+    state: tauri::State<'_, AppState>,
+    project_id: Option<String>,
+    limit: Option<usize>,
+) -> Result<String, String> {
+    let entries = redaction::audit_log(&state.db, project_id.as_deref(), limit.unwrap_or(50))?;
+    serde_json::to_string(&entries).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Outbound policy rules (block / local_only per channel, scope, project, knowledge_type, provider)
+#[tauri::command]
+fn rag_outbound_rules(state: tauri::State<'_, AppState>) -> Result<String, String> {
+    let rules = policy::list_rules(&state.db)?;
+    serde_json::to_string(&rules).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Create or replace an outbound rule (empty id = new rule)
+#[tauri::command]
+fn rag_set_outbound_rule(state: tauri::State<'_, AppState>, rule: policy::PolicyRule) -> Result<String, String> {
+    let rule = policy::set_rule(&state.db, &rule)?;
+    serde_json::to_string(&rule).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Remove an outbound rule
+#[tauri::command]
+fn rag_remove_outbound_rule(state: tauri::State<'_, AppState>, id: String) -> Result<bool, String> {
+    policy::remove_rule(&state.db, &id)
+}
+
+/// IPC: Local-only mode — no external LLM provider, no sync
+#[tauri::command]
+fn rag_set_local_only(state: tauri::State<'_, AppState>, enabled: bool) -> Result<(), String> {
+    policy::set_local_only(&state.db, enabled)
+}
+
+#[tauri::command]
+fn rag_local_only(state: tauri::State<'_, AppState>) -> Result<bool, String> {
+    policy::is_local_only(&state.db)
+}
+
+/// IPC: Recent outbound calls, newest first (channel: llm / sync; content hashes only)
+#[tauri::command]
+fn rag_outbound_log(
+    state: tauri::State<'_, AppState>,
+    channel: Option<String>,
+    limit: Option<usize>,
+) -> Result<String, String> {
+    let channel = channel.as_deref().map(policy::Channel::parse).transpose()?;
+    let entries = policy::outbound_log(&state.db, channel, limit.unwrap_or(50))?;
+    serde_json::to_string(&entries).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Ontology vocabulary (built-in + organisation terms)
+#[tauri::command]
+fn rag_ontology(state: tauri::State<'_, AppState>) -> Result<String, String> {
+    let vocabulary = ontology::load(&state.db)?;
+    serde_json::to_string(&vocabulary).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Add an organisation-specific knowledge_type or role_tag
+#[tauri::command]
+fn rag_add_ontology_term(
+    state: tauri::State<'_, AppState>,
+    dimension: String,
+    value: String,
+    label: Option<String>,
+    category: Option<String>,
+    aliases: Option<Vec<String>>,
+    traits: Option<Vec<ontology::TermTrait>>,
+) -> Result<String, String> {
+    let term = ontology::add_term(
+        &state.db,
+        ontology::Dimension::parse(&dimension)?,
+        &value,
+        label.as_deref().unwrap_or_default(),
+        category.as_deref(),
+        &aliases.unwrap_or_default(),
+        &traits.unwrap_or_default(),
+    )?;
+    serde_json::to_string(&term).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Remove an unused organisation term
+#[tauri::command]
+fn rag_remove_ontology_term(
+    state: tauri::State<'_, AppState>,
+    dimension: String,
+    value: String,
+) -> Result<(), String> {
+    ontology::remove_term(&state.db, ontology::Dimension::parse(&dimension)?, &value)
+}
+
+/// IPC: Prompt templates in effect per prompt and language (built-in or override)
+#[tauri::command]
+fn rag_prompt_templates(state: tauri::State<'_, AppState>) -> Result<String, String> {
+    let catalog = prompts::catalog(&state.db);
+    serde_json::to_string(&catalog).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Re-read prompt overrides from `<app data>/prompts`
+#[tauri::command]
+fn rag_reload_prompts(state: tauri::State<'_, AppState>) -> Result<String, String> {
+    let catalog = prompts::reload(&state.db);
+    serde_json::to_string(&catalog).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Prompt language (ko / en / th) of a room or project; `None` clears it
+#[tauri::command]
+fn rag_set_prompt_language(
+    state: tauri::State<'_, AppState>,
+    scope_type: String,
+    scope_id: String,
+    language: Option<String>,
+) -> Result<(), String> {
+    let language = language.as_deref().map(prompts::Language::parse).transpose()?;
+    prompts::set_language(&state.db, prompts::Scope::parse(&scope_type)?, &scope_id, language)
+}
+
+/// IPC: Prompt language used for a room (falling back to its project, then Korean)
+#[tauri::command]
+fn rag_prompt_language(
+    state: tauri::State<'_, AppState>,
+    room_id: Option<String>,
+    project_id: Option<String>,
+) -> Result<String, String> {
+    let language = prompts::language_for(&state.db, room_id.as_deref(), project_id.as_deref())?;
+    Ok(language.as_str().to_string())
+}
+
+/// IPC: Run the knowledge aging job now
+#[tauri::command]
+fn rag_run_aging(state: tauri::State<'_, AppState>) -> Result<String, String> {
+    let run = aging::run_aging(&state.db)?;
+    serde_json::to_string(&run).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Recent aging runs
+#[tauri::command]
+fn rag_aging_runs(
+    state: tauri::State<'_, AppState>,
+    limit: Option<usize>,
+) -> Result<String, String> {
+    let runs = aging::list_runs(&state.db, limit.unwrap_or(20))?;
+    serde_json::to_string(&runs).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Score changes / expiries made by aging (by run, by item, or all)
+#[tauri::command]
+fn rag_aging_changes(
+    state: tauri::State<'_, AppState>,
+    run_id: Option<String>,
+    knowledge_id: Option<String>,
+    limit: Option<usize>,
+) -> Result<String, String> {
+    let changes = aging::list_changes(
+        &state.db,
+        run_id.as_deref(),
+        knowledge_id.as_deref(),
+        limit.unwrap_or(100),
+    )?;
+    serde_json::to_string(&changes).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Effective decay policy per knowledge type
+#[tauri::command]
+fn rag_decay_policies(state: tauri::State<'_, AppState>) -> Result<String, String> {
+    let policies = aging::list_policies(&state.db)?;
+    serde_json::to_string(&policies).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Override a knowledge type's half-life / TTL (null = never)
+#[tauri::command]
+fn rag_set_decay_policy(
+    state: tauri::State<'_, AppState>,
+    knowledge_type: String,
+    half_life_days: Option<f64>,
+    ttl_days: Option<f64>,
+) -> Result<(), String> {
+    aging::set_policy(&state.db, &knowledge_type, half_life_days, ttl_days)
+}
+
+/// IPC: Restore the built-in policy of a knowledge type
+#[tauri::command]
+fn rag_reset_decay_policy(
+    state: tauri::State<'_, AppState>,
+    knowledge_type: String,
+) -> Result<bool, String> {
+    aging::reset_policy(&state.db, &knowledge_type)
+}
+
+/// IPC: Re-mine decision patterns (all decision makers, or one)
+#[tauri::command]
+fn rag_mine_decision_patterns(
+    state: tauri::State<'_, AppState>,
+    decision_maker: Option<String>,
+) -> Result<String, String> {
+    let report = patterns::mine_all(&state.db, decision_maker.as_deref())?;
+    serde_json::to_string(&report).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Stored decision patterns, optionally per decision maker / domain
+#[tauri::command]
+fn rag_decision_patterns(
+    state: tauri::State<'_, AppState>,
+    decision_maker: Option<String>,
+    domain: Option<String>,
+) -> Result<String, String> {
+    let results = patterns::list_patterns(&state.db, decision_maker.as_deref(), domain.as_deref())?;
+    serde_json::to_string(&results).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: "How does X usually decide …?" — patterns ranked against a question, with evidence
+#[tauri::command]
+fn rag_ask_decision_patterns(
+    state: tauri::State<'_, AppState>,
+    query: String,
+    decision_maker: Option<String>,
+    limit: Option<usize>,
+) -> Result<String, String> {
+    let embedding = state.embedding.embed(&query)?;
+    let results = patterns::find_patterns(
+        &state.db,
+        decision_maker.as_deref(),
+        &embedding.vector,
+        limit.unwrap_or(3),
+    )?;
+    serde_json::to_string(&results).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Compiled project state (decisions, open risks, action items, digests, key knowledge)
+#[tauri::command]
+fn rag_project_snapshot(
+    state: tauri::State<'_, AppState>,
+    project_id: String,
+    refresh: Option<bool>,
+) -> Result<String, String> {
+    let result = if refresh.unwrap_or(false) {
+        snapshot::generate(&state.db, &project_id, snapshot::DEFAULT_TTL_SECS)?
+    } else {
+        snapshot::get_or_generate(&state.db, &project_id, snapshot::DEFAULT_TTL_SECS)?
+    };
+    serde_json::to_string(&result).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Get local RAG statistics
+#[tauri::command]
+fn rag_stats(state: tauri::State<'_, AppState>) -> Result<String, String> {
+    let stats = knowledge::get_stats(&state.db)?;
+    serde_json::to_string(&stats).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Submit feedback on a RAG query
+#[tauri::command]
+fn rag_feedback(
+    state: tauri::State<'_, AppState>,
+    query_log_id: String,
+    was_helpful: bool,
+) -> Result<(), String> {
+    knowledge::record_query_feedback(&state.db, &query_log_id, was_helpful)
+}
 
-    // Pass 1 (정 thesis): General hybrid search
-    let thesis_params = query::SearchParams {
-        query_embedding: embedding_result.vector.clone(),
-        scope: scope.unwrap_or_else(|| "all".to_string()),
-        user_id: user_id.clone(),
-        project_id: project_id.clone(),
-        role_tag: role_tag.clone(),
-        threshold: 0.30,
-        limit: 5,
-        ..Default::default()
-    };
-    let thesis_results = query::hybrid_search(&state.db, &thesis_params)?;
+/// IPC: Most frequent queries (filterable by project and date range)
+#[tauri::command]
+fn rag_analytics_top_queries(
+    state: tauri::State<'_, AppState>,
+    project_id: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+    limit: Option<usize>,
+) -> Result<String, String> {
+    let filter = analytics_filter(project_id, since, until, limit);
+    let results = analytics::top_queries(&state.db, &filter)?;
+    serde_json::to_string(&results).map_err(|e| format!("Serialize failed: {}", e))
+}
 
-    // Pass 2 (반 antithesis): Dialectic opposing search
-    let anti_params = query::DialecticParams {
-        query_embedding: embedding_result.vector.clone(),
-        user_id: user_id.clone(),
-        project_id: project_id.clone(),
-        role_tag: role_tag.clone(),
-        threshold: 0.25,
-        limit: 3,
-        ..Default::default()
-    };
-    let anti_results = query::dialectic_search(&state.db, &anti_params)?;
+/// IPC: Knowledge gaps — zero-result or low-similarity queries
+#[tauri::command]
+fn rag_analytics_knowledge_gaps(
+    state: tauri::State<'_, AppState>,
+    project_id: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+    similarity_threshold: Option<f64>,
+    limit: Option<usize>,
+) -> Result<String, String> {
+    let filter = analytics_filter(project_id, since, until, limit);
+    let results = analytics::knowledge_gaps(
+        &state.db,
+        &filter,
+        similarity_threshold.unwrap_or(analytics::DEFAULT_GAP_SIMILARITY),
+    )?;
+    serde_json::to_string(&results).map_err(|e| format!("Serialize failed: {}", e))
+}
 
-    // Pass 3 (개인 personal): Personal scope search
-    let personal_params = query::SearchParams {
-        query_embedding: embedding_result.vector,
-        scope: "personal".to_string(),
-        user_id,
-        project_id: None,
-        role_tag: None,
-        threshold: 0.25,
-        limit: 3,
-        ..Default::default()
-    };
-    let personal_results = query::hybrid_search(&state.db, &personal_params)?;
+/// IPC: Helpful rate over time (bucket: "day" | "week" | "month")
+#[tauri::command]
+fn rag_analytics_helpful_rate(
+    state: tauri::State<'_, AppState>,
+    project_id: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+    bucket: Option<String>,
+    limit: Option<usize>,
+) -> Result<String, String> {
+    let bucket = analytics::TimeBucket::parse(bucket.as_deref().unwrap_or("day"))?;
+    let filter = analytics_filter(project_id, since, until, limit);
+    let results = analytics::helpful_rate(&state.db, &filter, bucket)?;
+    serde_json::to_string(&results).map_err(|e| format!("Serialize failed: {}", e))
+}
 
-    // Merge and deduplicate (thesis → anti → personal)
-    let mut all_results: Vec<query::SearchResult> = Vec::new();
-    let mut seen_ids: std::collections::HashSet<String> = std::collections::HashSet::new();
+/// IPC: Items most often retrieved but rated unhelpful
+#[tauri::command]
+fn rag_analytics_unhelpful_items(
+    state: tauri::State<'_, AppState>,
+    project_id: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+    limit: Option<usize>,
+) -> Result<String, String> {
+    let filter = analytics_filter(project_id, since, until, limit);
+    let results = analytics::unhelpful_items(&state.db, &filter)?;
+    serde_json::to_string(&results).map_err(|e| format!("Serialize failed: {}", e))
+}
 
-    for result in thesis_results
-        .into_iter()
-        .chain(anti_results)
-        .chain(personal_results)
-    {
-        if seen_ids.insert(result.id.clone()) {
-            all_results.push(result);
-        }
+fn analytics_filter(
+    project_id: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+    limit: Option<usize>,
+) -> analytics::AnalyticsFilter {
+    let defaults = analytics::AnalyticsFilter::default();
+    analytics::AnalyticsFilter {
+        project_id,
+        since,
+        until,
+        limit: limit.unwrap_or(defaults.limit),
     }
-
-    let context = query::build_rag_context(&all_results, max_chars.unwrap_or(800));
-    Ok(context)
 }
 
-/// IPC: Ingest knowledge item into local DB (with DID author tagging)
+// ── Standing Queries IPC ────────────────────────────────
+
+/// IPC: Save a standing query (fires when matching knowledge arrives)
 #[tauri::command]
-fn rag_ingest(
+#[allow(clippy::too_many_arguments)]
+fn rag_standing_query_create(
     state: tauri::State<'_, AppState>,
-    content: String,
-    knowledge_type: String,
-    source_type: String,
-    scope: Option<String>,
-    scope_layer: Option<String>,
-    role_tag: Option<String>,
-    dialectic_tag: Option<String>,
-    confidence: Option<f64>,
+    name: String,
+    query: String,
     user_id: Option<String>,
     project_id: Option<String>,
-    source_id: Option<String>,
-    source_context: Option<String>,
+    knowledge_type: Option<String>,
+    dialectic_tag: Option<String>,
+    role_tag: Option<String>,
+    threshold: Option<f64>,
+    notify: Option<bool>,
 ) -> Result<String, String> {
-    let embedding_result = state.embedding.embed(&content)?;
-
-    // Tag with DID author
-    let did_author = state.did_identity.get_did().ok();
+    let embedding_result = state.embedding.embed(&query)?;
 
-    let item = knowledge::KnowledgeItem {
-        id: String::new(),
-        content,
-        summary: None,
-        knowledge_type,
-        source_type,
-        scope: scope.unwrap_or_else(|| "personal".to_string()),
-        scope_layer,
-        role_tag,
-        dialectic_tag,
-        confidence: confidence.unwrap_or(0.5),
-        relevance_score: 0.5,
-        usage_count: 0,
-        decision_maker: None,
-        outcome: None,
-        financial_impact_krw: None,
-        source_id,
-        source_context,
+    let new_query = standing::NewStandingQuery {
+        name,
+        query_text: query,
         user_id,
         project_id,
-        did_author,
-        is_active: true,
-        expires_at: None,
-        created_at: chrono::Utc::now().to_rfc3339(),
-        updated_at: chrono::Utc::now().to_rfc3339(),
+        knowledge_type,
+        dialectic_tag,
+        role_tag,
+        threshold: threshold.unwrap_or(standing::DEFAULT_THRESHOLD),
+        notify: notify.unwrap_or(false),
     };
 
-    let id = knowledge::create_knowledge_item(&state.db, &item, &embedding_result.vector)?;
+    let created = standing::create_standing_query(&state.db, &new_query, &embedding_result.vector)?;
+    serde_json::to_string(&created).map_err(|e| format!("Serialize failed: {}", e))
+}
 
-    Ok(serde_json::json!({
-        "id": id,
-        "is_pseudo_embedding": embedding_result.is_pseudo,
-        "did_author": item.did_author,
-    })
-    .to_string())
+/// IPC: List standing queries (optionally for one user)
+#[tauri::command]
+fn rag_standing_query_list(
+    state: tauri::State<'_, AppState>,
+    user_id: Option<String>,
+) -> Result<String, String> {
+    let queries = standing::list_standing_queries(&state.db, user_id.as_deref())?;
+    serde_json::to_string(&queries).map_err(|e| format!("Serialize failed: {}", e))
 }
 
-/// IPC: Get local RAG statistics
+/// IPC: Pause or resume a standing query
 #[tauri::command]
-fn rag_stats(state: tauri::State<'_, AppState>) -> Result<String, String> {
-    let stats = knowledge::get_stats(&state.db)?;
-    serde_json::to_string(&stats).map_err(|e| format!("Serialize failed: {}", e))
+fn rag_standing_query_set_active(
+    state: tauri::State<'_, AppState>,
+    id: String,
+    active: bool,
+) -> Result<(), String> {
+    standing::set_standing_query_active(&state.db, &id, active)
 }
 
-/// IPC: Submit feedback on a RAG query
+/// IPC: Delete a standing query
 #[tauri::command]
-fn rag_feedback(
+fn rag_standing_query_delete(
     state: tauri::State<'_, AppState>,
-    query_log_id: String,
-    was_helpful: bool,
+    id: String,
 ) -> Result<(), String> {
-    knowledge::record_query_feedback(&state.db, &query_log_id, was_helpful)
+    standing::delete_standing_query(&state.db, &id)
+}
+
+/// IPC: Recent matches for a standing query
+#[tauri::command]
+fn rag_standing_query_matches(
+    state: tauri::State<'_, AppState>,
+    id: String,
+    limit: Option<usize>,
+) -> Result<String, String> {
+    let matches = standing::get_matches(&state.db, &id, limit.unwrap_or(20))?;
+    serde_json::to_string(&matches).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// Forward knowledge-layer events to the frontend (Tauri event + optional native notification)
+fn forward_rag_event(app: &tauri::AppHandle, event: &RagEvent) {
+    match event {
+        RagEvent::KnowledgeChanged(change) => {
+            if let Err(e) = app.emit("rag:knowledge-changed", change) {
+                log::warn!("Failed to emit knowledge change: {}", e);
+            }
+        }
+        RagEvent::ConflictDetected(conflict) => {
+            if let Err(e) = app.emit("rag:conflict-detected", conflict) {
+                log::warn!("Failed to emit knowledge conflict: {}", e);
+            }
+        }
+        RagEvent::DecisionReminder(decision) => {
+            if let Err(e) = app.emit("rag:decision-reminder", decision) {
+                log::warn!("Failed to emit decision reminder: {}", e);
+            }
+
+            let body: String = decision.content.chars().take(120).collect();
+            if let Err(e) = app
+                .notification()
+                .builder()
+                .title(format!("⏳ 결정 대기 {}일", decision.days_in_outcome.floor() as i64))
+                .body(body)
+                .show()
+            {
+                log::warn!("Failed to show decision reminder notification: {}", e);
+            }
+        }
+        RagEvent::DigestDue(room) => {
+            if let Err(e) = app.emit("rag:digest-due", room) {
+                log::warn!("Failed to emit digest due: {}", e);
+            }
+        }
+        RagEvent::BudgetAlert(alert) => {
+            if let Err(e) = app.emit("rag:llm-budget", alert) {
+                log::warn!("Failed to emit LLM budget alert: {}", e);
+            }
+        }
+        // Consumed by the context cache; the frontend re-reads snapshots on demand
+        RagEvent::SnapshotInvalidated(_) => {}
+        RagEvent::StandingQueryMatched(m) => {
+            if let Err(e) = app.emit("rag:standing-query-matched", m) {
+                log::warn!("Failed to emit standing query match: {}", e);
+            }
+
+            if m.notify {
+                let body: String = m.content.chars().take(120).collect();
+                if let Err(e) = app
+                    .notification()
+                    .builder()
+                    .title(format!("🔔 {}", m.standing_query_name))
+                    .body(body)
+                    .show()
+                {
+                    log::warn!("Failed to show standing query notification: {}", e);
+                }
+            }
+        }
+    }
 }
 
 // ── Phase 3: Knowledge Pipeline IPC ─────────────────────
 
-/// IPC: Analyze chat messages using Claude Haiku (digest)
+/// IPC: Analyze chat messages with the digest LLM (Claude Haiku by default),
+/// or with the offline rules when no provider is usable
 #[tauri::command]
 async fn rag_digest(
     state: tauri::State<'_, AppState>,
     messages: Vec<digest::ChatMessage>,
     room_id: String,
     project_id: Option<String>,
-    api_key: String,
+    api_key: Option<String>,
+    request_id: Option<String>,
 ) -> Result<String, String> {
     if messages.is_empty() {
         return Err("No messages to analyze".into());
     }
 
-    let result = digest::analyze_conversation(&messages, &api_key).await?;
+    let call = state.http.begin(request_id.as_deref());
+    let llm = LlmClient::for_task(&state.db, &state.http, LlmTask::Digest, api_key.as_deref())
+        .map(|llm| llm.with_cancel(call.token()).with_usage("digest", project_id.as_deref(), Some(&room_id)));
+    let prompts = prompts::for_scope(&state.db, Some(&room_id), project_id.as_deref())?;
+    let result = digest::analyze_or_fallback(
+        &messages,
+        llm.as_ref().map_err(String::as_str),
+        &prompts,
+        digest::WhenUnreachable::Fallback,
+    )
+    .await?;
 
-    let digest_ids = digest::store_digest(
-        &state.db,
-        &room_id,
-        project_id.as_deref(),
-        &result,
-        messages.len() as i64,
-    )?;
+    let range = digest::MessageRange::of(&messages).ok_or("No messages to analyze")?;
+    let digest_ids = digest::store_digest(&state.db, &room_id, project_id.as_deref(), &result, &range)?;
 
     Ok(serde_json::json!({
         "digest": result,
@@ -312,7 +1272,156 @@ async fn rag_digest(
     .to_string())
 }
 
-/// IPC: Extract knowledge from a stored digest (deep analysis via Claude)
+/// IPC: Queue a room's messages for incremental digest (only those past the watermark)
+#[tauri::command]
+fn rag_enqueue_messages(
+    state: tauri::State<'_, AppState>,
+    room_id: String,
+    project_id: Option<String>,
+    messages: Vec<digest::ChatMessage>,
+) -> Result<String, String> {
+    let result = scheduler::enqueue(&state.db, &room_id, project_id.as_deref(), &messages)?;
+    serde_json::to_string(&result).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Digest a room's queued messages now and advance its watermark (null if nothing queued)
+#[tauri::command]
+async fn rag_digest_room(
+    state: tauri::State<'_, AppState>,
+    room_id: String,
+    api_key: Option<String>,
+    request_id: Option<String>,
+) -> Result<String, String> {
+    let project_id = scheduler::room_status(&state.db, Some(&room_id), &SchedulerPolicy::default())?
+        .into_iter()
+        .next()
+        .and_then(|room| room.project_id);
+    let call = state.http.begin(request_id.as_deref());
+    let llm = LlmClient::for_task(&state.db, &state.http, LlmTask::Digest, api_key.as_deref())
+        .map(|llm| llm.with_cancel(call.token()).with_usage("digest_room", project_id.as_deref(), Some(&room_id)));
+    let result = scheduler::digest_room(
+        &state.db,
+        &room_id,
+        llm.as_ref().map_err(String::as_str),
+        digest::WhenUnreachable::Fallback,
+    )
+    .await?;
+    serde_json::to_string(&result).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Digest queue and watermark per room (one room or all)
+#[tauri::command]
+fn rag_digest_queue_status(state: tauri::State<'_, AppState>, room_id: Option<String>) -> Result<String, String> {
+    let results = scheduler::room_status(&state.db, room_id.as_deref(), &SchedulerPolicy::default())?;
+    serde_json::to_string(&results).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: 정반합 report for a proposal (optionally stored as a linked lesson_learned item)
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn rag_synthesize(
+    state: tauri::State<'_, AppState>,
+    proposal: String,
+    user_id: Option<String>,
+    project_id: Option<String>,
+    role_tag: Option<String>,
+    store: Option<bool>,
+    api_key: Option<String>,
+    request_id: Option<String>,
+) -> Result<String, String> {
+    let base = query::SearchParams {
+        user_id: user_id.clone(),
+        project_id: project_id.clone(),
+        role_tag,
+        ..Default::default()
+    };
+    let passes = synthesis::gather_passes(&state.db, &state.embedding, &proposal, base)?;
+    let call = state.http.begin(request_id.as_deref());
+    let llm = LlmClient::for_task(&state.db, &state.http, LlmTask::Synthesis, api_key.as_deref())?
+        .with_cancel(call.token())
+        .with_usage("synthesize", project_id.as_deref(), None)
+        .with_data(DataLabels::of_context(&state.db, &passes)?);
+    let mut report = synthesis::synthesize(&proposal, &passes, &llm).await?;
+
+    if store.unwrap_or(false) {
+        synthesis::store_as_lesson(
+            &state.db,
+            &state.embedding,
+            &mut report,
+            user_id.as_deref(),
+            project_id.as_deref(),
+        )?;
+    }
+    serde_json::to_string(&report).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Available personas (CEO, CD, PD, …)
+#[tauri::command]
+fn list_personas() -> Result<String, String> {
+    serde_json::to_string(&persona::builtin_personas()).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Answer a question as a persona, grounded in local RAG (logged for feedback)
+#[tauri::command]
+async fn ask_persona(
+    state: tauri::State<'_, AppState>,
+    persona_id: String,
+    query: String,
+    user_id: Option<String>,
+    project_id: Option<String>,
+    api_key: Option<String>,
+    request_id: Option<String>,
+) -> Result<String, String> {
+    let definition = persona::get_persona(&persona_id)?;
+    let built = persona::build_persona_context(
+        &state.db,
+        &state.embedding,
+        &definition,
+        &query,
+        user_id.as_deref(),
+        project_id.as_deref(),
+    )?;
+    let system = persona::system_prompt(&definition, &built);
+
+    let call = state.http.begin(request_id.as_deref());
+    let llm = LlmClient::for_task(&state.db, &state.http, LlmTask::Persona, api_key.as_deref())?
+        .with_cancel(call.token())
+        .with_usage("ask_persona", project_id.as_deref(), None)
+        .with_data(DataLabels::of_context(&state.db, &built)?);
+    let answer = persona::generate_answer(&system, &query, &llm).await?;
+    let query_log_id = persona::log_query(&state.db, &definition.id, &query, &built, &answer)?;
+
+    let result = persona::PersonaAnswer {
+        query_log_id,
+        persona_id: definition.id,
+        answer,
+        context: built,
+    };
+    serde_json::to_string(&result).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Rate a persona answer
+#[tauri::command]
+fn persona_feedback(
+    state: tauri::State<'_, AppState>,
+    query_log_id: String,
+    was_helpful: bool,
+) -> Result<(), String> {
+    persona::record_feedback(&state.db, &query_log_id, was_helpful)
+}
+
+/// IPC: Recent persona questions and answers
+#[tauri::command]
+fn persona_history(
+    state: tauri::State<'_, AppState>,
+    persona_id: Option<String>,
+    limit: Option<usize>,
+) -> Result<String, String> {
+    let results = persona::list_queries(&state.db, persona_id.as_deref(), limit.unwrap_or(20))?;
+    serde_json::to_string(&results).map_err(|e| format!("Serialize failed: {}", e))
+}
+
+/// IPC: Extract knowledge from a stored digest (deep analysis via the extraction LLM)
 #[tauri::command]
 async fn rag_extract_from_digest(
     state: tauri::State<'_, AppState>,
@@ -320,10 +1429,15 @@ async fn rag_extract_from_digest(
     user_id: Option<String>,
     project_id: Option<String>,
     source_id: String,
-    api_key: String,
+    api_key: Option<String>,
+    request_id: Option<String>,
 ) -> Result<String, String> {
     let digest_result: digest::DigestResult = serde_json::from_str(&digest_json)
         .map_err(|e| format!("Invalid digest JSON: {}", e))?;
+    let call = state.http.begin(request_id.as_deref());
+    let llm = LlmClient::for_task(&state.db, &state.http, LlmTask::Extraction, api_key.as_deref())?
+        .with_cancel(call.token())
+        .with_usage("extract_from_digest", project_id.as_deref(), None);
 
     let result = ingest::from_digest(
         &state.db,
@@ -332,7 +1446,7 @@ async fn rag_extract_from_digest(
         user_id.as_deref(),
         project_id.as_deref(),
         &source_id,
-        &api_key,
+        &llm,
     )
     .await?;
 
@@ -663,6 +1777,34 @@ pub fn run() {
             };
             let db = Arc::new(db);
 
+            // Prompt overrides edited by the user (built-ins when absent)
+            prompts::init(&db, &app_data_dir.join("prompts"));
+
+            // Forward knowledge-layer events (standing query matches, …) to the frontend
+            let app_handle = app.handle().clone();
+            db.subscribe(move |event| forward_rag_event(&app_handle, event));
+
+            // rag_get_context cache, invalidated on knowledge changes
+            let context_cache = Arc::new(ContextCache::default());
+            let cache_listener = context_cache.clone();
+            db.subscribe(move |event| cache_listener.on_event(event));
+
+            // Project snapshots are dropped on any write to their project
+            let snapshot_db = Arc::downgrade(&db);
+            db.subscribe(move |event| {
+                if let Some(db) = snapshot_db.upgrade() {
+                    snapshot::on_event(&db, event);
+                }
+            });
+
+            // Keep decision patterns in step with decision knowledge
+            let pattern_db = Arc::downgrade(&db);
+            db.subscribe(move |event| {
+                if let Some(db) = pattern_db.upgrade() {
+                    patterns::on_event(&db, event);
+                }
+            });
+
             // Initialize embedding engine
             let model_dir = app_data_dir.join("models").join("all-MiniLM-L6-v2");
             let embedding = EmbeddingEngine::new(model_dir);
@@ -703,11 +1845,46 @@ pub fn run() {
                 }
             }
 
+            // Knowledge aging: decay scores / expire stale items in the background
+            let aging_db = Arc::downgrade(&db);
+            tauri::async_runtime::spawn(async move {
+                let mut interval =
+                    tokio::time::interval(std::time::Duration::from_secs(aging::RUN_INTERVAL_SECS));
+                loop {
+                    interval.tick().await;
+                    let Some(db) = aging_db.upgrade() else { break };
+                    let result = tokio::task::spawn_blocking(move || aging::run_aging(&db)).await;
+                    if let Ok(Err(e)) = result {
+                        log::warn!("Knowledge aging failed: {}", e);
+                    }
+                }
+            });
+
+            // Shared LLM transport (timeouts, retries, circuit breaker, cancellation)
+            let http = Arc::new(ResilientHttp::new(HttpPolicy::default())?);
+
+            // Digest scheduler: digest (or announce) rooms whose queue crossed a threshold
+            let scheduler_db = Arc::downgrade(&db);
+            let scheduler_http = http.clone();
+            tauri::async_runtime::spawn(async move {
+                let policy = SchedulerPolicy::default();
+                let mut interval = tokio::time::interval(std::time::Duration::from_secs(scheduler::TICK_SECS));
+                loop {
+                    interval.tick().await;
+                    let Some(db) = scheduler_db.upgrade() else { break };
+                    if let Err(e) = scheduler::run_due(&db, &scheduler_http, &policy).await {
+                        log::warn!("Digest scheduler failed: {}", e);
+                    }
+                }
+            });
+
             // Store shared state
             app.manage(AppState {
                 db,
                 embedding,
                 did_identity,
+                context_cache,
+                http,
             });
 
             log::info!(
@@ -726,19 +1903,113 @@ pub fn run() {
             rag_search,
             rag_dialectic_search,
             rag_get_context,
+            rag_get_cited_context,
+            rag_context_cache_stats,
             // RAG ingest (Phase 2)
             rag_ingest,
+            rag_dedupe,
+            // Knowledge item CRUD + history
+            rag_get_knowledge,
+            rag_update_knowledge,
+            rag_deactivate_knowledge,
+            rag_restore_knowledge,
+            rag_delete_knowledge,
+            rag_knowledge_history,
+            rag_revert_knowledge,
+            // Knowledge relations
+            rag_link_knowledge,
+            rag_unlink_knowledge,
+            rag_knowledge_relations,
+            rag_traverse_relations,
+            // Contradiction detection
+            rag_detect_conflicts,
+            rag_list_conflicts,
+            rag_resolve_conflict,
+            // Decision outcome lifecycle
+            rag_set_decision_outcome,
+            rag_decision_outcome_history,
+            rag_decisions_by_outcome,
+            rag_run_decision_reminders,
+            // LLM providers
+            rag_llm_settings,
+            rag_set_llm_settings,
+            rag_reset_llm_settings,
+            rag_cancel_llm,
+            rag_llm_circuits,
+            // LLM usage ledger + budgets
+            rag_llm_usage,
+            rag_llm_budgets,
+            rag_set_llm_budget,
+            rag_remove_llm_budget,
+            rag_llm_prices,
+            rag_set_llm_price,
+            // PII redaction
+            rag_redaction_settings,
+            rag_set_redaction_settings,
+            rag_reset_redaction_settings,
+            rag_redaction_preview,
+            rag_redaction_audit,
+            // Outbound policy
+            rag_outbound_rules,
+            rag_set_outbound_rule,
+            rag_remove_outbound_rule,
+            rag_set_local_only,
+            rag_local_only,
+            rag_outbound_log,
+            // Ontology vocabulary
+            rag_ontology,
+            rag_add_ontology_term,
+            rag_remove_ontology_term,
+            // Prompt registry
+            rag_prompt_templates,
+            rag_reload_prompts,
+            rag_set_prompt_language,
+            rag_prompt_language,
+            // Knowledge aging
+            rag_run_aging,
+            rag_aging_runs,
+            rag_aging_changes,
+            rag_decay_policies,
+            rag_set_decay_policy,
+            rag_reset_decay_policy,
+            // Decision patterns
+            rag_mine_decision_patterns,
+            rag_decision_patterns,
+            rag_ask_decision_patterns,
+            // Project context snapshots
+            rag_project_snapshot,
             // RAG stats & feedback (Phase 2)
             rag_stats,
             rag_feedback,
+            // RAG query analytics
+            rag_analytics_top_queries,
+            rag_analytics_knowledge_gaps,
+            rag_analytics_helpful_rate,
+            rag_analytics_unhelpful_items,
+            // Standing queries
+            rag_standing_query_create,
+            rag_standing_query_list,
+            rag_standing_query_set_active,
+            rag_standing_query_delete,
+            rag_standing_query_matches,
             // Knowledge pipeline (Phase 3)
             rag_digest,
+            rag_enqueue_messages,
+            rag_digest_room,
+            rag_digest_queue_status,
             rag_extract_from_digest,
             rag_ingest_action,
             rag_ingest_review,
             rag_get_digests,
             rag_seed_ceo,
             rag_is_seeded,
+            // 정반합 synthesis
+            rag_synthesize,
+            // Personas
+            list_personas,
+            ask_persona,
+            persona_feedback,
+            persona_history,
             // DID identity (Phase 4)
             did_get_identity,
             did_has_identity,
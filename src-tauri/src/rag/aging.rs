@@ -0,0 +1,474 @@
+/// Knowledge Aging — half-life decay of confidence / relevance + TTL expiry
+///
+/// Scores otherwise only move through query feedback, so stale knowledge would
+/// stay authoritative forever. A periodic job (see `lib.rs`) applies
+/// `score × 0.5^(elapsed / half_life)` per `knowledge_type`, where elapsed is
+/// measured from the item's last decay (or creation), so the result does not
+/// depend on how often the job runs.
+///
+/// Items older than their type's TTL get `expires_at` set, which hides them
+/// from search without deleting anything. Every change is written to
+/// `aging_log` under its run for review. CEO seed patterns never age.
+
+use crate::rag::db::RagDb;
+use crate::rag::events::ChangeKind;
+use crate::rag::knowledge;
+use crate::rag::ontology;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Interval of the background aging job
+pub const RUN_INTERVAL_SECS: u64 = 6 * 60 * 60;
+
+/// Half-life for knowledge types without a built-in or stored policy
+pub const DEFAULT_HALF_LIFE_DAYS: f64 = 180.0;
+
+/// Scores never decay below this
+const SCORE_FLOOR: f64 = 0.05;
+
+/// Items are re-decayed at most once a day (keeps the log readable)
+const MIN_DECAY_DAYS: f64 = 1.0;
+
+/// Source types exempt from aging
+const EXEMPT_SOURCE_TYPES: [&str; 1] = ["ceo_pattern_seed"];
+
+// ── Types ──────────────────────────────────────────────
+
+/// Effective decay policy of a knowledge type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DecayPolicy {
+    pub knowledge_type: String,
+    /// `None` = never decays
+    pub half_life_days: Option<f64>,
+    /// `None` = never expires
+    pub ttl_days: Option<f64>,
+    /// "builtin" or "custom"
+    pub source: String,
+}
+
+/// Result of one aging run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgingRun {
+    pub id: String,
+    pub decayed_count: i64,
+    pub expired_count: i64,
+    pub ran_at: String,
+}
+
+/// One logged change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgingChange {
+    pub run_id: String,
+    pub knowledge_id: String,
+    pub content: String,
+    pub knowledge_type: String,
+    pub action: String,
+    pub confidence_before: f64,
+    pub confidence_after: f64,
+    pub relevance_before: f64,
+    pub relevance_after: f64,
+    pub expires_at: Option<String>,
+}
+
+struct AgingCandidate {
+    id: String,
+    knowledge_type: String,
+    confidence: f64,
+    relevance: f64,
+    elapsed_days: f64,
+    age_days: f64,
+    created_at: String,
+}
+
+// ── Policies ───────────────────────────────────────────
+
+/// Built-in policies (from the ontology) merged with stored overrides, sorted by knowledge type.
+pub fn list_policies(db: &RagDb) -> Result<Vec<DecayPolicy>, String> {
+    let mut policies: HashMap<String, DecayPolicy> = ontology::builtin_decay()
+        .map(|(kt, half_life, ttl)| {
+            (
+                kt.to_string(),
+                DecayPolicy {
+                    knowledge_type: kt.to_string(),
+                    half_life_days: Some(half_life),
+                    ttl_days: ttl,
+                    source: "builtin".to_string(),
+                },
+            )
+        })
+        .collect();
+
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare("SELECT knowledge_type, half_life_days, ttl_days FROM decay_policies")
+        .map_err(|e| format!("Prepare decay policies failed: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(DecayPolicy {
+                knowledge_type: row.get(0)?,
+                half_life_days: row.get(1)?,
+                ttl_days: row.get(2)?,
+                source: "custom".to_string(),
+            })
+        })
+        .map_err(|e| format!("Query decay policies failed: {}", e))?;
+    for policy in rows.filter_map(|r| r.ok()) {
+        policies.insert(policy.knowledge_type.clone(), policy);
+    }
+
+    let mut policies: Vec<DecayPolicy> = policies.into_values().collect();
+    policies.sort_by(|a, b| a.knowledge_type.cmp(&b.knowledge_type));
+    Ok(policies)
+}
+
+/// Override the policy of a knowledge type (`None` = never decays / never expires).
+pub fn set_policy(
+    db: &RagDb,
+    knowledge_type: &str,
+    half_life_days: Option<f64>,
+    ttl_days: Option<f64>,
+) -> Result<(), String> {
+    if knowledge_type.trim().is_empty() {
+        return Err("knowledge_type is required".to_string());
+    }
+    if half_life_days.is_some_and(|d| d <= 0.0) || ttl_days.is_some_and(|d| d <= 0.0) {
+        return Err("half_life_days and ttl_days must be positive".to_string());
+    }
+
+    let conn = db.conn();
+    conn.execute(
+        "INSERT INTO decay_policies (knowledge_type, half_life_days, ttl_days)
+         VALUES (?1, ?2, ?3)
+         ON CONFLICT(knowledge_type) DO UPDATE SET
+            half_life_days = excluded.half_life_days,
+            ttl_days = excluded.ttl_days,
+            updated_at = datetime('now')",
+        rusqlite::params![knowledge_type, half_life_days, ttl_days],
+    )
+    .map_err(|e| format!("Save decay policy failed: {}", e))?;
+    Ok(())
+}
+
+/// Drop a stored override, falling back to the built-in policy.
+pub fn reset_policy(db: &RagDb, knowledge_type: &str) -> Result<bool, String> {
+    let conn = db.conn();
+    let removed = conn
+        .execute("DELETE FROM decay_policies WHERE knowledge_type = ?1", [knowledge_type])
+        .map_err(|e| format!("Reset decay policy failed: {}", e))?;
+    Ok(removed > 0)
+}
+
+// ── Aging run ──────────────────────────────────────────
+
+/// Decay scores and expire items past their TTL. Always records a run.
+pub fn run_aging(db: &RagDb) -> Result<AgingRun, String> {
+    let policies: HashMap<String, DecayPolicy> = list_policies(db)?
+        .into_iter()
+        .map(|p| (p.knowledge_type.clone(), p))
+        .collect();
+    let run_id = Uuid::new_v4().to_string();
+    let mut decayed = 0i64;
+    let mut expired_ids = Vec::new();
+
+    {
+        // One transaction: a single commit for the whole run, and a run row
+        // whose counts always match its `aging_log` rows
+        let conn = db.conn();
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(|e| format!("Begin aging run failed: {}", e))?;
+        tx.execute("INSERT INTO aging_runs (id) VALUES (?1)", [&run_id])
+            .map_err(|e| format!("Record aging run failed: {}", e))?;
+
+        let exempt = serde_json::to_string(&EXEMPT_SOURCE_TYPES).unwrap_or_else(|_| "[]".to_string());
+        let mut stmt = tx
+            .prepare(
+                "SELECT id, knowledge_type, confidence, relevance_score,
+                        julianday('now') - julianday(COALESCE(decayed_at, created_at)),
+                        julianday('now') - julianday(created_at),
+                        created_at
+                 FROM knowledge_items
+                 WHERE is_active = 1
+                   AND expires_at IS NULL
+                   AND source_type NOT IN (SELECT value FROM json_each(?1))",
+            )
+            .map_err(|e| format!("Prepare aging candidates failed: {}", e))?;
+        let candidates: Vec<AgingCandidate> = stmt
+            .query_map([exempt], |row| {
+                Ok(AgingCandidate {
+                    id: row.get(0)?,
+                    knowledge_type: row.get(1)?,
+                    confidence: row.get(2)?,
+                    relevance: row.get(3)?,
+                    elapsed_days: row.get::<_, Option<f64>>(4)?.unwrap_or(0.0),
+                    age_days: row.get::<_, Option<f64>>(5)?.unwrap_or(0.0),
+                    created_at: row.get(6)?,
+                })
+            })
+            .map_err(|e| format!("Query aging candidates failed: {}", e))?
+            .filter_map(|r| r.ok())
+            .collect();
+        drop(stmt);
+
+        for item in candidates {
+            let (half_life, ttl) = match policies.get(&item.knowledge_type) {
+                Some(policy) => (policy.half_life_days, policy.ttl_days),
+                None => (Some(DEFAULT_HALF_LIFE_DAYS), None),
+            };
+
+            if let Some(ttl) = ttl.filter(|ttl| item.age_days >= *ttl) {
+                let expires_at: String = tx
+                    .query_row(
+                        "SELECT datetime(?1, ?2)",
+                        rusqlite::params![item.created_at, format!("{:+} days", ttl)],
+                        |row| row.get(0),
+                    )
+                    .map_err(|e| format!("Compute expiry failed: {}", e))?;
+                tx.execute(
+                    "UPDATE knowledge_items SET expires_at = ?2 WHERE id = ?1",
+                    rusqlite::params![item.id, expires_at],
+                )
+                .map_err(|e| format!("Expire item failed: {}", e))?;
+                log_change(&tx, &run_id, &item, "expired", item.confidence, item.relevance, Some(&expires_at))?;
+                expired_ids.push(item.id);
+                continue;
+            }
+
+            let Some(half_life) = half_life else { continue };
+            if item.elapsed_days < MIN_DECAY_DAYS {
+                continue;
+            }
+            let factor = 0.5f64.powf(item.elapsed_days / half_life);
+            let confidence = decay(item.confidence, factor);
+            let relevance = decay(item.relevance, factor);
+
+            // The clock advances even when a score already sits at the floor
+            tx.execute(
+                "UPDATE knowledge_items
+                 SET confidence = ?2, relevance_score = ?3, decayed_at = datetime('now')
+                 WHERE id = ?1",
+                rusqlite::params![item.id, confidence, relevance],
+            )
+            .map_err(|e| format!("Decay item failed: {}", e))?;
+            if confidence != item.confidence || relevance != item.relevance {
+                log_change(&tx, &run_id, &item, "decayed", confidence, relevance, None)?;
+                decayed += 1;
+            }
+        }
+
+        tx.execute(
+            "UPDATE aging_runs SET decayed_count = ?2, expired_count = ?3 WHERE id = ?1",
+            rusqlite::params![run_id, decayed, expired_ids.len() as i64],
+        )
+        .map_err(|e| format!("Update aging run failed: {}", e))?;
+        tx.commit().map_err(|e| format!("Commit aging run failed: {}", e))?;
+    }
+
+    // Expired items drop out of search like deactivated ones
+    for id in &expired_ids {
+        if let Some(item) = knowledge::get_knowledge_item(db, id)? {
+            knowledge::notify_changed(db, &item, ChangeKind::Deactivated);
+        }
+    }
+
+    log::info!("Knowledge aging: {} decayed, {} expired", decayed, expired_ids.len());
+    get_run(db, &run_id)?.ok_or_else(|| "Aging run vanished".to_string())
+}
+
+fn decay(score: f64, factor: f64) -> f64 {
+    if score <= SCORE_FLOOR {
+        return score;
+    }
+    (score * factor).max(SCORE_FLOOR)
+}
+
+fn log_change(
+    conn: &rusqlite::Connection,
+    run_id: &str,
+    item: &AgingCandidate,
+    action: &str,
+    confidence_after: f64,
+    relevance_after: f64,
+    expires_at: Option<&str>,
+) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO aging_log
+            (run_id, knowledge_id, action, confidence_before, confidence_after,
+             relevance_before, relevance_after, expires_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        rusqlite::params![
+            run_id,
+            item.id,
+            action,
+            item.confidence,
+            confidence_after,
+            item.relevance,
+            relevance_after,
+            expires_at,
+        ],
+    )
+    .map_err(|e| format!("Log aging change failed: {}", e))?;
+    Ok(())
+}
+
+// ── Review ─────────────────────────────────────────────
+
+fn get_run(db: &RagDb, run_id: &str) -> Result<Option<AgingRun>, String> {
+    Ok(list_runs_where(db, Some(run_id), 1)?.into_iter().next())
+}
+
+/// Recent aging runs, newest first.
+pub fn list_runs(db: &RagDb, limit: usize) -> Result<Vec<AgingRun>, String> {
+    list_runs_where(db, None, limit)
+}
+
+fn list_runs_where(db: &RagDb, run_id: Option<&str>, limit: usize) -> Result<Vec<AgingRun>, String> {
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, decayed_count, expired_count, ran_at
+             FROM aging_runs
+             WHERE ?1 IS NULL OR id = ?1
+             ORDER BY ran_at DESC, rowid DESC
+             LIMIT ?2",
+        )
+        .map_err(|e| format!("Prepare aging runs failed: {}", e))?;
+    let rows = stmt
+        .query_map(rusqlite::params![run_id, limit as i64], |row| {
+            Ok(AgingRun {
+                id: row.get(0)?,
+                decayed_count: row.get(1)?,
+                expired_count: row.get(2)?,
+                ran_at: row.get(3)?,
+            })
+        })
+        .map_err(|e| format!("Query aging runs failed: {}", e))?;
+    Ok(rows.filter_map(|r| r.ok()).collect())
+}
+
+/// Changes made by one run, or by all runs for one item (newest first).
+pub fn list_changes(
+    db: &RagDb,
+    run_id: Option<&str>,
+    knowledge_id: Option<&str>,
+    limit: usize,
+) -> Result<Vec<AgingChange>, String> {
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare(
+            "SELECT l.run_id, l.knowledge_id, k.content, k.knowledge_type, l.action,
+                    l.confidence_before, l.confidence_after,
+                    l.relevance_before, l.relevance_after, l.expires_at
+             FROM aging_log l
+             JOIN knowledge_items k ON k.id = l.knowledge_id
+             WHERE (?1 IS NULL OR l.run_id = ?1)
+               AND (?2 IS NULL OR l.knowledge_id = ?2)
+             ORDER BY l.id DESC
+             LIMIT ?3",
+        )
+        .map_err(|e| format!("Prepare aging log failed: {}", e))?;
+    let rows = stmt
+        .query_map(rusqlite::params![run_id, knowledge_id, limit as i64], |row| {
+            Ok(AgingChange {
+                run_id: row.get(0)?,
+                knowledge_id: row.get(1)?,
+                content: row.get(2)?,
+                knowledge_type: row.get(3)?,
+                action: row.get(4)?,
+                confidence_before: row.get(5)?,
+                confidence_after: row.get(6)?,
+                relevance_before: row.get(7)?,
+                relevance_after: row.get(8)?,
+                expires_at: row.get(9)?,
+            })
+        })
+        .map_err(|e| format!("Query aging log failed: {}", e))?;
+    Ok(rows.filter_map(|r| r.ok()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::embedding::EMBEDDING_DIM;
+    use crate::rag::knowledge::KnowledgeItem;
+
+    fn setup() -> RagDb {
+        let dir = std::env::temp_dir().join(format!("rag_aging_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        RagDb::open(&dir.join("test.db")).unwrap()
+    }
+
+    fn create(db: &RagDb, knowledge_type: &str, source_type: &str, age_days: i64) -> String {
+        let created_at = (chrono::Utc::now() - chrono::Duration::days(age_days)).to_rfc3339();
+        let item = KnowledgeItem {
+            knowledge_type: knowledge_type.to_string(),
+            source_type: source_type.to_string(),
+            confidence: 0.8,
+            relevance_score: 0.6,
+            user_id: Some("u1".to_string()),
+            project_id: Some("p1".to_string()),
+            created_at: created_at.clone(),
+            updated_at: created_at,
+            ..KnowledgeItem::for_test(&format!("{} {}", knowledge_type, Uuid::new_v4()))
+        };
+        let mut vector = vec![0.0; EMBEDDING_DIM];
+        vector[(age_days as usize) % EMBEDDING_DIM] = 1.0;
+        knowledge::create_knowledge_item(db, &item, &vector).unwrap()
+    }
+
+    #[test]
+    fn test_decay_and_expiry() {
+        let db = setup();
+        let decision = create(&db, "decision_pattern", "manual", 365);
+        let schedule = create(&db, "schedule_change", "manual", 100);
+        let ceo = create(&db, "decision_pattern", "ceo_pattern_seed", 365);
+        let fresh = create(&db, "context", "manual", 0);
+
+        let run = run_aging(&db).unwrap();
+        assert_eq!((run.decayed_count, run.expired_count), (1, 1));
+
+        // One half-life elapsed
+        let item = knowledge::get_knowledge_item(&db, &decision).unwrap().unwrap();
+        assert!((item.confidence - 0.4).abs() < 0.01, "confidence {}", item.confidence);
+        assert!((item.relevance_score - 0.3).abs() < 0.01);
+
+        // Past its 90-day TTL: expired at created_at + 90 days, scores untouched
+        let item = knowledge::get_knowledge_item(&db, &schedule).unwrap().unwrap();
+        assert!(item.expires_at.is_some());
+        assert_eq!(item.confidence, 0.8);
+
+        for id in [&ceo, &fresh] {
+            assert_eq!(knowledge::get_knowledge_item(&db, id).unwrap().unwrap().confidence, 0.8);
+        }
+
+        let changes = list_changes(&db, Some(&run.id), None, 10).unwrap();
+        assert_eq!(changes.len(), 2);
+
+        // The decay clock restarted: an immediate second run changes nothing
+        let again = run_aging(&db).unwrap();
+        assert_eq!((again.decayed_count, again.expired_count), (0, 0));
+        assert_eq!(list_runs(&db, 10).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_policy_overrides() {
+        let db = setup();
+        let pattern = create(&db, "decision_pattern", "manual", 30);
+
+        set_policy(&db, "decision_pattern", None, None).unwrap();
+        assert!(set_policy(&db, "context", Some(0.0), None).is_err());
+        let policy = list_policies(&db)
+            .unwrap()
+            .into_iter()
+            .find(|p| p.knowledge_type == "decision_pattern")
+            .unwrap();
+        assert_eq!((policy.half_life_days, policy.source.as_str()), (None, "custom"));
+
+        assert_eq!(run_aging(&db).unwrap().decayed_count, 0);
+        assert_eq!(knowledge::get_knowledge_item(&db, &pattern).unwrap().unwrap().confidence, 0.8);
+
+        assert!(reset_policy(&db, "decision_pattern").unwrap());
+        assert_eq!(run_aging(&db).unwrap().decayed_count, 1);
+    }
+}
@@ -0,0 +1,404 @@
+/// Query Analytics — Read-side reports over rag_query_log
+///
+/// `knowledge::log_query` records every search; this module turns the log into:
+/// - Top queries (what people ask most)
+/// - Knowledge gaps (zero-result or low-similarity queries — "what the brain doesn't know yet")
+/// - Helpful rate over time (👍 / rated queries per day, week or month)
+/// - Unhelpful items (retrieved often, but rated 👎)
+///
+/// Every report is filterable by project and date range.
+
+use crate::rag::db::RagDb;
+use serde::{Deserialize, Serialize};
+
+/// Default similarity below which a query counts as a knowledge gap
+pub const DEFAULT_GAP_SIMILARITY: f64 = 0.45;
+
+/// Shared filter for all analytics reports.
+///
+/// `since` / `until` accept any SQLite datetime input (e.g. `2026-10-01` or RFC 3339).
+/// `since` is inclusive, `until` is exclusive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalyticsFilter {
+    pub project_id: Option<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub limit: usize,
+}
+
+impl Default for AnalyticsFilter {
+    fn default() -> Self {
+        Self {
+            project_id: None,
+            since: None,
+            until: None,
+            limit: 20,
+        }
+    }
+}
+
+/// A frequently asked query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopQuery {
+    pub query_text: String,
+    pub count: i64,
+    pub avg_result_count: f64,
+    pub avg_top_similarity: f64,
+    pub helpful_count: i64,
+    pub unhelpful_count: i64,
+    pub last_asked_at: String,
+}
+
+/// A query the knowledge base could not answer well
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnowledgeGap {
+    pub query_text: String,
+    pub count: i64,
+    pub zero_result_count: i64,
+    pub best_similarity: f64,
+    pub last_asked_at: String,
+}
+
+/// Time bucket for helpful-rate reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeBucket {
+    Day,
+    Week,
+    Month,
+}
+
+impl TimeBucket {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "day" => Ok(Self::Day),
+            "week" => Ok(Self::Week),
+            "month" => Ok(Self::Month),
+            other => Err(format!("Unknown time bucket: {}", other)),
+        }
+    }
+
+    fn strftime_format(&self) -> &'static str {
+        match self {
+            Self::Day => "%Y-%m-%d",
+            Self::Week => "%Y-W%W",
+            Self::Month => "%Y-%m",
+        }
+    }
+}
+
+/// Helpful rate for one time bucket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HelpfulRatePoint {
+    pub period: String,
+    pub query_count: i64,
+    pub rated_count: i64,
+    pub helpful_count: i64,
+    /// helpful / rated — None when no query in the bucket was rated
+    pub helpful_rate: Option<f64>,
+}
+
+/// A knowledge item that keeps being retrieved but rated unhelpful
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnhelpfulItem {
+    pub knowledge_id: String,
+    pub content: String,
+    pub knowledge_type: String,
+    pub retrieval_count: i64,
+    pub helpful_count: i64,
+    pub unhelpful_count: i64,
+    pub unhelpful_rate: f64,
+}
+
+/// Common WHERE clause for project + date range (params ?1, ?2, ?3).
+const FILTER_CLAUSE: &str = "(?1 IS NULL OR q.project_id = ?1)
+           AND (?2 IS NULL OR q.created_at >= datetime(?2))
+           AND (?3 IS NULL OR q.created_at < datetime(?3))";
+
+/// Most frequent queries (case/whitespace-insensitive grouping).
+pub fn top_queries(db: &RagDb, filter: &AnalyticsFilter) -> Result<Vec<TopQuery>, String> {
+    let conn = db.conn();
+    let sql = format!(
+        "SELECT MIN(q.query_text), COUNT(*),
+                COALESCE(AVG(q.result_count), 0.0),
+                COALESCE(AVG(q.top_similarity), 0.0),
+                SUM(CASE WHEN q.was_helpful = 1 THEN 1 ELSE 0 END),
+                SUM(CASE WHEN q.was_helpful = 0 THEN 1 ELSE 0 END),
+                MAX(q.created_at)
+         FROM rag_query_log q
+         WHERE q.query_text IS NOT NULL AND {}
+         GROUP BY LOWER(TRIM(q.query_text))
+         ORDER BY COUNT(*) DESC, MAX(q.created_at) DESC
+         LIMIT ?4",
+        FILTER_CLAUSE
+    );
+
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| format!("Top queries prepare failed: {}", e))?;
+
+    let results: Vec<TopQuery> = stmt
+        .query_map(
+            rusqlite::params![filter.project_id, filter.since, filter.until, filter.limit as i64],
+            |row| {
+                Ok(TopQuery {
+                    query_text: row.get(0)?,
+                    count: row.get(1)?,
+                    avg_result_count: row.get(2)?,
+                    avg_top_similarity: row.get(3)?,
+                    helpful_count: row.get(4)?,
+                    unhelpful_count: row.get(5)?,
+                    last_asked_at: row.get(6)?,
+                })
+            },
+        )
+        .map_err(|e| format!("Top queries failed: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(results)
+}
+
+/// Queries that returned nothing, or nothing above `similarity_threshold`.
+pub fn knowledge_gaps(
+    db: &RagDb,
+    filter: &AnalyticsFilter,
+    similarity_threshold: f64,
+) -> Result<Vec<KnowledgeGap>, String> {
+    let conn = db.conn();
+    let sql = format!(
+        "SELECT MIN(q.query_text), COUNT(*),
+                SUM(CASE WHEN COALESCE(q.result_count, 0) = 0 THEN 1 ELSE 0 END),
+                COALESCE(MAX(q.top_similarity), 0.0),
+                MAX(q.created_at)
+         FROM rag_query_log q
+         WHERE q.query_text IS NOT NULL AND {}
+           AND (COALESCE(q.result_count, 0) = 0 OR COALESCE(q.top_similarity, 0.0) < ?5)
+         GROUP BY LOWER(TRIM(q.query_text))
+         ORDER BY COUNT(*) DESC, MAX(q.created_at) DESC
+         LIMIT ?4",
+        FILTER_CLAUSE
+    );
+
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| format!("Knowledge gaps prepare failed: {}", e))?;
+
+    let results: Vec<KnowledgeGap> = stmt
+        .query_map(
+            rusqlite::params![
+                filter.project_id,
+                filter.since,
+                filter.until,
+                filter.limit as i64,
+                similarity_threshold,
+            ],
+            |row| {
+                Ok(KnowledgeGap {
+                    query_text: row.get(0)?,
+                    count: row.get(1)?,
+                    zero_result_count: row.get(2)?,
+                    best_similarity: row.get(3)?,
+                    last_asked_at: row.get(4)?,
+                })
+            },
+        )
+        .map_err(|e| format!("Knowledge gaps failed: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(results)
+}
+
+/// Helpful rate per time bucket, oldest first. `filter.limit` caps the number of buckets.
+pub fn helpful_rate(
+    db: &RagDb,
+    filter: &AnalyticsFilter,
+    bucket: TimeBucket,
+) -> Result<Vec<HelpfulRatePoint>, String> {
+    let conn = db.conn();
+    let sql = format!(
+        "SELECT period, query_count, rated_count, helpful_count FROM (
+            SELECT strftime('{}', q.created_at) AS period,
+                   COUNT(*) AS query_count,
+                   SUM(CASE WHEN q.was_helpful IS NOT NULL THEN 1 ELSE 0 END) AS rated_count,
+                   SUM(CASE WHEN q.was_helpful = 1 THEN 1 ELSE 0 END) AS helpful_count
+            FROM rag_query_log q
+            WHERE {}
+            GROUP BY period
+            ORDER BY period DESC
+            LIMIT ?4
+         ) ORDER BY period ASC",
+        bucket.strftime_format(),
+        FILTER_CLAUSE
+    );
+
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| format!("Helpful rate prepare failed: {}", e))?;
+
+    let results: Vec<HelpfulRatePoint> = stmt
+        .query_map(
+            rusqlite::params![filter.project_id, filter.since, filter.until, filter.limit as i64],
+            |row| {
+                let rated_count: i64 = row.get(2)?;
+                let helpful_count: i64 = row.get(3)?;
+                Ok(HelpfulRatePoint {
+                    period: row.get(0)?,
+                    query_count: row.get(1)?,
+                    rated_count,
+                    helpful_count,
+                    helpful_rate: if rated_count > 0 {
+                        Some(helpful_count as f64 / rated_count as f64)
+                    } else {
+                        None
+                    },
+                })
+            },
+        )
+        .map_err(|e| format!("Helpful rate failed: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(results)
+}
+
+/// Items most often retrieved by queries that were rated unhelpful.
+pub fn unhelpful_items(db: &RagDb, filter: &AnalyticsFilter) -> Result<Vec<UnhelpfulItem>, String> {
+    let conn = db.conn();
+    let sql = format!(
+        "SELECT ki.id, ki.content, ki.knowledge_type,
+                COUNT(*) AS retrieval_count,
+                SUM(CASE WHEN q.was_helpful = 1 THEN 1 ELSE 0 END) AS helpful_count,
+                SUM(CASE WHEN q.was_helpful = 0 THEN 1 ELSE 0 END) AS unhelpful_count
+         FROM rag_query_log q, json_each(q.retrieved_item_ids) j
+         JOIN knowledge_items ki ON ki.id = j.value
+         WHERE {}
+         GROUP BY ki.id
+         HAVING unhelpful_count > 0
+         ORDER BY unhelpful_count DESC, retrieval_count DESC
+         LIMIT ?4",
+        FILTER_CLAUSE
+    );
+
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| format!("Unhelpful items prepare failed: {}", e))?;
+
+    let results: Vec<UnhelpfulItem> = stmt
+        .query_map(
+            rusqlite::params![filter.project_id, filter.since, filter.until, filter.limit as i64],
+            |row| {
+                let helpful_count: i64 = row.get(4)?;
+                let unhelpful_count: i64 = row.get(5)?;
+                Ok(UnhelpfulItem {
+                    knowledge_id: row.get(0)?,
+                    content: row.get(1)?,
+                    knowledge_type: row.get(2)?,
+                    retrieval_count: row.get(3)?,
+                    helpful_count,
+                    unhelpful_count,
+                    unhelpful_rate: unhelpful_count as f64
+                        / (helpful_count + unhelpful_count).max(1) as f64,
+                })
+            },
+        )
+        .map_err(|e| format!("Unhelpful items failed: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::knowledge;
+
+    fn setup_db() -> RagDb {
+        let path = std::env::temp_dir().join(format!("rag_analytics_{}.db", uuid::Uuid::new_v4()));
+        RagDb::open(&path).unwrap()
+    }
+
+    fn insert_item(db: &RagDb, id: &str) {
+        let conn = db.conn();
+        conn.execute(
+            "INSERT INTO knowledge_items (id, content) VALUES (?1, ?2)",
+            rusqlite::params![id, format!("content {}", id)],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_top_queries_and_gaps() {
+        let db = setup_db();
+        let ids = vec!["k1".to_string()];
+        knowledge::log_query(&db, "촬영 일정", "all", Some("p1"), &ids, 0.8).unwrap();
+        knowledge::log_query(&db, "촬영 일정 ", "all", Some("p1"), &ids, 0.7).unwrap();
+        knowledge::log_query(&db, "계약서 양식", "all", Some("p1"), &[], 0.0).unwrap();
+        knowledge::log_query(&db, "촬영 일정", "all", Some("p2"), &ids, 0.9).unwrap();
+
+        let filter = AnalyticsFilter {
+            project_id: Some("p1".to_string()),
+            ..Default::default()
+        };
+        let top = top_queries(&db, &filter).unwrap();
+        assert_eq!(top[0].count, 2);
+        assert_eq!(top.len(), 2);
+
+        let gaps = knowledge_gaps(&db, &filter, DEFAULT_GAP_SIMILARITY).unwrap();
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].query_text, "계약서 양식");
+        assert_eq!(gaps[0].zero_result_count, 1);
+    }
+
+    #[test]
+    fn test_helpful_rate_and_unhelpful_items() {
+        let db = setup_db();
+        insert_item(&db, "k1");
+        insert_item(&db, "k2");
+
+        let q1 = knowledge::log_query(&db, "a", "all", None, &["k1".to_string()], 0.8).unwrap();
+        let q2 = knowledge::log_query(&db, "b", "all", None, &["k1".to_string(), "k2".to_string()], 0.8).unwrap();
+        knowledge::log_query(&db, "c", "all", None, &[], 0.0).unwrap();
+        knowledge::record_query_feedback(&db, &q1, false).unwrap();
+        knowledge::record_query_feedback(&db, &q2, true).unwrap();
+
+        let points = helpful_rate(&db, &AnalyticsFilter::default(), TimeBucket::Day).unwrap();
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].query_count, 3);
+        assert_eq!(points[0].rated_count, 2);
+        assert_eq!(points[0].helpful_rate, Some(0.5));
+
+        let items = unhelpful_items(&db, &AnalyticsFilter::default()).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].knowledge_id, "k1");
+        assert_eq!(items[0].retrieval_count, 2);
+        assert_eq!(items[0].unhelpful_count, 1);
+    }
+
+    #[test]
+    fn test_date_range_filter() {
+        let db = setup_db();
+        knowledge::log_query(&db, "old", "all", None, &[], 0.0).unwrap();
+        {
+            let conn = db.conn();
+            conn.execute(
+                "UPDATE rag_query_log SET created_at = '2025-01-15 10:00:00'",
+                [],
+            )
+            .unwrap();
+        }
+        knowledge::log_query(&db, "new", "all", None, &[], 0.0).unwrap();
+
+        let filter = AnalyticsFilter {
+            since: Some("2026-01-01".to_string()),
+            ..Default::default()
+        };
+        let top = top_queries(&db, &filter).unwrap();
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].query_text, "new");
+
+        assert!(TimeBucket::parse("quarter").is_err());
+    }
+}
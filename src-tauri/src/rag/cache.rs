@@ -0,0 +1,279 @@
+/// Semantic Context Cache — Short-lived cache for rag_get_context
+///
+/// `rag_get_context` runs for nearly every chat turn (1 embedding + 3 searches).
+/// Repeated or paraphrased questions within a session reuse the previous context:
+///
+///   hit = same scope parameters AND cosine(query, cached query) >= similarity_threshold
+///         AND entry younger than ttl
+///
+/// Entries are invalidated on `RagEvent::KnowledgeChanged` when the changed
+/// item could appear in the entry's results (global/role items affect every entry),
+/// and on `RagEvent::SnapshotInvalidated` for the project whose snapshot they embed.
+
+use crate::rag::embedding::cosine_similarity;
+use crate::rag::events::{KnowledgeChange, RagEvent};
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Default entry lifetime
+pub const DEFAULT_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Default query similarity for a cache hit
+pub const DEFAULT_SIMILARITY_THRESHOLD: f32 = 0.95;
+
+/// Default maximum number of cached contexts
+pub const DEFAULT_MAX_ENTRIES: usize = 64;
+
+/// Scope parameters that must match exactly for a cache hit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextKey {
+    pub scope: String,
+    pub user_id: Option<String>,
+    pub project_id: Option<String>,
+    pub role_tag: Option<String>,
+    /// Output shaping (budget/format) — different shapes never share an entry
+    pub shape: String,
+}
+
+struct CacheEntry {
+    key: ContextKey,
+    embedding: Vec<f32>,
+    context: String,
+    created_at: Instant,
+}
+
+/// Hit/miss counters (for diagnostics)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub invalidations: u64,
+}
+
+pub struct ContextCache {
+    entries: Mutex<Vec<CacheEntry>>,
+    stats: Mutex<CacheStats>,
+    ttl: Duration,
+    similarity_threshold: f32,
+    max_entries: usize,
+}
+
+impl Default for ContextCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_TTL, DEFAULT_SIMILARITY_THRESHOLD, DEFAULT_MAX_ENTRIES)
+    }
+}
+
+impl ContextCache {
+    pub fn new(ttl: Duration, similarity_threshold: f32, max_entries: usize) -> Self {
+        Self {
+            entries: Mutex::new(Vec::new()),
+            stats: Mutex::new(CacheStats::default()),
+            ttl,
+            similarity_threshold,
+            max_entries,
+        }
+    }
+
+    /// Look up a cached context for a semantically similar query with the same scope.
+    pub fn get(&self, key: &ContextKey, embedding: &[f32]) -> Option<String> {
+        let mut entries = self.entries.lock().expect("Cache lock poisoned");
+        entries.retain(|e| e.created_at.elapsed() < self.ttl);
+
+        let best = entries
+            .iter()
+            .filter(|e| e.key == *key && e.embedding.len() == embedding.len())
+            .map(|e| (cosine_similarity(&e.embedding, embedding), e))
+            .filter(|(sim, _)| *sim >= self.similarity_threshold)
+            .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(_, e)| e.context.clone());
+
+        let mut stats = self.stats.lock().expect("Cache stats lock poisoned");
+        if best.is_some() {
+            stats.hits += 1;
+        } else {
+            stats.misses += 1;
+        }
+        best
+    }
+
+    /// Store a freshly built context (evicts the oldest entry when full).
+    pub fn put(&self, key: ContextKey, embedding: Vec<f32>, context: String) {
+        let mut entries = self.entries.lock().expect("Cache lock poisoned");
+        entries.retain(|e| e.created_at.elapsed() < self.ttl);
+        if entries.len() >= self.max_entries && !entries.is_empty() {
+            entries.remove(0);
+        }
+        entries.push(CacheEntry {
+            key,
+            embedding,
+            context,
+            created_at: Instant::now(),
+        });
+    }
+
+    /// Drop entries that the changed item could affect.
+    pub fn invalidate(&self, change: &KnowledgeChange) {
+        let mut entries = self.entries.lock().expect("Cache lock poisoned");
+        let before = entries.len();
+        entries.retain(|e| !is_affected(&e.key, change));
+        let removed = before - entries.len();
+
+        if removed > 0 {
+            self.stats.lock().expect("Cache stats lock poisoned").invalidations += removed as u64;
+            log::debug!(
+                "Context cache: invalidated {} entries after change to {}",
+                removed,
+                change.knowledge_id
+            );
+        }
+    }
+
+    /// Drop every entry built for `project_id` (its snapshot changed).
+    pub fn invalidate_project(&self, project_id: &str) {
+        let mut entries = self.entries.lock().expect("Cache lock poisoned");
+        let before = entries.len();
+        entries.retain(|e| e.key.project_id.as_deref() != Some(project_id));
+        let removed = before - entries.len();
+
+        if removed > 0 {
+            self.stats.lock().expect("Cache stats lock poisoned").invalidations += removed as u64;
+            log::debug!(
+                "Context cache: invalidated {} entries after snapshot change in {}",
+                removed,
+                project_id
+            );
+        }
+    }
+
+    /// Listener entry point for `RagDb::subscribe`.
+    pub fn on_event(&self, event: &RagEvent) {
+        match event {
+            RagEvent::KnowledgeChanged(change) => self.invalidate(change),
+            RagEvent::SnapshotInvalidated(project_id) => self.invalidate_project(project_id),
+            _ => {}
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let mut stats = self.stats.lock().expect("Cache stats lock poisoned").clone();
+        stats.entries = self.entries.lock().expect("Cache lock poisoned").len();
+        stats
+    }
+}
+
+/// Conservative visibility check mirroring the 3-pass search scopes.
+fn is_affected(key: &ContextKey, change: &KnowledgeChange) -> bool {
+    // The owner sees their own items under scope "all" whatever their scope or project
+    if change.user_id.is_some() && key.user_id == change.user_id {
+        return true;
+    }
+    match change.scope.as_str() {
+        "personal" => change.user_id.is_none() || key.user_id == change.user_id,
+        "team" => change.project_id.is_none() || key.project_id == change.project_id,
+        _ => true, // global / role items are visible to every query
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::events::ChangeKind;
+
+    fn key(project_id: Option<&str>) -> ContextKey {
+        ContextKey {
+            scope: "all".to_string(),
+            user_id: Some("u1".to_string()),
+            project_id: project_id.map(|s| s.to_string()),
+            role_tag: None,
+            shape: "800".to_string(),
+        }
+    }
+
+    fn change(scope: &str, project_id: Option<&str>) -> KnowledgeChange {
+        KnowledgeChange {
+            knowledge_id: "k1".to_string(),
+            kind: ChangeKind::Created,
+            scope: scope.to_string(),
+            user_id: Some("u2".to_string()),
+            project_id: project_id.map(|s| s.to_string()),
+        }
+    }
+
+    fn own_change(scope: &str, project_id: Option<&str>) -> KnowledgeChange {
+        KnowledgeChange {
+            user_id: Some("u1".to_string()),
+            ..change(scope, project_id)
+        }
+    }
+
+    #[test]
+    fn test_hit_requires_similar_query_and_same_scope() {
+        let cache = ContextCache::default();
+        cache.put(key(Some("p1")), vec![1.0, 0.0, 0.0], "ctx".to_string());
+
+        assert_eq!(cache.get(&key(Some("p1")), &[0.99, 0.05, 0.0]), Some("ctx".to_string()));
+        assert_eq!(cache.get(&key(Some("p1")), &[0.0, 1.0, 0.0]), None);
+        assert_eq!(cache.get(&key(Some("p2")), &[1.0, 0.0, 0.0]), None);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 2));
+    }
+
+    #[test]
+    fn test_invalidation_by_scope() {
+        let cache = ContextCache::default();
+        cache.put(key(Some("p1")), vec![1.0, 0.0], "p1".to_string());
+        cache.put(key(Some("p2")), vec![1.0, 0.0], "p2".to_string());
+
+        // Team item in p1 only affects p1 entries; another user's personal item affects none
+        cache.invalidate(&change("team", Some("p1")));
+        cache.invalidate(&change("personal", None));
+        assert_eq!(cache.get(&key(Some("p1")), &[1.0, 0.0]), None);
+        assert_eq!(cache.get(&key(Some("p2")), &[1.0, 0.0]), Some("p2".to_string()));
+
+        // Global items affect everything
+        cache.on_event(&RagEvent::KnowledgeChanged(change("global", None)));
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn test_invalidation_by_owner() {
+        let cache = ContextCache::default();
+        cache.put(key(Some("p1")), vec![1.0, 0.0], "p1".to_string());
+
+        // The user's own team item in another project is visible to them under scope "all"
+        cache.invalidate(&own_change("team", Some("p2")));
+        assert_eq!(cache.get(&key(Some("p1")), &[1.0, 0.0]), None);
+
+        cache.put(key(Some("p1")), vec![1.0, 0.0], "p1".to_string());
+        cache.invalidate(&own_change("personal", None));
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn test_invalidation_by_snapshot() {
+        let cache = ContextCache::default();
+        cache.put(key(Some("p1")), vec![1.0, 0.0], "p1".to_string());
+        cache.put(key(Some("p2")), vec![1.0, 0.0], "p2".to_string());
+
+        cache.on_event(&RagEvent::SnapshotInvalidated("p1".to_string()));
+        assert_eq!(cache.get(&key(Some("p1")), &[1.0, 0.0]), None);
+        assert_eq!(cache.get(&key(Some("p2")), &[1.0, 0.0]), Some("p2".to_string()));
+    }
+
+    #[test]
+    fn test_ttl_and_capacity() {
+        let expired = ContextCache::new(Duration::from_millis(0), 0.9, 4);
+        expired.put(key(None), vec![1.0], "ctx".to_string());
+        assert_eq!(expired.get(&key(None), &[1.0]), None);
+
+        let small = ContextCache::new(DEFAULT_TTL, 0.9, 1);
+        small.put(key(Some("p1")), vec![1.0], "old".to_string());
+        small.put(key(Some("p2")), vec![1.0], "new".to_string());
+        assert_eq!(small.stats().entries, 1);
+        assert_eq!(small.get(&key(Some("p2")), &[1.0]), Some("new".to_string()));
+    }
+}
@@ -0,0 +1,947 @@
+/// Contradiction Detection — Conflict chronicler (Stage 1)
+///
+/// Finds pairs of active, highly similar knowledge items in the same project
+/// whose content disagrees, e.g. "예산 3000만원 확정" vs "예산 5000만원으로 증액":
+///
+///   candidate = same project_id AND cosine(a, b) >= threshold
+///               AND not linked as supersedes / duplicate_of
+///   conflict  = different amounts / dates / percentages,
+///               confirmed vs rejected outcome, or opposite polarity (취소, 보류, …)
+///
+/// Runs on every create / content update (heuristics only) and as a batch job,
+/// where an optional LLM check confirms heuristic hits and reviews very
+/// similar pairs the heuristics cannot judge. Findings are stored in
+/// `knowledge_conflicts`, linked `contradicts`, and published as
+/// `RagEvent::ConflictDetected`.
+
+use crate::rag::db::RagDb;
+use crate::rag::embedding::{blob_to_vector, cosine_similarity};
+use crate::rag::events::RagEvent;
+use crate::rag::knowledge::KnowledgeItem;
+use crate::rag::llm::{LlmClient, LlmTask};
+use crate::rag::policy::{DataLabels, PolicyViolation};
+use crate::rag::relations::{self, RelationType};
+use crate::rag::structured;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+use uuid::Uuid;
+
+/// Minimum similarity for two items to be compared
+pub const DEFAULT_CONFLICT_SIMILARITY: f32 = 0.80;
+
+/// Polarity conflicts need near-identical wording
+const POLARITY_SIMILARITY: f32 = 0.90;
+
+/// Pairs without a heuristic signal are sent to the LLM above this similarity
+const LLM_REVIEW_SIMILARITY: f32 = 0.90;
+
+/// Maximum unflagged pairs reviewed by the LLM per project
+const MAX_LLM_REVIEWS: usize = 10;
+
+/// Max tokens for the LLM verdict
+const CONFLICT_MAX_TOKENS: u32 = 256;
+
+const CONFLICT_SYSTEM_PROMPT: &str = r#"You compare two knowledge records from the same Korean creative agency project.
+Decide whether they CONTRADICT each other (they cannot both be true at the same time, e.g. different budget, date, vendor, or one approves what the other rejects).
+Records that merely add detail, or describe different topics, do not contradict.
+
+Respond ONLY with JSON: {"conflict": true|false, "reason": "한 문장 한국어 설명"}"#;
+
+/// Words that flip the meaning of an otherwise identical statement
+const NEGATION_MARKERS: [&str; 10] = ["않", "취소", "불가", "보류", "중단", "철회", "반려", "거절", "금지", "하지 말"];
+
+/// Words that make a bare "3억" / "500만" (no 원) read as money
+const MONEY_WORDS: [&str; 12] = ["예산", "비용", "금액", "단가", "견적", "계약금", "매출", "수수료", "가격", "대금", "제작비", "출연료"];
+
+/// What disagrees between the two items
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    Amount,
+    Date,
+    Percentage,
+    Outcome,
+    Polarity,
+    /// Judged by the LLM without a heuristic signal
+    Semantic,
+}
+
+impl ConflictKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Amount => "amount",
+            Self::Date => "date",
+            Self::Percentage => "percentage",
+            Self::Outcome => "outcome",
+            Self::Polarity => "polarity",
+            Self::Semantic => "semantic",
+        }
+    }
+}
+
+/// Pair awaiting a decision (not yet stored)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictCandidate {
+    /// Older item
+    pub item_a: String,
+    /// Newer item
+    pub item_b: String,
+    pub content_a: String,
+    pub content_b: String,
+    pub project_id: String,
+    pub similarity: f32,
+    pub kind: ConflictKind,
+    pub reason: String,
+}
+
+/// Stored conflict
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnowledgeConflict {
+    pub id: String,
+    pub item_a: String,
+    pub item_b: String,
+    pub content_a: String,
+    pub content_b: String,
+    pub project_id: Option<String>,
+    pub similarity: f64,
+    pub conflict_kind: String,
+    pub reason: String,
+    pub detected_by: String,
+    pub status: String,
+    pub resolution_note: Option<String>,
+    pub detected_at: String,
+    pub resolved_at: Option<String>,
+}
+
+/// Heuristic scan of one project
+#[derive(Debug, Clone, Default)]
+pub struct ProjectScan {
+    /// Pairs with a heuristic conflict signal
+    pub flagged: Vec<ConflictCandidate>,
+    /// Very similar pairs without a signal (for the optional LLM review)
+    pub unflagged: Vec<ConflictCandidate>,
+}
+
+/// Ingest hook: compare a new or edited item against its project (heuristics only).
+pub fn check_item(db: &RagDb, item: &KnowledgeItem, embedding: &[f32]) -> Result<Vec<KnowledgeConflict>, String> {
+    let Some(project_id) = item.project_id.as_deref() else {
+        return Ok(vec![]);
+    };
+    if !item.is_active {
+        return Ok(vec![]);
+    }
+
+    let candidates = {
+        let conn = db.conn();
+        let others = load_project_items(&conn, project_id)?;
+        let excluded = excluded_pairs(&conn, project_id)?;
+        let this = PairItem {
+            id: item.id.clone(),
+            content: item.content.clone(),
+            outcome: item.outcome.clone(),
+            vector: embedding.to_vec(),
+        };
+
+        others
+            .iter()
+            .filter(|other| other.id != this.id && !excluded.contains(&ordered(&this.id, &other.id)))
+            .filter_map(|other| {
+                let similarity = cosine_similarity(&this.vector, &other.vector);
+                if similarity < DEFAULT_CONFLICT_SIMILARITY {
+                    return None;
+                }
+                let (kind, reason) = compare(other, &this, similarity)?;
+                Some(candidate(other, &this, project_id, similarity, kind, reason))
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut recorded = Vec::new();
+    for c in &candidates {
+        if let Some(conflict) = record(db, c, "heuristic")? {
+            recorded.push(conflict);
+        }
+    }
+    Ok(recorded)
+}
+
+/// Heuristic scan over all pairs of a project.
+pub fn scan_project(db: &RagDb, project_id: &str, threshold: f32) -> Result<ProjectScan, String> {
+    let conn = db.conn();
+    let items = load_project_items(&conn, project_id)?;
+    let excluded = excluded_pairs(&conn, project_id)?;
+    drop(conn);
+
+    let mut scan = ProjectScan::default();
+    // Items are ordered oldest first, so `a` is always the older one
+    for (i, a) in items.iter().enumerate() {
+        for b in &items[i + 1..] {
+            if excluded.contains(&ordered(&a.id, &b.id)) {
+                continue;
+            }
+            let similarity = cosine_similarity(&a.vector, &b.vector);
+            if similarity < threshold {
+                continue;
+            }
+            match compare(a, b, similarity) {
+                Some((kind, reason)) => scan.flagged.push(candidate(a, b, project_id, similarity, kind, reason)),
+                None if similarity >= LLM_REVIEW_SIMILARITY => scan.unflagged.push(candidate(
+                    a,
+                    b,
+                    project_id,
+                    similarity,
+                    ConflictKind::Semantic,
+                    String::new(),
+                )),
+                None => {}
+            }
+        }
+    }
+
+    scan.unflagged
+        .sort_by(|x, y| y.similarity.partial_cmp(&x.similarity).unwrap_or(std::cmp::Ordering::Equal));
+    scan.unflagged.truncate(MAX_LLM_REVIEWS);
+    Ok(scan)
+}
+
+/// Projects with active knowledge (for batch runs over everything).
+pub fn project_ids(db: &RagDb) -> Result<Vec<String>, String> {
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare("SELECT DISTINCT project_id FROM knowledge_items WHERE is_active = 1 AND project_id IS NOT NULL")
+        .map_err(|e| format!("Prepare project ids failed: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| format!("Query project ids failed: {}", e))?;
+    Ok(rows.filter_map(|r| r.ok()).collect())
+}
+
+/// Batch job (heuristics only): scan and record conflicts in one or all projects.
+pub fn detect(db: &RagDb, project_id: Option<&str>, threshold: f32) -> Result<Vec<KnowledgeConflict>, String> {
+    let projects = match project_id {
+        Some(p) => vec![p.to_string()],
+        None => project_ids(db)?,
+    };
+
+    let mut recorded = Vec::new();
+    for project in &projects {
+        for c in scan_project(db, project, threshold)?.flagged {
+            if let Some(conflict) = record(db, &c, "heuristic")? {
+                recorded.push(conflict);
+            }
+        }
+    }
+    Ok(recorded)
+}
+
+/// Batch job with LLM review: the model confirms heuristic hits (rejected ones
+/// are not stored) and judges very similar pairs without a heuristic signal.
+///
+/// `make_llm` builds the client for each pair from its project and the labels of
+/// both items, so outbound rules, redaction and budgets apply per project. In an
+/// all-projects scan a pair the policy refuses is skipped; with a `project_id`
+/// the violation is returned.
+pub async fn detect_with_llm<F>(
+    db: &RagDb,
+    project_id: Option<&str>,
+    threshold: f32,
+    make_llm: F,
+) -> Result<Vec<KnowledgeConflict>, String>
+where
+    F: Fn(&str, DataLabels) -> Result<LlmClient, String>,
+{
+    let projects = match project_id {
+        Some(p) => vec![p.to_string()],
+        None => project_ids(db)?,
+    };
+
+    let mut recorded = Vec::new();
+    for project in &projects {
+        let scan = scan_project(db, project, threshold)?;
+        for mut c in scan.flagged.into_iter().chain(scan.unflagged) {
+            let llm = make_llm(project, DataLabels::of_items(db, &[&c.item_a, &c.item_b])?)?;
+            let (conflict, reason) = match llm_check(&c.content_a, &c.content_b, &llm).await {
+                Ok(verdict) => verdict,
+                Err(e) if project_id.is_none() && PolicyViolation::parse(&e).is_some() => {
+                    log::warn!("Conflict review skipped in project {}: {}", project, e);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            if !conflict {
+                continue;
+            }
+            if c.kind == ConflictKind::Semantic && !reason.is_empty() {
+                c.reason = reason;
+            }
+            if let Some(stored) = record(db, &c, "llm")? {
+                recorded.push(stored);
+            }
+        }
+    }
+    Ok(recorded)
+}
+
+/// Ask the LLM whether two records contradict. Returns (conflict, reason).
+pub async fn llm_check(content_a: &str, content_b: &str, llm: &LlmClient) -> Result<(bool, String), String> {
+    let user_prompt = format!("[A]\n{}\n\n[B]\n{}", content_a, content_b);
+    let schema = serde_json::json!({
+        "type": "object",
+        "properties": {
+            "conflict": { "type": "boolean" },
+            "reason": { "type": "string" }
+        },
+        "required": ["conflict", "reason"]
+    });
+    let verdict = llm
+        .complete_json(LlmTask::Conflict, CONFLICT_SYSTEM_PROMPT, &user_prompt, CONFLICT_MAX_TOKENS, schema)
+        .await?;
+
+    let conflict = match &verdict["conflict"] {
+        serde_json::Value::Bool(b) => *b,
+        serde_json::Value::String(s) => s.trim().eq_ignore_ascii_case("true"),
+        _ => false,
+    };
+    Ok((conflict, structured::optional_str(&verdict, "reason").unwrap_or_default()))
+}
+
+/// Store a conflict, link the newer item `contradicts` the older one and publish it.
+///
+/// Returns `None` if the pair was already recorded (in any status).
+pub fn record(db: &RagDb, c: &ConflictCandidate, detected_by: &str) -> Result<Option<KnowledgeConflict>, String> {
+    let (first, second) = ordered(&c.item_a, &c.item_b);
+    let id = Uuid::new_v4().to_string();
+
+    let inserted = {
+        let conn = db.conn();
+        conn.execute(
+            "INSERT OR IGNORE INTO knowledge_conflicts
+                (id, item_a, item_b, project_id, similarity, conflict_kind, reason, detected_by)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![
+                id,
+                first,
+                second,
+                c.project_id,
+                c.similarity as f64,
+                c.kind.as_str(),
+                c.reason,
+                detected_by,
+            ],
+        )
+        .map_err(|e| format!("Record conflict failed: {}", e))?
+    };
+    if inserted == 0 {
+        return Ok(None);
+    }
+
+    relations::link(db, &c.item_b, &c.item_a, RelationType::Contradicts, Some(&c.reason), Some("conflict_detector"))?;
+
+    let conflict = get_conflict(db, &id)?.ok_or_else(|| format!("Conflict not found: {}", id))?;
+    log::info!("Knowledge conflict {} ↔ {}: {}", c.item_a, c.item_b, c.reason);
+    db.emit(RagEvent::ConflictDetected(conflict.clone()));
+    Ok(Some(conflict))
+}
+
+/// Conflicts, optionally filtered by project and status (newest first).
+pub fn list_conflicts(
+    db: &RagDb,
+    project_id: Option<&str>,
+    status: Option<&str>,
+) -> Result<Vec<KnowledgeConflict>, String> {
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} WHERE (?1 IS NULL OR c.project_id = ?1) AND (?2 IS NULL OR c.status = ?2)
+               ORDER BY c.detected_at DESC, c.rowid DESC",
+            SELECT_CONFLICT
+        ))
+        .map_err(|e| format!("Prepare conflicts failed: {}", e))?;
+
+    let rows = stmt
+        .query_map(rusqlite::params![project_id, status], row_to_conflict)
+        .map_err(|e| format!("Query conflicts failed: {}", e))?;
+
+    Ok(rows.filter_map(|r| r.ok()).collect())
+}
+
+/// A single conflict.
+pub fn get_conflict(db: &RagDb, id: &str) -> Result<Option<KnowledgeConflict>, String> {
+    let conn = db.conn();
+    let result = conn.query_row(&format!("{} WHERE c.id = ?1", SELECT_CONFLICT), [id], row_to_conflict);
+
+    match result {
+        Ok(c) => Ok(Some(c)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(format!("Get conflict failed: {}", e)),
+    }
+}
+
+/// Mark a conflict resolved / dismissed (or reopen it).
+pub fn set_status(db: &RagDb, id: &str, status: &str, note: Option<&str>) -> Result<KnowledgeConflict, String> {
+    if !["open", "resolved", "dismissed"].contains(&status) {
+        return Err(format!("Invalid conflict status: {}", status));
+    }
+
+    {
+        let conn = db.conn();
+        let updated = conn
+            .execute(
+                "UPDATE knowledge_conflicts SET
+                    status = ?2,
+                    resolution_note = COALESCE(?3, resolution_note),
+                    resolved_at = CASE WHEN ?2 = 'open' THEN NULL ELSE datetime('now') END
+                 WHERE id = ?1",
+                rusqlite::params![id, status, note],
+            )
+            .map_err(|e| format!("Update conflict failed: {}", e))?;
+        if updated == 0 {
+            return Err(format!("Conflict not found: {}", id));
+        }
+    }
+
+    get_conflict(db, id)?.ok_or_else(|| format!("Conflict not found: {}", id))
+}
+
+// ── Heuristics ──────────────────────────────────────────
+
+struct PairItem {
+    id: String,
+    content: String,
+    outcome: Option<String>,
+    vector: Vec<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum QuantityKind {
+    Money,
+    Percent,
+    Date,
+}
+
+impl QuantityKind {
+    /// Same value; a date without a year matches that day in any year.
+    fn same(self, a: i64, b: i64) -> bool {
+        match self {
+            QuantityKind::Date => a % 10_000 == b % 10_000 && (a < 10_000 || b < 10_000 || a == b),
+            _ => a == b,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Quantity {
+    kind: QuantityKind,
+    /// KRW for money, basis points for percent, year * 10000 + month * 100 + day
+    /// for dates (year 0 when not given)
+    value: i64,
+}
+
+/// Why `a` and `b` conflict, if they do.
+fn compare(a: &PairItem, b: &PairItem, similarity: f32) -> Option<(ConflictKind, String)> {
+    let qa = extract_quantities(&a.content);
+    let qb = extract_quantities(&b.content);
+
+    for (kind, conflict_kind, label) in [
+        (QuantityKind::Money, ConflictKind::Amount, "금액"),
+        (QuantityKind::Date, ConflictKind::Date, "날짜"),
+        (QuantityKind::Percent, ConflictKind::Percentage, "비율"),
+    ] {
+        let va: BTreeSet<i64> = qa.iter().filter(|q| q.kind == kind).map(|q| q.value).collect();
+        let vb: BTreeSet<i64> = qb.iter().filter(|q| q.kind == kind).map(|q| q.value).collect();
+        let overlap = va.iter().any(|x| vb.iter().any(|y| kind.same(*x, *y)));
+        if !va.is_empty() && !vb.is_empty() && !overlap {
+            let show = |values: &BTreeSet<i64>| {
+                values.iter().map(|v| format_quantity(kind, *v)).collect::<Vec<_>>().join(", ")
+            };
+            return Some((conflict_kind, format!("{} 불일치: {} ↔ {}", label, show(&va), show(&vb))));
+        }
+    }
+
+    match (a.outcome.as_deref(), b.outcome.as_deref()) {
+        (Some("confirmed"), Some("rejected")) | (Some("rejected"), Some("confirmed")) => {
+            return Some((ConflictKind::Outcome, "같은 결정이 확정/기각으로 엇갈림".to_string()));
+        }
+        _ => {}
+    }
+
+    let negated = |text: &str| NEGATION_MARKERS.iter().any(|m| text.contains(m));
+    if similarity >= POLARITY_SIMILARITY && negated(&a.content) != negated(&b.content) {
+        return Some((ConflictKind::Polarity, "같은 내용에 대해 진행/취소(보류)가 엇갈림".to_string()));
+    }
+
+    None
+}
+
+/// Amounts (3000만원, 1억 5000만원, 5,000원), percentages (10%) and dates (3월 15일, 2026-03-15).
+fn extract_quantities(text: &str) -> Vec<Quantity> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = Vec::new();
+    // Amounts without 원 count only next to a money word ("관객 3만 명" is not money)
+    let money_context = MONEY_WORDS.iter().any(|w| text.contains(w));
+    let bare_money = |out: &mut Vec<Quantity>, value: i64| {
+        if money_context {
+            out.push(Quantity { kind: QuantityKind::Money, value });
+        }
+    };
+    // Money tokens like "1억" without 원 may continue with "5000만원"
+    let mut pending_money: Option<i64> = None;
+    let mut i = 0;
+
+    while i < chars.len() {
+        if !chars[i].is_ascii_digit() {
+            if !chars[i].is_whitespace() {
+                if let Some(v) = pending_money.take() {
+                    bare_money(&mut out, v);
+                }
+            }
+            i += 1;
+            continue;
+        }
+
+        // ISO-like date: 2026-03-15 / 2026.03.15 / 2026/03/15
+        if let Some((value, next)) = read_iso_date(&chars, i) {
+            out.push(Quantity { kind: QuantityKind::Date, value });
+            i = next;
+            continue;
+        }
+
+        let (number, next) = read_number(&chars, i);
+        let suffix: String = chars[next..]
+            .iter()
+            .take_while(|c| c.is_alphabetic() || **c == '%')
+            .collect();
+        i = next + suffix.chars().count();
+
+        if suffix.starts_with('%') || suffix.starts_with("퍼센트") {
+            out.push(Quantity { kind: QuantityKind::Percent, value: (number * 100.0).round() as i64 });
+            continue;
+        }
+
+        if suffix == "월" {
+            // "3월 15일"
+            let mut j = i;
+            while j < chars.len() && chars[j] == ' ' {
+                j += 1;
+            }
+            if j < chars.len() && chars[j].is_ascii_digit() {
+                let (day, after) = read_number(&chars, j);
+                if chars.get(after) == Some(&'일') && (1.0..=12.0).contains(&number) && (1.0..=31.0).contains(&day) {
+                    out.push(Quantity { kind: QuantityKind::Date, value: number as i64 * 100 + day as i64 });
+                    i = after + 1;
+                }
+            }
+            continue;
+        }
+
+        if let Some((multiplier, has_won)) = money_unit(&suffix) {
+            let value = (number * multiplier as f64).round() as i64;
+            match pending_money.take() {
+                Some(big) if multiplier < 100_000_000 => {
+                    let total = big + value;
+                    if has_won {
+                        out.push(Quantity { kind: QuantityKind::Money, value: total });
+                    } else {
+                        pending_money = Some(total);
+                    }
+                }
+                previous => {
+                    if let Some(v) = previous {
+                        bare_money(&mut out, v);
+                    }
+                    if has_won {
+                        out.push(Quantity { kind: QuantityKind::Money, value });
+                    } else if multiplier < 100_000_000 {
+                        bare_money(&mut out, value);
+                    } else {
+                        pending_money = Some(value);
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(v) = pending_money {
+        bare_money(&mut out, v);
+    }
+    out
+}
+
+/// "1,500.5" → (1500.5, index after the number)
+fn read_number(chars: &[char], start: usize) -> (f64, usize) {
+    let mut digits = String::new();
+    let mut i = start;
+    while i < chars.len() {
+        let c = chars[i];
+        let next_is_digit = chars.get(i + 1).is_some_and(|n| n.is_ascii_digit());
+        if c.is_ascii_digit() {
+            digits.push(c);
+        } else if c == ',' && next_is_digit && !digits.contains('.') {
+            // thousands separator
+        } else if c == '.' && next_is_digit && !digits.contains('.') {
+            digits.push(c);
+        } else {
+            break;
+        }
+        i += 1;
+    }
+    (digits.parse().unwrap_or(0.0), i)
+}
+
+fn read_iso_date(chars: &[char], start: usize) -> Option<(i64, usize)> {
+    let digits = |from: usize, max: usize| -> (String, usize) {
+        let s: String = chars[from..].iter().take(max).take_while(|c| c.is_ascii_digit()).collect();
+        let len = s.len();
+        (s, from + len)
+    };
+
+    let (year_digits, i) = digits(start, 4);
+    let sep = *chars.get(i)?;
+    if year_digits.len() != 4 || !['-', '.', '/'].contains(&sep) {
+        return None;
+    }
+    let (month, j) = digits(i + 1, 2);
+    if month.is_empty() || chars.get(j) != Some(&sep) {
+        return None;
+    }
+    let (day, k) = digits(j + 1, 2);
+    let (year, month, day): (i64, i64, i64) = (year_digits.parse().ok()?, month.parse().ok()?, day.parse().ok()?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some((year * 10_000 + month * 100 + day, k))
+}
+
+/// Korean money suffix → (multiplier, ends with 원). Plain "천" without 원 is not
+/// money, and neither is a unit followed by a counter ("3만명").
+fn money_unit(suffix: &str) -> Option<(i64, bool)> {
+    for (unit, multiplier) in [
+        ("억", 100_000_000),
+        ("천만", 10_000_000),
+        ("백만", 1_000_000),
+        ("만", 10_000),
+        ("천", 1_000),
+        ("", 1),
+    ] {
+        if let Some(rest) = suffix.strip_prefix(unit) {
+            let has_won = rest.starts_with('원');
+            if has_won || (multiplier >= 10_000 && rest.is_empty()) {
+                return Some((multiplier, has_won));
+            }
+            return None;
+        }
+    }
+    None
+}
+
+fn format_quantity(kind: QuantityKind, value: i64) -> String {
+    match kind {
+        QuantityKind::Money if value >= 100_000_000 && value % 100_000_000 == 0 => {
+            format!("{}억원", value / 100_000_000)
+        }
+        QuantityKind::Money if value >= 10_000 && value % 10_000 == 0 => format!("{}만원", value / 10_000),
+        QuantityKind::Money => format!("{}원", value),
+        QuantityKind::Percent => format!("{}%", value as f64 / 100.0),
+        QuantityKind::Date if value >= 10_000 => {
+            format!("{}년 {}월 {}일", value / 10_000, value % 10_000 / 100, value % 100)
+        }
+        QuantityKind::Date => format!("{}월 {}일", value / 100, value % 100),
+    }
+}
+
+// ── Internal helpers ────────────────────────────────────
+
+const SELECT_CONFLICT: &str = "SELECT c.id, c.item_a, c.item_b, a.content, b.content, c.project_id,
+            c.similarity, c.conflict_kind, c.reason, c.detected_by, c.status,
+            c.resolution_note, c.detected_at, c.resolved_at
+     FROM knowledge_conflicts c
+     JOIN knowledge_items a ON a.id = c.item_a
+     JOIN knowledge_items b ON b.id = c.item_b";
+
+fn row_to_conflict(row: &rusqlite::Row) -> rusqlite::Result<KnowledgeConflict> {
+    Ok(KnowledgeConflict {
+        id: row.get(0)?,
+        item_a: row.get(1)?,
+        item_b: row.get(2)?,
+        content_a: row.get(3)?,
+        content_b: row.get(4)?,
+        project_id: row.get(5)?,
+        similarity: row.get(6)?,
+        conflict_kind: row.get(7)?,
+        reason: row.get(8)?,
+        detected_by: row.get(9)?,
+        status: row.get(10)?,
+        resolution_note: row.get(11)?,
+        detected_at: row.get(12)?,
+        resolved_at: row.get(13)?,
+    })
+}
+
+fn candidate(
+    a: &PairItem,
+    b: &PairItem,
+    project_id: &str,
+    similarity: f32,
+    kind: ConflictKind,
+    reason: String,
+) -> ConflictCandidate {
+    ConflictCandidate {
+        item_a: a.id.clone(),
+        item_b: b.id.clone(),
+        content_a: a.content.clone(),
+        content_b: b.content.clone(),
+        project_id: project_id.to_string(),
+        similarity,
+        kind,
+        reason,
+    }
+}
+
+fn ordered(a: &str, b: &str) -> (String, String) {
+    if a < b {
+        (a.to_string(), b.to_string())
+    } else {
+        (b.to_string(), a.to_string())
+    }
+}
+
+/// Active, unexpired items of a project with their vectors, oldest first.
+fn load_project_items(conn: &Connection, project_id: &str) -> Result<Vec<PairItem>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT k.id, k.content, k.outcome, e.vector
+             FROM knowledge_items k
+             JOIN embeddings e ON e.knowledge_id = k.id
+             WHERE k.is_active = 1 AND k.project_id = ?1
+               AND (k.expires_at IS NULL OR k.expires_at > datetime('now'))
+             ORDER BY julianday(k.created_at), k.id",
+        )
+        .map_err(|e| format!("Prepare project items failed: {}", e))?;
+
+    let rows = stmt
+        .query_map([project_id], |row| {
+            Ok(PairItem {
+                id: row.get(0)?,
+                content: row.get(1)?,
+                outcome: row.get(2)?,
+                vector: blob_to_vector(&row.get::<_, Vec<u8>>(3)?),
+            })
+        })
+        .map_err(|e| format!("Query project items failed: {}", e))?;
+
+    Ok(rows.filter_map(|r| r.ok()).collect())
+}
+
+/// Pairs that are intentionally different versions or already known conflicts.
+fn excluded_pairs(conn: &Connection, project_id: &str) -> Result<HashSet<(String, String)>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT r.source_id, r.target_id
+             FROM knowledge_relations r
+             JOIN knowledge_items k ON k.id = r.source_id
+             WHERE k.project_id = ?1 AND r.relation_type IN ('supersedes', 'duplicate_of')
+             UNION ALL
+             SELECT item_a, item_b FROM knowledge_conflicts WHERE project_id = ?1",
+        )
+        .map_err(|e| format!("Prepare excluded pairs failed: {}", e))?;
+
+    let rows = stmt
+        .query_map([project_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("Query excluded pairs failed: {}", e))?;
+
+    Ok(rows.filter_map(|r| r.ok()).map(|(a, b)| ordered(&a, &b)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::embedding::EMBEDDING_DIM;
+    use crate::rag::knowledge;
+    use std::sync::{Arc, Mutex};
+
+    fn setup() -> RagDb {
+        let dir = std::env::temp_dir().join(format!("rag_conflicts_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        RagDb::open(&dir.join("test.db")).unwrap()
+    }
+
+    fn vector(nudge: f32) -> Vec<f32> {
+        let mut v = vec![0.0; EMBEDDING_DIM];
+        v[0] = 1.0;
+        v[1] = nudge;
+        v
+    }
+
+    fn create(db: &RagDb, content: &str, project: &str, outcome: Option<&str>, embedding: &[f32]) -> String {
+        let item = KnowledgeItem {
+            knowledge_type: "budget_decision".to_string(),
+            confidence: 0.8,
+            outcome: outcome.map(String::from),
+            user_id: Some("u1".to_string()),
+            project_id: Some(project.to_string()),
+            ..KnowledgeItem::for_test(content)
+        };
+        knowledge::create_knowledge_item(db, &item, embedding).unwrap()
+    }
+
+    #[test]
+    fn test_extract_quantities() {
+        let values = |text: &str, kind: QuantityKind| -> Vec<i64> {
+            extract_quantities(text).into_iter().filter(|q| q.kind == kind).map(|q| q.value).collect()
+        };
+        assert_eq!(values("예산 3000만원 확정", QuantityKind::Money), vec![30_000_000]);
+        assert_eq!(values("총 1억 5,000만원, 계약금 500원", QuantityKind::Money), vec![150_000_000, 500]);
+        assert_eq!(values("예산 3억 규모", QuantityKind::Money), vec![300_000_000]);
+        assert_eq!(values("3천 명 참여", QuantityKind::Money), Vec::<i64>::new());
+        assert_eq!(values("관객 3만명, 조회수 50만 회", QuantityKind::Money), Vec::<i64>::new());
+        assert_eq!(values("수수료 12.5%", QuantityKind::Percent), vec![1250]);
+        assert_eq!(values("촬영 3월 15일, 납품 2026-04-02", QuantityKind::Date), vec![315, 20260402]);
+
+        let pair = |a: &str, b: &str| {
+            let item = |content: &str| PairItem { id: String::new(), content: content.to_string(), outcome: None, vector: vec![] };
+            compare(&item(a), &item(b), 0.0).map(|(kind, _)| kind)
+        };
+        assert_eq!(pair("관객 3만명 목표", "관객 5만명 목표"), None);
+        assert_eq!(pair("납품 2026-03-15", "납품 2027-03-15"), Some(ConflictKind::Date));
+        assert_eq!(pair("납품 2026-03-15", "납품 3월 15일"), None);
+    }
+
+    #[test]
+    fn test_ingest_records_conflicts_and_events() {
+        let db = setup();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        db.subscribe(move |event| {
+            if let RagEvent::ConflictDetected(c) = event {
+                sink.lock().unwrap().push(c.id.clone());
+            }
+        });
+
+        let old = create(&db, "촬영 예산 3000만원으로 고정", "p1", None, &vector(0.0));
+        // Same amount: no conflict; other project: ignored; dissimilar: ignored
+        create(&db, "촬영 예산은 3000만원", "p1", None, &vector(0.1));
+        create(&db, "촬영 예산 5000만원", "p2", None, &vector(0.0));
+        let mut far = vec![0.0; EMBEDDING_DIM];
+        far[5] = 1.0;
+        create(&db, "케이터링 예산 200만원", "p1", None, &far);
+        assert!(events.lock().unwrap().is_empty());
+
+        let new = create(&db, "촬영 예산 5000만원으로 증액", "p1", None, &vector(0.05));
+        // Conflicts with both 3000만원 records
+        let conflicts = list_conflicts(&db, Some("p1"), Some("open")).unwrap();
+        assert_eq!(conflicts.len(), 2);
+        assert!(conflicts.iter().all(|c| c.conflict_kind == "amount"));
+        assert!(conflicts[0].reason.contains("3000만원 ↔ 5000만원"));
+        assert_eq!(events.lock().unwrap().len(), 2);
+
+        let relations = relations::list_relations(&db, &new).unwrap();
+        assert!(relations
+            .iter()
+            .any(|r| r.relation_type == RelationType::Contradicts && r.target_id == old));
+
+        // Batch run does not duplicate; resolving keeps it out of the open list
+        assert!(detect(&db, None, DEFAULT_CONFLICT_SIMILARITY).unwrap().is_empty());
+        let resolved = set_status(&db, &conflicts[0].id, "resolved", Some("5000만원이 최종")).unwrap();
+        assert!(resolved.resolved_at.is_some());
+        assert_eq!(list_conflicts(&db, Some("p1"), Some("open")).unwrap().len(), 1);
+        assert!(set_status(&db, &conflicts[0].id, "ignored", None).is_err());
+    }
+
+    #[test]
+    fn test_outcome_polarity_and_supersedes() {
+        let db = setup();
+        create(&db, "A 업체로 진행", "p1", Some("confirmed"), &vector(0.0));
+        create(&db, "A 업체로 진행", "p1", Some("rejected"), &vector(0.0));
+        let kinds: Vec<String> = list_conflicts(&db, None, None).unwrap().into_iter().map(|c| c.conflict_kind).collect();
+        assert_eq!(kinds, vec!["outcome"]);
+
+        create(&db, "B 로케이션 진행", "p2", None, &vector(0.0));
+        create(&db, "B 로케이션 취소", "p2", None, &vector(0.02));
+        let conflict = list_conflicts(&db, Some("p2"), None).unwrap();
+        assert_eq!(conflict[0].conflict_kind, "polarity");
+
+        // Superseding pairs are intended updates, not conflicts
+        let mut far = vec![0.0; EMBEDDING_DIM];
+        far[5] = 1.0;
+        let a = create(&db, "C 모델 섭외비 500만원", "p3", None, &vector(0.0));
+        let b = create(&db, "C 모델 섭외비 700만원", "p3", None, &far);
+        assert_eq!(scan_project(&db, "p3", -1.0).unwrap().flagged.len(), 1);
+        relations::link(&db, &b, &a, RelationType::Supersedes, None, None).unwrap();
+        assert!(scan_project(&db, "p3", -1.0).unwrap().flagged.is_empty());
+
+        // Expired items are out of search results and out of conflict checks
+        let expired = KnowledgeItem {
+            knowledge_type: "budget_decision".to_string(),
+            project_id: Some("p4".to_string()),
+            expires_at: Some("2020-01-01 00:00:00".to_string()),
+            ..KnowledgeItem::for_test("D 장비 대여 300만원")
+        };
+        knowledge::create_knowledge_item(&db, &expired, &vector(0.0)).unwrap();
+        create(&db, "D 장비 대여 900만원", "p4", None, &vector(0.0));
+        assert!(list_conflicts(&db, Some("p4"), None).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_llm_review_applies_project_policy() {
+        use crate::rag::llm::{LlmFuture, LlmProvider, LlmReply, LlmRequest};
+        use crate::rag::policy::{self, Channel, Effect, PolicyRule};
+
+        struct Remote;
+        impl LlmProvider for Remote {
+            fn name(&self) -> &'static str {
+                "Remote"
+            }
+            fn kind(&self) -> crate::rag::llm::ProviderKind {
+                crate::rag::llm::ProviderKind::OpenaiCompatible
+            }
+            fn complete<'a>(&'a self, _: &'a str, _: &'a LlmRequest) -> LlmFuture<'a> {
+                let text = r#"{"conflict": true, "reason": "장소가 다름"}"#.to_string();
+                Box::pin(async move { Ok(LlmReply { text, usage: None }) })
+            }
+        }
+
+        let db = Arc::new(setup());
+        for project in ["p1", "p2"] {
+            create(&db, "촬영 장소는 A 스튜디오", project, None, &vector(0.0));
+            create(&db, "촬영 장소는 B 스튜디오", project, None, &vector(0.01));
+        }
+        assert!(list_conflicts(&db, None, None).unwrap().is_empty());
+        let rule = PolicyRule {
+            id: String::new(),
+            channel: Channel::Llm,
+            effect: Effect::LocalOnly,
+            scope: None,
+            project_id: Some("p1".to_string()),
+            knowledge_type: None,
+            provider: None,
+            description: String::new(),
+        };
+        let rule = policy::set_rule(&db, &rule).unwrap();
+
+        let make_llm = |project: &str, labels: DataLabels| -> Result<LlmClient, String> {
+            Ok(LlmClient::new(Box::new(Remote), "remote")
+                .with_ledger(db.clone())
+                .with_usage("detect_conflicts", Some(project), None)
+                .with_data(labels))
+        };
+        // All projects: p1 is refused and skipped, p2 is reviewed
+        let recorded = detect_with_llm(&db, None, DEFAULT_CONFLICT_SIMILARITY, make_llm).await.unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].project_id.as_deref(), Some("p2"));
+        assert_eq!(recorded[0].reason, "장소가 다름");
+
+        let log = policy::outbound_log(&db, Some(Channel::Llm), 10).unwrap();
+        let blocked: Vec<_> = log.iter().filter(|e| !e.allowed).collect();
+        assert_eq!(blocked.len(), 1);
+        assert_eq!(blocked[0].project_id.as_deref(), Some("p1"));
+        assert_eq!(blocked[0].rule_id.as_deref(), Some(rule.id.as_str()));
+        let sent = log.iter().find(|e| e.allowed).unwrap();
+        assert_eq!(sent.project_id.as_deref(), Some("p2"));
+        assert_eq!((sent.scopes.clone(), sent.knowledge_types.clone()), (vec!["team".to_string()], vec!["budget_decision".to_string()]));
+
+        // A single-project scan returns the violation
+        let error = detect_with_llm(&db, Some("p1"), DEFAULT_CONFLICT_SIMILARITY, make_llm).await.unwrap_err();
+        assert!(policy::PolicyViolation::parse(&error).is_some());
+    }
+}
@@ -0,0 +1,602 @@
+/// Context Builder — Token-budgeted, cited RAG context for LLM injection
+///
+/// Replaces the old byte-length `build_rag_context`:
+/// - Budgets by tokens (tokenizer when available, otherwise `estimate_tokens`)
+/// - Attaches stable citation ids ([K1], [K2], …) in section order
+/// - Groups items into 정 thesis / 반 antithesis / 개인 personal sections
+/// - Renders as Markdown, XML-tagged or JSON
+///
+/// The returned citation map lets the UI link `[K3]` in an answer back to its knowledge item.
+
+use crate::rag::db::RagDb;
+use crate::rag::query::{self, SearchResult};
+use serde::{Deserialize, Serialize};
+
+/// Default token budget for rag_get_context
+pub const DEFAULT_MAX_TOKENS: usize = 600;
+
+/// Items are only truncated into the remaining budget if at least this many tokens are left
+const MIN_TRUNCATED_TOKENS: usize = 24;
+
+/// Output format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextFormat {
+    Markdown,
+    Xml,
+    Json,
+}
+
+impl ContextFormat {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "markdown" | "md" => Ok(Self::Markdown),
+            "xml" => Ok(Self::Xml),
+            "json" => Ok(Self::Json),
+            other => Err(format!("Unknown context format: {}", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Markdown => "markdown",
+            Self::Xml => "xml",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// 정반합 section an item was retrieved for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextSection {
+    Thesis,
+    Antithesis,
+    Personal,
+}
+
+impl ContextSection {
+    const ALL: [ContextSection; 3] = [Self::Thesis, Self::Antithesis, Self::Personal];
+
+    fn key(&self) -> &'static str {
+        match self {
+            Self::Thesis => "thesis",
+            Self::Antithesis => "antithesis",
+            Self::Personal => "personal",
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            Self::Thesis => "정 (Thesis) — 관련 판단",
+            Self::Antithesis => "반 (Antithesis) — 반론·리스크",
+            Self::Personal => "개인 (Personal) — 나의 기록",
+        }
+    }
+}
+
+/// Deduplicated search results grouped by section (thesis → antithesis → personal)
+#[derive(Debug, Clone, Default)]
+pub struct SectionedResults {
+    pub thesis: Vec<SearchResult>,
+    pub antithesis: Vec<SearchResult>,
+    pub personal: Vec<SearchResult>,
+}
+
+impl SectionedResults {
+    /// Group three search passes, keeping only the first occurrence of each item.
+    pub fn from_passes(
+        thesis: Vec<SearchResult>,
+        antithesis: Vec<SearchResult>,
+        personal: Vec<SearchResult>,
+    ) -> Self {
+        let mut seen = std::collections::HashSet::new();
+        let mut dedup = |items: Vec<SearchResult>| -> Vec<SearchResult> {
+            items.into_iter().filter(|r| seen.insert(r.id.clone())).collect()
+        };
+        let thesis = dedup(thesis);
+        let antithesis = dedup(antithesis);
+        let personal = dedup(personal);
+        Self { thesis, antithesis, personal }
+    }
+
+    fn section(&self, section: ContextSection) -> &[SearchResult] {
+        match section {
+            ContextSection::Thesis => &self.thesis,
+            ContextSection::Antithesis => &self.antithesis,
+            ContextSection::Personal => &self.personal,
+        }
+    }
+
+    /// All items in citation order.
+    pub fn iter(&self) -> impl Iterator<Item = (ContextSection, &SearchResult)> {
+        ContextSection::ALL
+            .into_iter()
+            .flat_map(move |s| self.section(s).iter().map(move |r| (s, r)))
+    }
+}
+
+/// Thresholds and sizes of the three retrieval passes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetrievalSettings {
+    pub thesis_threshold: f32,
+    pub thesis_limit: usize,
+    pub antithesis_threshold: f32,
+    pub antithesis_limit: usize,
+    pub personal_threshold: f32,
+    pub personal_limit: usize,
+    /// Added to the thesis score of items tagged with the requested role_tag
+    pub role_boost: f64,
+}
+
+impl Default for RetrievalSettings {
+    fn default() -> Self {
+        Self {
+            thesis_threshold: 0.30,
+            thesis_limit: 5,
+            antithesis_threshold: 0.25,
+            antithesis_limit: 3,
+            personal_threshold: 0.25,
+            personal_limit: 3,
+            role_boost: 0.0,
+        }
+    }
+}
+
+/// Run the 정/반/개인 passes for `base` (embedding, scope, user, project, role).
+///
+/// Pass 1 (정 thesis): hybrid search in `base.scope`
+/// Pass 2 (반 antithesis): dialectic search for opposing views
+/// Pass 3 (개인 personal): the user's own personal knowledge
+pub fn retrieve(
+    db: &RagDb,
+    base: &query::SearchParams,
+    settings: &RetrievalSettings,
+) -> Result<SectionedResults, String> {
+    let boost_role = base.role_tag.as_deref().filter(|_| settings.role_boost > 0.0);
+
+    let thesis_params = query::SearchParams {
+        threshold: settings.thesis_threshold,
+        // Over-fetch so role-boosted items can move up into the limit
+        limit: if boost_role.is_some() { settings.thesis_limit * 2 } else { settings.thesis_limit },
+        ..base.clone()
+    };
+    let mut thesis = query::hybrid_search(db, &thesis_params)?;
+    if let Some(role) = boost_role {
+        for r in thesis.iter_mut().filter(|r| r.role_tag.as_deref() == Some(role)) {
+            r.hybrid_score += settings.role_boost;
+        }
+        thesis.sort_by(|a, b| b.hybrid_score.partial_cmp(&a.hybrid_score).unwrap_or(std::cmp::Ordering::Equal));
+        thesis.truncate(settings.thesis_limit);
+    }
+
+    let anti_params = query::DialecticParams {
+        query_embedding: base.query_embedding.clone(),
+        user_id: base.user_id.clone(),
+        project_id: base.project_id.clone(),
+        role_tag: base.role_tag.clone(),
+        threshold: settings.antithesis_threshold,
+        limit: settings.antithesis_limit,
+        ..Default::default()
+    };
+    let antithesis = query::dialectic_search(db, &anti_params)?;
+
+    let personal_params = query::SearchParams {
+        query_embedding: base.query_embedding.clone(),
+        scope: "personal".to_string(),
+        user_id: base.user_id.clone(),
+        threshold: settings.personal_threshold,
+        limit: settings.personal_limit,
+        ..Default::default()
+    };
+    let personal = query::hybrid_search(db, &personal_params)?;
+
+    Ok(SectionedResults::from_passes(thesis, antithesis, personal))
+}
+
+/// Budget + format options
+#[derive(Debug, Clone)]
+pub struct ContextOptions {
+    pub max_tokens: usize,
+    /// Legacy character cap (counted in chars, not bytes)
+    pub max_chars: Option<usize>,
+    pub format: ContextFormat,
+}
+
+impl Default for ContextOptions {
+    fn default() -> Self {
+        Self {
+            max_tokens: DEFAULT_MAX_TOKENS,
+            max_chars: None,
+            format: ContextFormat::Markdown,
+        }
+    }
+}
+
+impl ContextOptions {
+    /// Options from caller-supplied limits. Legacy callers that pass only
+    /// `max_chars` keep their character-only budget; the token default applies
+    /// when neither limit is given.
+    pub fn with_limits(max_chars: Option<usize>, max_tokens: Option<usize>, format: ContextFormat) -> Self {
+        let max_tokens = match (max_tokens, max_chars) {
+            (Some(tokens), _) => tokens,
+            (None, Some(_)) => usize::MAX,
+            (None, None) => DEFAULT_MAX_TOKENS,
+        };
+        Self { max_tokens, max_chars, format }
+    }
+}
+
+/// Citation id → knowledge item mapping
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Citation {
+    pub cite_id: String,
+    pub knowledge_id: String,
+    pub section: ContextSection,
+    pub knowledge_type: String,
+    pub confidence: f64,
+    pub truncated: bool,
+}
+
+/// Rendered context with its citation map
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuiltContext {
+    pub text: String,
+    pub format: ContextFormat,
+    pub citations: Vec<Citation>,
+    pub tokens_used: usize,
+    /// Items dropped because the budget ran out
+    pub omitted_count: usize,
+}
+
+const INSTRUCTIONS: &str = "아래는 이 조직에서 축적된 실제 판단 기록입니다. 반드시 이 내용을 바탕으로 구체적으로 답변하고, 사용한 지식은 [K1]처럼 인용하세요.";
+
+/// Rough token estimate when no tokenizer is loaded.
+///
+/// Hangul/CJK/Thai and other non-ASCII characters count as one token each,
+/// ASCII words as one token per 4 characters, punctuation as one token.
+pub fn estimate_tokens(text: &str) -> usize {
+    let mut tokens = 0;
+    let mut word_len: usize = 0;
+
+    for ch in text.chars() {
+        if ch.is_ascii_alphanumeric() {
+            word_len += 1;
+            continue;
+        }
+        tokens += word_len.div_ceil(4);
+        word_len = 0;
+        if !ch.is_whitespace() {
+            tokens += 1;
+        }
+    }
+
+    tokens + word_len.div_ceil(4)
+}
+
+/// Build a cited context within the token budget.
+///
+/// `count_tokens` is usually `EmbeddingEngine::count_tokens`.
+pub fn build_context(
+    results: &SectionedResults,
+    options: &ContextOptions,
+    count_tokens: &dyn Fn(&str) -> usize,
+) -> BuiltContext {
+    let total = results.iter().count();
+    if total == 0 {
+        return BuiltContext {
+            text: String::new(),
+            format: options.format,
+            citations: vec![],
+            tokens_used: 0,
+            omitted_count: 0,
+        };
+    }
+
+    // Budget is checked against the fully rendered output (headers and
+    // section titles included); result sets are small (≤ ~11 items).
+    let char_cap = options.max_chars.unwrap_or(usize::MAX);
+    let fits = |entries: &[Entry]| -> bool {
+        let text = render(options.format, entries);
+        count_tokens(&text) <= options.max_tokens && text.chars().count() <= char_cap
+    };
+    let mut selected: Vec<Entry> = Vec::new();
+
+    for (index, (section, item)) in results.iter().enumerate() {
+        let cite_id = format!("K{}", index + 1);
+        let body = item.summary.as_deref().unwrap_or(&item.content);
+
+        selected.push(Entry::new(section, &cite_id, item, body.to_string(), false));
+        if fits(&selected) {
+            continue;
+        }
+        selected.pop();
+
+        // Does not fit: truncate into the remaining budget (once), then stop
+        let used = count_tokens(&render(options.format, &selected));
+        if options.max_tokens.saturating_sub(used) >= MIN_TRUNCATED_TOKENS {
+            if let Some(entry) = truncate_to_fit(&mut selected, section, &cite_id, item, body, &fits) {
+                selected.push(entry);
+            }
+        }
+        break;
+    }
+
+    let text = render(options.format, &selected);
+    let citations = selected
+        .iter()
+        .map(|e| Citation {
+            cite_id: e.cite_id.clone(),
+            knowledge_id: e.knowledge_id.clone(),
+            section: e.section,
+            knowledge_type: e.knowledge_type.clone(),
+            confidence: e.confidence,
+            truncated: e.truncated,
+        })
+        .collect();
+
+    BuiltContext {
+        tokens_used: count_tokens(&text),
+        omitted_count: total - selected.len(),
+        text,
+        format: options.format,
+        citations,
+    }
+}
+
+// ── Rendering ───────────────────────────────────────────
+
+struct Entry {
+    section: ContextSection,
+    cite_id: String,
+    knowledge_id: String,
+    knowledge_type: String,
+    confidence: f64,
+    body: String,
+    truncated: bool,
+}
+
+impl Entry {
+    fn new(section: ContextSection, cite_id: &str, item: &SearchResult, body: String, truncated: bool) -> Self {
+        Self {
+            section,
+            cite_id: cite_id.to_string(),
+            knowledge_id: item.id.clone(),
+            knowledge_type: item.knowledge_type.clone(),
+            confidence: item.confidence,
+            body,
+            truncated,
+        }
+    }
+
+    fn render(&self, format: ContextFormat) -> String {
+        match format {
+            ContextFormat::Markdown => format!(
+                "[{}] {} (신뢰도: {}%)\n{}\n\n",
+                self.cite_id,
+                self.knowledge_type,
+                (self.confidence * 100.0) as i32,
+                self.body
+            ),
+            ContextFormat::Xml => format!(
+                "<item id=\"{}\" type=\"{}\" confidence=\"{:.2}\">{}</item>\n",
+                self.cite_id,
+                xml_escape(&self.knowledge_type),
+                self.confidence,
+                xml_escape(&self.body)
+            ),
+            ContextFormat::Json => self.to_json().to_string(),
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.cite_id,
+            "knowledge_id": self.knowledge_id,
+            "type": self.knowledge_type,
+            "confidence": self.confidence,
+            "content": self.body,
+        })
+    }
+}
+
+fn render(format: ContextFormat, entries: &[Entry]) -> String {
+    let in_section = |s: ContextSection| entries.iter().filter(move |e| e.section == s);
+
+    match format {
+        ContextFormat::Markdown => {
+            let mut out = format!("## 참고 지식 (Knowledge Base)\n{}\n\n", INSTRUCTIONS);
+            for section in ContextSection::ALL {
+                let items: Vec<&Entry> = in_section(section).collect();
+                if items.is_empty() {
+                    continue;
+                }
+                out.push_str(&format!("### {}\n", section.title()));
+                for e in items {
+                    out.push_str(&e.render(format));
+                }
+            }
+            out
+        }
+        ContextFormat::Xml => {
+            let mut out = format!(
+                "<knowledge_base>\n<instructions>{}</instructions>\n",
+                xml_escape(INSTRUCTIONS)
+            );
+            for section in ContextSection::ALL {
+                let items: Vec<&Entry> = in_section(section).collect();
+                if items.is_empty() {
+                    continue;
+                }
+                out.push_str(&format!("<section name=\"{}\">\n", section.key()));
+                for e in items {
+                    out.push_str(&e.render(format));
+                }
+                out.push_str("</section>\n");
+            }
+            out.push_str("</knowledge_base>");
+            out
+        }
+        ContextFormat::Json => {
+            let mut sections = serde_json::Map::new();
+            for section in ContextSection::ALL {
+                let items: Vec<serde_json::Value> = in_section(section).map(|e| e.to_json()).collect();
+                if !items.is_empty() {
+                    sections.insert(section.key().to_string(), serde_json::Value::Array(items));
+                }
+            }
+            serde_json::json!({
+                "instructions": INSTRUCTIONS,
+                "sections": sections,
+            })
+            .to_string()
+        }
+    }
+}
+
+/// Largest char-prefix of `body` (with "…") that still fits after `selected`.
+fn truncate_to_fit(
+    selected: &mut Vec<Entry>,
+    section: ContextSection,
+    cite_id: &str,
+    item: &SearchResult,
+    body: &str,
+    fits: &dyn Fn(&[Entry]) -> bool,
+) -> Option<Entry> {
+    let chars: Vec<char> = body.chars().collect();
+    let mut try_prefix = |n: usize| -> bool {
+        let text: String = chars[..n].iter().collect::<String>() + "…";
+        selected.push(Entry::new(section, cite_id, item, text, true));
+        let ok = fits(selected);
+        selected.pop();
+        ok
+    };
+
+    // Binary search for the longest prefix that fits
+    let (mut lo, mut hi) = (0usize, chars.len());
+    while lo < hi {
+        let mid = (lo + hi).div_ceil(2);
+        if try_prefix(mid) {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+
+    // Too short to be useful
+    (lo > 10 && try_prefix(lo)).then(|| {
+        let text: String = chars[..lo].iter().collect::<String>() + "…";
+        Entry::new(section, cite_id, item, text, true)
+    })
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(id: &str, content: &str) -> SearchResult {
+        SearchResult {
+            id: id.to_string(),
+            content: content.to_string(),
+            summary: None,
+            knowledge_type: "decision_pattern".to_string(),
+            source_type: "test".to_string(),
+            scope: "global".to_string(),
+            role_tag: None,
+            dialectic_tag: None,
+            confidence: 0.85,
+            relevance_score: 0.5,
+            usage_count: 0,
+            similarity: 0.8,
+            hybrid_score: 0.8,
+            project_id: None,
+            user_id: None,
+            related_via: None,
+        }
+    }
+
+    fn sample() -> SectionedResults {
+        SectionedResults::from_passes(
+            vec![result("a", "예산 3000만원 확정"), result("b", "촬영은 2월 말")],
+            vec![result("c", "예산 초과 리스크"), result("a", "예산 3000만원 확정")],
+            vec![result("d", "내 메모")],
+        )
+    }
+
+    #[test]
+    fn test_estimate_tokens_korean_vs_ascii() {
+        assert_eq!(estimate_tokens("예산 확정"), 4);
+        assert_eq!(estimate_tokens("budget"), 2);
+        assert_eq!(estimate_tokens(""), 0);
+    }
+
+    #[test]
+    fn test_citations_are_stable_and_deduplicated() {
+        let built = build_context(&sample(), &ContextOptions::default(), &estimate_tokens);
+        let ids: Vec<&str> = built.citations.iter().map(|c| c.cite_id.as_str()).collect();
+        assert_eq!(ids, vec!["K1", "K2", "K3", "K4"]);
+        assert_eq!(built.citations[2].knowledge_id, "c");
+        assert_eq!(built.citations[2].section, ContextSection::Antithesis);
+        assert!(built.text.contains("[K3] decision_pattern"));
+        assert!(built.text.contains("### 반 (Antithesis)"));
+        assert_eq!(built.omitted_count, 0);
+    }
+
+    #[test]
+    fn test_budget_is_respected_for_korean() {
+        let long = "가".repeat(500);
+        let results = SectionedResults::from_passes(vec![result("a", &long), result("b", &long)], vec![], vec![]);
+        let options = ContextOptions {
+            max_tokens: 200,
+            ..Default::default()
+        };
+        let built = build_context(&results, &options, &estimate_tokens);
+        assert!(built.tokens_used <= 200, "used {}", built.tokens_used);
+        assert_eq!(built.citations.len(), 1);
+        assert!(built.citations[0].truncated);
+        assert_eq!(built.omitted_count, 1);
+
+        let options = ContextOptions {
+            max_chars: Some(300),
+            ..Default::default()
+        };
+        let built = build_context(&results, &options, &estimate_tokens);
+        assert!(built.text.chars().count() <= 300);
+
+        // A char-only caller is not also held to the default token cap
+        let options = ContextOptions::with_limits(Some(1500), None, ContextFormat::Markdown);
+        let built = build_context(&results, &options, &estimate_tokens);
+        assert!(built.tokens_used > DEFAULT_MAX_TOKENS, "used {}", built.tokens_used);
+        assert!(built.text.chars().count() <= 1500);
+        assert_eq!(ContextOptions::with_limits(None, None, ContextFormat::Markdown).max_tokens, DEFAULT_MAX_TOKENS);
+    }
+
+    #[test]
+    fn test_xml_and_json_formats() {
+        let xml = build_context(
+            &sample(),
+            &ContextOptions { format: ContextFormat::Xml, ..Default::default() },
+            &estimate_tokens,
+        );
+        assert!(xml.text.starts_with("<knowledge_base>"));
+        assert!(xml.text.contains("<section name=\"antithesis\">"));
+        assert!(xml.text.contains("<item id=\"K1\""));
+
+        let json = build_context(
+            &sample(),
+            &ContextOptions { format: ContextFormat::Json, ..Default::default() },
+            &estimate_tokens,
+        );
+        let parsed: serde_json::Value = serde_json::from_str(&json.text).unwrap();
+        assert_eq!(parsed["sections"]["personal"][0]["id"], "K4");
+        assert!(ContextFormat::parse("yaml").is_err());
+    }
+}
@@ -2,13 +2,35 @@
 ///
 /// Migration v1: Core tables (knowledge_items, embeddings, extraction_log, etc.)
 /// Migration v2: sqlite-vec virtual table for native vector similarity search
-
+/// Migration v3: rag_query_log indexes for query analytics
+/// Migration v4: standing queries (saved searches) + match log
+/// Migration v5: knowledge item version history
+/// Migration v6: typed relations between knowledge items
+/// Migration v7: decision_patterns keyed by decision maker, one row per cluster
+/// Migration v8: knowledge_conflicts (contradiction detector findings)
+/// Migration v9: decision outcome audit log + reminder bookkeeping
+/// Migration v10: confidence/relevance aging (decay policies, runs, change log)
+/// Migration v11: ontology_terms vocabulary + knowledge_type / role_tag triggers
+/// Migration v12: llm_settings (provider + model per LLM task)
+/// Migration v13: digest_watermarks + digest_queue (incremental per-room digests)
+/// Migration v14: prompt_languages + chat_digests.prompt_version (prompt registry)
+/// Migration v15: llm_usage ledger + llm_budgets + llm_prices
+/// Migration v16: redaction_settings + redaction_audit (PII redaction before LLM calls)
+/// Migration v17: outbound_rules + outbound_log (outbound data policy)
+/// Migration v18: ontology_terms.traits (decision / pattern-source knowledge types)
+/// Migration v19: digest_watermarks.last_failed_at (scheduler backoff after a failed digest)
+
+use crate::rag::events::{EventListener, RagEvent};
+use crate::rag::ontology;
+use crate::rag::prompts::PromptRegistry;
 use rusqlite::{Connection, Result as SqlResult, ffi::sqlite3_auto_extension};
 use std::path::PathBuf;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
 
 pub struct RagDb {
     conn: Mutex<Connection>,
+    listeners: Mutex<Vec<EventListener>>,
+    prompts: RwLock<PromptRegistry>,
 }
 
 impl RagDb {
@@ -35,6 +57,8 @@ impl RagDb {
 
         let db = Self {
             conn: Mutex::new(conn),
+            listeners: Mutex::new(Vec::new()),
+            prompts: RwLock::new(PromptRegistry::default()),
         };
         db.run_migrations()?;
 
@@ -46,6 +70,37 @@ impl RagDb {
         self.conn.lock().expect("Database lock poisoned")
     }
 
+    /// Prompt templates in effect (built-ins until `prompts::init` loads overrides).
+    pub fn prompts(&self) -> RwLockReadGuard<'_, PromptRegistry> {
+        self.prompts.read().expect("Prompt registry lock poisoned")
+    }
+
+    pub fn set_prompts(&self, registry: PromptRegistry) {
+        *self.prompts.write().expect("Prompt registry lock poisoned") = registry;
+    }
+
+    /// Register a listener for knowledge-layer events.
+    pub fn subscribe<F>(&self, listener: F)
+    where
+        F: Fn(&RagEvent) + Send + Sync + 'static,
+    {
+        self.listeners
+            .lock()
+            .expect("Listener lock poisoned")
+            .push(Arc::new(listener));
+    }
+
+    /// Publish an event to all listeners.
+    /// Callers must not hold the connection guard (listeners may query the DB).
+    /// The listener list is copied out first so listeners may emit or subscribe.
+    pub fn emit(&self, event: RagEvent) {
+        let listeners: Vec<EventListener> =
+            self.listeners.lock().expect("Listener lock poisoned").clone();
+        for listener in &listeners {
+            listener(&event);
+        }
+    }
+
     fn run_migrations(&self) -> SqlResult<()> {
         let conn = self.conn();
 
@@ -70,6 +125,60 @@ impl RagDb {
         if current_version < 2 {
             self.migrate_v2(&conn)?;
         }
+        if current_version < 3 {
+            self.migrate_v3(&conn)?;
+        }
+        if current_version < 4 {
+            self.migrate_v4(&conn)?;
+        }
+        if current_version < 5 {
+            self.migrate_v5(&conn)?;
+        }
+        if current_version < 6 {
+            self.migrate_v6(&conn)?;
+        }
+        if current_version < 7 {
+            self.migrate_v7(&conn)?;
+        }
+        if current_version < 8 {
+            self.migrate_v8(&conn)?;
+        }
+        if current_version < 9 {
+            self.migrate_v9(&conn)?;
+        }
+        if current_version < 10 {
+            self.migrate_v10(&conn)?;
+        }
+        if current_version < 11 {
+            self.migrate_v11(&conn)?;
+        }
+        if current_version < 12 {
+            self.migrate_v12(&conn)?;
+        }
+        if current_version < 13 {
+            self.migrate_v13(&conn)?;
+        }
+        if current_version < 14 {
+            self.migrate_v14(&conn)?;
+        }
+        if current_version < 15 {
+            self.migrate_v15(&conn)?;
+        }
+        if current_version < 16 {
+            self.migrate_v16(&conn)?;
+        }
+        if current_version < 17 {
+            self.migrate_v17(&conn)?;
+        }
+        if current_version < 18 {
+            self.migrate_v18(&conn)?;
+        }
+        if current_version < 19 {
+            self.migrate_v19(&conn)?;
+        }
+
+        // The built-in vocabulary lives in code; mirror it for the v11 triggers
+        ontology::sync_builtin(&conn)?;
 
         Ok(())
     }
@@ -238,6 +347,605 @@ impl RagDb {
         log::info!("RAG database migrated to v2 (sqlite-vec)");
         Ok(())
     }
+
+    /// V3: Indexes for query analytics (date range + project filters)
+    fn migrate_v3(&self, conn: &Connection) -> SqlResult<()> {
+        conn.execute_batch(
+            "
+            CREATE INDEX IF NOT EXISTS idx_rql_created
+                ON rag_query_log(created_at);
+            CREATE INDEX IF NOT EXISTS idx_rql_project
+                ON rag_query_log(project_id, created_at);
+
+            INSERT INTO _schema_version (version) VALUES (3);
+            "
+        )?;
+
+        log::info!("RAG database migrated to v3 (query analytics)");
+        Ok(())
+    }
+
+    /// V4: Standing queries — saved searches evaluated against new knowledge
+    fn migrate_v4(&self, conn: &Connection) -> SqlResult<()> {
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS standing_queries (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                query_text TEXT NOT NULL,
+                embedding BLOB NOT NULL,
+                user_id TEXT,
+                project_id TEXT,
+                knowledge_type TEXT,
+                dialectic_tag TEXT,
+                role_tag TEXT,
+                threshold REAL NOT NULL DEFAULT 0.55,
+                notify INTEGER NOT NULL DEFAULT 0,
+                is_active INTEGER NOT NULL DEFAULT 1,
+                match_count INTEGER NOT NULL DEFAULT 0,
+                last_matched_at TEXT,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_sq_user
+                ON standing_queries(user_id) WHERE is_active = 1;
+
+            CREATE TABLE IF NOT EXISTS standing_query_matches (
+                id TEXT PRIMARY KEY,
+                standing_query_id TEXT NOT NULL
+                    REFERENCES standing_queries(id) ON DELETE CASCADE,
+                knowledge_id TEXT NOT NULL,
+                similarity REAL NOT NULL,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                UNIQUE(standing_query_id, knowledge_id)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_sqm_query
+                ON standing_query_matches(standing_query_id, created_at);
+
+            INSERT INTO _schema_version (version) VALUES (4);
+            "
+        )?;
+
+        log::info!("RAG database migrated to v4 (standing queries)");
+        Ok(())
+    }
+
+    /// V5: Version history — snapshot of a knowledge item after every change
+    fn migrate_v5(&self, conn: &Connection) -> SqlResult<()> {
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS knowledge_item_versions (
+                id TEXT PRIMARY KEY,
+                knowledge_id TEXT NOT NULL
+                    REFERENCES knowledge_items(id) ON DELETE CASCADE,
+                version INTEGER NOT NULL,
+                change_type TEXT NOT NULL
+                    CHECK (change_type IN (
+                        'baseline', 'update', 'deactivate', 'restore', 'revert', 'merge'
+                    )),
+                changed_fields TEXT NOT NULL DEFAULT '[]',
+                snapshot TEXT NOT NULL,
+                changed_by TEXT,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                UNIQUE(knowledge_id, version)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_kiv_item
+                ON knowledge_item_versions(knowledge_id, version);
+
+            INSERT INTO _schema_version (version) VALUES (5);
+            "
+        )?;
+
+        log::info!("RAG database migrated to v5 (knowledge version history)");
+        Ok(())
+    }
+
+    /// V6: Knowledge relations graph — source --relation_type--> target
+    fn migrate_v6(&self, conn: &Connection) -> SqlResult<()> {
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS knowledge_relations (
+                id TEXT PRIMARY KEY,
+                source_id TEXT NOT NULL
+                    REFERENCES knowledge_items(id) ON DELETE CASCADE,
+                target_id TEXT NOT NULL
+                    REFERENCES knowledge_items(id) ON DELETE CASCADE,
+                relation_type TEXT NOT NULL
+                    CHECK (relation_type IN (
+                        'supersedes', 'contradicts', 'supports',
+                        'derived_from', 'duplicate_of'
+                    )),
+                note TEXT,
+                created_by TEXT,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                UNIQUE(source_id, target_id, relation_type),
+                CHECK (source_id != target_id)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_kr_source
+                ON knowledge_relations(source_id, relation_type);
+            CREATE INDEX IF NOT EXISTS idx_kr_target
+                ON knowledge_relations(target_id, relation_type);
+
+            INSERT INTO _schema_version (version) VALUES (6);
+            "
+        )?;
+
+        log::info!("RAG database migrated to v6 (knowledge relations)");
+        Ok(())
+    }
+
+    /// V7: decision_patterns rebuilt for pattern mining — several clusters per
+    /// (decision maker, domain), each with a centroid for incremental updates
+    fn migrate_v7(&self, conn: &Connection) -> SqlResult<()> {
+        conn.execute_batch(
+            "
+            ALTER TABLE decision_patterns RENAME TO decision_patterns_v1;
+
+            CREATE TABLE decision_patterns (
+                id TEXT PRIMARY KEY,
+                user_id TEXT,
+                decision_maker TEXT NOT NULL,
+                knowledge_domain TEXT NOT NULL,
+                pattern_type TEXT,
+                pattern_summary TEXT,
+                evidence_item_ids TEXT,
+                confidence REAL,
+                sample_count INTEGER,
+                centroid BLOB,
+                created_at TEXT DEFAULT (datetime('now')),
+                updated_at TEXT DEFAULT (datetime('now'))
+            );
+
+            INSERT INTO decision_patterns (
+                id, user_id, decision_maker, knowledge_domain, pattern_type,
+                pattern_summary, evidence_item_ids, confidence, sample_count,
+                created_at, updated_at
+            )
+            SELECT id, user_id, COALESCE(user_id, ''), knowledge_domain, pattern_type,
+                   pattern_summary, evidence_item_ids, confidence, sample_count,
+                   created_at, updated_at
+            FROM decision_patterns_v1;
+
+            DROP TABLE decision_patterns_v1;
+
+            CREATE INDEX IF NOT EXISTS idx_dp_maker
+                ON decision_patterns(decision_maker, knowledge_domain);
+
+            INSERT INTO _schema_version (version) VALUES (7);
+            "
+        )?;
+
+        log::info!("RAG database migrated to v7 (decision pattern mining)");
+        Ok(())
+    }
+
+    /// V8: Conflicting knowledge pairs (item_a < item_b) with review status
+    fn migrate_v8(&self, conn: &Connection) -> SqlResult<()> {
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS knowledge_conflicts (
+                id TEXT PRIMARY KEY,
+                item_a TEXT NOT NULL
+                    REFERENCES knowledge_items(id) ON DELETE CASCADE,
+                item_b TEXT NOT NULL
+                    REFERENCES knowledge_items(id) ON DELETE CASCADE,
+                project_id TEXT,
+                similarity REAL NOT NULL,
+                conflict_kind TEXT NOT NULL,
+                reason TEXT NOT NULL,
+                detected_by TEXT NOT NULL DEFAULT 'heuristic'
+                    CHECK (detected_by IN ('heuristic', 'llm')),
+                status TEXT NOT NULL DEFAULT 'open'
+                    CHECK (status IN ('open', 'resolved', 'dismissed')),
+                resolution_note TEXT,
+                detected_at TEXT NOT NULL DEFAULT (datetime('now')),
+                resolved_at TEXT,
+                UNIQUE(item_a, item_b),
+                CHECK (item_a < item_b)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_kc_project
+                ON knowledge_conflicts(project_id, status);
+
+            INSERT INTO _schema_version (version) VALUES (8);
+            "
+        )?;
+
+        log::info!("RAG database migrated to v8 (knowledge conflicts)");
+        Ok(())
+    }
+
+    /// V9: Decision outcome lifecycle — every outcome transition with its reason,
+    /// and when a stale pending/escalated decision was last reminded about
+    fn migrate_v9(&self, conn: &Connection) -> SqlResult<()> {
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS decision_outcome_log (
+                id TEXT PRIMARY KEY,
+                knowledge_id TEXT NOT NULL
+                    REFERENCES knowledge_items(id) ON DELETE CASCADE,
+                from_outcome TEXT,
+                to_outcome TEXT NOT NULL
+                    CHECK (to_outcome IN ('confirmed', 'rejected', 'pending', 'escalated')),
+                reason TEXT,
+                changed_by TEXT,
+                changed_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_dol_item
+                ON decision_outcome_log(knowledge_id, changed_at);
+
+            CREATE TABLE IF NOT EXISTS decision_reminders (
+                knowledge_id TEXT PRIMARY KEY
+                    REFERENCES knowledge_items(id) ON DELETE CASCADE,
+                reminded_at TEXT NOT NULL DEFAULT (datetime('now')),
+                reminder_count INTEGER NOT NULL DEFAULT 1
+            );
+
+            INSERT INTO _schema_version (version) VALUES (9);
+            "
+        )?;
+
+        log::info!("RAG database migrated to v9 (decision outcome lifecycle)");
+        Ok(())
+    }
+
+    /// V10: Knowledge aging — per-type half-life / TTL overrides, the decay clock
+    /// on each item, and a reviewable log of every score change and expiry
+    fn migrate_v10(&self, conn: &Connection) -> SqlResult<()> {
+        conn.execute_batch(
+            "
+            ALTER TABLE knowledge_items ADD COLUMN decayed_at TEXT;
+
+            CREATE TABLE IF NOT EXISTS decay_policies (
+                knowledge_type TEXT PRIMARY KEY,
+                half_life_days REAL CHECK (half_life_days IS NULL OR half_life_days > 0),
+                ttl_days REAL CHECK (ttl_days IS NULL OR ttl_days > 0),
+                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            CREATE TABLE IF NOT EXISTS aging_runs (
+                id TEXT PRIMARY KEY,
+                decayed_count INTEGER NOT NULL DEFAULT 0,
+                expired_count INTEGER NOT NULL DEFAULT 0,
+                ran_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            CREATE TABLE IF NOT EXISTS aging_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                run_id TEXT NOT NULL REFERENCES aging_runs(id) ON DELETE CASCADE,
+                knowledge_id TEXT NOT NULL
+                    REFERENCES knowledge_items(id) ON DELETE CASCADE,
+                action TEXT NOT NULL CHECK (action IN ('decayed', 'expired')),
+                confidence_before REAL NOT NULL,
+                confidence_after REAL NOT NULL,
+                relevance_before REAL NOT NULL,
+                relevance_after REAL NOT NULL,
+                expires_at TEXT
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_aging_log_run ON aging_log(run_id);
+            CREATE INDEX IF NOT EXISTS idx_aging_log_item ON aging_log(knowledge_id);
+
+            INSERT INTO _schema_version (version) VALUES (10);
+            "
+        )?;
+
+        log::info!("RAG database migrated to v10 (knowledge aging)");
+        Ok(())
+    }
+
+    /// V11: Ontology vocabulary — built-in terms (synced from `ontology` on open)
+    /// plus organisation extensions. knowledge_type and role_tag have no CHECK
+    /// constraint, so triggers validate new values against this table instead.
+    /// Existing rows are left alone; only new or changed values are checked.
+    fn migrate_v11(&self, conn: &Connection) -> SqlResult<()> {
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS ontology_terms (
+                dimension TEXT NOT NULL CHECK (dimension IN (
+                    'knowledge_type', 'role_tag', 'scope_layer', 'dialectic_tag'
+                )),
+                value TEXT NOT NULL,
+                label TEXT NOT NULL,
+                category TEXT,
+                aliases TEXT NOT NULL DEFAULT '[]',
+                is_builtin INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                PRIMARY KEY (dimension, value)
+            );
+
+            CREATE TRIGGER IF NOT EXISTS trg_ki_knowledge_type_insert
+            BEFORE INSERT ON knowledge_items
+            WHEN NOT EXISTS (SELECT 1 FROM ontology_terms
+                             WHERE dimension = 'knowledge_type' AND value = NEW.knowledge_type)
+            BEGIN
+                SELECT RAISE(ABORT, 'unknown knowledge_type');
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS trg_ki_knowledge_type_update
+            BEFORE UPDATE OF knowledge_type ON knowledge_items
+            WHEN NEW.knowledge_type IS NOT OLD.knowledge_type
+             AND NOT EXISTS (SELECT 1 FROM ontology_terms
+                             WHERE dimension = 'knowledge_type' AND value = NEW.knowledge_type)
+            BEGIN
+                SELECT RAISE(ABORT, 'unknown knowledge_type');
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS trg_ki_role_tag_insert
+            BEFORE INSERT ON knowledge_items
+            WHEN NEW.role_tag IS NOT NULL
+             AND NOT EXISTS (SELECT 1 FROM ontology_terms
+                             WHERE dimension = 'role_tag' AND value = NEW.role_tag)
+            BEGIN
+                SELECT RAISE(ABORT, 'unknown role_tag');
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS trg_ki_role_tag_update
+            BEFORE UPDATE OF role_tag ON knowledge_items
+            WHEN NEW.role_tag IS NOT NULL
+             AND NEW.role_tag IS NOT OLD.role_tag
+             AND NOT EXISTS (SELECT 1 FROM ontology_terms
+                             WHERE dimension = 'role_tag' AND value = NEW.role_tag)
+            BEGIN
+                SELECT RAISE(ABORT, 'unknown role_tag');
+            END;
+
+            INSERT INTO _schema_version (version) VALUES (11);
+            "
+        )?;
+
+        log::info!("RAG database migrated to v11 (ontology vocabulary)");
+        Ok(())
+    }
+
+    /// V12: LLM provider per task (digest, extraction, …). Absent rows mean the
+    /// default Anthropic provider; API keys are never stored here.
+    fn migrate_v12(&self, conn: &Connection) -> SqlResult<()> {
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS llm_settings (
+                task TEXT PRIMARY KEY CHECK (task IN (
+                    'digest', 'extraction', 'conflict', 'synthesis', 'persona'
+                )),
+                provider TEXT NOT NULL CHECK (provider IN ('anthropic', 'openai_compatible', 'mock')),
+                model TEXT NOT NULL,
+                base_url TEXT,
+                api_key_env TEXT,
+                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            INSERT INTO _schema_version (version) VALUES (12);
+            "
+        )?;
+
+        log::info!("RAG database migrated to v12 (LLM provider settings)");
+        Ok(())
+    }
+
+    /// V13: Incremental digests — per-room watermark (last digested message) and
+    /// the queue of messages received since, which survives restarts
+    fn migrate_v13(&self, conn: &Connection) -> SqlResult<()> {
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS digest_watermarks (
+                room_id TEXT PRIMARY KEY,
+                project_id TEXT,
+                last_message_at TEXT,
+                last_message_id TEXT,
+                digested_count INTEGER NOT NULL DEFAULT 0,
+                last_digest_at TEXT,
+                due_notified_at TEXT,
+                digest_started_at TEXT,
+                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            CREATE TABLE IF NOT EXISTS digest_queue (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                room_id TEXT NOT NULL REFERENCES digest_watermarks(room_id) ON DELETE CASCADE,
+                message_key TEXT NOT NULL,
+                message_id TEXT,
+                user_id TEXT NOT NULL,
+                user_name TEXT NOT NULL,
+                content TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                queued_at TEXT NOT NULL DEFAULT (datetime('now')),
+                UNIQUE (room_id, message_key)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_digest_queue_room ON digest_queue(room_id, created_at);
+
+            INSERT INTO _schema_version (version) VALUES (13);
+            "
+        )?;
+
+        log::info!("RAG database migrated to v13 (digest watermarks + queue)");
+        Ok(())
+    }
+
+    /// V14: Prompt registry — language pack per room / project, and the
+    /// template version that produced each digest
+    fn migrate_v14(&self, conn: &Connection) -> SqlResult<()> {
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS prompt_languages (
+                scope_type TEXT NOT NULL CHECK (scope_type IN ('room', 'project')),
+                scope_id TEXT NOT NULL,
+                language TEXT NOT NULL CHECK (language IN ('ko', 'en', 'th')),
+                updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+                PRIMARY KEY (scope_type, scope_id)
+            );
+
+            ALTER TABLE chat_digests ADD COLUMN prompt_version TEXT;
+
+            INSERT INTO _schema_version (version) VALUES (14);
+            "
+        )?;
+
+        log::info!("RAG database migrated to v14 (prompt languages + digest prompt versions)");
+        Ok(())
+    }
+
+    /// V15: LLM usage ledger (one row per provider call), monthly budgets per
+    /// project, and custom model prices
+    fn migrate_v15(&self, conn: &Connection) -> SqlResult<()> {
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS llm_usage (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                task TEXT NOT NULL,
+                purpose TEXT NOT NULL DEFAULT '',
+                project_id TEXT,
+                room_id TEXT,
+                input_tokens INTEGER NOT NULL DEFAULT 0,
+                output_tokens INTEGER NOT NULL DEFAULT 0,
+                tokens_estimated INTEGER NOT NULL DEFAULT 0,
+                cost_usd REAL,
+                latency_ms INTEGER NOT NULL DEFAULT 0,
+                status TEXT NOT NULL CHECK (status IN ('ok', 'error', 'cancelled', 'blocked')),
+                error TEXT
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_llm_usage_created ON llm_usage(created_at);
+            CREATE INDEX IF NOT EXISTS idx_llm_usage_project ON llm_usage(project_id, created_at);
+
+            CREATE TABLE IF NOT EXISTS llm_budgets (
+                project_id TEXT PRIMARY KEY,
+                monthly_limit_usd REAL NOT NULL CHECK (monthly_limit_usd >= 0),
+                mode TEXT NOT NULL DEFAULT 'warn' CHECK (mode IN ('warn', 'block')),
+                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            CREATE TABLE IF NOT EXISTS llm_prices (
+                model TEXT PRIMARY KEY,
+                input_per_mtok REAL NOT NULL,
+                output_per_mtok REAL NOT NULL,
+                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            INSERT INTO _schema_version (version) VALUES (15);
+            "
+        )?;
+
+        log::info!("RAG database migrated to v15 (LLM usage ledger + budgets)");
+        Ok(())
+    }
+
+    /// V16: per-project PII redaction settings and an audit of redacting
+    /// LLM calls (counts per kind only, never the values)
+    fn migrate_v16(&self, conn: &Connection) -> SqlResult<()> {
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS redaction_settings (
+                project_id TEXT PRIMARY KEY,
+                enabled INTEGER NOT NULL DEFAULT 1,
+                kinds TEXT NOT NULL DEFAULT '[]',
+                client_names TEXT NOT NULL DEFAULT '[]',
+                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            CREATE TABLE IF NOT EXISTS redaction_audit (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                project_id TEXT,
+                room_id TEXT,
+                task TEXT NOT NULL,
+                purpose TEXT NOT NULL DEFAULT '',
+                counts TEXT NOT NULL DEFAULT '{}',
+                total INTEGER NOT NULL DEFAULT 0
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_redaction_audit_project ON redaction_audit(project_id, created_at);
+
+            INSERT INTO _schema_version (version) VALUES (16);
+            "
+        )?;
+
+        log::info!("RAG database migrated to v16 (PII redaction settings + audit)");
+        Ok(())
+    }
+
+    /// V17: outbound data policy rules and a log of every LLM / sync call
+    /// leaving the device (content hash only)
+    fn migrate_v17(&self, conn: &Connection) -> SqlResult<()> {
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS outbound_rules (
+                id TEXT PRIMARY KEY,
+                channel TEXT NOT NULL CHECK (channel IN ('llm', 'sync', 'any')),
+                effect TEXT NOT NULL CHECK (effect IN ('block', 'local_only')),
+                scope TEXT,
+                project_id TEXT,
+                knowledge_type TEXT,
+                provider TEXT,
+                description TEXT NOT NULL DEFAULT '',
+                created_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            CREATE TABLE IF NOT EXISTS outbound_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                channel TEXT NOT NULL,
+                destination TEXT NOT NULL,
+                local INTEGER NOT NULL DEFAULT 0,
+                purpose TEXT NOT NULL DEFAULT '',
+                project_id TEXT,
+                room_id TEXT,
+                scopes TEXT NOT NULL DEFAULT '[]',
+                knowledge_types TEXT NOT NULL DEFAULT '[]',
+                content_hash TEXT NOT NULL,
+                bytes INTEGER NOT NULL DEFAULT 0,
+                allowed INTEGER NOT NULL,
+                rule_id TEXT
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_outbound_log_created ON outbound_log(created_at);
+
+            INSERT INTO _schema_version (version) VALUES (17);
+            "
+        )?;
+
+        log::info!("RAG database migrated to v17 (outbound policy + log)");
+        Ok(())
+    }
+
+    /// V18: traits per ontology term, so snapshots and pattern mining pick
+    /// knowledge types (organisation ones included) from the vocabulary.
+    /// Built-in terms get theirs from `ontology::sync_builtin` after migrating.
+    fn migrate_v18(&self, conn: &Connection) -> SqlResult<()> {
+        conn.execute_batch(
+            "
+            ALTER TABLE ontology_terms ADD COLUMN traits TEXT NOT NULL DEFAULT '[]';
+
+            INSERT INTO _schema_version (version) VALUES (18);
+            "
+        )?;
+
+        log::info!("RAG database migrated to v18 (ontology term traits)");
+        Ok(())
+    }
+
+    /// V19: when a room's last scheduled digest failed, so the scheduler backs
+    /// off for `min_interval_secs` instead of retrying it every tick
+    fn migrate_v19(&self, conn: &Connection) -> SqlResult<()> {
+        conn.execute_batch(
+            "
+            ALTER TABLE digest_watermarks ADD COLUMN last_failed_at TEXT;
+
+            INSERT INTO _schema_version (version) VALUES (19);
+            "
+        )?;
+
+        log::info!("RAG database migrated to v19 (digest failure timestamp)");
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -274,4 +982,36 @@ mod tests {
         drop(db);
         let _ = std::fs::remove_file(tmp);
     }
+
+    #[test]
+    fn test_listener_may_emit_and_subscribe() {
+        use crate::rag::events::{ChangeKind, KnowledgeChange};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let tmp = std::env::temp_dir().join("rag_test_emit_reentrant.db");
+        let _ = std::fs::remove_file(&tmp);
+        let db = Arc::new(RagDb::open(&tmp).expect("Failed to open DB"));
+        let change = || KnowledgeChange {
+            knowledge_id: "k1".to_string(),
+            kind: ChangeKind::Created,
+            scope: "personal".to_string(),
+            user_id: None,
+            project_id: None,
+        };
+
+        let seen = Arc::new(AtomicUsize::new(0));
+        let (inner_db, inner_seen) = (Arc::downgrade(&db), seen.clone());
+        db.subscribe(move |event| {
+            let n = inner_seen.fetch_add(1, Ordering::SeqCst);
+            if let (0, Some(db)) = (n, inner_db.upgrade()) {
+                db.subscribe(|_| {});
+                db.emit(event.clone());
+            }
+        });
+        db.emit(RagEvent::KnowledgeChanged(change()));
+        assert_eq!(seen.load(Ordering::SeqCst), 2);
+
+        drop(db);
+        let _ = std::fs::remove_file(tmp);
+    }
 }
@@ -0,0 +1,370 @@
+/// Near-Duplicate Detection — Merge instead of insert
+///
+/// Digests of overlapping message windows repeat the same decisions and risks.
+/// Before `create_knowledge_item`, ingest paths call `create_or_merge`:
+///
+///   duplicate = active item in the same scope + project (+ owner for personal)
+///               with cosine(embedding) >= threshold
+///
+/// A duplicate is merged into the existing item: confidence is bumped, the new
+/// source is appended to `source_context.merged_sources`, and the earliest
+/// `created_at` is kept. `dedupe_existing` applies the same rule to the stored DB,
+/// deactivating each duplicate and linking it `duplicate_of` the survivor.
+
+use crate::rag::db::RagDb;
+use crate::rag::embedding::{blob_to_vector, cosine_similarity, EMBEDDING_DIM};
+use crate::rag::events::ChangeKind;
+use crate::rag::history;
+use crate::rag::knowledge::{self, KnowledgeItem};
+use crate::rag::relations::{self, RelationType};
+use serde::{Deserialize, Serialize};
+
+/// Default similarity above which two items are considered the same knowledge
+pub const DEFAULT_DUPLICATE_THRESHOLD: f32 = 0.92;
+
+/// Confidence added to the surviving item per merged duplicate
+const CONFIDENCE_BUMP: f64 = 0.05;
+
+/// What happened to an ingested item
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum DedupOutcome {
+    Created { id: String },
+    Merged { into_id: String, similarity: f32 },
+}
+
+/// Result of deduplicating the existing DB
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DedupeReport {
+    pub scanned: usize,
+    pub merged: usize,
+    pub groups: Vec<DuplicateGroup>,
+    pub dry_run: bool,
+}
+
+/// Surviving item and the duplicates merged into it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub kept_id: String,
+    pub merged_ids: Vec<String>,
+}
+
+/// Insert `item`, or merge it into an existing near-duplicate.
+pub fn create_or_merge(
+    db: &RagDb,
+    item: &KnowledgeItem,
+    embedding: &[f32],
+    threshold: f32,
+) -> Result<DedupOutcome, String> {
+    if let Some((existing_id, similarity)) = find_duplicate(db, item, embedding, threshold)? {
+        merge_into(db, &existing_id, item, similarity)?;
+        log::info!(
+            "Merged near-duplicate into {} (similarity {:.3})",
+            existing_id,
+            similarity
+        );
+        return Ok(DedupOutcome::Merged { into_id: existing_id, similarity });
+    }
+
+    let id = knowledge::create_knowledge_item(db, item, embedding)?;
+    Ok(DedupOutcome::Created { id })
+}
+
+/// Most similar active item in the same scope/project at or above `threshold`.
+pub fn find_duplicate(
+    db: &RagDb,
+    item: &KnowledgeItem,
+    embedding: &[f32],
+    threshold: f32,
+) -> Result<Option<(String, f32)>, String> {
+    let candidates = load_candidates(db, item)?;
+
+    Ok(candidates
+        .into_iter()
+        .filter(|(id, _)| *id != item.id)
+        .map(|(id, vector)| (id, cosine_similarity(embedding, &vector)))
+        .filter(|(_, sim)| *sim >= threshold)
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal)))
+}
+
+/// Fold `duplicate` into the stored item `existing_id`.
+pub fn merge_into(
+    db: &RagDb,
+    existing_id: &str,
+    duplicate: &KnowledgeItem,
+    similarity: f32,
+) -> Result<(), String> {
+    let existing = knowledge::get_knowledge_item(db, existing_id)?
+        .ok_or_else(|| format!("Knowledge item not found: {}", existing_id))?;
+
+    history::ensure_baseline(db, &existing)?;
+
+    let confidence = (existing.confidence.max(duplicate.confidence) + CONFIDENCE_BUMP).min(1.0);
+    let source_context = append_provenance(existing.source_context.as_deref(), duplicate, similarity);
+
+    {
+        let conn = db.conn();
+        conn.execute(
+            "UPDATE knowledge_items SET
+                confidence = ?2,
+                source_context = ?3,
+                created_at = CASE WHEN julianday(?4) < julianday(created_at) THEN ?4 ELSE created_at END,
+                updated_at = ?5
+             WHERE id = ?1",
+            rusqlite::params![
+                existing_id,
+                confidence,
+                source_context,
+                duplicate.created_at,
+                chrono::Utc::now().to_rfc3339(),
+            ],
+        )
+        .map_err(|e| format!("Merge duplicate failed: {}", e))?;
+    }
+
+    if let Some(merged) = knowledge::get_knowledge_item(db, existing_id)? {
+        history::record_version(db, &merged, "merge", &["confidence", "source_context", "created_at"], None)?;
+    }
+    knowledge::notify_changed(db, &existing, ChangeKind::Updated);
+    Ok(())
+}
+
+/// Merge near-duplicates already stored in the DB (oldest item survives).
+///
+/// With `dry_run`, only reports the groups that would be merged.
+pub fn dedupe_existing(
+    db: &RagDb,
+    project_id: Option<&str>,
+    threshold: f32,
+    dry_run: bool,
+) -> Result<DedupeReport, String> {
+    let rows: Vec<ScanRow> = {
+        let conn = db.conn();
+        let mut stmt = conn
+            .prepare(
+                "SELECT k.id, k.scope, k.project_id, k.user_id, e.vector
+                 FROM knowledge_items k
+                 JOIN embeddings e ON e.knowledge_id = k.id
+                 WHERE k.is_active = 1
+                   AND (?1 IS NULL OR k.project_id = ?1)
+                 ORDER BY julianday(k.created_at) ASC",
+            )
+            .map_err(|e| format!("Prepare dedupe scan failed: {}", e))?;
+
+        let rows = stmt
+            .query_map(rusqlite::params![project_id], |row| {
+                Ok(ScanRow {
+                    id: row.get(0)?,
+                    scope: row.get(1)?,
+                    project_id: row.get(2)?,
+                    user_id: row.get(3)?,
+                    vector: blob_to_vector(&row.get::<_, Vec<u8>>(4)?),
+                })
+            })
+            .map_err(|e| format!("Dedupe scan failed: {}", e))?;
+        rows.filter_map(|r| r.ok())
+            .filter(|r| r.vector.len() == EMBEDDING_DIM)
+            .collect()
+    };
+
+    let mut report = DedupeReport {
+        scanned: rows.len(),
+        dry_run,
+        ..Default::default()
+    };
+
+    // Survivors: (row index, group index in report)
+    let mut kept: Vec<(usize, Option<usize>)> = Vec::new();
+
+    for (i, row) in rows.iter().enumerate() {
+        let best = kept
+            .iter_mut()
+            .filter(|(k, _)| row.same_partition(&rows[*k]))
+            .map(|entry| (cosine_similarity(&row.vector, &rows[entry.0].vector), entry))
+            .filter(|(sim, _)| *sim >= threshold)
+            .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        let Some((similarity, (kept_index, group))) = best else {
+            kept.push((i, None));
+            continue;
+        };
+
+        let kept_id = &rows[*kept_index].id;
+        let group_index = *group.get_or_insert_with(|| {
+            report.groups.push(DuplicateGroup {
+                kept_id: kept_id.clone(),
+                merged_ids: Vec::new(),
+            });
+            report.groups.len() - 1
+        });
+        report.groups[group_index].merged_ids.push(row.id.clone());
+        report.merged += 1;
+
+        if !dry_run {
+            if let Some(duplicate) = knowledge::get_knowledge_item(db, &row.id)? {
+                merge_into(db, kept_id, &duplicate, similarity)?;
+                knowledge::deactivate_knowledge_item(db, &row.id, None)?;
+                relations::link(db, &row.id, kept_id, RelationType::DuplicateOf, None, None)?;
+            }
+        }
+    }
+
+    log::info!(
+        "Dedupe: scanned {}, merged {} into {} groups{}",
+        report.scanned,
+        report.merged,
+        report.groups.len(),
+        if dry_run { " (dry run)" } else { "" }
+    );
+
+    Ok(report)
+}
+
+// ── Internal helpers ────────────────────────────────────
+
+struct ScanRow {
+    id: String,
+    scope: String,
+    project_id: Option<String>,
+    user_id: Option<String>,
+    vector: Vec<f32>,
+}
+
+impl ScanRow {
+    /// Same scope + project (+ owner for personal items), mirroring `load_candidates`.
+    fn same_partition(&self, other: &ScanRow) -> bool {
+        self.scope == other.scope
+            && self.project_id == other.project_id
+            && (self.scope != "personal" || self.user_id == other.user_id)
+    }
+}
+
+/// Active items visible in the same scope/project (and owner, for personal items).
+fn load_candidates(db: &RagDb, item: &KnowledgeItem) -> Result<Vec<(String, Vec<f32>)>, String> {
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare(
+            "SELECT k.id, e.vector
+             FROM knowledge_items k
+             JOIN embeddings e ON e.knowledge_id = k.id
+             WHERE k.is_active = 1
+               AND k.scope = ?1
+               AND k.project_id IS ?2
+               AND (?1 != 'personal' OR k.user_id IS ?3)",
+        )
+        .map_err(|e| format!("Prepare duplicate lookup failed: {}", e))?;
+
+    let rows = stmt
+        .query_map(
+            rusqlite::params![item.scope, item.project_id, item.user_id],
+            |row| Ok((row.get::<_, String>(0)?, blob_to_vector(&row.get::<_, Vec<u8>>(1)?))),
+        )
+        .map_err(|e| format!("Duplicate lookup failed: {}", e))?;
+
+    Ok(rows
+        .filter_map(|r| r.ok())
+        .filter(|(_, v)| v.len() == EMBEDDING_DIM)
+        .collect())
+}
+
+/// Append the duplicate's source to `merged_sources` in the source_context JSON.
+fn append_provenance(existing: Option<&str>, duplicate: &KnowledgeItem, similarity: f32) -> String {
+    let mut context = match existing.map(serde_json::from_str::<serde_json::Value>) {
+        Some(Ok(serde_json::Value::Object(map))) => map,
+        Some(_) => {
+            let mut map = serde_json::Map::new();
+            map.insert("original".to_string(), existing.unwrap_or_default().into());
+            map
+        }
+        None => serde_json::Map::new(),
+    };
+
+    let sources = context
+        .entry("merged_sources")
+        .or_insert_with(|| serde_json::Value::Array(vec![]));
+    if let serde_json::Value::Array(list) = sources {
+        list.push(serde_json::json!({
+            "source_type": duplicate.source_type,
+            "source_id": duplicate.source_id,
+            "content": duplicate.content,
+            "confidence": duplicate.confidence,
+            "similarity": similarity,
+            "created_at": duplicate.created_at,
+        }));
+    }
+
+    serde_json::Value::Object(context).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::embedding::EmbeddingEngine;
+    use uuid::Uuid;
+
+    fn setup() -> (RagDb, EmbeddingEngine) {
+        let dir = std::env::temp_dir().join(format!("rag_dedup_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        (RagDb::open(&dir.join("test.db")).unwrap(), EmbeddingEngine::new(dir.join("models")))
+    }
+
+    fn test_item(content: &str, project_id: Option<&str>, created_at: &str) -> KnowledgeItem {
+        KnowledgeItem {
+            source_type: "chat_digest".to_string(),
+            source_id: Some(format!("src-{}", created_at)),
+            project_id: project_id.map(|s| s.to_string()),
+            created_at: created_at.to_string(),
+            updated_at: created_at.to_string(),
+            ..KnowledgeItem::for_test(content)
+        }
+    }
+
+    #[test]
+    fn test_create_or_merge() {
+        let (db, engine) = setup();
+        let text = "촬영 예산은 3000만원으로 확정";
+        let vector = engine.embed(text).unwrap().vector;
+
+        let first = create_or_merge(&db, &test_item(text, Some("p1"), "2026-02-10T09:00:00Z"), &vector, 0.92).unwrap();
+        let DedupOutcome::Created { id } = first else { panic!("expected insert") };
+
+        // Same sentence from an overlapping digest window, reported earlier
+        let second = create_or_merge(&db, &test_item(text, Some("p1"), "2026-02-01T09:00:00Z"), &vector, 0.92).unwrap();
+        assert!(matches!(second, DedupOutcome::Merged { ref into_id, .. } if *into_id == id));
+
+        let merged = knowledge::get_knowledge_item(&db, &id).unwrap().unwrap();
+        assert!((merged.confidence - 0.75).abs() < 1e-9);
+        assert_eq!(merged.created_at, "2026-02-01T09:00:00Z");
+        assert!(chrono::DateTime::parse_from_rfc3339(&merged.updated_at).is_ok());
+        let context: serde_json::Value = serde_json::from_str(merged.source_context.as_deref().unwrap()).unwrap();
+        assert_eq!(context["merged_sources"][0]["source_id"], "src-2026-02-01T09:00:00Z");
+
+        // Other project → separate item
+        let other = create_or_merge(&db, &test_item(text, Some("p2"), "2026-02-10T09:00:00Z"), &vector, 0.92).unwrap();
+        assert!(matches!(other, DedupOutcome::Created { .. }));
+    }
+
+    #[test]
+    fn test_dedupe_existing() {
+        let (db, engine) = setup();
+        let text = "클라이언트 피드백은 금요일까지 반영";
+        let vector = engine.embed(text).unwrap().vector;
+        let unrelated = engine.embed("장비 렌탈 업체 변경").unwrap().vector;
+
+        let oldest = knowledge::create_knowledge_item(&db, &test_item(text, Some("p1"), "2026-01-01T00:00:00Z"), &vector).unwrap();
+        let dup = knowledge::create_knowledge_item(&db, &test_item(text, Some("p1"), "2026-01-05T00:00:00Z"), &vector).unwrap();
+        knowledge::create_knowledge_item(&db, &test_item("장비 렌탈 업체 변경", Some("p1"), "2026-01-06T00:00:00Z"), &unrelated).unwrap();
+
+        let preview = dedupe_existing(&db, Some("p1"), 0.92, true).unwrap();
+        assert_eq!((preview.scanned, preview.merged), (3, 1));
+        assert!(knowledge::get_knowledge_item(&db, &dup).unwrap().unwrap().is_active);
+
+        let report = dedupe_existing(&db, Some("p1"), 0.92, false).unwrap();
+        assert_eq!(report.groups[0].kept_id, oldest);
+        assert_eq!(report.groups[0].merged_ids, vec![dup.clone()]);
+        assert!(!knowledge::get_knowledge_item(&db, &dup).unwrap().unwrap().is_active);
+        let links = relations::list_relations(&db, &oldest).unwrap();
+        assert_eq!((links[0].source_id.as_str(), links[0].relation_type), (dup.as_str(), RelationType::DuplicateOf));
+        assert_eq!(dedupe_existing(&db, Some("p1"), 0.92, false).unwrap().merged, 0);
+    }
+}
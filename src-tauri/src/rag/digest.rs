@@ -1,4 +1,4 @@
-/// Chat Digest — Claude Haiku conversation analysis
+/// Chat Digest — LLM conversation analysis (Claude Haiku by default)
 ///
 /// Analyzes batches of chat messages to extract:
 /// - Decisions (의사결정)
@@ -6,82 +6,71 @@
 /// - Risks/Blockers (리스크/차단 요소)
 /// - Summary (요약)
 ///
-/// Local-first: messages are sent to Claude API for analysis only,
+/// Long conversations are map-reduced: messages are split into token-estimated
+/// windows, each window is digested on its own, and the results are merged
+/// (near-duplicate items collapsed, summaries combined). Stored digests record
+/// the time range of the messages they cover.
+///
+/// Prompts come from the registry (`prompts`) in the room's language; the
+/// digest template version is stored with every digest. Participant names
+/// and user ids are redacted from the prompts along with other PII
+/// (`redaction`) and restored in the reply.
+///
+/// Local-first: messages are sent to the configured LLM provider for analysis
+/// only (a local OpenAI-compatible server keeps them on the machine);
 /// extracted knowledge is stored locally in SQLite.
 /// Anthropic does NOT train on API data.
 
+use crate::rag::context::estimate_tokens;
 use crate::rag::db::RagDb;
+use crate::rag::events::RagEvent;
+use crate::rag::http;
+use crate::rag::llm::{LlmClient, LlmTask};
+use crate::rag::offline_digest;
+use crate::rag::prompts::{PromptId, PromptSet};
+use crate::rag::redaction::SensitiveTerm;
+use crate::rag::snapshot;
+use crate::rag::structured::{self, Discarded, ItemValidator};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use uuid::Uuid;
 
-/// Claude Haiku model ID for digest analysis
-const CLAUDE_HAIKU_MODEL: &str = "claude-haiku-4-5-20251001";
-
-/// Anthropic API endpoint
-const ANTHROPIC_API_URL: &str = "https://api.anthropic.com/v1/messages";
-
-/// API version header
-const ANTHROPIC_API_VERSION: &str = "2023-06-01";
-
 /// Max tokens for digest response
 const DIGEST_MAX_TOKENS: u32 = 2048;
 
-/// System prompt for chat digest analysis (exact Korean port from llm-digest.ts)
-const DIGEST_SYSTEM_PROMPT: &str = r#"You are "Re-Be Brain", an AI assistant analyzing Korean project management chat conversations.
-Your job is to analyze a batch of messages and extract structured intelligence.
-
-## Analysis Categories
-
-1. **DECISIONS** — Agreements, approvals, choices made by the team
-   - Look for: consensus phrases ("그렇게 하죠", "알겠습니다", "확정", "결정", "합의")
-   - Include: WHO decided, WHAT was decided, implied confidence level
-
-2. **ACTION ITEMS** — Tasks or commitments mentioned but not yet formalized
-   - Look for: explicit assignments ("~가 ~하기로", "~에게 ~요청")
-   - Also: implicit assignments ("이거 누가 해야 하는데...", "~해야 할 것 같은데")
-   - Include: assignee if identifiable, deadline if mentioned
-
-3. **RISKS / BLOCKERS** — Problems, delays, dependencies, concerns
-   - Look for: problem signals ("문제가", "걱정", "지연", "어려움", "빡빡해")
-   - Include: severity (low/medium/high), what's affected
-
-4. **SUMMARY** — 2-3 sentence Korean summary of the conversation batch
-
-## Korean Language Notes
-- Recognize indirect agreement patterns ("그렇게 하죠" = decision)
-- Extract assignees from honorific context ("김 대표님이 확인해주신다고" = 김 대표님 has action)
-- Detect urgency signals ("급합니다", "ASAP", "긴급" = high priority)
-- "ㅇㅇ", "ㄴㄴ" are casual yes/no
-
-## Response Format (JSON only, no markdown fences)
-{
-  "decisions": [
-    { "text": "description in Korean", "confidence": 0.0-1.0, "relatedUserIds": ["uuid"], "priority": "low|medium|high" }
-  ],
-  "actionItems": [
-    { "text": "description in Korean", "confidence": 0.0-1.0, "relatedUserIds": ["uuid"], "priority": "low|medium|high" }
-  ],
-  "risks": [
-    { "text": "description in Korean", "confidence": 0.0-1.0, "priority": "low|medium|high" }
-  ],
-  "summary": "2-3 sentence summary in Korean"
-}
+/// Estimated prompt tokens of chat messages per digest window
+pub const DIGEST_WINDOW_TOKENS: usize = 6000;
+
+/// Max tokens for the merged summary
+const SUMMARY_MAX_TOKENS: u32 = 512;
 
-If there are no items for a category, return an empty array.
-Always respond with valid JSON only."#;
+/// Character-bigram similarity above which two digest items are the same item
+const MERGE_SIMILARITY: f64 = 0.6;
 
 // ── Types ──────────────────────────────────────────────
 
+/// What `analyze_or_fallback` does when the LLM host cannot be reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WhenUnreachable {
+    /// Digest with the offline rules (interactive calls)
+    Fallback,
+    /// Return the error so the messages are retried later (scheduler)
+    Fail,
+}
+
 /// Chat message for digest input
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
+    /// Chat message id (lets the digest queue dedupe and order same-second messages)
+    #[serde(default)]
+    pub id: Option<String>,
     pub user_id: String,
     pub user_name: String,
     pub content: String,
     pub created_at: String,
 }
 
-/// Digest analysis result from Claude
+/// Digest analysis result from the LLM
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DigestResult {
     pub decisions: Vec<DigestItem>,
@@ -89,6 +78,18 @@ pub struct DigestResult {
     pub action_items: Vec<DigestItem>,
     pub risks: Vec<RiskItem>,
     pub summary: String,
+    /// Message windows analyzed (more than one for long conversations)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub windows: Vec<MessageRange>,
+    /// Reply items dropped during validation (not stored)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub discarded: Vec<Discarded>,
+    /// Why the rule-based digester was used instead of the LLM (absent for LLM digests)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<String>,
+    /// Digest template version (see `prompts`; absent for rule-based digests)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_version: Option<String>,
 }
 
 /// Decision or action item
@@ -115,6 +116,26 @@ fn default_priority() -> String {
     "medium".to_string()
 }
 
+/// Messages covered by a digest (or one window of it)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageRange {
+    /// `created_at` of the earliest message
+    pub start: String,
+    /// `created_at` of the latest message
+    pub end: String,
+    pub message_count: usize,
+}
+
+impl MessageRange {
+    pub fn of(messages: &[ChatMessage]) -> Option<Self> {
+        Some(Self {
+            start: messages.iter().map(|m| m.created_at.as_str()).min()?.to_string(),
+            end: messages.iter().map(|m| m.created_at.as_str()).max()?.to_string(),
+            message_count: messages.len(),
+        })
+    }
+}
+
 /// Stored digest record
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoredDigest {
@@ -123,92 +144,373 @@ pub struct StoredDigest {
     pub project_id: Option<String>,
     pub digest_type: String,
     pub content: String, // JSON string
+    pub message_range_start: Option<String>,
+    pub message_range_end: Option<String>,
     pub message_count: i64,
     pub confidence: f64,
+    pub prompt_version: Option<String>,
     pub created_at: String,
 }
 
-// ── Claude API call ────────────────────────────────────
+// ── LLM call ───────────────────────────────────────────
 
-/// Analyze a batch of chat messages using Claude Haiku.
+/// Analyze chat messages with the digest task's LLM.
 ///
 /// Returns structured DigestResult with decisions, action items, risks, summary.
-/// Requires ANTHROPIC_API_KEY environment variable.
+/// Conversations longer than one window are digested per window and merged.
 pub async fn analyze_conversation(
     messages: &[ChatMessage],
-    api_key: &str,
+    llm: &LlmClient,
+    prompts: &PromptSet,
+) -> Result<DigestResult, String> {
+    analyze_windows(messages, llm, prompts, DIGEST_WINDOW_TOKENS).await
+}
+
+/// Analyze with the LLM when one is configured, else with the offline rules.
+///
+/// `llm` is the result of building the task's client. A build error (no key,
+/// no base_url) falls back to `offline_digest`, recording the reason in
+/// `fallback`; so does an unreachable host (connect errors or timeouts after
+/// every retry, open circuit) unless `unreachable` is `Fail`. Cancellation,
+/// refusals and unparseable replies are returned as errors so callers keep
+/// the messages for a retry.
+pub async fn analyze_or_fallback(
+    messages: &[ChatMessage],
+    llm: Result<&LlmClient, &str>,
+    prompts: &PromptSet,
+    unreachable: WhenUnreachable,
+) -> Result<DigestResult, String> {
+    if messages.is_empty() {
+        return Err("No messages to analyze".to_string());
+    }
+    let reason = match llm {
+        Ok(llm) => match analyze_conversation(messages, llm, prompts).await {
+            Err(e) if unreachable == WhenUnreachable::Fallback && http::is_unreachable(&e) && !llm.is_cancelled() => e,
+            result => return result,
+        },
+        Err(e) => e.to_string(),
+    };
+    // The rules only know Korean signal phrases
+    log::warn!("Digest: using rule-based fallback ({}; room language {})", reason, prompts.language.as_str());
+    let mut digest = offline_digest::analyze(messages);
+    digest.fallback = Some(reason);
+    Ok(digest)
+}
+
+async fn analyze_windows(
+    messages: &[ChatMessage],
+    llm: &LlmClient,
+    prompts: &PromptSet,
+    window_tokens: usize,
 ) -> Result<DigestResult, String> {
     if messages.is_empty() {
         return Err("No messages to analyze".to_string());
     }
 
-    // Format messages for Claude
-    let formatted = messages
+    // Map: one digest per window
+    let windows = message_windows(messages, window_tokens);
+    let mut partials = Vec::with_capacity(windows.len());
+    for (index, window) in windows.iter().enumerate() {
+        if windows.len() > 1 {
+            log::info!("Digest window {}/{} ({} messages)", index + 1, windows.len(), window.len());
+        }
+        partials.push(analyze_window(window, llm, prompts).await?);
+    }
+
+    // Reduce: merge items, then combine the summaries
+    let summaries: Vec<String> = partials.iter().map(|p| p.summary.clone()).collect();
+    let mut digest = merge_digests(partials);
+    digest.windows = windows.iter().filter_map(|w| MessageRange::of(w)).collect();
+    digest.prompt_version = Some(prompts.get(PromptId::Digest).version.clone());
+    if summaries.iter().filter(|s| !s.is_empty()).count() > 1 {
+        digest.summary = match reduce_summaries(&summaries, &participant_terms(messages), llm, prompts).await {
+            Ok(summary) => summary,
+            Err(e) if !llm.is_cancelled() => {
+                log::warn!("Summary merge failed, joining window summaries: {}", e);
+                digest.summary
+            }
+            Err(e) => return Err(e),
+        };
+    }
+    Ok(digest)
+}
+
+async fn analyze_window(messages: &[ChatMessage], llm: &LlmClient, prompts: &PromptSet) -> Result<DigestResult, String> {
+    let formatted = messages.iter().map(format_message).collect::<Vec<_>>().join("\n");
+
+    let user_prompt = prompts
+        .get(PromptId::DigestRequest)
+        .render(&[("message_count", &messages.len().to_string()), ("messages", &formatted)]);
+
+    let system = &prompts.get(PromptId::Digest).text;
+    let reply = llm
+        .complete_json_redacting(
+            LlmTask::Digest,
+            system,
+            &user_prompt,
+            DIGEST_MAX_TOKENS,
+            digest_schema(),
+            &participant_terms(messages),
+        )
+        .await?;
+
+    let digest = parse_digest(&reply);
+    if !digest.discarded.is_empty() {
+        log::warn!("Digest: discarded {} invalid item(s)", digest.discarded.len());
+    }
+    Ok(digest)
+}
+
+/// Names and ids of the people in `messages` (redacted from prompts)
+fn participant_terms(messages: &[ChatMessage]) -> Vec<SensitiveTerm> {
+    let mut terms: Vec<SensitiveTerm> = Vec::new();
+    for m in messages {
+        for term in [SensitiveTerm::person(&m.user_name), SensitiveTerm::user_id(&m.user_id)] {
+            if !terms.contains(&term) {
+                terms.push(term);
+            }
+        }
+    }
+    terms
+}
+
+fn format_message(m: &ChatMessage) -> String {
+    format!("[{}] {} ({}): {}", m.created_at, m.user_name, m.user_id, m.content)
+}
+
+/// Split messages (in order) into windows of at most `max_tokens` estimated
+/// prompt tokens. A single oversized message gets a window of its own.
+pub fn message_windows(messages: &[ChatMessage], max_tokens: usize) -> Vec<&[ChatMessage]> {
+    let mut windows = Vec::new();
+    let mut start = 0;
+    let mut used = 0;
+    for (i, message) in messages.iter().enumerate() {
+        let tokens = estimate_tokens(&format_message(message)) + 1;
+        if i > start && used + tokens > max_tokens {
+            windows.push(&messages[start..i]);
+            start = i;
+            used = 0;
+        }
+        used += tokens;
+    }
+    if start < messages.len() {
+        windows.push(&messages[start..]);
+    }
+    windows
+}
+
+/// Merge per-window digests: near-duplicate items collapse into the most
+/// confident one (assignees unioned, highest priority kept). The summary is
+/// the window summaries joined; `discarded` paths are prefixed by window.
+pub fn merge_digests(partials: Vec<DigestResult>) -> DigestResult {
+    let multiple = partials.len() > 1;
+    let mut merged = DigestResult {
+        decisions: vec![],
+        action_items: vec![],
+        risks: vec![],
+        summary: String::new(),
+        windows: vec![],
+        discarded: vec![],
+        fallback: None,
+        prompt_version: None,
+    };
+    let mut summaries = Vec::new();
+
+    for (index, partial) in partials.into_iter().enumerate() {
+        merged.decisions.extend(partial.decisions);
+        merged.action_items.extend(partial.action_items);
+        merged.risks.extend(partial.risks);
+        if !partial.summary.is_empty() {
+            summaries.push(partial.summary);
+        }
+        merged.discarded.extend(partial.discarded.into_iter().map(|mut d| {
+            if multiple {
+                d.path = format!("window[{}].{}", index, d.path);
+            }
+            d
+        }));
+    }
+
+    let absorb = |kept: &mut DigestItem, other: DigestItem| {
+        for user in other.related_user_ids {
+            if !kept.related_user_ids.contains(&user) {
+                kept.related_user_ids.push(user);
+            }
+        }
+        if priority_rank(&other.priority) > priority_rank(&kept.priority) {
+            kept.priority = other.priority;
+        }
+        if other.confidence > kept.confidence {
+            kept.text = other.text;
+            kept.confidence = other.confidence;
+        }
+    };
+    merged.decisions = dedupe_items(merged.decisions, |i| &i.text, absorb);
+    merged.action_items = dedupe_items(merged.action_items, |i| &i.text, absorb);
+    merged.risks = dedupe_items(
+        merged.risks,
+        |r| &r.text,
+        |kept, other| {
+            if priority_rank(&other.priority) > priority_rank(&kept.priority) {
+                kept.priority = other.priority;
+            }
+            if other.confidence > kept.confidence {
+                kept.text = other.text;
+                kept.confidence = other.confidence;
+            }
+        },
+    );
+    merged.summary = summaries.join(" ");
+    merged
+}
+
+/// Keep the first of each group of near-duplicates, folding the rest into it.
+fn dedupe_items<T>(items: Vec<T>, text: impl Fn(&T) -> &str, absorb: impl Fn(&mut T, T)) -> Vec<T> {
+    let mut kept: Vec<T> = Vec::with_capacity(items.len());
+    for item in items {
+        match kept.iter().position(|k| text_similarity(text(k), text(&item)) >= MERGE_SIMILARITY) {
+            Some(pos) => absorb(&mut kept[pos], item),
+            None => kept.push(item),
+        }
+    }
+    kept
+}
+
+/// Dice coefficient over character bigrams (whitespace ignored); 1.0 if one contains the other.
+fn text_similarity(a: &str, b: &str) -> f64 {
+    let chars = |s: &str| s.chars().filter(|c| !c.is_whitespace()).collect::<Vec<char>>();
+    let (a, b) = (chars(a), chars(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let (short, long) = if a.len() <= b.len() { (&a, &b) } else { (&b, &a) };
+    if long.windows(short.len()).any(|w| w == short.as_slice()) {
+        return 1.0;
+    }
+    let bigrams = |v: &[char]| v.windows(2).map(|w| (w[0], w[1])).collect::<Vec<_>>();
+    let (ba, mut bb) = (bigrams(&a), bigrams(&b));
+    if ba.is_empty() || bb.is_empty() {
+        return 0.0;
+    }
+    let total = ba.len() + bb.len();
+    let mut shared = 0;
+    for gram in &ba {
+        if let Some(pos) = bb.iter().position(|g| g == gram) {
+            bb.swap_remove(pos);
+            shared += 1;
+        }
+    }
+    2.0 * shared as f64 / total as f64
+}
+
+fn priority_rank(priority: &str) -> u8 {
+    match priority {
+        "high" => 2,
+        "medium" => 1,
+        _ => 0,
+    }
+}
+
+/// One summary for the whole conversation from the per-window summaries.
+async fn reduce_summaries(
+    summaries: &[String],
+    terms: &[SensitiveTerm],
+    llm: &LlmClient,
+    prompts: &PromptSet,
+) -> Result<String, String> {
+    let window = prompts.get(PromptId::DigestReduceWindow);
+    let user_prompt = summaries
         .iter()
-        .map(|m| format!("[{}] {} ({}): {}", m.created_at, m.user_name, m.user_id, m.content))
+        .enumerate()
+        .map(|(i, s)| window.render(&[("window", &(i + 1).to_string()), ("summary", s)]))
         .collect::<Vec<_>>()
         .join("\n");
+    let schema = serde_json::json!({
+        "type": "object",
+        "properties": { "summary": { "type": "string" } },
+        "required": ["summary"]
+    });
+    let reply = llm
+        .complete_json_redacting(
+            LlmTask::Digest,
+            &prompts.get(PromptId::DigestReduce).text,
+            &user_prompt,
+            SUMMARY_MAX_TOKENS,
+            schema,
+            terms,
+        )
+        .await?;
+    structured::optional_str(&reply, "summary").ok_or_else(|| "Merged summary is empty".to_string())
+}
 
-    let user_prompt = format!(
-        "다음 채팅 메시지들을 분석해주세요 ({} messages):\n\n{}",
-        messages.len(),
-        formatted
-    );
+/// JSON schema of the digest reply (requested as structured output)
+fn digest_schema() -> Value {
+    let item = serde_json::json!({
+        "type": "object",
+        "properties": {
+            "text": { "type": "string" },
+            "confidence": { "type": "number", "minimum": 0, "maximum": 1 },
+            "relatedUserIds": { "type": "array", "items": { "type": "string" } },
+            "priority": { "type": "string", "enum": ["low", "medium", "high"] }
+        },
+        "required": ["text", "confidence", "priority"]
+    });
+    serde_json::json!({
+        "type": "object",
+        "properties": {
+            "decisions": { "type": "array", "items": item },
+            "actionItems": { "type": "array", "items": item },
+            "risks": { "type": "array", "items": item },
+            "summary": { "type": "string" }
+        },
+        "required": ["decisions", "actionItems", "risks", "summary"]
+    })
+}
 
-    // Build Anthropic API request
-    let request_body = serde_json::json!({
-        "model": CLAUDE_HAIKU_MODEL,
-        "max_tokens": DIGEST_MAX_TOKENS,
-        "system": DIGEST_SYSTEM_PROMPT,
-        "messages": [
-            {
-                "role": "user",
-                "content": user_prompt
-            }
-        ]
+/// Validate a parsed digest reply item by item; invalid items end up in `discarded`.
+pub fn parse_digest(reply: &Value) -> DigestResult {
+    let mut validator = ItemValidator::new();
+    let digest_item = |item: &Value| -> Result<DigestItem, String> {
+        Ok(DigestItem {
+            text: structured::required_str(item, "text")?,
+            confidence: structured::confidence(item, "confidence")?,
+            related_user_ids: structured::str_list(item, "relatedUserIds"),
+            priority: normalize_priority(item),
+        })
+    };
+
+    let decisions = validator.items(reply, "decisions", digest_item);
+    let action_items = validator.items(reply, "actionItems", digest_item);
+    let risks = validator.items(reply, "risks", |item| {
+        Ok(RiskItem {
+            text: structured::required_str(item, "text")?,
+            confidence: structured::confidence(item, "confidence")?,
+            priority: normalize_priority(item),
+        })
+    });
+    let summary = structured::optional_str(reply, "summary").unwrap_or_else(|| {
+        validator.discard("summary".to_string(), "summary is missing".to_string());
+        String::new()
     });
 
-    let client = reqwest::Client::new();
-    let response = client
-        .post(ANTHROPIC_API_URL)
-        .header("x-api-key", api_key)
-        .header("anthropic-version", ANTHROPIC_API_VERSION)
-        .header("content-type", "application/json")
-        .json(&request_body)
-        .send()
-        .await
-        .map_err(|e| format!("Claude API request failed: {}", e))?;
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        return Err(format!("Claude API error {}: {}", status, body));
+    DigestResult {
+        decisions,
+        action_items,
+        risks,
+        summary,
+        windows: vec![],
+        discarded: validator.discarded,
+        fallback: None,
+        prompt_version: None,
     }
+}
 
-    let api_response: serde_json::Value = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse Claude response: {}", e))?;
-
-    // Extract text content from Claude's response
-    let text = api_response["content"]
-        .as_array()
-        .and_then(|arr| arr.first())
-        .and_then(|block| block["text"].as_str())
-        .ok_or_else(|| "No text content in Claude response".to_string())?;
-
-    // Parse JSON from Claude's response (may contain markdown fences)
-    let clean_json = text
-        .trim()
-        .trim_start_matches("```json")
-        .trim_start_matches("```")
-        .trim_end_matches("```")
-        .trim();
-
-    let digest: DigestResult = serde_json::from_str(clean_json)
-        .map_err(|e| format!("Failed to parse digest JSON: {} — raw: {}", e, clean_json))?;
-
-    Ok(digest)
+/// low / medium / high (anything else → medium)
+fn normalize_priority(item: &Value) -> String {
+    match structured::optional_str(item, "priority").map(|p| p.to_lowercase()).as_deref() {
+        Some(p @ ("low" | "medium" | "high")) => p.to_string(),
+        _ => default_priority(),
+    }
 }
 
 // ── Storage ────────────────────────────────────────────
@@ -220,7 +522,7 @@ pub fn store_digest(
     room_id: &str,
     project_id: Option<&str>,
     digest: &DigestResult,
-    message_count: i64,
+    range: &MessageRange,
 ) -> Result<Vec<String>, String> {
     let conn = db.conn();
     let mut ids = Vec::new();
@@ -238,20 +540,26 @@ pub fn store_digest(
             "decisions" => avg_confidence_items(&digest.decisions),
             "action_items" => avg_confidence_items(&digest.action_items),
             "risks" => avg_confidence_risks(&digest.risks),
+            _ if digest.fallback.is_some() => offline_digest::RULE_MAX_CONFIDENCE,
             _ => 0.8,
         };
 
         conn.execute(
-            "INSERT INTO chat_digests (id, room_id, project_id, digest_type, content, message_count, confidence)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            "INSERT INTO chat_digests
+                (id, room_id, project_id, digest_type, content,
+                 message_range_start, message_range_end, message_count, confidence, prompt_version)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
             rusqlite::params![
                 id,
                 room_id,
                 project_id,
                 digest_type,
                 content_json,
-                message_count,
+                range.start,
+                range.end,
+                range.message_count as i64,
                 confidence,
+                digest.prompt_version,
             ],
         )
         .map_err(|e| format!("Store digest failed: {}", e))?;
@@ -259,10 +567,21 @@ pub fn store_digest(
         ids.push(id);
     }
 
+    if let Some(project_id) = project_id {
+        snapshot::invalidate_with(&conn, project_id)?;
+    }
+
+    drop(conn);
+    if let Some(project_id) = project_id {
+        db.emit(RagEvent::SnapshotInvalidated(project_id.to_string()));
+    }
+
     log::info!(
-        "Stored digest for room {} ({} messages, {} decisions, {} actions, {} risks)",
+        "Stored digest for room {} ({} messages {} – {}, {} decisions, {} actions, {} risks)",
         room_id,
-        message_count,
+        range.message_count,
+        range.start,
+        range.end,
         digest.decisions.len(),
         digest.action_items.len(),
         digest.risks.len(),
@@ -280,7 +599,8 @@ pub fn get_recent_digests(
     let conn = db.conn();
     let mut stmt = conn
         .prepare(
-            "SELECT id, room_id, project_id, digest_type, content, message_count, confidence, created_at
+            "SELECT id, room_id, project_id, digest_type, content,
+                    message_range_start, message_range_end, message_count, confidence, prompt_version, created_at
              FROM chat_digests
              WHERE room_id = ?1
              ORDER BY created_at DESC
@@ -296,9 +616,12 @@ pub fn get_recent_digests(
                 project_id: row.get(2)?,
                 digest_type: row.get(3)?,
                 content: row.get(4)?,
-                message_count: row.get(5)?,
-                confidence: row.get(6)?,
-                created_at: row.get(7)?,
+                message_range_start: row.get(5)?,
+                message_range_end: row.get(6)?,
+                message_count: row.get(7)?,
+                confidence: row.get(8)?,
+                prompt_version: row.get(9)?,
+                created_at: row.get(10)?,
             })
         })
         .map_err(|e| format!("Query digests failed: {}", e))?
@@ -327,6 +650,7 @@ fn avg_confidence_risks(items: &[RiskItem]) -> f64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rag::prompts::{Language, PromptRegistry};
 
     #[test]
     fn test_parse_digest_result() {
@@ -364,4 +688,124 @@ mod tests {
         assert!(result.action_items.is_empty());
         assert!(result.risks.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_analyze_with_mock_provider() {
+        use crate::rag::llm::MockProvider;
+
+        // Prose around the JSON, a trailing comma, one item without text
+        let reply = "분석 결과:\n```json\n{\"decisions\": [{\"text\": \"촬영일 3월 5일 확정\", \"confidence\": \"0.9\", \"priority\": \"HIGH\"}, {\"confidence\": 0.5},], \"actionItems\": [], \"risks\": [], \"summary\": \"촬영일 확정\"}\n```";
+        let llm = LlmClient::new(Box::new(MockProvider::with_reply(reply)), "mock");
+        let messages = vec![ChatMessage {
+            id: None,
+            user_id: "u1".to_string(),
+            user_name: "김PD".to_string(),
+            content: "촬영은 3월 5일로 하죠".to_string(),
+            created_at: "2026-02-01T10:00:00Z".to_string(),
+        }];
+
+        let prompts = PromptRegistry::default().set(Language::Ko);
+        let result = analyze_conversation(&messages, &llm, &prompts).await.unwrap();
+        assert_eq!(result.decisions.len(), 1);
+        assert_eq!(result.decisions[0].text, "촬영일 3월 5일 확정");
+        assert_eq!(result.decisions[0].confidence, 0.9);
+        assert_eq!(result.decisions[0].priority, "high");
+        assert_eq!(result.discarded.len(), 1);
+        assert_eq!(result.discarded[0].path, "decisions[1]");
+        assert_eq!(result.discarded[0].reason, "text is missing");
+        assert_eq!(result.prompt_version.as_deref(), Some("digest.ko.v1"));
+        assert!(analyze_conversation(&[], &llm, &prompts).await.is_err());
+    }
+
+    fn message(minute: u32, content: &str) -> ChatMessage {
+        ChatMessage {
+            id: Some(format!("m{}", minute)),
+            user_id: format!("u{}", minute % 3),
+            user_name: "김PD".to_string(),
+            content: content.to_string(),
+            created_at: format!("2026-03-02T10:{:02}:00Z", minute),
+        }
+    }
+
+    fn item(text: &str, confidence: f64, priority: &str, users: &[&str]) -> DigestItem {
+        DigestItem {
+            text: text.to_string(),
+            confidence,
+            related_user_ids: users.iter().map(|u| u.to_string()).collect(),
+            priority: priority.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_map_reduce_long_conversation() {
+        use crate::rag::llm::MockProvider;
+
+        let messages: Vec<ChatMessage> = (0..40)
+            .map(|m| message(m, "촬영 장소는 성수동 스튜디오로 하고 콘티는 금요일까지 공유하기로 했습니다"))
+            .collect();
+        let windows = message_windows(&messages, 200);
+        assert!(windows.len() > 2);
+        assert_eq!(windows.iter().map(|w| w.len()).sum::<usize>(), 40);
+        assert_eq!(message_windows(&messages, DIGEST_WINDOW_TOKENS).len(), 1);
+
+        // Every window reports the same decision; the reduce step keeps one
+        let reply = r#"{"decisions": [{"text": "촬영 장소 성수동 스튜디오 확정", "confidence": 0.8, "priority": "medium"}],
+                        "actionItems": [], "risks": [], "summary": "촬영 준비 논의"}"#;
+        let llm = LlmClient::new(Box::new(MockProvider::with_reply(reply)), "mock");
+        let prompts = PromptRegistry::default().set(Language::Ko);
+        let digest = analyze_windows(&messages, &llm, &prompts, 200).await.unwrap();
+        assert_eq!(digest.decisions.len(), 1);
+        assert_eq!(digest.windows.len(), windows.len());
+        assert_eq!(digest.windows[0].start, "2026-03-02T10:00:00Z");
+        assert_eq!(digest.windows.last().unwrap().end, "2026-03-02T10:39:00Z");
+        assert_eq!(digest.summary, "촬영 준비 논의");
+
+        // Ranges are stored with the digest
+        let dir = std::env::temp_dir().join(format!("rag_digest_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = RagDb::open(&dir.join("test.db")).unwrap();
+        let range = MessageRange::of(&messages).unwrap();
+        store_digest(&db, "room1", None, &digest, &range).unwrap();
+        let stored = get_recent_digests(&db, "room1", 10).unwrap();
+        assert_eq!(stored.len(), 4);
+        assert!(stored.iter().all(|d| d.message_range_start.as_deref() == Some("2026-03-02T10:00:00Z")
+            && d.message_range_end.as_deref() == Some("2026-03-02T10:39:00Z")
+            && d.message_count == 40));
+    }
+
+    #[test]
+    fn test_merge_digests_dedupes_across_windows() {
+        let window = |decisions: Vec<DigestItem>, summary: &str, discarded: Vec<Discarded>| DigestResult {
+            decisions,
+            action_items: vec![],
+            risks: vec![],
+            summary: summary.to_string(),
+            windows: vec![],
+            discarded,
+            fallback: None,
+            prompt_version: None,
+        };
+        let merged = merge_digests(vec![
+            window(vec![item("예산 3000만원 확정", 0.7, "medium", &["a"])], "오전 회의", vec![]),
+            window(
+                vec![
+                    item("예산 3000만원으로 최종 확정", 0.9, "high", &["b"]),
+                    item("촬영일 3월 5일", 0.8, "low", &[]),
+                ],
+                "오후 회의",
+                vec![Discarded { path: "risks[0]".to_string(), reason: "text is missing".to_string() }],
+            ),
+        ]);
+
+        assert_eq!(merged.decisions.len(), 2);
+        let budget = &merged.decisions[0];
+        assert_eq!(budget.text, "예산 3000만원으로 최종 확정");
+        assert_eq!(budget.confidence, 0.9);
+        assert_eq!(budget.priority, "high");
+        assert_eq!(budget.related_user_ids, vec!["a", "b"]);
+        assert_eq!(merged.summary, "오전 회의 오후 회의");
+        assert_eq!(merged.discarded[0].path, "window[1].risks[0]");
+        assert!(text_similarity("촬영일 3월 5일", "예산 3000만원 확정") < MERGE_SIMILARITY);
+    }
+
 }
@@ -112,6 +112,24 @@ impl EmbeddingEngine {
         Ok(self.pseudo_embed(text))
     }
 
+    /// Count tokens for context budgeting.
+    /// Uses the loaded tokenizer when available, otherwise `context::estimate_tokens`.
+    pub fn count_tokens(&self, text: &str) -> usize {
+        #[cfg(feature = "onnx")]
+        {
+            if self.is_model_available() && self.ensure_onnx_loaded().is_ok() {
+                if let Ok(guard) = self.onnx_session.lock() {
+                    if let Some(session) = guard.as_ref() {
+                        if let Ok(encoding) = session.tokenizer.encode(text, false) {
+                            return encoding.get_ids().len();
+                        }
+                    }
+                }
+            }
+        }
+        crate::rag::context::estimate_tokens(text)
+    }
+
     /// Generate embedding using ONNX Runtime.
     #[cfg(feature = "onnx")]
     fn embed_onnx(&self, text: &str) -> Result<EmbeddingResult, String> {
@@ -0,0 +1,61 @@
+/// RAG Events — In-process notifications from the knowledge layer
+///
+/// The rag module has no Tauri dependency, so anything that must reach the
+/// frontend (Tauri events, native notifications) is published here and
+/// forwarded by listeners registered in `lib.rs` via `RagDb::subscribe`.
+///
+/// Listeners are invoked synchronously on the emitting thread, always after
+/// the database connection has been released, so they may query the DB.
+/// The listener lock is not held during dispatch either, so a listener may
+/// itself emit further events or subscribe new listeners.
+
+use crate::rag::conflicts::KnowledgeConflict;
+use crate::rag::outcomes::TrackedDecision;
+use crate::rag::scheduler::RoomQueueStatus;
+use crate::rag::standing::StandingMatch;
+use crate::rag::usage::BudgetAlert;
+use serde::Serialize;
+use std::sync::Arc;
+
+/// Event published by the knowledge layer
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RagEvent {
+    /// A knowledge item was created, updated, deactivated or deleted
+    KnowledgeChanged(KnowledgeChange),
+    /// A newly stored knowledge item matched a saved standing query
+    StandingQueryMatched(StandingMatch),
+    /// Two knowledge items in the same project were found to contradict each other
+    ConflictDetected(KnowledgeConflict),
+    /// A decision has been pending or escalated longer than the reminder interval
+    DecisionReminder(TrackedDecision),
+    /// A room's digest queue crossed a threshold but the digest needs the frontend's API key
+    DigestDue(RoomQueueStatus),
+    /// An LLM call pushed a project's monthly spend past its warning level or limit
+    BudgetAlert(BudgetAlert),
+    /// A project's context snapshot was dropped (payload: project id)
+    SnapshotInvalidated(String),
+}
+
+/// What happened to a knowledge item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Created,
+    Updated,
+    Deactivated,
+    Deleted,
+}
+
+/// Scope information of a changed knowledge item (enough to invalidate caches)
+#[derive(Debug, Clone, Serialize)]
+pub struct KnowledgeChange {
+    pub knowledge_id: String,
+    pub kind: ChangeKind,
+    pub scope: String,
+    pub user_id: Option<String>,
+    pub project_id: Option<String>,
+}
+
+/// Listener callback registered on `RagDb`
+pub type EventListener = Arc<dyn Fn(&RagEvent) + Send + Sync>;
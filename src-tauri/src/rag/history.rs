@@ -0,0 +1,270 @@
+/// Knowledge Version History — Snapshots of knowledge items after every change
+///
+/// Each row in `knowledge_item_versions` holds the full item as JSON after an
+/// update, deactivate, restore, revert or merge. Items created before history
+/// existed get a `baseline` snapshot of their prior state on first change, so
+/// every edit can be reverted.
+
+use crate::rag::db::RagDb;
+use crate::rag::embedding::EmbeddingEngine;
+use crate::rag::knowledge::{self, KnowledgeItem};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// One stored version of a knowledge item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnowledgeVersion {
+    pub id: String,
+    pub knowledge_id: String,
+    pub version: i64,
+    pub change_type: String,
+    pub changed_fields: Vec<String>,
+    pub snapshot: KnowledgeItem,
+    pub changed_by: Option<String>,
+    pub created_at: String,
+}
+
+/// Append a snapshot of `item` as the next version. Returns the version number.
+pub fn record_version(
+    db: &RagDb,
+    item: &KnowledgeItem,
+    change_type: &str,
+    changed_fields: &[&str],
+    changed_by: Option<&str>,
+) -> Result<i64, String> {
+    record_version_with(&db.conn(), item, change_type, changed_fields, changed_by)
+}
+
+/// `record_version` for callers already holding the connection or a transaction.
+pub(crate) fn record_version_with(
+    conn: &Connection,
+    item: &KnowledgeItem,
+    change_type: &str,
+    changed_fields: &[&str],
+    changed_by: Option<&str>,
+) -> Result<i64, String> {
+    let snapshot = serde_json::to_string(item).map_err(|e| format!("Serialize snapshot failed: {}", e))?;
+    let fields = serde_json::to_string(changed_fields).map_err(|e| format!("Serialize fields failed: {}", e))?;
+
+    conn.query_row(
+        "INSERT INTO knowledge_item_versions
+            (id, knowledge_id, version, change_type, changed_fields, snapshot, changed_by)
+         SELECT ?1, ?2, COALESCE(MAX(version), 0) + 1, ?3, ?4, ?5, ?6
+         FROM knowledge_item_versions WHERE knowledge_id = ?2
+         RETURNING version",
+        rusqlite::params![
+            Uuid::new_v4().to_string(),
+            item.id,
+            change_type,
+            fields,
+            snapshot,
+            changed_by,
+        ],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Record version failed: {}", e))
+}
+
+/// Record the current state as a `baseline` version if the item has no history yet.
+pub fn ensure_baseline(db: &RagDb, item: &KnowledgeItem) -> Result<(), String> {
+    ensure_baseline_with(&db.conn(), item)
+}
+
+/// `ensure_baseline` for callers already holding the connection or a transaction.
+pub(crate) fn ensure_baseline_with(conn: &Connection, item: &KnowledgeItem) -> Result<(), String> {
+    let has_history: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM knowledge_item_versions WHERE knowledge_id = ?1)",
+            [&item.id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Check history failed: {}", e))?;
+
+    if !has_history {
+        record_version_with(conn, item, "baseline", &[], None)?;
+    }
+    Ok(())
+}
+
+/// All versions of an item, newest first.
+pub fn list_versions(db: &RagDb, knowledge_id: &str) -> Result<Vec<KnowledgeVersion>, String> {
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, knowledge_id, version, change_type, changed_fields, snapshot, changed_by, created_at
+             FROM knowledge_item_versions
+             WHERE knowledge_id = ?1
+             ORDER BY version DESC",
+        )
+        .map_err(|e| format!("Prepare versions failed: {}", e))?;
+
+    let rows = stmt
+        .query_map([knowledge_id], row_to_version)
+        .map_err(|e| format!("Query versions failed: {}", e))?;
+
+    Ok(rows.filter_map(|r| r.ok()).collect())
+}
+
+/// A single version of an item.
+pub fn get_version(db: &RagDb, knowledge_id: &str, version: i64) -> Result<Option<KnowledgeVersion>, String> {
+    let conn = db.conn();
+    let result = conn.query_row(
+        "SELECT id, knowledge_id, version, change_type, changed_fields, snapshot, changed_by, created_at
+         FROM knowledge_item_versions
+         WHERE knowledge_id = ?1 AND version = ?2",
+        rusqlite::params![knowledge_id, version],
+        row_to_version,
+    );
+
+    match result {
+        Ok(v) => Ok(Some(v)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(format!("Get version failed: {}", e)),
+    }
+}
+
+/// Restore the editable fields of an earlier version (re-embeds if content differs).
+///
+/// Activity state and usage statistics stay as they are; the revert itself is
+/// recorded as a new version.
+pub fn revert_to_version(
+    db: &RagDb,
+    engine: &EmbeddingEngine,
+    knowledge_id: &str,
+    version: i64,
+    changed_by: Option<&str>,
+) -> Result<KnowledgeItem, String> {
+    let target = get_version(db, knowledge_id, version)?
+        .ok_or_else(|| format!("Version {} not found for {}", version, knowledge_id))?;
+    let current = knowledge::get_knowledge_item(db, knowledge_id)?
+        .ok_or_else(|| format!("Knowledge item not found: {}", knowledge_id))?;
+
+    let mut next = current.clone();
+    knowledge::copy_editable_fields(&target.snapshot, &mut next);
+
+    knowledge::save_changes(db, engine, &current, &next, "revert", changed_by)
+}
+
+fn row_to_version(row: &rusqlite::Row) -> rusqlite::Result<KnowledgeVersion> {
+    let fields: String = row.get(4)?;
+    let snapshot: String = row.get(5)?;
+    Ok(KnowledgeVersion {
+        id: row.get(0)?,
+        knowledge_id: row.get(1)?,
+        version: row.get(2)?,
+        change_type: row.get(3)?,
+        changed_fields: serde_json::from_str(&fields).unwrap_or_default(),
+        snapshot: serde_json::from_str(&snapshot).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e))
+        })?,
+        changed_by: row.get(6)?,
+        created_at: row.get(7)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::embedding::blob_to_vector;
+    use crate::rag::knowledge::KnowledgeUpdate;
+
+    fn setup() -> (RagDb, EmbeddingEngine) {
+        let dir = std::env::temp_dir().join(format!("rag_history_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        (RagDb::open(&dir.join("test.db")).unwrap(), EmbeddingEngine::new(dir.join("models")))
+    }
+
+    fn create(db: &RagDb, engine: &EmbeddingEngine, content: &str) -> String {
+        let item = KnowledgeItem {
+            dialectic_tag: Some("risk".to_string()),
+            user_id: Some("u1".to_string()),
+            project_id: Some("p1".to_string()),
+            ..KnowledgeItem::for_test(content)
+        };
+        let vector = engine.embed(content).unwrap().vector;
+        knowledge::create_knowledge_item(db, &item, &vector).unwrap()
+    }
+
+    fn stored_vector(db: &RagDb, id: &str) -> Vec<f32> {
+        let conn = db.conn();
+        let blob: Vec<u8> = conn
+            .query_row("SELECT vector FROM embeddings WHERE knowledge_id = ?1", [id], |r| r.get(0))
+            .unwrap();
+        blob_to_vector(&blob)
+    }
+
+    #[test]
+    fn test_update_records_history_and_reembeds() {
+        let (db, engine) = setup();
+        let id = create(&db, &engine, "촬영 예산 3000만원");
+
+        let update = KnowledgeUpdate {
+            content: Some("촬영 예산 3500만원으로 증액".to_string()),
+            dialectic_tag: Some(String::new()), // clear
+            ..Default::default()
+        };
+        let updated = knowledge::update_knowledge_item(&db, &engine, &id, &update, Some("u1")).unwrap();
+        assert_eq!(updated.dialectic_tag, None);
+        assert_eq!(stored_vector(&db, &id), engine.embed("촬영 예산 3500만원으로 증액").unwrap().vector);
+
+        // No-op updates do not create versions
+        knowledge::update_knowledge_item(&db, &engine, &id, &update, Some("u1")).unwrap();
+
+        let versions = list_versions(&db, &id).unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].change_type, "update");
+        assert_eq!(versions[0].changed_fields, vec!["content", "dialectic_tag"]);
+        assert_eq!(versions[1].change_type, "baseline");
+        assert_eq!(versions[1].snapshot.content, "촬영 예산 3000만원");
+    }
+
+    #[test]
+    fn test_revert_and_lifecycle() {
+        let (db, engine) = setup();
+        let id = create(&db, &engine, "원본 내용");
+
+        let update = KnowledgeUpdate {
+            content: Some("수정된 내용".to_string()),
+            ..Default::default()
+        };
+        knowledge::update_knowledge_item(&db, &engine, &id, &update, None).unwrap();
+
+        let reverted = revert_to_version(&db, &engine, &id, 1, Some("u1")).unwrap();
+        assert_eq!(reverted.content, "원본 내용");
+        assert_eq!(reverted.dialectic_tag.as_deref(), Some("risk"));
+        assert_eq!(stored_vector(&db, &id), engine.embed("원본 내용").unwrap().vector);
+
+        knowledge::deactivate_knowledge_item(&db, &id, Some("u1")).unwrap();
+        assert!(!knowledge::get_knowledge_item(&db, &id).unwrap().unwrap().is_active);
+        knowledge::restore_knowledge_item(&db, &id, Some("u1")).unwrap();
+        assert!(knowledge::get_knowledge_item(&db, &id).unwrap().unwrap().is_active);
+
+        let types: Vec<String> = list_versions(&db, &id).unwrap().into_iter().map(|v| v.change_type).collect();
+        assert_eq!(types, vec!["restore", "deactivate", "revert", "update", "baseline"]);
+
+        knowledge::delete_knowledge_item(&db, &id).unwrap();
+        assert!(knowledge::get_knowledge_item(&db, &id).unwrap().is_none());
+        assert!(list_versions(&db, &id).unwrap().is_empty());
+        assert!(knowledge::delete_knowledge_item(&db, &id).is_err());
+    }
+
+    #[test]
+    fn test_sync_update_keeps_history_and_embedding() {
+        let (db, engine) = setup();
+        let id = create(&db, &engine, "동기화 전 내용");
+
+        let mut delta = crate::sync::delta::get_delta(&db, None).unwrap();
+        delta.items.retain(|i| i.id == id);
+        delta.items[0].content = "다른 기기에서 수정".to_string();
+        delta.items[0].embedding = vec![];
+        delta.items[0].updated_at = "2999-01-01T00:00:00Z".to_string();
+        crate::sync::delta::apply_delta(&db, &delta).unwrap();
+
+        let versions = list_versions(&db, &id).unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].changed_by.as_deref(), Some("sync"));
+        assert_eq!(versions[1].snapshot.content, "동기화 전 내용");
+        assert!(!stored_vector(&db, &id).is_empty());
+    }
+}
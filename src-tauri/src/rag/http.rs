@@ -0,0 +1,538 @@
+/// Resilient HTTP — the shared transport under every LLM provider
+///
+/// One `ResilientHttp` lives in app state and is handed to each `LlmClient`:
+/// - Connect / read / total timeouts on a single pooled `reqwest::Client`
+/// - Retries on transient failures (connect errors, timeouts, 408/425/429/5xx/529)
+///   with exponential backoff + jitter, honouring `retry-after` / `retry-after-ms`
+/// - Per-host circuit breaker: after repeated failed calls the host is skipped
+///   for a cooldown instead of making the user wait through more retries
+/// - Cancellation: callers register a request id; `cancel(id)` aborts the
+///   in-flight request or backoff sleep immediately
+///
+/// Non-transient errors (400, 401, 403, …) fail on the first attempt and do
+/// not count against the breaker. Errors where the host never answered
+/// (connect failures or timeouts on every attempt, open circuit) are marked so
+/// callers can tell an outage from a refusal: see `is_unreachable`.
+
+use rand::Rng;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// HTTP statuses worth retrying (529 = Anthropic "overloaded")
+const RETRYABLE_STATUSES: [u16; 9] = [408, 425, 429, 500, 502, 503, 504, 522, 529];
+
+/// Ends errors where the host could not be reached
+const UNREACHABLE: &str = " (host unreachable)";
+
+/// True if the call failed because the host could not be reached — not for
+/// refusals, bad replies or cancellation.
+pub fn is_unreachable(error: &str) -> bool {
+    error.ends_with(UNREACHABLE)
+}
+
+/// Timeouts, retry and breaker settings
+#[derive(Debug, Clone)]
+pub struct HttpPolicy {
+    pub connect_timeout: Duration,
+    /// Max idle time between reads of the response
+    pub read_timeout: Duration,
+    /// Max time for one attempt (request + full response)
+    pub request_timeout: Duration,
+    /// Retries after the first attempt
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// A `retry-after` longer than this fails the call instead of waiting
+    pub max_retry_after: Duration,
+    /// Consecutive failed calls that open a host's circuit
+    pub breaker_threshold: u32,
+    pub breaker_cooldown: Duration,
+}
+
+impl Default for HttpPolicy {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(60),
+            request_timeout: Duration::from_secs(120),
+            max_retries: 4,
+            base_delay: Duration::from_millis(1000),
+            max_delay: Duration::from_secs(30),
+            max_retry_after: Duration::from_secs(60),
+            breaker_threshold: 5,
+            breaker_cooldown: Duration::from_secs(60),
+        }
+    }
+}
+
+// ── Cancellation ───────────────────────────────────────
+
+/// Cheap, cloneable cancel flag
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    inner: Arc<CancelInner>,
+}
+
+#[derive(Debug, Default)]
+struct CancelInner {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once `cancel` has been called.
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.inner.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// A registered in-flight call; unregisters itself when dropped.
+pub struct InFlight<'a> {
+    http: &'a ResilientHttp,
+    request_id: Option<String>,
+    token: CancelToken,
+}
+
+impl InFlight<'_> {
+    pub fn token(&self) -> CancelToken {
+        self.token.clone()
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        if let Some(id) = &self.request_id {
+            self.http.in_flight.lock().expect("In-flight lock poisoned").remove(id);
+        }
+    }
+}
+
+// ── Circuit breaker ────────────────────────────────────
+
+#[derive(Debug, Default)]
+struct Breaker {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// Breaker state of one host (for the UI)
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitStatus {
+    pub host: String,
+    pub consecutive_failures: u32,
+    pub open: bool,
+    /// Seconds until a trial request is allowed again (0 when closed)
+    pub retry_in_secs: u64,
+}
+
+// ── Client ─────────────────────────────────────────────
+
+/// Shared HTTP transport with retries, breaker and cancellation
+pub struct ResilientHttp {
+    client: reqwest::Client,
+    policy: HttpPolicy,
+    breakers: Mutex<HashMap<String, Breaker>>,
+    in_flight: Mutex<HashMap<String, CancelToken>>,
+}
+
+/// Outcome of one attempt
+enum Attempt {
+    Done(serde_json::Value),
+    /// Transient failure; optional server-requested delay
+    Retry(String, Option<Duration>),
+    /// Connect failure or timeout: the server did not answer
+    Unreached(String),
+    Fatal(String),
+}
+
+impl ResilientHttp {
+    pub fn new(policy: HttpPolicy) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .connect_timeout(policy.connect_timeout)
+            .read_timeout(policy.read_timeout)
+            .timeout(policy.request_timeout)
+            .build()
+            .map_err(|e| format!("HTTP client init failed: {}", e))?;
+        Ok(Self {
+            client,
+            policy,
+            breakers: Mutex::new(HashMap::new()),
+            in_flight: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Register a call the UI may cancel by `request_id` (unregistered calls get a private token).
+    pub fn begin(&self, request_id: Option<&str>) -> InFlight<'_> {
+        let token = CancelToken::default();
+        let request_id = request_id.map(String::from);
+        if let Some(id) = &request_id {
+            self.in_flight.lock().expect("In-flight lock poisoned").insert(id.clone(), token.clone());
+        }
+        InFlight { http: self, request_id, token }
+    }
+
+    /// Cancel a registered call. Returns false if no such call is running.
+    pub fn cancel(&self, request_id: &str) -> bool {
+        match self.in_flight.lock().expect("In-flight lock poisoned").get(request_id) {
+            Some(token) => {
+                token.cancel();
+                log::info!("HTTP request {} cancelled", request_id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Hosts that have failed recently.
+    pub fn circuits(&self) -> Vec<CircuitStatus> {
+        let now = Instant::now();
+        let breakers = self.breakers.lock().expect("Circuit breaker lock poisoned");
+        let mut statuses: Vec<CircuitStatus> = breakers
+            .iter()
+            .map(|(host, b)| {
+                let remaining = b.open_until.map(|t| t.saturating_duration_since(now)).unwrap_or_default();
+                CircuitStatus {
+                    host: host.clone(),
+                    consecutive_failures: b.consecutive_failures,
+                    open: !remaining.is_zero(),
+                    retry_in_secs: remaining.as_secs_f64().ceil() as u64,
+                }
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.host.cmp(&b.host));
+        statuses
+    }
+
+    /// POST a JSON body and return the JSON response, retrying transient failures.
+    /// `label` prefixes errors (e.g. "Claude extraction").
+    pub async fn post_json(
+        &self,
+        label: &str,
+        url: &str,
+        headers: &[(&str, &str)],
+        bearer: Option<&str>,
+        body: &serde_json::Value,
+        cancel: Option<&CancelToken>,
+    ) -> Result<serde_json::Value, String> {
+        let host = reqwest::Url::parse(url)
+            .map_err(|e| format!("Invalid URL {}: {}", url, e))
+            .map(|u| match u.port() {
+                Some(port) => format!("{}:{}", u.host_str().unwrap_or_default(), port),
+                None => u.host_str().unwrap_or_default().to_string(),
+            })?;
+        self.check_circuit(&host).map_err(|e| e + UNREACHABLE)?;
+
+        let mut attempt = 0;
+        loop {
+            let request = {
+                let mut builder = self.client.post(url).json(body);
+                for (name, value) in headers {
+                    builder = builder.header(*name, *value);
+                }
+                if let Some(key) = bearer {
+                    builder = builder.bearer_auth(key);
+                }
+                builder
+            };
+
+            let outcome = cancellable(cancel, label, Self::attempt(request, label)).await?;
+            let (error, server_delay, answered) = match outcome {
+                Attempt::Done(value) => {
+                    self.record(&host, true);
+                    return Ok(value);
+                }
+                Attempt::Fatal(error) => return Err(error),
+                Attempt::Retry(error, server_delay) => (error, server_delay, true),
+                Attempt::Unreached(error) => (error, None, false),
+            };
+
+            if attempt >= self.policy.max_retries {
+                self.record(&host, false);
+                let error = format!("{} (gave up after {} attempts)", error, attempt + 1);
+                return Err(if answered { error } else { error + UNREACHABLE });
+            }
+            if let Some(wait) = server_delay.filter(|d| *d > self.policy.max_retry_after) {
+                self.record(&host, false);
+                return Err(format!("{} (server asked to retry in {}s)", error, wait.as_secs()));
+            }
+            let delay = server_delay.unwrap_or_else(|| backoff(&self.policy, attempt));
+            attempt += 1;
+            log::warn!("{} — retry {}/{} in {:?}", error, attempt, self.policy.max_retries, delay);
+            cancellable(cancel, label, tokio::time::sleep(delay)).await?;
+        }
+    }
+
+    async fn attempt(request: reqwest::RequestBuilder, label: &str) -> Attempt {
+        let response = match request.send().await {
+            Ok(r) => r,
+            Err(e) if e.is_timeout() || e.is_connect() || e.is_request() => {
+                return Attempt::Unreached(format!("{} request failed: {}", label, e))
+            }
+            Err(e) => return Attempt::Fatal(format!("{} request failed: {}", label, e)),
+        };
+
+        let status = response.status();
+        if !status.is_success() {
+            let server_delay = retry_after(response.headers());
+            let body = response.text().await.unwrap_or_default();
+            let error = format!("{} API error {}: {}", label, status, body);
+            return if RETRYABLE_STATUSES.contains(&status.as_u16()) {
+                Attempt::Retry(error, server_delay)
+            } else {
+                Attempt::Fatal(error)
+            };
+        }
+
+        match response.json().await {
+            Ok(value) => Attempt::Done(value),
+            Err(e) if e.is_timeout() => Attempt::Unreached(format!("{} response timed out: {}", label, e)),
+            Err(e) if e.is_body() => Attempt::Retry(format!("{} response interrupted: {}", label, e), None),
+            Err(e) => Attempt::Fatal(format!("Failed to parse {} response: {}", label, e)),
+        }
+    }
+
+    fn check_circuit(&self, host: &str) -> Result<(), String> {
+        let breakers = self.breakers.lock().expect("Circuit breaker lock poisoned");
+        if let Some(until) = breakers.get(host).and_then(|b| b.open_until) {
+            let now = Instant::now();
+            if until > now {
+                return Err(format!(
+                    "{} is unavailable after repeated failures; retrying in {}s",
+                    host,
+                    until.duration_since(now).as_secs_f64().ceil() as u64
+                ));
+            }
+            // Cooldown over: half-open, let this call through as the trial
+        }
+        Ok(())
+    }
+
+    fn record(&self, host: &str, success: bool) {
+        let mut breakers = self.breakers.lock().expect("Circuit breaker lock poisoned");
+        if success {
+            breakers.remove(host);
+            return;
+        }
+        let breaker = breakers.entry(host.to_string()).or_default();
+        breaker.consecutive_failures += 1;
+        if breaker.consecutive_failures >= self.policy.breaker_threshold {
+            breaker.open_until = Some(Instant::now() + self.policy.breaker_cooldown);
+            log::warn!(
+                "Circuit open for {} after {} failed calls ({:?} cooldown)",
+                host,
+                breaker.consecutive_failures,
+                self.policy.breaker_cooldown
+            );
+        }
+    }
+}
+
+/// Run `future` unless `cancel` fires first.
+async fn cancellable<T>(
+    cancel: Option<&CancelToken>,
+    label: &str,
+    future: impl std::future::Future<Output = T>,
+) -> Result<T, String> {
+    let Some(token) = cancel else {
+        return Ok(future.await);
+    };
+    tokio::select! {
+        value = future => Ok(value),
+        _ = token.cancelled() => Err(format!("{} request cancelled", label)),
+    }
+}
+
+/// Exponential backoff with jitter: half fixed, half random.
+fn backoff(policy: &HttpPolicy, attempt: u32) -> Duration {
+    let exp = policy
+        .base_delay
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(policy.max_delay);
+    let half = exp.as_millis() as u64 / 2;
+    Duration::from_millis(half + rand::thread_rng().gen_range(0..=half))
+}
+
+/// Server-requested delay from `retry-after-ms`, `retry-after` (seconds or HTTP date).
+fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(str::trim);
+
+    if let Some(ms) = header("retry-after-ms").and_then(|v| v.parse::<f64>().ok()) {
+        return Some(Duration::from_secs_f64(ms.max(0.0) / 1000.0));
+    }
+    let value = header("retry-after")?;
+    if let Ok(secs) = value.parse::<f64>() {
+        return Some(Duration::from_secs_f64(secs.max(0.0)));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let secs = (at.timestamp() - chrono::Utc::now().timestamp()).max(0);
+    Some(Duration::from_secs(secs as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn fast_policy() -> HttpPolicy {
+        HttpPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(5),
+            max_delay: Duration::from_millis(20),
+            breaker_threshold: 2,
+            breaker_cooldown: Duration::from_secs(60),
+            ..Default::default()
+        }
+    }
+
+    fn reply(status: &str, headers: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n{}\r\n{}",
+            status,
+            body.len(),
+            headers,
+            body
+        )
+    }
+
+    /// Local server answering with `replies` in order (the last one repeats).
+    /// An empty reply means: never answer.
+    async fn mock_server(replies: Vec<String>) -> (String, Arc<AtomicUsize>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1/messages", listener.local_addr().unwrap());
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                let response = replies[n.min(replies.len() - 1)].clone();
+                tokio::spawn(async move {
+                    // Read headers + body before answering
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 4096];
+                    loop {
+                        let read = socket.read(&mut chunk).await.unwrap_or(0);
+                        if read == 0 {
+                            return;
+                        }
+                        buf.extend_from_slice(&chunk[..read]);
+                        let text = String::from_utf8_lossy(&buf).to_lowercase();
+                        if let Some(end) = text.find("\r\n\r\n") {
+                            let length = text
+                                .lines()
+                                .find_map(|l| l.strip_prefix("content-length:"))
+                                .and_then(|v| v.trim().parse::<usize>().ok())
+                                .unwrap_or(0);
+                            if buf.len() >= end + 4 + length {
+                                break;
+                            }
+                        }
+                    }
+                    if response.is_empty() {
+                        tokio::time::sleep(Duration::from_secs(30)).await;
+                        return;
+                    }
+                    let _ = socket.write_all(response.as_bytes()).await;
+                    let _ = socket.shutdown().await;
+                });
+            }
+        });
+        (url, hits)
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors_only() {
+        let http = ResilientHttp::new(fast_policy()).unwrap();
+        let body = serde_json::json!({"q": 1});
+
+        // Overloaded, rate limited, then success
+        let (url, hits) = mock_server(vec![
+            reply("529 Overloaded", "retry-after: 0\r\n", r#"{"error":"overloaded"}"#),
+            reply("429 Too Many Requests", "retry-after-ms: 10\r\n", "{}"),
+            reply("200 OK", "", r#"{"ok":true}"#),
+        ])
+        .await;
+        let value = http.post_json("Test", &url, &[], None, &body, None).await.unwrap();
+        assert_eq!(value["ok"], true);
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+
+        // Client errors are not retried
+        let (url, hits) = mock_server(vec![reply("400 Bad Request", "", r#"{"error":"bad"}"#)]).await;
+        let err = http.post_json("Test", &url, &[], None, &body, None).await.unwrap_err();
+        assert!(err.contains("400"), "{}", err);
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        // A retry-after beyond the policy limit fails instead of waiting
+        let (url, hits) = mock_server(vec![reply("429 Too Many Requests", "retry-after: 3600\r\n", "{}")]).await;
+        let err = http.post_json("Test", &url, &[], None, &body, None).await.unwrap_err();
+        assert!(err.contains("3600s"), "{}", err);
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        // Nothing listening: connect errors on every attempt mark the host unreachable
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let err = http
+            .post_json("Test", &format!("http://{}/v1/messages", closed), &[], None, &body, None)
+            .await
+            .unwrap_err();
+        assert!(err.contains("gave up after 3 attempts") && is_unreachable(&err), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_and_cancel() {
+        let http = ResilientHttp::new(HttpPolicy { max_retries: 0, ..fast_policy() }).unwrap();
+        let body = serde_json::json!({});
+
+        let (url, hits) = mock_server(vec![reply("503 Service Unavailable", "", "{}")]).await;
+        for _ in 0..2 {
+            let err = http.post_json("Test", &url, &[], None, &body, None).await.unwrap_err();
+            assert!(!is_unreachable(&err), "{}", err);
+        }
+        // Circuit open: fails fast without reaching the server
+        let err = http.post_json("Test", &url, &[], None, &body, None).await.unwrap_err();
+        assert!(err.contains("unavailable") && is_unreachable(&err), "{}", err);
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+        let circuits = http.circuits();
+        assert_eq!(circuits.len(), 1);
+        assert!(circuits[0].open && circuits[0].retry_in_secs > 0);
+
+        // A hanging request is aborted by cancel(request_id)
+        let (url, _) = mock_server(vec![String::new()]).await;
+        let call = http.begin(Some("req-1"));
+        let token = call.token();
+        let started = Instant::now();
+        let (result, cancelled) = tokio::join!(
+            http.post_json("Test", &url, &[], None, &body, Some(&token)),
+            async {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                http.cancel("req-1")
+            }
+        );
+        assert!(cancelled);
+        let err = result.unwrap_err();
+        assert!(err.contains("cancelled") && !is_unreachable(&err), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        drop(call);
+        assert!(!http.cancel("req-1"));
+    }
+}
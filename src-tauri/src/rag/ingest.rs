@@ -1,73 +1,31 @@
 /// Knowledge Ingest Pipeline — Extract knowledge from various sources
 ///
 /// Extracts structured knowledge items from:
-/// - Chat digests (via Claude Haiku deep analysis)
+/// - Chat digests (via LLM deep analysis, Claude Haiku by default)
 /// - Brain actions (task completions, decisions)
 /// - Peer reviews (project completion reviews)
 ///
 /// All extracted knowledge is embedded locally (ONNX/pseudo) and stored in SQLite.
+/// Digest/action items that nearly repeat stored knowledge are merged (see `dedup`).
 
 use crate::rag::db::RagDb;
+use crate::rag::dedup::{self, DedupOutcome};
 use crate::rag::digest::DigestResult;
 use crate::rag::embedding::EmbeddingEngine;
 use crate::rag::knowledge::{self, KnowledgeItem};
+use crate::rag::llm::{LlmClient, LlmTask};
+use crate::rag::ontology::{self, Dimension, Ontology};
+use crate::rag::prompts::{self, PromptId, Template};
+use crate::rag::structured::{self, Discarded, ItemValidator};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-/// Claude Haiku model for deep knowledge extraction
-const CLAUDE_HAIKU_MODEL: &str = "claude-haiku-4-5-20251001";
-const ANTHROPIC_API_URL: &str = "https://api.anthropic.com/v1/messages";
-const ANTHROPIC_API_VERSION: &str = "2023-06-01";
-
-/// System prompt for deep knowledge extraction from digest
-const EXTRACT_SYSTEM_PROMPT: &str = r#"You are a knowledge extraction engine for a Korean creative agency project management system.
-
-Given a chat digest (decisions, action items, risks, summary), extract reusable knowledge patterns.
-
-## Extraction Rules
-1. Focus on REUSABLE patterns, not one-time facts
-2. Each item should be a general principle or recurring pattern
-3. Include context about WHY this knowledge matters
-4. Tag with appropriate knowledge_type and role_tag
-5. Maximum 5 items per digest (quality over quantity)
-
-## Available knowledge_types:
-decision_pattern, preference, judgment, collaboration_pattern, recurring_risk,
-workflow, domain_expertise, feedback_pattern, communication_style, lesson_learned,
-creative_direction, budget_judgment, stakeholder_alignment, schedule_change, context,
-deal_decision, budget_decision, payment_tracking, vendor_selection, campaign_strategy,
-naming_decision, award_strategy, pitch_execution, talent_casting
-
-## Available role_tags:
-CEO, CD, PD, EDITOR, DIRECTOR, WRITER, DESIGNER, MANAGER,
-BUDGET_MANAGER, PROJECT_MANAGER, EXECUTIVE_PRODUCER, LINE_PD,
-SENIOR_ART_DIRECTOR, ART_DIRECTOR
-
-## Available dialectic_tags:
-risk, opportunity, constraint, quality, client_concern
-
-## Available scope_layers:
-operations, creative, pitch
-
-## Response Format (JSON only, no markdown fences)
-{
-  "items": [
-    {
-      "content": "한국어로 작성된 재사용 가능한 지식 패턴",
-      "knowledge_type": "decision_pattern",
-      "role_tag": "CEO",
-      "dialectic_tag": null,
-      "scope_layer": "operations",
-      "confidence": 0.7
-    }
-  ]
-}
-
-If no reusable knowledge can be extracted, return: {"items": []}
-Always respond with valid JSON only."#;
+/// Max tokens for the extraction response
+const EXTRACT_MAX_TOKENS: u32 = 2048;
 
 // ── Types ──────────────────────────────────────────────
 
-/// Extracted knowledge item from Claude
+/// Extracted knowledge item from the LLM
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExtractedItem {
     pub content: String,
@@ -82,19 +40,28 @@ pub struct ExtractedItem {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExtractionResult {
     pub items: Vec<ExtractedItem>,
+    /// Reply items dropped during validation
+    #[serde(default)]
+    pub discarded: Vec<Discarded>,
 }
 
 /// Result of ingesting knowledge
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IngestResult {
     pub created_ids: Vec<String>,
+    /// Existing items that absorbed a near-duplicate instead of an insert
+    #[serde(default)]
+    pub merged_ids: Vec<String>,
     pub skipped_count: usize,
     pub is_pseudo_embedding: bool,
+    /// LLM reply items dropped as invalid (digest extraction only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub discarded: Vec<Discarded>,
 }
 
-// ── From Digest (Claude deep extraction) ───────────────
+// ── From Digest (LLM deep extraction) ──────────────────
 
-/// Extract knowledge from a digest using Claude Haiku.
+/// Extract knowledge from a digest with the extraction task's LLM.
 /// This is the "deep" extraction that finds reusable patterns.
 pub async fn from_digest(
     db: &RagDb,
@@ -103,26 +70,35 @@ pub async fn from_digest(
     user_id: Option<&str>,
     project_id: Option<&str>,
     source_id: &str,
-    api_key: &str,
+    llm: &LlmClient,
 ) -> Result<IngestResult, String> {
     // Check if already extracted (duplicate prevention)
     if knowledge::is_extracted(db, "chat_digest", source_id)? {
         log::info!("Digest {} already extracted, skipping", source_id);
         return Ok(IngestResult {
             created_ids: vec![],
+            merged_ids: vec![],
             skipped_count: 0,
             is_pseudo_embedding: false,
+            discarded: vec![],
         });
     }
 
-    // Format digest for Claude
+    // Format digest for the model
     let digest_text = format_digest_for_extraction(digest);
 
-    // Call Claude Haiku for deep extraction
-    let extracted = call_extraction_api(&digest_text, api_key).await?;
+    // Deep extraction in the project's language, then map the model's tags onto the vocabulary
+    let ontology = ontology::load(db)?;
+    let template = prompts::for_scope(db, None, project_id)?.get(PromptId::Extraction).clone();
+    let system = extraction_prompt(&template, &ontology);
+    let mut extracted = call_extraction_api(&digest_text, &system, &extraction_schema(&ontology), llm).await?;
+    for item in &mut extracted.items {
+        conform_to_ontology(&ontology, item);
+    }
 
     // Ingest each extracted item
     let mut created_ids = Vec::new();
+    let mut merged_ids = Vec::new();
     let mut is_pseudo = false;
 
     for item in &extracted.items {
@@ -149,6 +125,8 @@ pub async fn from_digest(
             source_context: Some(serde_json::json!({
                 "extraction_source": "digest_deep_analysis",
                 "digest_summary": digest.summary,
+                "prompt_version": template.version,
+                "digest_prompt_version": digest.prompt_version,
             }).to_string()),
             user_id: user_id.map(|s| s.to_string()),
             project_id: project_id.map(|s| s.to_string()),
@@ -159,30 +137,32 @@ pub async fn from_digest(
             updated_at: chrono::Utc::now().to_rfc3339(),
         };
 
-        let id = knowledge::create_knowledge_item(db, &knowledge_item, &embed_result.vector)?;
-        created_ids.push(id);
+        store_deduplicated(db, &knowledge_item, &embed_result.vector, &mut created_ids, &mut merged_ids)?;
     }
 
     // Mark as extracted
     knowledge::mark_extracted(db, "chat_digest", source_id, created_ids.len() as i64)?;
 
     log::info!(
-        "Extracted {} knowledge items from digest {}",
+        "Extracted {} knowledge items from digest {} ({} merged into existing)",
         created_ids.len(),
-        source_id
+        source_id,
+        merged_ids.len()
     );
 
     Ok(IngestResult {
         created_ids,
+        merged_ids,
         skipped_count: 0,
         is_pseudo_embedding: is_pseudo,
+        discarded: extracted.discarded,
     })
 }
 
 // ── From Action (brain actions) ────────────────────────
 
 /// Extract knowledge from a brain action (task creation, completion, etc.)
-/// This is a simpler, rule-based extraction (no Claude API needed).
+/// This is a simpler, rule-based extraction (no LLM call needed).
 pub fn from_action(
     db: &RagDb,
     embedding: &EmbeddingEngine,
@@ -196,8 +176,10 @@ pub fn from_action(
     if knowledge::is_extracted(db, "brain_action", source_id)? {
         return Ok(IngestResult {
             created_ids: vec![],
+            merged_ids: vec![],
             skipped_count: 0,
             is_pseudo_embedding: false,
+            discarded: vec![],
         });
     }
 
@@ -242,13 +224,17 @@ pub fn from_action(
         updated_at: chrono::Utc::now().to_rfc3339(),
     };
 
-    let id = knowledge::create_knowledge_item(db, &item, &embed_result.vector)?;
-    knowledge::mark_extracted(db, "brain_action", source_id, 1)?;
+    let mut created_ids = Vec::new();
+    let mut merged_ids = Vec::new();
+    store_deduplicated(db, &item, &embed_result.vector, &mut created_ids, &mut merged_ids)?;
+    knowledge::mark_extracted(db, "brain_action", source_id, created_ids.len() as i64)?;
 
     Ok(IngestResult {
-        created_ids: vec![id],
+        created_ids,
+        merged_ids,
         skipped_count: 0,
         is_pseudo_embedding: embed_result.is_pseudo,
+        discarded: vec![],
     })
 }
 
@@ -270,8 +256,10 @@ pub fn from_review(
     if knowledge::is_extracted(db, "peer_review", source_id)? {
         return Ok(IngestResult {
             created_ids: vec![],
+            merged_ids: vec![],
             skipped_count: 0,
             is_pseudo_embedding: false,
+            discarded: vec![],
         });
     }
 
@@ -279,8 +267,10 @@ pub fn from_review(
     if comment.trim().is_empty() || comment.len() < 10 {
         return Ok(IngestResult {
             created_ids: vec![],
+            merged_ids: vec![],
             skipped_count: 1,
             is_pseudo_embedding: false,
+            discarded: vec![],
         });
     }
 
@@ -327,15 +317,17 @@ pub fn from_review(
 
     Ok(IngestResult {
         created_ids: vec![id],
+        merged_ids: vec![],
         skipped_count: 0,
         is_pseudo_embedding: embed_result.is_pseudo,
+        discarded: vec![],
     })
 }
 
-// ── From Digest Items (lightweight, no Claude) ─────────
+// ── From Digest Items (lightweight, no LLM) ────────────
 
 /// Lightweight ingest: store digest decisions/risks directly as knowledge.
-/// No Claude API call — uses digest items directly.
+/// No LLM call — uses digest items directly.
 pub fn from_digest_items(
     db: &RagDb,
     embedding: &EmbeddingEngine,
@@ -344,6 +336,7 @@ pub fn from_digest_items(
     project_id: Option<&str>,
 ) -> Result<IngestResult, String> {
     let mut created_ids = Vec::new();
+    let mut merged_ids = Vec::new();
     let mut is_pseudo = false;
 
     // Decisions → decision_pattern
@@ -381,8 +374,7 @@ pub fn from_digest_items(
             updated_at: chrono::Utc::now().to_rfc3339(),
         };
 
-        let id = knowledge::create_knowledge_item(db, &item, &embed_result.vector)?;
-        created_ids.push(id);
+        store_deduplicated(db, &item, &embed_result.vector, &mut created_ids, &mut merged_ids)?;
     }
 
     // Risks → recurring_risk
@@ -420,79 +412,155 @@ pub fn from_digest_items(
             updated_at: chrono::Utc::now().to_rfc3339(),
         };
 
-        let id = knowledge::create_knowledge_item(db, &item, &embed_result.vector)?;
-        created_ids.push(id);
+        store_deduplicated(db, &item, &embed_result.vector, &mut created_ids, &mut merged_ids)?;
     }
 
     Ok(IngestResult {
         created_ids,
+        merged_ids,
         skipped_count: 0,
         is_pseudo_embedding: is_pseudo,
+        discarded: vec![],
     })
 }
 
-// ── Claude API helper ──────────────────────────────────
+/// Insert, or merge into an existing near-duplicate (same scope + project).
+fn store_deduplicated(
+    db: &RagDb,
+    item: &KnowledgeItem,
+    embedding: &[f32],
+    created_ids: &mut Vec<String>,
+    merged_ids: &mut Vec<String>,
+) -> Result<(), String> {
+    match dedup::create_or_merge(db, item, embedding, dedup::DEFAULT_DUPLICATE_THRESHOLD)? {
+        DedupOutcome::Created { id } => created_ids.push(id),
+        DedupOutcome::Merged { into_id, .. } => {
+            if !merged_ids.contains(&into_id) {
+                merged_ids.push(into_id);
+            }
+        }
+    }
+    Ok(())
+}
+
+// ── LLM helper ─────────────────────────────────────────
 
 async fn call_extraction_api(
     digest_text: &str,
-    api_key: &str,
+    system_prompt: &str,
+    schema: &Value,
+    llm: &LlmClient,
 ) -> Result<ExtractionResult, String> {
     let user_prompt = format!(
         "다음 채팅 다이제스트에서 재사용 가능한 지식 패턴을 추출해주세요:\n\n{}",
         digest_text
     );
 
-    let request_body = serde_json::json!({
-        "model": CLAUDE_HAIKU_MODEL,
-        "max_tokens": 2048,
-        "system": EXTRACT_SYSTEM_PROMPT,
-        "messages": [
-            {
-                "role": "user",
-                "content": user_prompt
-            }
-        ]
-    });
+    let reply = llm
+        .complete_json(LlmTask::Extraction, system_prompt, &user_prompt, EXTRACT_MAX_TOKENS, schema.clone())
+        .await?;
 
-    let client = reqwest::Client::new();
-    let response = client
-        .post(ANTHROPIC_API_URL)
-        .header("x-api-key", api_key)
-        .header("anthropic-version", ANTHROPIC_API_VERSION)
-        .header("content-type", "application/json")
-        .json(&request_body)
-        .send()
-        .await
-        .map_err(|e| format!("Claude extraction API failed: {}", e))?;
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        return Err(format!("Claude extraction API error {}: {}", status, body));
+    let result = parse_extraction(&reply);
+    if !result.discarded.is_empty() {
+        log::warn!("Extraction: discarded {} invalid item(s)", result.discarded.len());
     }
+    Ok(result)
+}
 
-    let api_response: serde_json::Value = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse extraction response: {}", e))?;
+/// JSON schema of the extraction reply, with the vocabulary as enums
+fn extraction_schema(ontology: &Ontology) -> Value {
+    let values = |dimension: Dimension| -> Vec<Value> {
+        ontology.terms(dimension).map(|t| Value::from(t.value.as_str())).collect()
+    };
+    let nullable = |dimension: Dimension| {
+        let mut allowed = values(dimension);
+        allowed.push(Value::Null);
+        serde_json::json!({ "type": ["string", "null"], "enum": allowed })
+    };
+    serde_json::json!({
+        "type": "object",
+        "properties": {
+            "items": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "content": { "type": "string" },
+                        "knowledge_type": { "type": "string", "enum": values(Dimension::KnowledgeType) },
+                        "role_tag": nullable(Dimension::RoleTag),
+                        "dialectic_tag": nullable(Dimension::DialecticTag),
+                        "scope_layer": nullable(Dimension::ScopeLayer),
+                        "confidence": { "type": "number", "minimum": 0, "maximum": 1 }
+                    },
+                    "required": ["content", "knowledge_type", "confidence"]
+                }
+            }
+        },
+        "required": ["items"]
+    })
+}
 
-    let text = api_response["content"]
-        .as_array()
-        .and_then(|arr| arr.first())
-        .and_then(|block| block["text"].as_str())
-        .ok_or_else(|| "No text in extraction response".to_string())?;
+/// Validate a parsed extraction reply item by item; invalid items end up in `discarded`.
+fn parse_extraction(reply: &Value) -> ExtractionResult {
+    let mut validator = ItemValidator::new();
+    let items = validator.items(reply, "items", |item| {
+        Ok(ExtractedItem {
+            content: structured::required_str(item, "content")?,
+            knowledge_type: structured::required_str(item, "knowledge_type")?,
+            role_tag: structured::optional_str(item, "role_tag"),
+            dialectic_tag: structured::optional_str(item, "dialectic_tag"),
+            scope_layer: structured::optional_str(item, "scope_layer"),
+            confidence: structured::confidence(item, "confidence")?,
+        })
+    });
+    ExtractionResult { items, discarded: validator.discarded }
+}
 
-    let clean_json = text
-        .trim()
-        .trim_start_matches("```json")
-        .trim_start_matches("```")
-        .trim_end_matches("```")
-        .trim();
+/// Extraction prompt listing the current vocabulary
+fn extraction_prompt(template: &Template, ontology: &Ontology) -> String {
+    template.render(&[
+        ("knowledge_types", &ontology.prompt_list(Dimension::KnowledgeType)),
+        ("role_tags", &ontology.prompt_list(Dimension::RoleTag)),
+        ("dialectic_tags", &ontology.prompt_list(Dimension::DialecticTag)),
+        ("scope_layers", &ontology.prompt_list(Dimension::ScopeLayer)),
+    ])
+}
 
-    let result: ExtractionResult = serde_json::from_str(clean_json)
-        .map_err(|e| format!("Failed to parse extraction JSON: {} — raw: {}", e, clean_json))?;
+/// Replace tags the model invented with the closest vocabulary term (or drop them).
+fn conform_to_ontology(ontology: &Ontology, item: &mut ExtractedItem) {
+    let resolve_opt = |dimension: Dimension, value: &Option<String>| {
+        value.as_deref().and_then(|v| ontology.resolve(dimension, v))
+    };
 
-    Ok(result)
+    let knowledge_type = ontology
+        .resolve(Dimension::KnowledgeType, &item.knowledge_type)
+        .unwrap_or_else(|| ontology::FALLBACK_KNOWLEDGE_TYPE.to_string());
+    let role_tag = resolve_opt(Dimension::RoleTag, &item.role_tag);
+    let dialectic_tag = resolve_opt(Dimension::DialecticTag, &item.dialectic_tag);
+    let scope_layer = resolve_opt(Dimension::ScopeLayer, &item.scope_layer);
+
+    if knowledge_type != item.knowledge_type
+        || role_tag != item.role_tag
+        || dialectic_tag != item.dialectic_tag
+        || scope_layer != item.scope_layer
+    {
+        log::info!(
+            "Mapped extracted tags onto ontology: {} → {}, {:?} → {:?}, {:?} → {:?}, {:?} → {:?}",
+            item.knowledge_type,
+            knowledge_type,
+            item.role_tag,
+            role_tag,
+            item.dialectic_tag,
+            dialectic_tag,
+            item.scope_layer,
+            scope_layer
+        );
+    }
+
+    item.knowledge_type = knowledge_type;
+    item.role_tag = role_tag;
+    item.dialectic_tag = dialectic_tag;
+    item.scope_layer = scope_layer;
 }
 
 fn format_digest_for_extraction(digest: &DigestResult) -> String {
@@ -528,6 +596,7 @@ fn format_digest_for_extraction(digest: &DigestResult) -> String {
 mod tests {
     use super::*;
     use crate::rag::digest::{DigestItem, RiskItem};
+    use crate::rag::prompts::{Language, PromptRegistry};
 
     #[test]
     fn test_format_digest() {
@@ -545,6 +614,10 @@ mod tests {
                 priority: "medium".to_string(),
             }],
             summary: "예산 확정 회의".to_string(),
+            windows: vec![],
+            discarded: vec![],
+            fallback: None,
+            prompt_version: None,
         };
 
         let formatted = format_digest_for_extraction(&digest);
@@ -571,5 +644,48 @@ mod tests {
         let result: ExtractionResult = serde_json::from_str(json).unwrap();
         assert_eq!(result.items.len(), 1);
         assert_eq!(result.items[0].knowledge_type, "budget_decision");
+
+        // Lenient path: one bad item does not lose the others
+        let reply = structured::parse_value(
+            r#"추출 결과입니다. {"items": [
+                {"content": "견적 하한선은 목표 내수율로 역산", "knowledge_type": "budget_decision", "confidence": "0.8",},
+                {"content": "유형 없음", "confidence": 0.9},
+                {"content": "신뢰도 이상", "knowledge_type": "context", "confidence": "높음"}
+            ]}"#,
+            "extraction",
+        )
+        .unwrap();
+        let result = parse_extraction(&reply);
+        assert_eq!(result.items.len(), 1);
+        assert_eq!(result.items[0].confidence, 0.8);
+        let reasons: Vec<&str> = result.discarded.iter().map(|d| d.reason.as_str()).collect();
+        assert_eq!(reasons, vec!["knowledge_type is missing", "confidence is not a number: \"높음\""]);
+    }
+
+    #[test]
+    fn test_conform_to_ontology() {
+        let ontology = Ontology::builtin();
+        let template = PromptRegistry::default().template(PromptId::Extraction, Language::Ko);
+        let prompt = extraction_prompt(&template, &ontology);
+        assert!(prompt.contains("talent_casting") && prompt.contains("strategy, execution, culture"));
+        assert!(!prompt.contains("{role_tags}"));
+
+        let mut item = ExtractedItem {
+            content: "예산 협상 시 견적 하한선 설정".to_string(),
+            knowledge_type: "Budget Judgement".to_string(),
+            role_tag: Some("ceo".to_string()),
+            dialectic_tag: Some("위험".to_string()),
+            scope_layer: Some("galaxy".to_string()),
+            confidence: 0.8,
+        };
+        conform_to_ontology(&ontology, &mut item);
+        assert_eq!(item.knowledge_type, "budget_judgment");
+        assert_eq!(item.role_tag.as_deref(), Some("CEO"));
+        assert_eq!(item.dialectic_tag.as_deref(), Some("risk"));
+        assert_eq!(item.scope_layer, None);
+
+        item.knowledge_type = "something else entirely".to_string();
+        conform_to_ontology(&ontology, &mut item);
+        assert_eq!(item.knowledge_type, ontology::FALLBACK_KNOWLEDGE_TYPE);
     }
 }
@@ -2,9 +2,16 @@
 ///
 /// Provides create, read, update, delete for knowledge_items + embeddings.
 /// Maps to Supabase knowledge_items table operations.
+/// Edits, deactivation and restores are versioned in `history`.
 
+use crate::rag::conflicts;
 use crate::rag::db::RagDb;
-use crate::rag::embedding::vector_to_blob;
+use crate::rag::embedding::{vector_to_blob, EmbeddingEngine};
+use crate::rag::events::{ChangeKind, KnowledgeChange, RagEvent};
+use crate::rag::history;
+use crate::rag::ontology::Ontology;
+use crate::rag::standing;
+use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -37,6 +44,40 @@ pub struct KnowledgeItem {
     pub updated_at: String,
 }
 
+#[cfg(test)]
+impl KnowledgeItem {
+    /// Active team-scope decision with no owner or project; override fields with `..`.
+    pub(crate) fn for_test(content: &str) -> Self {
+        let now = chrono::Utc::now().to_rfc3339();
+        Self {
+            id: String::new(),
+            content: content.to_string(),
+            summary: None,
+            knowledge_type: "decision_pattern".to_string(),
+            source_type: "manual".to_string(),
+            scope: "team".to_string(),
+            scope_layer: None,
+            role_tag: None,
+            dialectic_tag: None,
+            confidence: 0.7,
+            relevance_score: 0.5,
+            usage_count: 0,
+            decision_maker: None,
+            outcome: None,
+            financial_impact_krw: None,
+            source_id: None,
+            source_context: None,
+            user_id: None,
+            project_id: None,
+            did_author: None,
+            is_active: true,
+            expires_at: None,
+            created_at: now.clone(),
+            updated_at: now,
+        }
+    }
+}
+
 /// Create a new knowledge item with its embedding vector.
 pub fn create_knowledge_item(
     db: &RagDb,
@@ -44,6 +85,7 @@ pub fn create_knowledge_item(
     embedding: &[f32],
 ) -> Result<String, String> {
     let conn = db.conn();
+    let item = &Ontology::load(&conn)?.canonicalize(item)?;
 
     let id = if item.id.is_empty() {
         Uuid::new_v4().to_string()
@@ -106,13 +148,29 @@ pub fn create_knowledge_item(
     );
 
     log::info!("Created knowledge item {} (type: {})", id, item.knowledge_type);
+
+    // Notify listeners + evaluate standing queries (connection must be released first)
+    drop(conn);
+    let mut stored = item.clone();
+    stored.id = id.clone();
+    notify_changed(db, &stored, ChangeKind::Created);
+    if let Err(e) = standing::evaluate_item(db, &stored, embedding) {
+        log::warn!("Standing query evaluation failed for {}: {}", id, e);
+    }
+    if let Err(e) = conflicts::check_item(db, &stored, embedding) {
+        log::warn!("Conflict check failed for {}: {}", id, e);
+    }
+
     Ok(id)
 }
 
 /// Get knowledge item by ID.
 pub fn get_knowledge_item(db: &RagDb, id: &str) -> Result<Option<KnowledgeItem>, String> {
-    let conn = db.conn();
+    get_knowledge_item_with(&db.conn(), id)
+}
 
+/// `get_knowledge_item` for callers already holding the connection or a transaction.
+pub(crate) fn get_knowledge_item_with(conn: &Connection, id: &str) -> Result<Option<KnowledgeItem>, String> {
     let result = conn.query_row(
         "SELECT id, content, summary, knowledge_type, source_type, scope, scope_layer,
                 role_tag, dialectic_tag, confidence, relevance_score, usage_count,
@@ -176,17 +234,311 @@ pub fn update_feedback(db: &RagDb, item_id: &str, was_helpful: bool) -> Result<(
     Ok(())
 }
 
-/// Soft-delete a knowledge item.
-pub fn deactivate_knowledge_item(db: &RagDb, id: &str) -> Result<(), String> {
-    let conn = db.conn();
+/// Editable fields of a knowledge item.
+///
+/// `None` leaves a field unchanged; for optional text fields an empty string clears the value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KnowledgeUpdate {
+    pub content: Option<String>,
+    pub summary: Option<String>,
+    pub knowledge_type: Option<String>,
+    pub scope: Option<String>,
+    pub scope_layer: Option<String>,
+    pub role_tag: Option<String>,
+    pub dialectic_tag: Option<String>,
+    pub confidence: Option<f64>,
+    pub decision_maker: Option<String>,
+    pub outcome: Option<String>,
+    pub financial_impact_krw: Option<i64>,
+    pub project_id: Option<String>,
+    pub expires_at: Option<String>,
+}
+
+impl KnowledgeUpdate {
+    fn apply(&self, item: &mut KnowledgeItem) {
+        fn set(target: &mut String, value: &Option<String>) {
+            if let Some(v) = value {
+                *target = v.clone();
+            }
+        }
+        fn set_opt(target: &mut Option<String>, value: &Option<String>) {
+            if let Some(v) = value {
+                *target = if v.is_empty() { None } else { Some(v.clone()) };
+            }
+        }
+
+        set(&mut item.content, &self.content);
+        set_opt(&mut item.summary, &self.summary);
+        set(&mut item.knowledge_type, &self.knowledge_type);
+        set(&mut item.scope, &self.scope);
+        set_opt(&mut item.scope_layer, &self.scope_layer);
+        set_opt(&mut item.role_tag, &self.role_tag);
+        set_opt(&mut item.dialectic_tag, &self.dialectic_tag);
+        if let Some(c) = self.confidence {
+            item.confidence = c.clamp(0.0, 1.0);
+        }
+        set_opt(&mut item.decision_maker, &self.decision_maker);
+        set_opt(&mut item.outcome, &self.outcome);
+        if let Some(f) = self.financial_impact_krw {
+            item.financial_impact_krw = Some(f);
+        }
+        set_opt(&mut item.project_id, &self.project_id);
+        set_opt(&mut item.expires_at, &self.expires_at);
+    }
+}
+
+/// Edit a knowledge item. Content changes are re-embedded; every change is versioned.
+pub fn update_knowledge_item(
+    db: &RagDb,
+    engine: &EmbeddingEngine,
+    id: &str,
+    update: &KnowledgeUpdate,
+    changed_by: Option<&str>,
+) -> Result<KnowledgeItem, String> {
+    let current = get_knowledge_item(db, id)?
+        .ok_or_else(|| format!("Knowledge item not found: {}", id))?;
+
+    let mut next = current.clone();
+    update.apply(&mut next);
+
+    save_changes(db, engine, &current, &next, "update", changed_by)
+}
+
+/// Copy the user-editable fields (those in `KnowledgeUpdate`) from `from` to `to`.
+pub fn copy_editable_fields(from: &KnowledgeItem, to: &mut KnowledgeItem) {
+    to.content = from.content.clone();
+    to.summary = from.summary.clone();
+    to.knowledge_type = from.knowledge_type.clone();
+    to.scope = from.scope.clone();
+    to.scope_layer = from.scope_layer.clone();
+    to.role_tag = from.role_tag.clone();
+    to.dialectic_tag = from.dialectic_tag.clone();
+    to.confidence = from.confidence;
+    to.decision_maker = from.decision_maker.clone();
+    to.outcome = from.outcome.clone();
+    to.financial_impact_krw = from.financial_impact_krw;
+    to.project_id = from.project_id.clone();
+    to.expires_at = from.expires_at.clone();
+}
+
+/// Names of editable fields that differ between two versions of an item.
+pub fn changed_fields(before: &KnowledgeItem, after: &KnowledgeItem) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    macro_rules! diff {
+        ($($field:ident),*) => {
+            $(if before.$field != after.$field { fields.push(stringify!($field)); })*
+        };
+    }
+    diff!(
+        content, summary, knowledge_type, scope, scope_layer, role_tag, dialectic_tag,
+        confidence, decision_maker, outcome, financial_impact_krw, project_id, expires_at
+    );
+    fields
+}
+
+/// Persist `next` over `current`: re-embed on content change, record a version, notify.
+/// Returns the stored item (unchanged `current` when nothing differs).
+pub(crate) fn save_changes(
+    db: &RagDb,
+    engine: &EmbeddingEngine,
+    current: &KnowledgeItem,
+    next: &KnowledgeItem,
+    change_type: &str,
+    changed_by: Option<&str>,
+) -> Result<KnowledgeItem, String> {
+    let Some(change) = prepare_changes(db, engine, current, next)? else {
+        return Ok(current.clone());
+    };
+
+    let stored = {
+        let conn = db.conn();
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(|e| format!("Begin knowledge update failed: {}", e))?;
+        let stored = save_changes_with(&tx, current, &change, change_type, changed_by)?;
+        tx.commit().map_err(|e| format!("Commit knowledge update failed: {}", e))?;
+        stored
+    };
+    after_save(db, current, &stored, &change);
+    Ok(stored)
+}
+
+/// An edit checked against the ontology, with the new embedding if the content changed
+pub(crate) struct PendingChange {
+    pub next: KnowledgeItem,
+    pub fields: Vec<&'static str>,
+    pub embedding: Option<Vec<f32>>,
+}
+
+/// First step of `save_changes`: canonicalize `next` and embed new content.
+/// `None` when nothing differs from `current`.
+pub(crate) fn prepare_changes(
+    db: &RagDb,
+    engine: &EmbeddingEngine,
+    current: &KnowledgeItem,
+    next: &KnowledgeItem,
+) -> Result<Option<PendingChange>, String> {
+    let next = Ontology::load(&db.conn())?.canonicalize_update(Some(current), next)?;
+    let fields = changed_fields(current, &next);
+    if fields.is_empty() {
+        return Ok(None);
+    }
+
+    let embedding = if fields.contains(&"content") {
+        Some(engine.embed(&next.content)?.vector)
+    } else {
+        None
+    };
+    Ok(Some(PendingChange { next, fields, embedding }))
+}
+
+/// Write a prepared change through `conn`, so callers can put it in one
+/// transaction with their own rows. Call `after_save` once the connection is released.
+pub(crate) fn save_changes_with(
+    conn: &Connection,
+    current: &KnowledgeItem,
+    change: &PendingChange,
+    change_type: &str,
+    changed_by: Option<&str>,
+) -> Result<KnowledgeItem, String> {
+    let next = &change.next;
+    history::ensure_baseline_with(conn, current)?;
+
+    // RFC3339 like created items: sync compares `updated_at` as text
     conn.execute(
-        "UPDATE knowledge_items SET is_active = 0, updated_at = datetime('now') WHERE id = ?1",
-        [id],
+        "UPDATE knowledge_items SET
+            content = ?2, summary = ?3, knowledge_type = ?4, scope = ?5, scope_layer = ?6,
+            role_tag = ?7, dialectic_tag = ?8, confidence = ?9, decision_maker = ?10,
+            outcome = ?11, financial_impact_krw = ?12, project_id = ?13, expires_at = ?14,
+            updated_at = ?15
+         WHERE id = ?1",
+        rusqlite::params![
+            next.id,
+            next.content,
+            next.summary,
+            next.knowledge_type,
+            next.scope,
+            next.scope_layer,
+            next.role_tag,
+            next.dialectic_tag,
+            next.confidence,
+            next.decision_maker,
+            next.outcome,
+            next.financial_impact_krw,
+            next.project_id,
+            next.expires_at,
+            chrono::Utc::now().to_rfc3339(),
+        ],
     )
-    .map_err(|e| format!("Deactivate failed: {}", e))?;
+    .map_err(|e| format!("Update knowledge_item failed: {}", e))?;
+
+    if let Some(vector) = &change.embedding {
+        let blob = vector_to_blob(vector);
+        conn.execute(
+            "INSERT OR REPLACE INTO embeddings (knowledge_id, vector) VALUES (?1, ?2)",
+            rusqlite::params![next.id, blob],
+        )
+        .map_err(|e| format!("Update embedding failed: {}", e))?;
+
+        // vec0 tables do not support UPDATE of the vector column reliably
+        let _ = conn.execute("DELETE FROM vec_knowledge WHERE knowledge_id = ?1", [&next.id]);
+        let _ = conn.execute(
+            "INSERT INTO vec_knowledge (knowledge_id, embedding) VALUES (?1, ?2)",
+            rusqlite::params![next.id, blob],
+        );
+    }
+
+    let stored = get_knowledge_item_with(conn, &next.id)?
+        .ok_or_else(|| format!("Knowledge item not found: {}", next.id))?;
+    history::record_version_with(conn, &stored, change_type, &change.fields, changed_by)?;
+    Ok(stored)
+}
+
+/// Last step of `save_changes`: publish the change and check it for conflicts.
+pub(crate) fn after_save(db: &RagDb, current: &KnowledgeItem, stored: &KnowledgeItem, change: &PendingChange) {
+    log::info!("Updated knowledge item {} ({})", stored.id, change.fields.join(", "));
+
+    // Caches keyed on the old scope/project must see the change too
+    if current.scope != stored.scope || current.project_id != stored.project_id {
+        notify_changed(db, current, ChangeKind::Updated);
+    }
+    notify_changed(db, stored, ChangeKind::Updated);
+    if let Some(vector) = &change.embedding {
+        if let Err(e) = conflicts::check_item(db, stored, vector) {
+            log::warn!("Conflict check failed for {}: {}", stored.id, e);
+        }
+    }
+}
+
+/// Soft-delete a knowledge item.
+pub fn deactivate_knowledge_item(db: &RagDb, id: &str, changed_by: Option<&str>) -> Result<(), String> {
+    set_active(db, id, false, changed_by)
+}
+
+/// Undo a soft delete.
+pub fn restore_knowledge_item(db: &RagDb, id: &str, changed_by: Option<&str>) -> Result<(), String> {
+    set_active(db, id, true, changed_by)
+}
+
+fn set_active(db: &RagDb, id: &str, active: bool, changed_by: Option<&str>) -> Result<(), String> {
+    let current = get_knowledge_item(db, id)?
+        .ok_or_else(|| format!("Knowledge item not found: {}", id))?;
+    if current.is_active == active {
+        return Ok(());
+    }
+    history::ensure_baseline(db, &current)?;
+
+    {
+        let conn = db.conn();
+        conn.execute(
+            "UPDATE knowledge_items SET is_active = ?2, updated_at = ?3 WHERE id = ?1",
+            rusqlite::params![id, active as i32, chrono::Utc::now().to_rfc3339()],
+        )
+        .map_err(|e| format!("{} failed: {}", if active { "Restore" } else { "Deactivate" }, e))?;
+    }
+
+    if let Some(item) = get_knowledge_item(db, id)? {
+        let change_type = if active { "restore" } else { "deactivate" };
+        history::record_version(db, &item, change_type, &["is_active"], changed_by)?;
+        let kind = if active { ChangeKind::Updated } else { ChangeKind::Deactivated };
+        notify_changed(db, &item, kind);
+    }
     Ok(())
 }
 
+/// Permanently delete a knowledge item with its embeddings, matches and history.
+pub fn delete_knowledge_item(db: &RagDb, id: &str) -> Result<(), String> {
+    let item = get_knowledge_item(db, id)?
+        .ok_or_else(|| format!("Knowledge item not found: {}", id))?;
+
+    {
+        let conn = db.conn();
+        let _ = conn.execute("DELETE FROM vec_knowledge WHERE knowledge_id = ?1", [id]);
+        conn.execute("DELETE FROM standing_query_matches WHERE knowledge_id = ?1", [id])
+            .map_err(|e| format!("Delete matches failed: {}", e))?;
+        // embeddings + knowledge_item_versions cascade
+        conn.execute("DELETE FROM knowledge_items WHERE id = ?1", [id])
+            .map_err(|e| format!("Delete knowledge_item failed: {}", e))?;
+    }
+
+    log::info!("Deleted knowledge item {}", id);
+    notify_changed(db, &item, ChangeKind::Deleted);
+    Ok(())
+}
+
+/// Publish a `KnowledgeChanged` event for an item.
+/// Must be called without holding the connection guard.
+pub fn notify_changed(db: &RagDb, item: &KnowledgeItem, kind: ChangeKind) {
+    db.emit(RagEvent::KnowledgeChanged(KnowledgeChange {
+        knowledge_id: item.id.clone(),
+        kind,
+        scope: item.scope.clone(),
+        user_id: item.user_id.clone(),
+        project_id: item.project_id.clone(),
+    }));
+}
+
 /// Get RAG statistics for the local knowledge base.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RagStats {
@@ -376,6 +728,9 @@ pub fn record_query_feedback(
         )
         .unwrap_or_else(|_| "[]".to_string());
 
+    // Release the connection before update_feedback re-acquires it
+    drop(conn);
+
     if let Ok(ids) = serde_json::from_str::<Vec<String>>(&ids_json) {
         for item_id in ids {
             let _ = update_feedback(db, &item_id, was_helpful);
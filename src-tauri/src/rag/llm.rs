@@ -0,0 +1,881 @@
+/// LLM Providers — one interface for every generation call ("Your Choice of Brain")
+///
+/// Digest, extraction, conflict review, synthesis and persona answers all go
+/// through `LlmClient`, which wraps an `LlmProvider`:
+/// - `AnthropicProvider` — Claude Messages API (default, Claude Haiku)
+/// - `OpenAiCompatibleProvider` — any `/v1/chat/completions` server
+///   (OpenAI, local Ollama / llama.cpp / vLLM)
+/// - `MockProvider` — deterministic canned replies (tests, offline demos)
+///
+/// Provider and model are chosen per `LlmTask` from `llm_settings`; tasks
+/// without a row use Anthropic + Claude Haiku. No secrets are stored: the
+/// Anthropic key comes from the caller, OpenAI-compatible servers read theirs
+/// from the environment variable named in `api_key_env`.
+///
+/// HTTP providers share one `ResilientHttp` (timeouts, retries, circuit
+/// breaker); a client built `with_cancel` stops when the UI cancels the call.
+///
+/// JSON tasks call `complete_json` with a JSON schema: Anthropic answers via a
+/// forced tool call, OpenAI-compatible servers via `response_format`, and the
+/// reply is parsed leniently (`structured::parse_value`) either way.
+///
+/// Clients built by `for_task` record every call in the usage ledger and
+/// check the project's monthly budget first (see `usage`), and redact PII
+/// from the prompt before it is sent, restoring it in the reply (see `redaction`).
+/// The redacted prompt is then checked against the outbound policy and
+/// logged (see `policy`); `with_data` labels the scopes / knowledge types it holds.
+
+use crate::rag::context::estimate_tokens;
+use crate::rag::db::RagDb;
+use crate::rag::http::{CancelToken, ResilientHttp};
+use crate::rag::policy::{self, Channel, DataLabels, Outbound};
+use crate::rag::redaction::{self, Redactor, SensitiveTerm};
+use crate::rag::structured;
+use crate::rag::usage::{self, CallRecord, CallStatus, TokenUsage, UsageContext};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Default model for every task
+pub const CLAUDE_HAIKU_MODEL: &str = "claude-haiku-4-5-20251001";
+
+/// Anthropic API endpoint
+const ANTHROPIC_API_URL: &str = "https://api.anthropic.com/v1/messages";
+
+/// API version header
+const ANTHROPIC_API_VERSION: &str = "2023-06-01";
+
+/// Tool the model is forced to call for schema-shaped output
+const STRUCTURED_TOOL_NAME: &str = "record_result";
+
+// ── Types ──────────────────────────────────────────────
+
+/// What the model is used for (settings key)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LlmTask {
+    Digest,
+    Extraction,
+    Conflict,
+    Synthesis,
+    Persona,
+}
+
+impl LlmTask {
+    pub const ALL: [LlmTask; 5] = [
+        LlmTask::Digest,
+        LlmTask::Extraction,
+        LlmTask::Conflict,
+        LlmTask::Synthesis,
+        LlmTask::Persona,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LlmTask::Digest => "digest",
+            LlmTask::Extraction => "extraction",
+            LlmTask::Conflict => "conflict",
+            LlmTask::Synthesis => "synthesis",
+            LlmTask::Persona => "persona",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|t| t.as_str() == s)
+            .ok_or_else(|| format!("Unknown LLM task: {}", s))
+    }
+}
+
+/// Provider implementations selectable in settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    Anthropic,
+    OpenaiCompatible,
+    Mock,
+}
+
+impl ProviderKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProviderKind::Anthropic => "anthropic",
+            ProviderKind::OpenaiCompatible => "openai_compatible",
+            ProviderKind::Mock => "mock",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "anthropic" => Ok(ProviderKind::Anthropic),
+            "openai_compatible" => Ok(ProviderKind::OpenaiCompatible),
+            "mock" => Ok(ProviderKind::Mock),
+            _ => Err(format!("Unknown LLM provider: {}", s)),
+        }
+    }
+}
+
+/// One single-turn generation request
+#[derive(Debug, Clone)]
+pub struct LlmRequest {
+    pub task: LlmTask,
+    pub system: String,
+    pub user: String,
+    pub max_tokens: u32,
+    /// JSON schema the reply must follow (structured output), if any
+    pub schema: Option<serde_json::Value>,
+    pub cancel: Option<CancelToken>,
+}
+
+/// Provider reply
+#[derive(Debug, Clone)]
+pub struct LlmReply {
+    pub text: String,
+    /// Token counts reported by the provider, if any
+    pub usage: Option<TokenUsage>,
+}
+
+/// Boxed future returned by providers (keeps the trait object-safe)
+pub type LlmFuture<'a> = Pin<Box<dyn Future<Output = Result<LlmReply, String>> + Send + 'a>>;
+
+/// A text-generation backend
+pub trait LlmProvider: Send + Sync {
+    /// Provider name for logs and errors
+    fn name(&self) -> &'static str;
+
+    /// Settings kind, matched by the `provider` condition of policy rules.
+    fn kind(&self) -> ProviderKind;
+
+    /// Generate the reply for `request` with `model`.
+    fn complete<'a>(&'a self, model: &'a str, request: &'a LlmRequest) -> LlmFuture<'a>;
+
+    /// Requests stay on this machine (checked by `local_only` policy rules).
+    fn is_local(&self) -> bool {
+        false
+    }
+}
+
+/// Per-task provider settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LlmSettings {
+    pub task: LlmTask,
+    pub provider: ProviderKind,
+    pub model: String,
+    /// Endpoint base for OpenAI-compatible servers (e.g. `http://localhost:11434/v1`)
+    pub base_url: Option<String>,
+    /// Environment variable holding the OpenAI-compatible API key, if any
+    pub api_key_env: Option<String>,
+    /// false = built-in default (no stored row)
+    pub is_custom: bool,
+}
+
+impl LlmSettings {
+    fn default_for(task: LlmTask) -> Self {
+        Self {
+            task,
+            provider: ProviderKind::Anthropic,
+            model: CLAUDE_HAIKU_MODEL.to_string(),
+            base_url: None,
+            api_key_env: None,
+            is_custom: false,
+        }
+    }
+}
+
+// ── Client ─────────────────────────────────────────────
+
+/// Provider + model resolved for one task
+pub struct LlmClient {
+    provider: Box<dyn LlmProvider>,
+    model: String,
+    cancel: Option<CancelToken>,
+    /// Usage ledger (none for bare clients in tests)
+    ledger: Option<Arc<RagDb>>,
+    usage: UsageContext,
+    /// Scopes / knowledge types of the data sent (outbound policy)
+    data: DataLabels,
+}
+
+impl LlmClient {
+    pub fn new(provider: Box<dyn LlmProvider>, model: impl Into<String>) -> Self {
+        Self {
+            provider,
+            model: model.into(),
+            cancel: None,
+            ledger: None,
+            usage: UsageContext::default(),
+            data: DataLabels::default(),
+        }
+    }
+
+    /// Client configured for `task`. `api_key` is the Anthropic key (if the task uses Anthropic).
+    pub fn for_task(
+        db: &Arc<RagDb>,
+        http: &Arc<ResilientHttp>,
+        task: LlmTask,
+        api_key: Option<&str>,
+    ) -> Result<Self, String> {
+        let settings = get_settings(db, task)?;
+        let provider: Box<dyn LlmProvider> = match settings.provider {
+            ProviderKind::Anthropic => {
+                let key = api_key
+                    .filter(|k| !k.trim().is_empty())
+                    .ok_or_else(|| format!("Anthropic API key required for the {} task", task.as_str()))?;
+                Box::new(AnthropicProvider::new(http.clone(), key))
+            }
+            ProviderKind::OpenaiCompatible => {
+                let base_url = settings
+                    .base_url
+                    .clone()
+                    .ok_or_else(|| format!("base_url is not set for the {} task", task.as_str()))?;
+                let key = settings.api_key_env.as_deref().and_then(|var| std::env::var(var).ok());
+                Box::new(OpenAiCompatibleProvider::new(http.clone(), base_url, key))
+            }
+            ProviderKind::Mock => Box::new(MockProvider::canned()),
+        };
+        Ok(Self::new(provider, settings.model).with_ledger(db.clone()))
+    }
+
+    /// Record calls in `db`'s usage ledger and enforce its budgets.
+    pub fn with_ledger(mut self, db: Arc<RagDb>) -> Self {
+        self.ledger = Some(db);
+        self
+    }
+
+    /// Attribute calls to a purpose (calling command) and project / room.
+    pub fn with_usage(mut self, purpose: &str, project_id: Option<&str>, room_id: Option<&str>) -> Self {
+        self.usage = UsageContext {
+            purpose: purpose.to_string(),
+            project_id: project_id.map(String::from),
+            room_id: room_id.map(String::from),
+        };
+        self
+    }
+
+    /// Label the data this client sends, for scope / knowledge_type policy rules.
+    pub fn with_data(mut self, labels: DataLabels) -> Self {
+        self.data = labels;
+        self
+    }
+
+    /// Abort requests when `token` is cancelled.
+    pub fn with_cancel(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// True once the UI cancelled this client's calls.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|t| t.is_cancelled())
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn provider_name(&self) -> &'static str {
+        self.provider.name()
+    }
+
+    /// Generate a reply (trimmed).
+    pub async fn complete(
+        &self,
+        task: LlmTask,
+        system: &str,
+        user: &str,
+        max_tokens: u32,
+    ) -> Result<String, String> {
+        let (text, redactor) = self.run(task, system, user, max_tokens, None, &[]).await?;
+        Ok(match redactor {
+            Some(redactor) => redactor.restore(&text),
+            None => text,
+        })
+    }
+
+    /// Generate a JSON object following `schema` (parsed leniently; callers validate items).
+    pub async fn complete_json(
+        &self,
+        task: LlmTask,
+        system: &str,
+        user: &str,
+        max_tokens: u32,
+        schema: serde_json::Value,
+    ) -> Result<serde_json::Value, String> {
+        self.complete_json_redacting(task, system, user, max_tokens, schema, &[]).await
+    }
+
+    /// `complete_json`, also hiding `terms` (participant names / ids) from the provider.
+    pub async fn complete_json_redacting(
+        &self,
+        task: LlmTask,
+        system: &str,
+        user: &str,
+        max_tokens: u32,
+        schema: serde_json::Value,
+        terms: &[SensitiveTerm],
+    ) -> Result<serde_json::Value, String> {
+        let (text, redactor) = self.run(task, system, user, max_tokens, Some(schema), terms).await?;
+        let mut value = structured::parse_value(&text, task.as_str())?;
+        if let Some(redactor) = redactor {
+            redactor.restore_value(&mut value);
+        }
+        Ok(value)
+    }
+
+    /// Redact the prompt with the project's settings (ledger clients only).
+    fn redact(
+        &self,
+        system: &str,
+        user: &str,
+        terms: &[SensitiveTerm],
+    ) -> Result<(String, String, Option<Redactor>), String> {
+        let Some(db) = &self.ledger else {
+            return Ok((system.to_string(), user.to_string(), None));
+        };
+        let settings = redaction::get_settings(db, self.usage.project_id.as_deref())?;
+        if !settings.enabled {
+            return Ok((system.to_string(), user.to_string(), None));
+        }
+        let mut redactor = Redactor::new(settings, terms);
+        let (system, user) = (redactor.redact(system), redactor.redact(user));
+        Ok((system, user, Some(redactor)))
+    }
+
+    /// Outbound policy check and budget check, then the outbound log and
+    /// redaction audit of the call about to be sent.
+    fn authorize(
+        &self,
+        db: &RagDb,
+        task: LlmTask,
+        system: &str,
+        user: &str,
+        max_tokens: u32,
+        redactor: Option<&Redactor>,
+    ) -> Result<(), String> {
+        let context = &self.usage;
+        let call = Outbound {
+            channel: Channel::Llm,
+            destination: self.provider_name(),
+            provider: Some(self.provider.kind().as_str()),
+            local: self.provider.is_local(),
+            purpose: &context.purpose,
+            project_id: context.project_id.as_deref(),
+            room_id: context.room_id.as_deref(),
+            labels: &self.data,
+        };
+        let content = format!("{}\n\n{}", system, user);
+        policy::check(db, &call, &content)?;
+
+        let input_estimate = (estimate_tokens(system) + estimate_tokens(user)) as u32;
+        usage::check_budget(db, context, task, self.provider_name(), &self.model, input_estimate, max_tokens)?;
+
+        // Logged as allowed only once nothing else can refuse the call
+        policy::record(db, &call, &content, None)?;
+
+        if let Some(redactor) = redactor {
+            let audited = redaction::audit(
+                db,
+                redactor,
+                context.project_id.as_deref(),
+                context.room_id.as_deref(),
+                task.as_str(),
+                &context.purpose,
+            );
+            // Bookkeeping must not fail the call
+            if let Err(e) = audited {
+                log::warn!("{}", e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn run(
+        &self,
+        task: LlmTask,
+        system: &str,
+        user: &str,
+        max_tokens: u32,
+        schema: Option<serde_json::Value>,
+        terms: &[SensitiveTerm],
+    ) -> Result<(String, Option<Redactor>), String> {
+        log::debug!("LLM {} via {} ({})", task.as_str(), self.provider_name(), self.model());
+        let (system, user, redactor) = self.redact(system, user, terms)?;
+        if let Some(db) = &self.ledger {
+            self.authorize(db, task, &system, &user, max_tokens, redactor.as_ref())?;
+        }
+        let input_estimate = (estimate_tokens(&system) + estimate_tokens(&user)) as u32;
+        let request = LlmRequest {
+            task,
+            system,
+            user,
+            max_tokens,
+            schema,
+            cancel: self.cancel.clone(),
+        };
+        let started = Instant::now();
+        let result = self.provider.complete(&self.model, &request).await;
+
+        if let Some(db) = &self.ledger {
+            let (tokens, status) = match &result {
+                Ok(reply) => (
+                    reply.usage.unwrap_or(TokenUsage {
+                        input_tokens: input_estimate,
+                        output_tokens: estimate_tokens(&reply.text) as u32,
+                        estimated: true,
+                    }),
+                    CallStatus::Ok,
+                ),
+                Err(_) => (
+                    TokenUsage { input_tokens: 0, output_tokens: 0, estimated: false },
+                    if self.is_cancelled() { CallStatus::Cancelled } else { CallStatus::Error },
+                ),
+            };
+            let call = CallRecord {
+                context: &self.usage,
+                task,
+                provider: self.provider_name(),
+                model: &self.model,
+                tokens,
+                latency_ms: started.elapsed().as_millis() as u64,
+                status,
+                error: result.as_ref().err().map(String::as_str),
+            };
+            // Bookkeeping must not fail the call
+            if let Err(e) = usage::record(db, &call) {
+                log::warn!("{}", e);
+            }
+        }
+
+        Ok((result?.text.trim().to_string(), redactor))
+    }
+}
+
+// ── Anthropic ──────────────────────────────────────────
+
+/// Claude Messages API
+pub struct AnthropicProvider {
+    api_key: String,
+    http: Arc<ResilientHttp>,
+}
+
+impl AnthropicProvider {
+    pub fn new(http: Arc<ResilientHttp>, api_key: &str) -> Self {
+        Self { api_key: api_key.to_string(), http }
+    }
+}
+
+impl LlmProvider for AnthropicProvider {
+    fn name(&self) -> &'static str {
+        "Claude"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Anthropic
+    }
+
+    fn complete<'a>(&'a self, model: &'a str, request: &'a LlmRequest) -> LlmFuture<'a> {
+        Box::pin(async move {
+            let mut request_body = serde_json::json!({
+                "model": model,
+                "max_tokens": request.max_tokens,
+                "system": request.system,
+                "messages": [
+                    {
+                        "role": "user",
+                        "content": request.user
+                    }
+                ]
+            });
+            if let Some(schema) = &request.schema {
+                request_body["tools"] = serde_json::json!([{
+                    "name": STRUCTURED_TOOL_NAME,
+                    "description": format!("Record the {} result", request.task.as_str()),
+                    "input_schema": schema,
+                }]);
+                request_body["tool_choice"] = serde_json::json!({"type": "tool", "name": STRUCTURED_TOOL_NAME});
+            }
+
+            let label = format!("{} {}", self.name(), request.task.as_str());
+            let headers = [("x-api-key", self.api_key.as_str()), ("anthropic-version", ANTHROPIC_API_VERSION)];
+            let body = self
+                .http
+                .post_json(&label, ANTHROPIC_API_URL, &headers, None, &request_body, request.cancel.as_ref())
+                .await?;
+            Ok(LlmReply {
+                text: anthropic_text(&body)?,
+                usage: reported_usage(&body["usage"], "input_tokens", "output_tokens"),
+            })
+        })
+    }
+}
+
+/// Token counts from a response's `usage` block (Anthropic and OpenAI field names differ)
+fn reported_usage(usage: &serde_json::Value, input: &str, output: &str) -> Option<TokenUsage> {
+    Some(TokenUsage {
+        input_tokens: usage[input].as_u64()? as u32,
+        output_tokens: usage[output].as_u64()? as u32,
+        estimated: false,
+    })
+}
+
+/// Text of a Messages API response: the structured tool input if the model
+/// called the tool, otherwise all text blocks concatenated
+fn anthropic_text(body: &serde_json::Value) -> Result<String, String> {
+    let tool_input = body["content"].as_array().and_then(|blocks| {
+        blocks
+            .iter()
+            .find(|b| b["type"] == "tool_use" && b["name"] == STRUCTURED_TOOL_NAME)
+            .map(|b| b["input"].to_string())
+    });
+    if let Some(input) = tool_input {
+        return Ok(input);
+    }
+
+    let text: String = body["content"]
+        .as_array()
+        .map(|blocks| blocks.iter().filter_map(|b| b["text"].as_str()).collect())
+        .unwrap_or_default();
+    if text.is_empty() {
+        return Err("No text content in Claude response".to_string());
+    }
+    Ok(text)
+}
+
+// ── OpenAI-compatible ──────────────────────────────────
+
+/// `/chat/completions` servers: OpenAI, Ollama, llama.cpp, vLLM, …
+pub struct OpenAiCompatibleProvider {
+    base_url: String,
+    api_key: Option<String>,
+    http: Arc<ResilientHttp>,
+}
+
+impl OpenAiCompatibleProvider {
+    pub fn new(http: Arc<ResilientHttp>, base_url: impl Into<String>, api_key: Option<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            api_key,
+            http,
+        }
+    }
+}
+
+impl LlmProvider for OpenAiCompatibleProvider {
+    fn name(&self) -> &'static str {
+        "OpenAI-compatible"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::OpenaiCompatible
+    }
+
+    fn is_local(&self) -> bool {
+        policy::is_local_url(&self.base_url)
+    }
+
+    fn complete<'a>(&'a self, model: &'a str, request: &'a LlmRequest) -> LlmFuture<'a> {
+        Box::pin(async move {
+            let mut request_body = serde_json::json!({
+                "model": model,
+                "max_tokens": request.max_tokens,
+                "messages": [
+                    { "role": "system", "content": request.system },
+                    { "role": "user", "content": request.user }
+                ]
+            });
+
+            let label = format!("{} {}", self.name(), request.task.as_str());
+            let url = format!("{}/chat/completions", self.base_url);
+            let post = |body: serde_json::Value| {
+                let label = label.clone();
+                let url = url.clone();
+                async move {
+                    self.http
+                        .post_json(&label, &url, &[], self.api_key.as_deref(), &body, request.cancel.as_ref())
+                        .await
+                }
+            };
+
+            let Some(schema) = &request.schema else {
+                return openai_reply(&post(request_body).await?);
+            };
+            let plain_body = request_body.clone();
+            request_body["response_format"] = serde_json::json!({
+                "type": "json_schema",
+                "json_schema": { "name": request.task.as_str(), "schema": schema },
+            });
+            match post(request_body).await {
+                Ok(body) => openai_reply(&body),
+                // Servers without json_schema support reject the request outright
+                Err(e) if e.contains("API error 400") || e.contains("API error 422") => {
+                    log::info!("{} rejected response_format, retrying as plain JSON: {}", label, e);
+                    openai_reply(&post(plain_body).await?)
+                }
+                Err(e) => Err(e),
+            }
+        })
+    }
+}
+
+fn openai_reply(body: &serde_json::Value) -> Result<LlmReply, String> {
+    Ok(LlmReply {
+        text: openai_text(body)?,
+        usage: reported_usage(&body["usage"], "prompt_tokens", "completion_tokens"),
+    })
+}
+
+/// Text of a chat completion response
+fn openai_text(body: &serde_json::Value) -> Result<String, String> {
+    body["choices"][0]["message"]["content"]
+        .as_str()
+        .filter(|t| !t.is_empty())
+        .map(String::from)
+        .ok_or_else(|| "No message content in chat completion response".to_string())
+}
+
+// ── Mock ───────────────────────────────────────────────
+
+/// Deterministic provider: a fixed reply, or a canned empty-but-valid reply per task.
+pub struct MockProvider {
+    reply: Option<String>,
+}
+
+impl MockProvider {
+    /// Canned replies (valid JSON for the JSON tasks)
+    pub fn canned() -> Self {
+        Self { reply: None }
+    }
+
+    /// Always answer `reply`
+    #[cfg(test)]
+    pub fn with_reply(reply: impl Into<String>) -> Self {
+        Self { reply: Some(reply.into()) }
+    }
+
+    fn canned_reply(request: &LlmRequest) -> String {
+        match request.task {
+            LlmTask::Digest => serde_json::json!({
+                "decisions": [],
+                "actionItems": [],
+                "risks": [],
+                "summary": format!("(mock) {} lines", request.user.lines().count()),
+            })
+            .to_string(),
+            LlmTask::Extraction => r#"{"items": []}"#.to_string(),
+            LlmTask::Conflict => r#"{"conflict": false, "reason": ""}"#.to_string(),
+            LlmTask::Synthesis => {
+                r#"{"thesis": [], "antithesis": [], "synthesis": [], "recommendation": "(mock)"}"#.to_string()
+            }
+            LlmTask::Persona => {
+                let preview: String = request.user.chars().take(80).collect();
+                format!("(mock) {}", preview)
+            }
+        }
+    }
+}
+
+impl LlmProvider for MockProvider {
+    fn name(&self) -> &'static str {
+        "Mock"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Mock
+    }
+
+    fn is_local(&self) -> bool {
+        true
+    }
+
+    fn complete<'a>(&'a self, _model: &'a str, request: &'a LlmRequest) -> LlmFuture<'a> {
+        let text = self.reply.clone().unwrap_or_else(|| Self::canned_reply(request));
+        Box::pin(async move { Ok(LlmReply { text, usage: None }) })
+    }
+}
+
+// ── Settings ───────────────────────────────────────────
+
+/// Effective settings for one task.
+pub fn get_settings(db: &RagDb, task: LlmTask) -> Result<LlmSettings, String> {
+    Ok(list_settings(db)?
+        .into_iter()
+        .find(|s| s.task == task)
+        .unwrap_or_else(|| LlmSettings::default_for(task)))
+}
+
+/// Effective settings for every task.
+pub fn list_settings(db: &RagDb) -> Result<Vec<LlmSettings>, String> {
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare("SELECT task, provider, model, base_url, api_key_env FROM llm_settings")
+        .map_err(|e| format!("Prepare LLM settings failed: {}", e))?;
+    let stored: Vec<LlmSettings> = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+            ))
+        })
+        .map_err(|e| format!("Query LLM settings failed: {}", e))?
+        .filter_map(|r| r.ok())
+        .filter_map(|(task, provider, model, base_url, api_key_env)| {
+            Some(LlmSettings {
+                task: LlmTask::parse(&task).ok()?,
+                provider: ProviderKind::parse(&provider).ok()?,
+                model,
+                base_url,
+                api_key_env,
+                is_custom: true,
+            })
+        })
+        .collect();
+
+    Ok(LlmTask::ALL
+        .into_iter()
+        .map(|task| {
+            stored
+                .iter()
+                .find(|s| s.task == task)
+                .cloned()
+                .unwrap_or_else(|| LlmSettings::default_for(task))
+        })
+        .collect())
+}
+
+/// Choose provider and model for a task.
+pub fn set_settings(
+    db: &RagDb,
+    task: LlmTask,
+    provider: ProviderKind,
+    model: &str,
+    base_url: Option<&str>,
+    api_key_env: Option<&str>,
+) -> Result<LlmSettings, String> {
+    let model = model.trim();
+    if model.is_empty() && provider != ProviderKind::Mock {
+        return Err("model is required".to_string());
+    }
+    let base_url = base_url.map(str::trim).filter(|u| !u.is_empty());
+    if provider == ProviderKind::OpenaiCompatible
+        && !base_url.is_some_and(|u| u.starts_with("http://") || u.starts_with("https://"))
+    {
+        return Err("OpenAI-compatible providers need an http(s) base_url".to_string());
+    }
+    let api_key_env = api_key_env.map(str::trim).filter(|v| !v.is_empty());
+
+    {
+        let conn = db.conn();
+        conn.execute(
+            "INSERT INTO llm_settings (task, provider, model, base_url, api_key_env)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(task) DO UPDATE SET
+                provider = excluded.provider,
+                model = excluded.model,
+                base_url = excluded.base_url,
+                api_key_env = excluded.api_key_env,
+                updated_at = datetime('now')",
+            rusqlite::params![task.as_str(), provider.as_str(), model, base_url, api_key_env],
+        )
+        .map_err(|e| format!("Save LLM settings failed: {}", e))?;
+    }
+
+    log::info!("LLM for {}: {} / {}", task.as_str(), provider.as_str(), model);
+    get_settings(db, task)
+}
+
+/// Back to the default (Anthropic + Claude Haiku).
+pub fn reset_settings(db: &RagDb, task: LlmTask) -> Result<bool, String> {
+    let conn = db.conn();
+    let removed = conn
+        .execute("DELETE FROM llm_settings WHERE task = ?1", [task.as_str()])
+        .map_err(|e| format!("Reset LLM settings failed: {}", e))?;
+    Ok(removed > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn http() -> Arc<ResilientHttp> {
+        Arc::new(ResilientHttp::new(Default::default()).unwrap())
+    }
+
+    fn setup() -> Arc<RagDb> {
+        let dir = std::env::temp_dir().join(format!("rag_llm_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        Arc::new(RagDb::open(&dir.join("test.db")).unwrap())
+    }
+
+    #[test]
+    fn test_response_parsing() {
+        let claude = serde_json::json!({
+            "content": [{"type": "text", "text": "안녕"}, {"type": "text", "text": "하세요"}]
+        });
+        assert_eq!(anthropic_text(&claude).unwrap(), "안녕하세요");
+        assert!(anthropic_text(&serde_json::json!({"content": []})).is_err());
+
+        let tool = serde_json::json!({
+            "content": [
+                {"type": "text", "text": "기록합니다."},
+                {"type": "tool_use", "name": STRUCTURED_TOOL_NAME, "input": {"conflict": true, "reason": "예산 불일치"}}
+            ]
+        });
+        let verdict: serde_json::Value = serde_json::from_str(&anthropic_text(&tool).unwrap()).unwrap();
+        assert_eq!(verdict["conflict"], true);
+
+        let openai = serde_json::json!({
+            "choices": [{"message": {"role": "assistant", "content": "{\"items\": []}"}}]
+        });
+        assert_eq!(openai_text(&openai).unwrap(), "{\"items\": []}");
+        assert_eq!(openai_reply(&openai).unwrap().usage, None);
+        let usage = reported_usage(&serde_json::json!({"input_tokens": 120, "output_tokens": 30}), "input_tokens", "output_tokens");
+        assert_eq!(usage.map(|u| (u.input_tokens, u.output_tokens, u.estimated)), Some((120, 30, false)));
+        assert!(openai_text(&serde_json::json!({"choices": []})).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_settings_select_provider_per_task() {
+        let db = setup();
+        let http = http();
+
+        // Default: Anthropic, which needs a key
+        assert!(LlmClient::for_task(&db, &http, LlmTask::Digest, None).is_err());
+        let claude = LlmClient::for_task(&db, &http, LlmTask::Digest, Some("sk-test")).unwrap();
+        assert_eq!((claude.provider_name(), claude.model()), ("Claude", CLAUDE_HAIKU_MODEL));
+
+        assert!(set_settings(&db, LlmTask::Extraction, ProviderKind::OpenaiCompatible, "llama3.1", None, None).is_err());
+        set_settings(
+            &db,
+            LlmTask::Extraction,
+            ProviderKind::OpenaiCompatible,
+            "llama3.1",
+            Some("http://localhost:11434/v1/"),
+            None,
+        )
+        .unwrap();
+        let local = LlmClient::for_task(&db, &http, LlmTask::Extraction, None).unwrap();
+        assert_eq!((local.provider_name(), local.model()), ("OpenAI-compatible", "llama3.1"));
+
+        set_settings(&db, LlmTask::Digest, ProviderKind::Mock, "", None, None).unwrap();
+        let mock = LlmClient::for_task(&db, &http, LlmTask::Digest, None).unwrap();
+        let reply = mock.complete(LlmTask::Digest, "system", "a\nb", 100).await.unwrap();
+        let digest: crate::rag::digest::DigestResult = serde_json::from_str(&reply).unwrap();
+        assert_eq!(digest.summary, "(mock) 2 lines");
+        // Deterministic
+        assert_eq!(mock.complete(LlmTask::Digest, "system", "a\nb", 100).await.unwrap(), reply);
+
+        let settings = list_settings(&db).unwrap();
+        assert_eq!(settings.len(), LlmTask::ALL.len());
+        assert_eq!(settings.iter().filter(|s| s.is_custom).count(), 2);
+
+        assert!(reset_settings(&db, LlmTask::Digest).unwrap());
+        assert_eq!(get_settings(&db, LlmTask::Digest).unwrap().provider, ProviderKind::Anthropic);
+    }
+}
@@ -5,9 +5,33 @@
 /// - ONNX all-MiniLM-L6-v2 for 384-dim embeddings (offline)
 /// - Hybrid search (vector similarity + relevance + usage)
 /// - Dialectic search for 정반합 (thesis-antithesis-synthesis)
-/// - Claude Haiku chat digest analysis
+/// - Chat digest analysis (Claude Haiku by default, see `llm`)
 /// - Knowledge extraction pipeline (digest → ingest → embed → store)
 /// - CEO 30-pattern initial seeding
+/// - Query analytics over rag_query_log (top queries, knowledge gaps)
+/// - Standing queries that fire events when matching knowledge arrives
+/// - Semantic cache for rag_get_context (invalidated on knowledge changes)
+/// - Token-budgeted, cited context builder (Markdown / XML / JSON)
+/// - Near-duplicate merge on ingest
+/// - Knowledge item version history with revert
+/// - Typed relations graph (supersedes, contradicts, supports, …)
+/// - Decision pattern mining per decision maker and domain
+/// - Project context snapshots prepended to rag_get_context
+/// - Persona engine (answer as CEO / CD / PD from local knowledge)
+/// - 정반합 synthesis reports with cited points
+/// - Contradiction detection across project knowledge
+/// - Decision outcome lifecycle with audit trail and reminders
+/// - Confidence/relevance aging with per-type half-life and TTL expiry
+/// - Ontology vocabulary (knowledge_type, role_tag, …) with org extensions
+/// - Pluggable LLM providers (Anthropic, OpenAI-compatible/local, mock) per task
+/// - Resilient LLM transport (timeouts, retries with backoff, circuit breaker, cancel)
+/// - Schema-requested LLM output with tolerant JSON repair and per-item validation
+/// - Incremental per-room digests (watermarks, persisted queue, background scheduler)
+/// - Rule-based offline digest fallback (Korean signal phrases, low confidence)
+/// - Versioned prompt templates (ko / en / th packs, file overrides, per-room language)
+/// - LLM usage ledger (tokens, latency, cost) with per-project monthly budgets
+/// - PII redaction (phones, 주민등록번호, emails, accounts, client names) before LLM calls
+/// - Outbound data policy (block / local-only rules for LLM and sync calls, hashed call log)
 
 pub mod db;
 pub mod embedding;
@@ -16,3 +40,28 @@ pub mod knowledge;
 pub mod digest;
 pub mod ingest;
 pub mod seed;
+pub mod analytics;
+pub mod events;
+pub mod standing;
+pub mod cache;
+pub mod context;
+pub mod dedup;
+pub mod history;
+pub mod relations;
+pub mod patterns;
+pub mod snapshot;
+pub mod persona;
+pub mod synthesis;
+pub mod conflicts;
+pub mod outcomes;
+pub mod aging;
+pub mod ontology;
+pub mod llm;
+pub mod http;
+pub mod structured;
+pub mod scheduler;
+pub mod offline_digest;
+pub mod prompts;
+pub mod usage;
+pub mod redaction;
+pub mod policy;
@@ -0,0 +1,295 @@
+/// Offline Digest — rule-based fallback when no LLM is configured
+///
+/// Scans messages for the Korean signal phrases listed in the digest prompt:
+/// - Consensus phrases ("확정", "결정", "합의", "그렇게 하죠") → decisions;
+///   a bare "ㅇㅇ" / "알겠습니다" makes the message it answers a decision
+/// - Assignment patterns ("~하기로", "부탁드립니다", "하겠습니다") → action items
+/// - Problem phrases ("문제", "지연", "빡빡") → risks
+/// - Urgency phrases ("급합니다", "ASAP", "긴급") → high priority
+///
+/// Output has the LLM digest's shape, but every confidence is capped at
+/// `RULE_MAX_CONFIDENCE` so rule-based items rank below LLM-extracted ones.
+
+use crate::rag::digest::{self, ChatMessage, DigestItem, DigestResult, MessageRange, RiskItem};
+
+/// Confidence of items matched by a weak signal (agreement reply, implicit assignment)
+const RULE_CONFIDENCE: f64 = 0.35;
+
+/// Upper bound for any rule-based item (explicit phrase)
+pub const RULE_MAX_CONFIDENCE: f64 = 0.5;
+
+/// Max characters of a message quoted as item text
+const MAX_ITEM_CHARS: usize = 200;
+
+/// Explicit consensus phrases
+const DECISION_PHRASES: &[&str] = &[
+    "확정", "결정", "합의", "그렇게 하죠", "그렇게 하시죠", "그렇게 진행", "그걸로 가", "진행하시죠", "진행하죠",
+];
+
+/// Bare agreement replies; the decision is the message they answer
+const AGREEMENT_REPLIES: &[&str] = &[
+    "ㅇㅇ", "ㅇㅋ", "넵", "네", "알겠습니다", "좋습니다", "좋아요", "동의합니다", "오케이", "ok", "콜",
+];
+
+/// Explicit assignments and commitments
+const ASSIGNMENT_PHRASES: &[&str] = &[
+    "하기로", "요청드", "부탁드", "해주세요", "해 주세요", "해주시", "맡아", "담당", "하겠습니다", "할게요",
+];
+
+/// Implicit assignments (weaker signal)
+const IMPLICIT_ASSIGNMENT_PHRASES: &[&str] = &["해야 할 것 같", "해야 하는데", "누가 해야", "누가 하"];
+
+const PROBLEM_PHRASES: &[&str] = &[
+    "문제", "걱정", "지연", "어려움", "어렵", "빡빡", "늦어", "막혀", "차질", "리스크", "장애", "버그",
+];
+
+/// Problems that block progress (medium priority at least)
+const BLOCKER_PHRASES: &[&str] = &["지연", "막혀", "차질", "장애", "불가"];
+
+const URGENCY_PHRASES: &[&str] = &["급합니다", "급해", "긴급", "asap", "당장", "지금 바로"];
+
+/// Deadline mentions (medium priority for action items)
+const DEADLINE_PHRASES: &[&str] = &["까지", "오늘", "내일", "이번 주", "마감"];
+
+// ── Analysis ───────────────────────────────────────────
+
+/// Digest messages with keyword rules only (no network).
+pub fn analyze(messages: &[ChatMessage]) -> DigestResult {
+    let mut decisions = Vec::new();
+    let mut action_items = Vec::new();
+    let mut risks = Vec::new();
+
+    for (index, message) in messages.iter().enumerate() {
+        let content = message.content.trim();
+        if content.is_empty() {
+            continue;
+        }
+        let lower = content.to_lowercase();
+        let urgent = contains_any(&lower, URGENCY_PHRASES);
+
+        if is_agreement_reply(&lower) {
+            if let Some(proposal) = answered_message(messages, index) {
+                decisions.push(DigestItem {
+                    text: format!("{} ({} 동의)", item_text(proposal), message.user_name),
+                    confidence: RULE_CONFIDENCE,
+                    related_user_ids: dedup_ids(vec![proposal.user_id.clone(), message.user_id.clone()]),
+                    priority: priority(urgent, false),
+                });
+            }
+            continue;
+        }
+
+        if contains_any(&lower, DECISION_PHRASES) {
+            decisions.push(DigestItem {
+                text: item_text(message),
+                confidence: RULE_MAX_CONFIDENCE,
+                related_user_ids: vec![message.user_id.clone()],
+                priority: priority(urgent, false),
+            });
+        }
+
+        let explicit = contains_any(&lower, ASSIGNMENT_PHRASES);
+        if explicit || contains_any(&lower, IMPLICIT_ASSIGNMENT_PHRASES) {
+            let mentioned = mentioned_users(messages, message);
+            action_items.push(DigestItem {
+                text: item_text(message),
+                confidence: if explicit { RULE_MAX_CONFIDENCE } else { RULE_CONFIDENCE },
+                related_user_ids: if mentioned.is_empty() { vec![message.user_id.clone()] } else { mentioned },
+                priority: priority(urgent, contains_any(&lower, DEADLINE_PHRASES)),
+            });
+        }
+
+        if contains_any(&lower, PROBLEM_PHRASES) || urgent {
+            risks.push(RiskItem {
+                text: item_text(message),
+                confidence: if contains_any(&lower, PROBLEM_PHRASES) { RULE_MAX_CONFIDENCE } else { RULE_CONFIDENCE },
+                priority: priority(urgent, contains_any(&lower, BLOCKER_PHRASES)),
+            });
+        }
+    }
+
+    let mut result = digest::merge_digests(vec![DigestResult {
+        decisions,
+        action_items,
+        risks,
+        summary: String::new(),
+        windows: vec![],
+        discarded: vec![],
+        fallback: None,
+        prompt_version: None,
+    }]);
+    result.summary = summary(messages, &result);
+    result.windows = MessageRange::of(messages).into_iter().collect();
+    result
+}
+
+fn summary(messages: &[ChatMessage], result: &DigestResult) -> String {
+    let mut names: Vec<&str> = Vec::new();
+    for m in messages {
+        if !names.contains(&m.user_name.as_str()) {
+            names.push(&m.user_name);
+        }
+    }
+    let high = result.risks.iter().filter(|r| r.priority == "high").count();
+    format!(
+        "[규칙 기반 요약] 메시지 {}개, 참여자 {}명({}). 결정 {}건, 액션 아이템 {}건, 리스크 {}건{}.",
+        messages.len(),
+        names.len(),
+        names.iter().take(5).copied().collect::<Vec<_>>().join(", "),
+        result.decisions.len(),
+        result.action_items.len(),
+        result.risks.len(),
+        if high > 0 { format!(" (긴급 {}건)", high) } else { String::new() },
+    )
+}
+
+// ── Helpers ────────────────────────────────────────────
+
+fn contains_any(text: &str, phrases: &[&str]) -> bool {
+    phrases.iter().any(|p| text.contains(p))
+}
+
+/// "ㅇㅇ", "넵!", "알겠습니다~" — a short reply made only of an agreement phrase.
+fn is_agreement_reply(lower: &str) -> bool {
+    let is_filler = |c: char| c.is_ascii_punctuation() || c.is_whitespace() || c == '~' || c == 'ㅎ';
+    let bare = lower.trim_end_matches(is_filler);
+    let first = bare.split_whitespace().next().unwrap_or("").trim_end_matches(is_filler);
+    bare.chars().count() <= 12 && AGREEMENT_REPLIES.contains(&first)
+}
+
+/// The latest earlier message by someone else that is not itself an agreement.
+fn answered_message(messages: &[ChatMessage], index: usize) -> Option<&ChatMessage> {
+    let replier = &messages[index].user_id;
+    messages[..index]
+        .iter()
+        .rev()
+        .find(|m| &m.user_id != replier && !m.content.trim().is_empty() && !is_agreement_reply(&m.content.to_lowercase()))
+}
+
+/// Other participants whose name appears in the message (assignees).
+fn mentioned_users(messages: &[ChatMessage], message: &ChatMessage) -> Vec<String> {
+    let ids = messages
+        .iter()
+        .filter(|m| m.user_id != message.user_id && !m.user_name.trim().is_empty())
+        .filter(|m| message.content.contains(m.user_name.trim()))
+        .map(|m| m.user_id.clone())
+        .collect();
+    dedup_ids(ids)
+}
+
+fn dedup_ids(ids: Vec<String>) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::with_capacity(ids.len());
+    for id in ids {
+        if !unique.contains(&id) {
+            unique.push(id);
+        }
+    }
+    unique
+}
+
+fn priority(urgent: bool, elevated: bool) -> String {
+    match (urgent, elevated) {
+        (true, _) => "high",
+        (false, true) => "medium",
+        _ => "low",
+    }
+    .to_string()
+}
+
+fn item_text(message: &ChatMessage) -> String {
+    let content = message.content.trim();
+    let clipped: String = content.chars().take(MAX_ITEM_CHARS).collect();
+    let ellipsis = if clipped.len() < content.len() { "…" } else { "" };
+    format!("{}: {}{}", message.user_name, clipped, ellipsis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::prompts::{Language, PromptRegistry};
+
+    fn message(minute: u32, user: &str, content: &str) -> ChatMessage {
+        ChatMessage {
+            id: None,
+            user_id: format!("u-{}", user),
+            user_name: user.to_string(),
+            content: content.to_string(),
+            created_at: format!("2026-03-01T10:{:02}:00Z", minute),
+        }
+    }
+
+    #[test]
+    fn test_rule_based_digest() {
+        let messages = vec![
+            message(0, "김대표", "런칭 영상은 30초 버전으로 가는 게 어떨까요"),
+            message(1, "이피디", "넵!"),
+            message(2, "김대표", "예산은 3천만원으로 확정합니다"),
+            message(3, "김대표", "이피디님이 내일까지 편집본 공유 부탁드립니다"),
+            message(4, "이피디", "촬영 일정이 빡빡해서 지연될 수도 있어요"),
+            message(5, "박실장", "클라이언트 피드백 급합니다 ASAP"),
+        ];
+        let result = analyze(&messages);
+
+        assert_eq!(result.decisions.len(), 2);
+        assert!(result.decisions[0].text.contains("30초 버전"));
+        assert_eq!(result.decisions[0].related_user_ids, vec!["u-김대표", "u-이피디"]);
+        assert!(result.decisions[1].text.contains("확정"));
+
+        assert_eq!(result.action_items.len(), 1);
+        assert_eq!(result.action_items[0].related_user_ids, vec!["u-이피디"]);
+        assert_eq!(result.action_items[0].priority, "medium");
+
+        assert_eq!(result.risks.len(), 2);
+        assert_eq!(result.risks[0].priority, "medium");
+        assert_eq!(result.risks[1].priority, "high");
+
+        assert!(result.summary.contains("메시지 6개"));
+        assert_eq!(result.windows.len(), 1);
+        let confidences = result.decisions.iter().chain(&result.action_items).map(|i| i.confidence);
+        assert!(confidences.chain(result.risks.iter().map(|r| r.confidence)).all(|c| c <= RULE_MAX_CONFIDENCE));
+    }
+
+    #[tokio::test]
+    async fn test_fallback_without_provider() {
+        let messages = vec![message(0, "김대표", "다음 주 화요일 미팅으로 결정")];
+        let prompts = PromptRegistry::default().set(Language::Ko);
+        let result = digest::analyze_or_fallback(
+            &messages,
+            Err("Anthropic API key required for the digest task"),
+            &prompts,
+            digest::WhenUnreachable::Fallback,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.decisions.len(), 1);
+        assert!(result.fallback.as_deref().unwrap().contains("API key"));
+        assert!(result.summary.starts_with("[규칙 기반 요약]"));
+    }
+
+    #[tokio::test]
+    async fn test_fallback_when_provider_unreachable() {
+        use crate::rag::http::{HttpPolicy, ResilientHttp};
+        use crate::rag::llm::{LlmClient, OpenAiCompatibleProvider};
+        use std::sync::Arc;
+
+        let messages = vec![message(0, "김대표", "다음 주 화요일 미팅으로 결정")];
+        let prompts = PromptRegistry::default().set(Language::Ko);
+        let http = Arc::new(ResilientHttp::new(HttpPolicy { max_retries: 0, ..Default::default() }).unwrap());
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let provider = OpenAiCompatibleProvider::new(http, format!("http://{}/v1", closed), None);
+        let llm = LlmClient::new(Box::new(provider), "llama3");
+
+        let result = digest::analyze_or_fallback(&messages, Ok(&llm), &prompts, digest::WhenUnreachable::Fallback)
+            .await
+            .unwrap();
+        assert_eq!(result.decisions.len(), 1);
+        assert!(result.fallback.as_deref().unwrap().contains("unreachable"));
+
+        // The scheduler keeps the messages queued instead
+        let err = digest::analyze_or_fallback(&messages, Ok(&llm), &prompts, digest::WhenUnreachable::Fail)
+            .await
+            .unwrap_err();
+        assert!(err.contains("unreachable"), "{}", err);
+    }
+}
@@ -0,0 +1,621 @@
+/// Knowledge Ontology — one vocabulary for knowledge_type, role_tag, scope_layer, dialectic_tag
+///
+/// The built-in terms below are the single definition of the vocabulary:
+/// - `sync_builtin` mirrors them into `ontology_terms` on every open, and the
+///   v11 triggers reject knowledge items whose knowledge_type / role_tag are
+///   not in that table (scope_layer / dialectic_tag keep their v1 CHECKs)
+/// - the extraction prompt lists the terms from here (`prompt_list`)
+/// - `create_knowledge_item` / `save_changes` validate through `validate`
+/// - snapshots and pattern mining select knowledge types by trait
+///   (`TermTrait`), aging takes its built-in half-lives from `builtin_decay`
+///
+/// Organisations may add their own knowledge types and role tags (`add_term`).
+/// scope_layer and dialectic_tag are fixed: the dialectic search passes and
+/// the SQL CHECKs depend on their exact values.
+///
+/// LLM output is never trusted verbatim: `resolve` maps unknown values to the
+/// closest term (aliases, Korean labels, edit distance, containment).
+
+use crate::rag::db::RagDb;
+use crate::rag::knowledge::KnowledgeItem;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use TermTrait::{Decision, PatternSource};
+
+/// knowledge_type used when LLM output matches nothing
+pub const FALLBACK_KNOWLEDGE_TYPE: &str = "context";
+
+// ── Built-in vocabulary ────────────────────────────────
+
+/// (value, Korean label, category, aliases)
+type BuiltinTerm = (&'static str, &'static str, &'static str, &'static [&'static str]);
+
+/// (term, traits, built-in aging (half-life days, TTL days)). Types without
+/// built-in aging decay at `aging::DEFAULT_HALF_LIFE_DAYS`.
+type BuiltinKnowledgeType = (BuiltinTerm, &'static [TermTrait], Option<(f64, Option<f64>)>);
+
+const KNOWLEDGE_TYPES: &[BuiltinKnowledgeType] = &[
+    // Protocol categories (docs/personalized-rag-protocol.md §4.1)
+    (("decision_pattern", "의사결정 패턴", "판단", &["decision", "결정"]), &[Decision, PatternSource], Some((365.0, None))),
+    (("preference", "작업 스타일 선호", "판단", &["선호"]), &[], Some((730.0, None))),
+    (("judgment", "판단 기준", "판단", &["judgement", "criteria", "판단"]), &[], None),
+    (("recurring_risk", "반복 리스크", "충돌", &["risk", "리스크", "위험"]), &[], Some((120.0, None))),
+    (("feedback_pattern", "피드백 패턴", "충돌", &["feedback", "피드백"]), &[], None),
+    (("lesson_learned", "교훈/시행착오", "학습", &["lesson", "lessons_learned", "교훈"]), &[], Some((365.0, None))),
+    (("domain_expertise", "전문 지식", "학습", &["expertise", "knowledge", "전문지식"]), &[], Some((730.0, None))),
+    (("collaboration_pattern", "협업 패턴", "소통", &["collaboration", "teamwork", "협업"]), &[], None),
+    (("communication_style", "커뮤니케이션 스타일", "소통", &["communication", "소통"]), &[], Some((730.0, None))),
+    (("workflow", "표준 작업 절차", "실행", &["process", "procedure", "task", "업무"]), &[], Some((90.0, None))),
+    (("creative_direction", "크리에이티브 방향성", "실행", &["creative", "크리에이티브"]), &[Decision], None),
+    (("pitch_execution", "입찰/PT 실행", "실행", &["pitch", "pitching", "pt", "입찰"]), &[], None),
+    (("budget_judgment", "예산 판단", "관리", &["budget", "cost", "예산"]), &[], None),
+    (("schedule_change", "일정 변경", "관리", &["schedule", "timeline", "deadline", "일정"]), &[Decision], Some((14.0, Some(90.0)))),
+    (("stakeholder_alignment", "이해관계자 조율", "관리", &["stakeholder", "client", "클라이언트"]), &[], None),
+    (("context", "일반 맥락 정보", "맥락", &["general", "info", "맥락"]), &[], Some((60.0, None))),
+    (("talent_casting", "캐스팅", "맥락", &["casting", "talent", "캐스팅"]), &[Decision], Some((120.0, None))),
+    // CEO domain types (seed patterns, brain actions)
+    (("deal_decision", "딜/수주 결정", "판단", &["deal", "수주"]), &[Decision], Some((120.0, None))),
+    (("budget_decision", "예산 결정", "관리", &["budget_approval", "예산결정"]), &[Decision, PatternSource], Some((120.0, None))),
+    (("payment_tracking", "정산/입금 관리", "관리", &["payment", "invoice", "정산"]), &[], Some((30.0, Some(365.0)))),
+    (("vendor_selection", "업체 선정", "관리", &["vendor", "supplier", "업체"]), &[Decision], Some((120.0, None))),
+    (("campaign_strategy", "캠페인 전략", "실행", &["campaign", "strategy", "캠페인"]), &[], None),
+    (("naming_decision", "네이밍 결정", "판단", &["naming", "네이밍"]), &[Decision], None),
+    (("award_strategy", "어워드 전략", "실행", &["award", "awards", "어워드"]), &[], None),
+];
+
+const ROLE_TAGS: &[BuiltinTerm] = &[
+    ("CEO", "대표", "leadership", &["대표", "대표님", "chief_executive"]),
+    ("CD", "크리에이티브 디렉터", "creative", &["creative_director", "크리에이티브 디렉터"]),
+    ("PD", "PD", "production", &["producer", "프로듀서", "피디"]),
+    ("EDITOR", "편집", "production", &["편집자", "에디터"]),
+    ("DIRECTOR", "감독", "production", &["감독"]),
+    ("WRITER", "작가", "creative", &["copywriter", "작가", "카피라이터"]),
+    ("DESIGNER", "디자이너", "creative", &["디자이너"]),
+    ("MANAGER", "매니저", "management", &["매니저"]),
+    ("BUDGET_MANAGER", "예산 담당", "management", &["finance", "accountant", "예산 담당", "재무"]),
+    ("PROJECT_MANAGER", "프로젝트 매니저", "management", &["pm", "프로젝트 매니저"]),
+    ("EXECUTIVE_PRODUCER", "EPD", "production", &["epd", "ep"]),
+    ("LINE_PD", "라인 PD", "production", &["line_producer"]),
+    ("SENIOR_ART_DIRECTOR", "시니어 아트 디렉터", "creative", &["sad", "senior_ad"]),
+    ("ART_DIRECTOR", "아트 디렉터", "creative", &["ad", "아트 디렉터"]),
+    ("STAKEHOLDER", "이해관계자", "external", &["client", "클라이언트", "광고주"]),
+    ("VENDOR", "협력 업체", "external", &["supplier", "업체"]),
+];
+
+const SCOPE_LAYERS: &[BuiltinTerm] = &[
+    ("operations", "운영/제작", "layer", &["operation", "production", "운영", "제작"]),
+    ("creative", "크리에이티브 방향", "layer", &["크리에이티브"]),
+    ("pitch", "입찰/프레젠테이션", "layer", &["bidding", "입찰"]),
+    ("strategy", "CEO 전략적 판단", "layer", &["strategic", "전략"]),
+    ("execution", "실행/배포", "layer", &["delivery", "실행"]),
+    ("culture", "팀 문화/관계", "layer", &["team_culture", "문화"]),
+];
+
+const DIALECTIC_TAGS: &[BuiltinTerm] = &[
+    ("risk", "리스크/주의", "反", &["danger", "warning", "리스크", "위험"]),
+    ("opportunity", "기회/성장", "合", &["chance", "growth", "기회"]),
+    ("constraint", "예산/일정/리소스 제약", "反", &["limitation", "limit", "제약"]),
+    ("quality", "품질 기준", "正", &["standard", "품질"]),
+    ("client_concern", "클라이언트 우려", "反", &["client", "client_issue", "클라이언트"]),
+];
+
+// ── Types ──────────────────────────────────────────────
+
+/// Vocabulary dimension (= knowledge_items column)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Dimension {
+    KnowledgeType,
+    RoleTag,
+    ScopeLayer,
+    DialecticTag,
+}
+
+impl Dimension {
+    pub const ALL: [Dimension; 4] = [
+        Dimension::KnowledgeType,
+        Dimension::RoleTag,
+        Dimension::ScopeLayer,
+        Dimension::DialecticTag,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Dimension::KnowledgeType => "knowledge_type",
+            Dimension::RoleTag => "role_tag",
+            Dimension::ScopeLayer => "scope_layer",
+            Dimension::DialecticTag => "dialectic_tag",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|d| d.as_str() == s)
+            .ok_or_else(|| format!("Unknown ontology dimension: {}", s))
+    }
+
+    /// Whether organisations may add their own terms
+    pub fn is_extensible(&self) -> bool {
+        matches!(self, Dimension::KnowledgeType | Dimension::RoleTag)
+    }
+
+    fn builtin_terms(&self) -> Vec<(&'static BuiltinTerm, &'static [TermTrait])> {
+        let plain = |terms: &'static [BuiltinTerm]| terms.iter().map(|t| (t, &[] as &[TermTrait])).collect();
+        match self {
+            Dimension::KnowledgeType => KNOWLEDGE_TYPES.iter().map(|(term, traits, _)| (term, *traits)).collect(),
+            Dimension::RoleTag => plain(ROLE_TAGS),
+            Dimension::ScopeLayer => plain(SCOPE_LAYERS),
+            Dimension::DialecticTag => plain(DIALECTIC_TAGS),
+        }
+    }
+
+    /// Role tags are upper case, everything else snake_case
+    fn canonical_case(&self, value: &str) -> String {
+        let snake = key(value);
+        match self {
+            Dimension::RoleTag => snake.to_uppercase(),
+            _ => snake,
+        }
+    }
+}
+
+/// What other modules derive from a knowledge type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TermTrait {
+    /// A decision record (listed under a project snapshot's decisions)
+    Decision,
+    /// Mined for decision patterns
+    PatternSource,
+}
+
+impl TermTrait {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TermTrait::Decision => "decision",
+            TermTrait::PatternSource => "pattern_source",
+        }
+    }
+
+    /// Subquery for `knowledge_type IN (…)`: the knowledge types carrying this trait.
+    pub fn types_sql(&self) -> String {
+        format!(
+            "SELECT value FROM ontology_terms
+             WHERE dimension = 'knowledge_type'
+               AND EXISTS (SELECT 1 FROM json_each(ontology_terms.traits) WHERE value = '{}')",
+            self.as_str()
+        )
+    }
+}
+
+/// One vocabulary term
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Term {
+    pub dimension: Dimension,
+    pub value: String,
+    pub label: String,
+    pub category: Option<String>,
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub traits: Vec<TermTrait>,
+    pub is_builtin: bool,
+}
+
+/// Loaded vocabulary (built-in + organisation terms)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ontology {
+    pub terms: Vec<Term>,
+}
+
+impl Ontology {
+    /// Built-in vocabulary only
+    pub fn builtin() -> Self {
+        let terms = Dimension::ALL
+            .into_iter()
+            .flat_map(|dimension| {
+                dimension.builtin_terms().into_iter().map(move |((value, label, category, aliases), traits)| Term {
+                    dimension,
+                    value: value.to_string(),
+                    label: label.to_string(),
+                    category: Some(category.to_string()),
+                    aliases: aliases.iter().map(|a| a.to_string()).collect(),
+                    traits: traits.to_vec(),
+                    is_builtin: true,
+                })
+            })
+            .collect();
+        Self { terms }
+    }
+
+    /// Vocabulary as stored (built-in terms are synced on open).
+    pub fn load(conn: &Connection) -> Result<Self, String> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT dimension, value, label, category, aliases, traits, is_builtin
+                 FROM ontology_terms
+                 ORDER BY dimension, is_builtin DESC, rowid",
+            )
+            .map_err(|e| format!("Prepare ontology failed: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, String>(5)?,
+                    row.get::<_, bool>(6)?,
+                ))
+            })
+            .map_err(|e| format!("Query ontology failed: {}", e))?;
+
+        let terms = rows
+            .filter_map(|r| r.ok())
+            .filter_map(|(dimension, value, label, category, aliases, traits, is_builtin)| {
+                Some(Term {
+                    dimension: Dimension::parse(&dimension).ok()?,
+                    value,
+                    label,
+                    category,
+                    aliases: serde_json::from_str(&aliases).unwrap_or_default(),
+                    traits: serde_json::from_str(&traits).unwrap_or_default(),
+                    is_builtin,
+                })
+            })
+            .collect();
+        Ok(Self { terms })
+    }
+
+    pub fn terms(&self, dimension: Dimension) -> impl Iterator<Item = &Term> {
+        self.terms.iter().filter(move |t| t.dimension == dimension)
+    }
+
+    /// Whether knowledge type `value` carries `term_trait`.
+    pub fn has_trait(&self, value: &str, term_trait: TermTrait) -> bool {
+        self.terms(Dimension::KnowledgeType)
+            .any(|t| t.value == value && t.traits.contains(&term_trait))
+    }
+
+    /// Canonical value of `value`, or an error naming the closest term.
+    pub fn validate(&self, dimension: Dimension, value: &str) -> Result<String, String> {
+        let canonical = dimension.canonical_case(value);
+        if self.terms(dimension).any(|t| t.value == canonical) {
+            return Ok(canonical);
+        }
+        Err(match self.resolve(dimension, value) {
+            Some(closest) => format!("Unknown {} '{}' (did you mean '{}'?)", dimension.as_str(), value, closest),
+            None => format!("Unknown {} '{}'", dimension.as_str(), value),
+        })
+    }
+
+    /// Validate an optional field; empty strings count as unset.
+    pub fn validate_opt(&self, dimension: Dimension, value: Option<&str>) -> Result<Option<String>, String> {
+        match value.map(str::trim).filter(|v| !v.is_empty()) {
+            Some(v) => self.validate(dimension, v).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Closest term for free-form (LLM) output: exact value, alias or label,
+    /// then edit distance, then containment. `None` when nothing is close.
+    pub fn resolve(&self, dimension: Dimension, raw: &str) -> Option<String> {
+        let wanted = key(raw);
+        if wanted.is_empty() {
+            return None;
+        }
+        let terms: Vec<&Term> = self.terms(dimension).collect();
+
+        let exact = terms.iter().find(|t| {
+            key(&t.value) == wanted
+                || key(&t.label) == wanted
+                || t.aliases.iter().any(|a| key(a) == wanted)
+        });
+        if let Some(term) = exact {
+            return Some(term.value.clone());
+        }
+
+        // Typos: "decison_pattern", "Budget Judgement"
+        let max_distance = (wanted.chars().count() / 4).max(2);
+        let nearest = terms
+            .iter()
+            .map(|t| (levenshtein(&wanted, &key(&t.value)), *t))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .min_by_key(|(distance, _)| *distance);
+        if let Some((_, term)) = nearest {
+            return Some(term.value.clone());
+        }
+
+        // Longer phrases: "budget_decision_record" → budget_decision, "risk_pattern" → recurring_risk
+        let contained = terms
+            .iter()
+            .flat_map(|t| {
+                std::iter::once(key(&t.value))
+                    .chain(t.aliases.iter().map(|a| key(a)))
+                    .filter(|k| k.chars().count() >= 3 && wanted.contains(k.as_str()))
+                    .map(move |k| (k.chars().count(), *t))
+            })
+            .max_by_key(|(len, _)| *len);
+        contained.map(|(_, term)| term.value.clone())
+    }
+
+    /// Copy of `item` with every vocabulary field validated and canonical.
+    pub fn canonicalize(&self, item: &KnowledgeItem) -> Result<KnowledgeItem, String> {
+        self.canonicalize_update(None, item)
+    }
+
+    /// Like `canonicalize`, but fields equal to `current` pass unchecked, so
+    /// items stored before the vocabulary existed stay editable.
+    pub fn canonicalize_update(
+        &self,
+        current: Option<&KnowledgeItem>,
+        next: &KnowledgeItem,
+    ) -> Result<KnowledgeItem, String> {
+        let mut item = next.clone();
+        let unchanged = |f: fn(&KnowledgeItem) -> Option<&str>| current.is_some_and(|c| f(c) == f(next));
+
+        if !unchanged(|i| Some(i.knowledge_type.as_str())) {
+            item.knowledge_type = self.validate(Dimension::KnowledgeType, &next.knowledge_type)?;
+        }
+        if !unchanged(|i| i.role_tag.as_deref()) {
+            item.role_tag = self.validate_opt(Dimension::RoleTag, next.role_tag.as_deref())?;
+        }
+        if !unchanged(|i| i.scope_layer.as_deref()) {
+            item.scope_layer = self.validate_opt(Dimension::ScopeLayer, next.scope_layer.as_deref())?;
+        }
+        if !unchanged(|i| i.dialectic_tag.as_deref()) {
+            item.dialectic_tag = self.validate_opt(Dimension::DialecticTag, next.dialectic_tag.as_deref())?;
+        }
+        Ok(item)
+    }
+
+    /// Comma-separated values for prompts
+    pub fn prompt_list(&self, dimension: Dimension) -> String {
+        self.terms(dimension).map(|t| t.value.as_str()).collect::<Vec<_>>().join(", ")
+    }
+}
+
+// ── Storage ────────────────────────────────────────────
+
+/// Mirror the built-in vocabulary into `ontology_terms` (called after migrations).
+pub fn sync_builtin(conn: &Connection) -> rusqlite::Result<()> {
+    let builtin = Ontology::builtin();
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("UPDATE ontology_terms SET is_builtin = -1 WHERE is_builtin = 1", [])?;
+    for term in &builtin.terms {
+        tx.execute(
+            "INSERT INTO ontology_terms (dimension, value, label, category, aliases, traits, is_builtin)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1)
+             ON CONFLICT(dimension, value) DO UPDATE SET
+                label = excluded.label,
+                category = excluded.category,
+                aliases = excluded.aliases,
+                traits = excluded.traits,
+                is_builtin = 1",
+            rusqlite::params![
+                term.dimension.as_str(),
+                term.value,
+                term.label,
+                term.category,
+                serde_json::to_string(&term.aliases).unwrap_or_else(|_| "[]".to_string()),
+                serde_json::to_string(&term.traits).unwrap_or_else(|_| "[]".to_string()),
+            ],
+        )?;
+    }
+    // Terms dropped from the built-in list
+    tx.execute("DELETE FROM ontology_terms WHERE is_builtin = -1", [])?;
+    tx.commit()
+}
+
+/// Current vocabulary.
+pub fn load(db: &RagDb) -> Result<Ontology, String> {
+    Ontology::load(&db.conn())
+}
+
+/// Built-in aging of knowledge types: (knowledge_type, half-life days, TTL days).
+pub fn builtin_decay() -> impl Iterator<Item = (&'static str, f64, Option<f64>)> {
+    KNOWLEDGE_TYPES
+        .iter()
+        .filter_map(|((value, ..), _, decay)| decay.map(|(half_life, ttl)| (*value, half_life, ttl)))
+}
+
+/// Add an organisation-specific knowledge type or role tag.
+pub fn add_term(
+    db: &RagDb,
+    dimension: Dimension,
+    value: &str,
+    label: &str,
+    category: Option<&str>,
+    aliases: &[String],
+    traits: &[TermTrait],
+) -> Result<Term, String> {
+    if !dimension.is_extensible() {
+        return Err(format!("{} is not extensible", dimension.as_str()));
+    }
+    let value = dimension.canonical_case(value);
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!("Invalid {} value: use ASCII letters, digits and '_'", dimension.as_str()));
+    }
+    let label = if label.trim().is_empty() { value.clone() } else { label.trim().to_string() };
+
+    let term = Term {
+        dimension,
+        value,
+        label,
+        category: category.map(String::from),
+        aliases: aliases.iter().map(|a| a.trim().to_string()).filter(|a| !a.is_empty()).collect(),
+        traits: traits.to_vec(),
+        is_builtin: false,
+    };
+
+    let conn = db.conn();
+    conn.execute(
+        "INSERT INTO ontology_terms (dimension, value, label, category, aliases, traits, is_builtin)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, 0)",
+        rusqlite::params![
+            dimension.as_str(),
+            term.value,
+            term.label,
+            term.category,
+            serde_json::to_string(&term.aliases).unwrap_or_else(|_| "[]".to_string()),
+            serde_json::to_string(&term.traits).unwrap_or_else(|_| "[]".to_string()),
+        ],
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::SqliteFailure(ref err, _) if err.code == rusqlite::ErrorCode::ConstraintViolation => {
+            format!("{} '{}' already exists", dimension.as_str(), term.value)
+        }
+        _ => format!("Add ontology term failed: {}", e),
+    })?;
+
+    log::info!("Ontology: added {} '{}'", dimension.as_str(), term.value);
+    Ok(term)
+}
+
+/// Remove an organisation term that no knowledge item uses.
+pub fn remove_term(db: &RagDb, dimension: Dimension, value: &str) -> Result<(), String> {
+    let conn = db.conn();
+    let is_builtin: Option<bool> = conn
+        .query_row(
+            "SELECT is_builtin FROM ontology_terms WHERE dimension = ?1 AND value = ?2",
+            rusqlite::params![dimension.as_str(), value],
+            |row| row.get(0),
+        )
+        .ok();
+    match is_builtin {
+        None => return Err(format!("Unknown {} '{}'", dimension.as_str(), value)),
+        Some(true) => return Err(format!("Built-in {} '{}' cannot be removed", dimension.as_str(), value)),
+        Some(false) => {}
+    }
+
+    let in_use: i64 = conn
+        .query_row(
+            &format!("SELECT COUNT(*) FROM knowledge_items WHERE {} = ?1", dimension.as_str()),
+            [value],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Count term usage failed: {}", e))?;
+    if in_use > 0 {
+        return Err(format!("{} '{}' is used by {} knowledge items", dimension.as_str(), value, in_use));
+    }
+
+    conn.execute(
+        "DELETE FROM ontology_terms WHERE dimension = ?1 AND value = ?2",
+        rusqlite::params![dimension.as_str(), value],
+    )
+    .map_err(|e| format!("Remove ontology term failed: {}", e))?;
+    Ok(())
+}
+
+// ── Helpers ────────────────────────────────────────────
+
+/// Comparison key: trimmed, lower case, separators as '_'
+fn key(s: &str) -> String {
+    s.trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_whitespace() || c == '-' || c == '/' { '_' } else { c })
+        .collect()
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(current[j] + 1);
+        }
+        prev = current;
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::embedding::EMBEDDING_DIM;
+    use crate::rag::knowledge;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_resolve_llm_output() {
+        let ontology = Ontology::builtin();
+        let kt = Dimension::KnowledgeType;
+
+        assert_eq!(ontology.resolve(kt, "budget_decision").as_deref(), Some("budget_decision"));
+        assert_eq!(ontology.resolve(kt, "Lesson Learned").as_deref(), Some("lesson_learned"));
+        assert_eq!(ontology.resolve(kt, "risk").as_deref(), Some("recurring_risk"));
+        assert_eq!(ontology.resolve(kt, "budget_decision_record").as_deref(), Some("budget_decision"));
+        assert_eq!(ontology.resolve(kt, "decison_pattern").as_deref(), Some("decision_pattern"));
+        assert_eq!(ontology.resolve(kt, "교훈").as_deref(), Some("lesson_learned"));
+        assert_eq!(ontology.resolve(kt, "xyz"), None);
+
+        assert_eq!(ontology.resolve(Dimension::RoleTag, "creative director").as_deref(), Some("CD"));
+        assert_eq!(ontology.resolve(Dimension::RoleTag, "ceo").as_deref(), Some("CEO"));
+        assert_eq!(ontology.resolve(Dimension::DialecticTag, "위험").as_deref(), Some("risk"));
+
+        assert_eq!(ontology.validate(Dimension::RoleTag, "budget_manager").unwrap(), "BUDGET_MANAGER");
+        let err = ontology.validate(kt, "desicion_pattern").unwrap_err();
+        assert!(err.contains("did you mean 'decision_pattern'"), "{}", err);
+    }
+
+    #[test]
+    fn test_extensions_and_db_enforcement() {
+        let dir = std::env::temp_dir().join(format!("rag_ontology_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = RagDb::open(&dir.join("test.db")).unwrap();
+
+        assert_eq!(
+            load(&db).unwrap().terms(Dimension::KnowledgeType).count(),
+            KNOWLEDGE_TYPES.len()
+        );
+        assert!(add_term(&db, Dimension::DialecticTag, "hope", "희망", None, &[], &[]).is_err());
+        let kt = Dimension::KnowledgeType;
+        add_term(&db, kt, "Music Licensing", "음원 라이선스", Some("관리"), &["bgm".to_string()], &[Decision]).unwrap();
+        assert!(add_term(&db, kt, "music_licensing", "", None, &[], &[]).is_err());
+
+        let ontology = load(&db).unwrap();
+        assert_eq!(ontology.resolve(Dimension::KnowledgeType, "BGM").as_deref(), Some("music_licensing"));
+        assert!(ontology.prompt_list(Dimension::KnowledgeType).ends_with("music_licensing"));
+
+        // Traits survive the round trip and drive the SQL type sets
+        assert!(ontology.has_trait("music_licensing", Decision));
+        assert!(ontology.has_trait("budget_decision", PatternSource));
+        assert!(!ontology.has_trait("music_licensing", PatternSource));
+        let decision_types: Vec<String> = {
+            let conn = db.conn();
+            let mut stmt = conn.prepare(&Decision.types_sql()).unwrap();
+            let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+            rows.map(|r| r.unwrap()).collect()
+        };
+        assert!(decision_types.contains(&"music_licensing".to_string()));
+        assert!(decision_types.contains(&"vendor_selection".to_string()));
+        assert!(!decision_types.contains(&"context".to_string()));
+
+        let item = |knowledge_type: &str| KnowledgeItem {
+            knowledge_type: knowledge_type.to_string(),
+            role_tag: Some("pd".to_string()),
+            project_id: Some("p1".to_string()),
+            ..KnowledgeItem::for_test(&format!("{} 항목", knowledge_type))
+        };
+        let vector = vec![0.1; EMBEDDING_DIM];
+
+        let id = knowledge::create_knowledge_item(&db, &item("music_licensing"), &vector).unwrap();
+        let stored = knowledge::get_knowledge_item(&db, &id).unwrap().unwrap();
+        assert_eq!(stored.role_tag.as_deref(), Some("PD"));
+        assert!(knowledge::create_knowledge_item(&db, &item("music"), &vector).is_err());
+
+        // The DB triggers hold even when the Rust validation is bypassed
+        let raw = db.conn().execute(
+            "UPDATE knowledge_items SET knowledge_type = 'not_a_type' WHERE id = ?1",
+            [&id],
+        );
+        assert!(raw.is_err());
+
+        assert!(remove_term(&db, Dimension::KnowledgeType, "music_licensing").is_err());
+        assert!(remove_term(&db, Dimension::KnowledgeType, "context").is_err());
+    }
+}
@@ -0,0 +1,406 @@
+/// Decision Outcome Lifecycle — pending → escalated → confirmed / rejected
+///
+/// Moves a decision between outcomes with a reason, keeps an audit trail in
+/// `decision_outcome_log`, and answers "what is still open?" per project or
+/// decision maker. The time a decision entered its current outcome is the
+/// latest log entry, or the item's creation time if it was never changed.
+///
+/// Reopening or reversing a final decision (confirmed / rejected) requires a
+/// reason. Stale pending/escalated decisions publish
+/// `RagEvent::DecisionReminder`, at most once per reminder interval.
+
+use crate::rag::db::RagDb;
+use crate::rag::embedding::EmbeddingEngine;
+use crate::rag::events::RagEvent;
+use crate::rag::knowledge;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Valid outcomes (mirrors the knowledge_items CHECK constraint)
+pub const OUTCOMES: [&str; 4] = ["pending", "escalated", "confirmed", "rejected"];
+
+/// Default "stale" age for reminders
+pub const DEFAULT_REMINDER_DAYS: f64 = 7.0;
+
+/// SQL for when an item entered its current outcome (needs alias `k`)
+const OUTCOME_SINCE: &str = "COALESCE(
+        (SELECT MAX(l.changed_at) FROM decision_outcome_log l WHERE l.knowledge_id = k.id),
+        k.created_at)";
+
+/// One outcome transition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutcomeChange {
+    pub id: String,
+    pub knowledge_id: String,
+    pub from_outcome: Option<String>,
+    pub to_outcome: String,
+    pub reason: Option<String>,
+    pub changed_by: Option<String>,
+    pub changed_at: String,
+}
+
+/// Decision with the time spent in its current outcome
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackedDecision {
+    pub knowledge_id: String,
+    pub content: String,
+    pub knowledge_type: String,
+    pub outcome: String,
+    pub decision_maker: Option<String>,
+    pub project_id: Option<String>,
+    pub financial_impact_krw: Option<i64>,
+    pub outcome_since: String,
+    pub days_in_outcome: f64,
+}
+
+/// Filter for `list_decisions`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecisionFilter {
+    pub outcomes: Vec<String>,
+    pub project_id: Option<String>,
+    pub decision_maker: Option<String>,
+    /// Only decisions in their outcome for at least this many days
+    pub min_days: Option<f64>,
+    pub limit: usize,
+}
+
+impl Default for DecisionFilter {
+    fn default() -> Self {
+        Self {
+            outcomes: vec!["pending".to_string(), "escalated".to_string()],
+            project_id: None,
+            decision_maker: None,
+            min_days: None,
+            limit: 50,
+        }
+    }
+}
+
+/// Move a decision to `to_outcome`, recording the transition.
+pub fn transition(
+    db: &RagDb,
+    engine: &EmbeddingEngine,
+    knowledge_id: &str,
+    to_outcome: &str,
+    reason: Option<&str>,
+    changed_by: Option<&str>,
+) -> Result<OutcomeChange, String> {
+    let current = knowledge::get_knowledge_item(db, knowledge_id)?
+        .ok_or_else(|| format!("Knowledge item not found: {}", knowledge_id))?;
+    let reason = reason.map(str::trim).filter(|r| !r.is_empty());
+    validate(current.outcome.as_deref(), to_outcome, reason)?;
+
+    let change = OutcomeChange {
+        id: Uuid::new_v4().to_string(),
+        knowledge_id: knowledge_id.to_string(),
+        from_outcome: current.outcome.clone(),
+        to_outcome: to_outcome.to_string(),
+        reason: reason.map(String::from),
+        changed_by: changed_by.map(String::from),
+        changed_at: String::new(),
+    };
+
+    let mut next = current.clone();
+    next.outcome = Some(to_outcome.to_string());
+    let pending = knowledge::prepare_changes(db, engine, &current, &next)?
+        .ok_or_else(|| format!("Decision is already {}", to_outcome))?;
+
+    // Audit row and outcome change commit together
+    let (stored, changed_at) = {
+        let conn = db.conn();
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(|e| format!("Begin outcome change failed: {}", e))?;
+        let changed_at: String = tx
+            .query_row(
+                "INSERT INTO decision_outcome_log
+                    (id, knowledge_id, from_outcome, to_outcome, reason, changed_by)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 RETURNING changed_at",
+                rusqlite::params![
+                    change.id,
+                    change.knowledge_id,
+                    change.from_outcome,
+                    change.to_outcome,
+                    change.reason,
+                    change.changed_by,
+                ],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Record outcome change failed: {}", e))?;
+        let stored = knowledge::save_changes_with(&tx, &current, &pending, "update", changed_by)?;
+
+        // A new outcome restarts the reminder clock
+        tx.execute("DELETE FROM decision_reminders WHERE knowledge_id = ?1", [knowledge_id])
+            .map_err(|e| format!("Reset reminder failed: {}", e))?;
+        tx.commit().map_err(|e| format!("Commit outcome change failed: {}", e))?;
+        (stored, changed_at)
+    };
+    knowledge::after_save(db, &current, &stored, &pending);
+
+    log::info!(
+        "Decision {} outcome {} → {}",
+        knowledge_id,
+        change.from_outcome.as_deref().unwrap_or("none"),
+        to_outcome
+    );
+    Ok(OutcomeChange { changed_at, ..change })
+}
+
+/// Outcome transitions of an item, oldest first.
+pub fn outcome_history(db: &RagDb, knowledge_id: &str) -> Result<Vec<OutcomeChange>, String> {
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, knowledge_id, from_outcome, to_outcome, reason, changed_by, changed_at
+             FROM decision_outcome_log
+             WHERE knowledge_id = ?1
+             ORDER BY changed_at ASC, rowid ASC",
+        )
+        .map_err(|e| format!("Prepare outcome history failed: {}", e))?;
+
+    let rows = stmt
+        .query_map([knowledge_id], |row| {
+            Ok(OutcomeChange {
+                id: row.get(0)?,
+                knowledge_id: row.get(1)?,
+                from_outcome: row.get(2)?,
+                to_outcome: row.get(3)?,
+                reason: row.get(4)?,
+                changed_by: row.get(5)?,
+                changed_at: row.get(6)?,
+            })
+        })
+        .map_err(|e| format!("Query outcome history failed: {}", e))?;
+
+    Ok(rows.filter_map(|r| r.ok()).collect())
+}
+
+/// Active decisions in the given outcomes, longest-waiting first.
+pub fn list_decisions(db: &RagDb, filter: &DecisionFilter) -> Result<Vec<TrackedDecision>, String> {
+    for outcome in &filter.outcomes {
+        if !OUTCOMES.contains(&outcome.as_str()) {
+            return Err(format!("Invalid outcome: {}", outcome));
+        }
+    }
+    let outcomes = serde_json::to_string(&filter.outcomes).unwrap_or_else(|_| "[]".to_string());
+
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT id, content, knowledge_type, outcome, decision_maker, project_id,
+                    financial_impact_krw, since, julianday('now') - julianday(since) AS days
+             FROM (
+                SELECT k.*, {} AS since
+                FROM knowledge_items k
+                WHERE k.is_active = 1
+                  AND k.outcome IN (SELECT value FROM json_each(?1))
+                  AND (?2 IS NULL OR k.project_id = ?2)
+                  AND (?3 IS NULL OR k.decision_maker = ?3)
+             )
+             WHERE (?4 IS NULL OR julianday('now') - julianday(since) >= ?4)
+             ORDER BY julianday(since) ASC
+             LIMIT ?5",
+            OUTCOME_SINCE
+        ))
+        .map_err(|e| format!("Prepare decisions failed: {}", e))?;
+
+    let rows = stmt
+        .query_map(
+            rusqlite::params![
+                outcomes,
+                filter.project_id,
+                filter.decision_maker,
+                filter.min_days,
+                filter.limit as i64,
+            ],
+            |row| {
+                Ok(TrackedDecision {
+                    knowledge_id: row.get(0)?,
+                    content: row.get(1)?,
+                    knowledge_type: row.get(2)?,
+                    outcome: row.get(3)?,
+                    decision_maker: row.get(4)?,
+                    project_id: row.get(5)?,
+                    financial_impact_krw: row.get(6)?,
+                    outcome_since: row.get(7)?,
+                    days_in_outcome: row.get::<_, Option<f64>>(8)?.unwrap_or(0.0),
+                })
+            },
+        )
+        .map_err(|e| format!("Query decisions failed: {}", e))?;
+
+    Ok(rows.filter_map(|r| r.ok()).collect())
+}
+
+/// Reminder hook: publish `DecisionReminder` for decisions pending or escalated
+/// for at least `days`, unless reminded within the last `days` already.
+pub fn run_reminders(db: &RagDb, days: f64) -> Result<Vec<TrackedDecision>, String> {
+    let filter = DecisionFilter {
+        min_days: Some(days),
+        limit: 500,
+        ..Default::default()
+    };
+    let stale = list_decisions(db, &filter)?;
+
+    let mut due = Vec::new();
+    {
+        let conn = db.conn();
+        for decision in stale {
+            let recently_reminded: bool = conn
+                .query_row(
+                    "SELECT EXISTS(
+                        SELECT 1 FROM decision_reminders
+                        WHERE knowledge_id = ?1 AND julianday('now') - julianday(reminded_at) < ?2)",
+                    rusqlite::params![decision.knowledge_id, days],
+                    |row| row.get(0),
+                )
+                .map_err(|e| format!("Check reminder failed: {}", e))?;
+            if recently_reminded {
+                continue;
+            }
+
+            conn.execute(
+                "INSERT INTO decision_reminders (knowledge_id) VALUES (?1)
+                 ON CONFLICT(knowledge_id) DO UPDATE SET
+                    reminded_at = datetime('now'),
+                    reminder_count = reminder_count + 1",
+                [&decision.knowledge_id],
+            )
+            .map_err(|e| format!("Record reminder failed: {}", e))?;
+            due.push(decision);
+        }
+    }
+
+    for decision in &due {
+        db.emit(RagEvent::DecisionReminder(decision.clone()));
+    }
+    if !due.is_empty() {
+        log::info!("Decision reminders: {} stale decisions", due.len());
+    }
+    Ok(due)
+}
+
+fn validate(from: Option<&str>, to: &str, reason: Option<&str>) -> Result<(), String> {
+    if !OUTCOMES.contains(&to) {
+        return Err(format!("Invalid outcome: {}", to));
+    }
+    if from == Some(to) {
+        return Err(format!("Decision is already {}", to));
+    }
+    if matches!(from, Some("confirmed") | Some("rejected")) && reason.is_none() {
+        return Err(format!("Changing a {} decision requires a reason", from.unwrap_or_default()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::knowledge::KnowledgeItem;
+
+    fn setup() -> (RagDb, EmbeddingEngine) {
+        let dir = std::env::temp_dir().join(format!("rag_outcomes_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        (RagDb::open(&dir.join("test.db")).unwrap(), EmbeddingEngine::new(dir.join("models")))
+    }
+
+    fn create(db: &RagDb, engine: &EmbeddingEngine, content: &str, maker: &str, created_at: &str) -> String {
+        let item = KnowledgeItem {
+            decision_maker: Some(maker.to_string()),
+            outcome: Some("pending".to_string()),
+            user_id: Some("u1".to_string()),
+            project_id: Some("p1".to_string()),
+            created_at: created_at.to_string(),
+            updated_at: created_at.to_string(),
+            ..KnowledgeItem::for_test(content)
+        };
+        let vector = engine.embed(content).unwrap().vector;
+        knowledge::create_knowledge_item(db, &item, &vector).unwrap()
+    }
+
+    #[test]
+    fn test_transitions_and_audit() {
+        let (db, engine) = setup();
+        let id = create(&db, &engine, "B 업체 견적 검토", "김경신", &chrono::Utc::now().to_rfc3339());
+
+        assert!(transition(&db, &engine, &id, "pending", None, None).is_err());
+        assert!(transition(&db, &engine, &id, "approved", None, None).is_err());
+
+        transition(&db, &engine, &id, "escalated", Some("예산 초과"), Some("u1")).unwrap();
+        transition(&db, &engine, &id, "confirmed", None, Some("ceo")).unwrap();
+        // Reversing a final decision needs a reason
+        assert!(transition(&db, &engine, &id, "rejected", Some("  "), None).is_err());
+        transition(&db, &engine, &id, "rejected", Some("클라이언트 취소"), None).unwrap();
+
+        let item = knowledge::get_knowledge_item(&db, &id).unwrap().unwrap();
+        assert_eq!(item.outcome.as_deref(), Some("rejected"));
+
+        let log = outcome_history(&db, &id).unwrap();
+        let steps: Vec<(Option<&str>, &str)> =
+            log.iter().map(|c| (c.from_outcome.as_deref(), c.to_outcome.as_str())).collect();
+        assert_eq!(
+            steps,
+            vec![(Some("pending"), "escalated"), (Some("escalated"), "confirmed"), (Some("confirmed"), "rejected")]
+        );
+        assert_eq!(log[0].reason.as_deref(), Some("예산 초과"));
+
+        // Outcome changes are part of the item's version history too
+        let versions = crate::rag::history::list_versions(&db, &id).unwrap();
+        assert_eq!(versions[0].changed_fields, vec!["outcome"]);
+
+        // A failed update leaves neither an audit row nor a version behind
+        db.conn()
+            .execute_batch(
+                "CREATE TEMP TRIGGER fail_outcome BEFORE UPDATE OF outcome ON knowledge_items
+                 BEGIN SELECT RAISE(ABORT, 'disk full'); END;",
+            )
+            .unwrap();
+        let error = transition(&db, &engine, &id, "pending", Some("재검토"), None).unwrap_err();
+        assert!(error.contains("disk full"), "{}", error);
+        assert_eq!(outcome_history(&db, &id).unwrap().len(), 3);
+        assert_eq!(crate::rag::history::list_versions(&db, &id).unwrap().len(), versions.len());
+        let item = knowledge::get_knowledge_item(&db, &id).unwrap().unwrap();
+        assert_eq!(item.outcome.as_deref(), Some("rejected"));
+    }
+
+    #[test]
+    fn test_stale_queries_and_reminders() {
+        let (db, engine) = setup();
+        let old = (chrono::Utc::now() - chrono::Duration::days(10)).to_rfc3339();
+        let stale = create(&db, &engine, "촬영 장소 확정 대기", "김경신", &old);
+        let escalated = create(&db, &engine, "추가 예산 승인 대기", "PD", &old);
+        create(&db, &engine, "오늘 올라온 안건", "김경신", &chrono::Utc::now().to_rfc3339());
+
+        // Escalating restarts the clock for that item
+        transition(&db, &engine, &escalated, "escalated", None, None).unwrap();
+
+        let by_maker = list_decisions(
+            &db,
+            &DecisionFilter {
+                decision_maker: Some("김경신".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(by_maker.len(), 2);
+        assert_eq!(by_maker[0].knowledge_id, stale);
+        assert!(by_maker[0].days_in_outcome > 9.0);
+
+        let escalated_only = list_decisions(
+            &db,
+            &DecisionFilter {
+                outcomes: vec!["escalated".to_string()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(escalated_only.len(), 1);
+
+        let due = run_reminders(&db, DEFAULT_REMINDER_DAYS).unwrap();
+        assert_eq!(due.iter().map(|d| d.knowledge_id.as_str()).collect::<Vec<_>>(), vec![stale.as_str()]);
+        // Not repeated within the interval
+        assert!(run_reminders(&db, DEFAULT_REMINDER_DAYS).unwrap().is_empty());
+    }
+}
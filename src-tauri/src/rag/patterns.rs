@@ -0,0 +1,592 @@
+/// Decision Pattern Mining — "How does 김경신 usually decide vendor selection?"
+///
+/// Clusters each decision maker's pattern-source items (knowledge types with
+/// `TermTrait::PatternSource`: `decision_pattern`, `budget_decision`, …) per domain (`scope_layer`, or "general") by embedding, and stores every
+/// cluster with at least `MIN_SAMPLES` items as a row in `decision_patterns`:
+///
+///   cluster    = greedy leader clustering, cosine(item, centroid) >= CLUSTER_THRESHOLD
+///   confidence = mean item confidence * n / (n + 2)   (more evidence → closer to the mean)
+///
+/// Patterns are kept current incrementally: `on_event` re-mines only the
+/// (decision maker, domain) groups touched by a knowledge change.
+
+use crate::rag::db::RagDb;
+use crate::rag::embedding::{blob_to_vector, cosine_similarity, vector_to_blob, EMBEDDING_DIM};
+use crate::rag::events::RagEvent;
+use crate::rag::knowledge;
+use crate::rag::ontology::{Ontology, TermTrait};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use uuid::Uuid;
+
+/// Minimum similarity between an item and a cluster centroid
+pub const CLUSTER_THRESHOLD: f32 = 0.60;
+
+/// Minimum cluster size to be stored as a pattern
+pub const MIN_SAMPLES: usize = 2;
+
+/// Domain used for items without a scope_layer
+const GENERAL_DOMAIN: &str = "general";
+
+/// Stored decision pattern
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecisionPattern {
+    pub id: String,
+    pub user_id: Option<String>,
+    pub decision_maker: String,
+    pub knowledge_domain: String,
+    pub pattern_type: Option<String>,
+    pub pattern_summary: Option<String>,
+    pub evidence_item_ids: Vec<String>,
+    pub confidence: f64,
+    pub sample_count: i64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Evidence item shown with a pattern
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternEvidence {
+    pub knowledge_id: String,
+    pub content: String,
+    pub outcome: Option<String>,
+    pub confidence: f64,
+}
+
+/// Pattern ranked against a question
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternMatch {
+    pub pattern: DecisionPattern,
+    pub similarity: f32,
+    pub evidence: Vec<PatternEvidence>,
+}
+
+/// Result of a mining run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MiningReport {
+    pub groups: usize,
+    pub created: usize,
+    pub updated: usize,
+    pub removed: usize,
+}
+
+/// Re-mine every group (optionally only one decision maker's).
+pub fn mine_all(db: &RagDb, decision_maker: Option<&str>) -> Result<MiningReport, String> {
+    let groups: BTreeSet<(String, String)> = {
+        let conn = db.conn();
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT decision_maker, COALESCE(scope_layer, ?2)
+                 FROM knowledge_items
+                 WHERE is_active = 1
+                   AND decision_maker IS NOT NULL
+                   AND knowledge_type IN ({})
+                   AND (?1 IS NULL OR decision_maker = ?1)
+                 UNION
+                 SELECT decision_maker, knowledge_domain
+                 FROM decision_patterns
+                 WHERE (?1 IS NULL OR decision_maker = ?1)",
+                TermTrait::PatternSource.types_sql()
+            ))
+            .map_err(|e| format!("Prepare pattern groups failed: {}", e))?;
+        let rows = stmt
+            .query_map(rusqlite::params![decision_maker, GENERAL_DOMAIN], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .map_err(|e| format!("Query pattern groups failed: {}", e))?;
+        rows.filter_map(|r| r.ok()).collect()
+    };
+
+    let mut report = MiningReport::default();
+    for (maker, domain) in &groups {
+        let group = mine_group(db, maker, domain)?;
+        report.created += group.created;
+        report.updated += group.updated;
+        report.removed += group.removed;
+    }
+    report.groups = groups.len();
+
+    log::info!(
+        "Decision pattern mining: {} groups, {} created, {} updated, {} removed",
+        report.groups,
+        report.created,
+        report.updated,
+        report.removed
+    );
+    Ok(report)
+}
+
+/// Re-cluster one (decision maker, domain) group and sync its pattern rows.
+///
+/// Existing rows are matched to new clusters by evidence overlap so pattern ids stay stable.
+pub fn mine_group(db: &RagDb, decision_maker: &str, domain: &str) -> Result<MiningReport, String> {
+    let items = load_group_items(db, decision_maker, domain)?;
+    let clusters: Vec<Cluster> = cluster(&items)
+        .into_iter()
+        .filter(|c| c.members.len() >= MIN_SAMPLES)
+        .collect();
+
+    let conn = db.conn();
+    let existing: Vec<(String, HashSet<String>)> = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, evidence_item_ids FROM decision_patterns
+                 WHERE decision_maker = ?1 AND knowledge_domain = ?2",
+            )
+            .map_err(|e| format!("Prepare existing patterns failed: {}", e))?;
+        let rows = stmt
+            .query_map(rusqlite::params![decision_maker, domain], |row| {
+                let evidence: Option<String> = row.get(1)?;
+                Ok((row.get::<_, String>(0)?, parse_ids(evidence.as_deref()).into_iter().collect()))
+            })
+            .map_err(|e| format!("Query existing patterns failed: {}", e))?;
+        rows.filter_map(|r| r.ok()).collect()
+    };
+
+    let mut report = MiningReport { groups: 1, ..Default::default() };
+    let mut reused: HashSet<String> = HashSet::new();
+
+    for cluster in &clusters {
+        let members: Vec<&GroupItem> = cluster.members.iter().map(|&i| &items[i]).collect();
+        let evidence_ids: Vec<String> = members.iter().map(|m| m.id.clone()).collect();
+        let summary = summarize(decision_maker, domain, &members, &cluster.centroid);
+        let pattern_type = dominant(members.iter().map(|m| m.knowledge_type.as_str()));
+        let owners: HashSet<Option<&str>> = members.iter().map(|m| m.user_id.as_deref()).collect();
+        let user_id = if owners.len() == 1 { owners.into_iter().next().flatten() } else { None };
+        let n = members.len() as f64;
+        let mean_confidence = members.iter().map(|m| m.confidence).sum::<f64>() / n;
+        let confidence = mean_confidence * n / (n + 2.0);
+
+        let best_existing = existing
+            .iter()
+            .filter(|(id, _)| !reused.contains(id))
+            .map(|(id, ids)| (id, evidence_ids.iter().filter(|e| ids.contains(*e)).count()))
+            .filter(|(_, overlap)| *overlap > 0)
+            .max_by_key(|(_, overlap)| *overlap)
+            .map(|(id, _)| id.clone());
+
+        let evidence_json = serde_json::to_string(&evidence_ids).unwrap_or_else(|_| "[]".to_string());
+        let centroid = vector_to_blob(&cluster.centroid);
+
+        match best_existing {
+            Some(id) => {
+                conn.execute(
+                    "UPDATE decision_patterns SET
+                        user_id = ?2, pattern_type = ?3, pattern_summary = ?4,
+                        evidence_item_ids = ?5, confidence = ?6, sample_count = ?7,
+                        centroid = ?8, updated_at = datetime('now')
+                     WHERE id = ?1",
+                    rusqlite::params![
+                        id,
+                        user_id,
+                        pattern_type,
+                        summary,
+                        evidence_json,
+                        confidence,
+                        members.len() as i64,
+                        centroid,
+                    ],
+                )
+                .map_err(|e| format!("Update pattern failed: {}", e))?;
+                reused.insert(id);
+                report.updated += 1;
+            }
+            None => {
+                conn.execute(
+                    "INSERT INTO decision_patterns (
+                        id, user_id, decision_maker, knowledge_domain, pattern_type,
+                        pattern_summary, evidence_item_ids, confidence, sample_count, centroid
+                     ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                    rusqlite::params![
+                        Uuid::new_v4().to_string(),
+                        user_id,
+                        decision_maker,
+                        domain,
+                        pattern_type,
+                        summary,
+                        evidence_json,
+                        confidence,
+                        members.len() as i64,
+                        centroid,
+                    ],
+                )
+                .map_err(|e| format!("Insert pattern failed: {}", e))?;
+                report.created += 1;
+            }
+        }
+    }
+
+    for (id, _) in existing.iter().filter(|(id, _)| !reused.contains(id)) {
+        conn.execute("DELETE FROM decision_patterns WHERE id = ?1", [id])
+            .map_err(|e| format!("Delete pattern failed: {}", e))?;
+        report.removed += 1;
+    }
+
+    Ok(report)
+}
+
+/// Listener entry point for `RagDb::subscribe`: re-mine groups touched by a change.
+pub fn on_event(db: &RagDb, event: &RagEvent) {
+    let RagEvent::KnowledgeChanged(change) = event else {
+        return;
+    };
+    if let Err(e) = refresh_for_item(db, &change.knowledge_id) {
+        log::warn!("Decision pattern refresh failed for {}: {}", change.knowledge_id, e);
+    }
+}
+
+/// Patterns, optionally filtered by decision maker and domain (largest first).
+pub fn list_patterns(
+    db: &RagDb,
+    decision_maker: Option<&str>,
+    domain: Option<&str>,
+) -> Result<Vec<DecisionPattern>, String> {
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} WHERE (?1 IS NULL OR decision_maker = ?1)
+                 AND (?2 IS NULL OR knowledge_domain = ?2)
+               ORDER BY sample_count DESC, confidence DESC",
+            SELECT_PATTERN
+        ))
+        .map_err(|e| format!("Prepare patterns failed: {}", e))?;
+
+    let rows = stmt
+        .query_map(rusqlite::params![decision_maker, domain], |row| Ok(row_to_pattern(row)?.0))
+        .map_err(|e| format!("Query patterns failed: {}", e))?;
+
+    Ok(rows.filter_map(|r| r.ok()).collect())
+}
+
+/// Rank a decision maker's patterns against a question embedding.
+pub fn find_patterns(
+    db: &RagDb,
+    decision_maker: Option<&str>,
+    query_embedding: &[f32],
+    limit: usize,
+) -> Result<Vec<PatternMatch>, String> {
+    let mut ranked: Vec<(DecisionPattern, f32)> = {
+        let conn = db.conn();
+        let mut stmt = conn
+            .prepare(&format!(
+                "{} WHERE (?1 IS NULL OR decision_maker = ?1) AND centroid IS NOT NULL",
+                SELECT_PATTERN
+            ))
+            .map_err(|e| format!("Prepare patterns failed: {}", e))?;
+        let rows = stmt
+            .query_map(rusqlite::params![decision_maker], row_to_pattern)
+            .map_err(|e| format!("Query patterns failed: {}", e))?;
+
+        rows.filter_map(|r| r.ok())
+            .filter(|(_, centroid)| centroid.len() == query_embedding.len())
+            .map(|(pattern, centroid)| {
+                let similarity = cosine_similarity(query_embedding, &centroid);
+                (pattern, similarity)
+            })
+            .collect()
+    };
+
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    ranked.truncate(limit);
+
+    let mut matches = Vec::new();
+    for (pattern, similarity) in ranked {
+        let mut evidence = Vec::new();
+        for id in pattern.evidence_item_ids.iter().take(5) {
+            if let Some(item) = knowledge::get_knowledge_item(db, id)? {
+                evidence.push(PatternEvidence {
+                    knowledge_id: item.id,
+                    content: item.content,
+                    outcome: item.outcome,
+                    confidence: item.confidence,
+                });
+            }
+        }
+        matches.push(PatternMatch { pattern, similarity, evidence });
+    }
+    Ok(matches)
+}
+
+// ── Internal helpers ────────────────────────────────────
+
+const SELECT_PATTERN: &str = "SELECT id, user_id, decision_maker, knowledge_domain, pattern_type,
+            pattern_summary, evidence_item_ids, confidence, sample_count,
+            created_at, updated_at, centroid
+     FROM decision_patterns";
+
+struct GroupItem {
+    id: String,
+    content: String,
+    knowledge_type: String,
+    confidence: f64,
+    outcome: Option<String>,
+    user_id: Option<String>,
+    vector: Vec<f32>,
+}
+
+struct Cluster {
+    members: Vec<usize>,
+    sum: Vec<f32>,
+    centroid: Vec<f32>,
+}
+
+fn row_to_pattern(row: &rusqlite::Row) -> rusqlite::Result<(DecisionPattern, Vec<f32>)> {
+    let evidence: Option<String> = row.get(6)?;
+    let centroid: Option<Vec<u8>> = row.get(11)?;
+    Ok((
+        DecisionPattern {
+            id: row.get(0)?,
+            user_id: row.get(1)?,
+            decision_maker: row.get(2)?,
+            knowledge_domain: row.get(3)?,
+            pattern_type: row.get(4)?,
+            pattern_summary: row.get(5)?,
+            evidence_item_ids: parse_ids(evidence.as_deref()),
+            confidence: row.get::<_, Option<f64>>(7)?.unwrap_or(0.0),
+            sample_count: row.get::<_, Option<i64>>(8)?.unwrap_or(0),
+            created_at: row.get::<_, Option<String>>(9)?.unwrap_or_default(),
+            updated_at: row.get::<_, Option<String>>(10)?.unwrap_or_default(),
+        },
+        centroid.map(|b| blob_to_vector(&b)).unwrap_or_default(),
+    ))
+}
+
+fn parse_ids(json: Option<&str>) -> Vec<String> {
+    json.and_then(|s| serde_json::from_str(s).ok()).unwrap_or_default()
+}
+
+fn load_group_items(db: &RagDb, decision_maker: &str, domain: &str) -> Result<Vec<GroupItem>, String> {
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT k.id, k.content, k.knowledge_type, k.confidence, k.outcome, k.user_id, e.vector
+             FROM knowledge_items k
+             JOIN embeddings e ON e.knowledge_id = k.id
+             WHERE k.is_active = 1
+               AND k.decision_maker = ?1
+               AND COALESCE(k.scope_layer, ?3) = ?2
+               AND k.knowledge_type IN ({})
+             ORDER BY julianday(k.created_at), k.id",
+            TermTrait::PatternSource.types_sql()
+        ))
+        .map_err(|e| format!("Prepare group items failed: {}", e))?;
+
+    let rows = stmt
+        .query_map(rusqlite::params![decision_maker, domain, GENERAL_DOMAIN], |row| {
+            Ok(GroupItem {
+                id: row.get(0)?,
+                content: row.get(1)?,
+                knowledge_type: row.get(2)?,
+                confidence: row.get(3)?,
+                outcome: row.get(4)?,
+                user_id: row.get(5)?,
+                vector: blob_to_vector(&row.get::<_, Vec<u8>>(6)?),
+            })
+        })
+        .map_err(|e| format!("Query group items failed: {}", e))?;
+
+    Ok(rows
+        .filter_map(|r| r.ok())
+        .filter(|i| i.vector.len() == EMBEDDING_DIM)
+        .collect())
+}
+
+/// Greedy leader clustering with running-mean centroids.
+fn cluster(items: &[GroupItem]) -> Vec<Cluster> {
+    let mut clusters: Vec<Cluster> = Vec::new();
+
+    for (i, item) in items.iter().enumerate() {
+        let best = clusters
+            .iter_mut()
+            .map(|c| (cosine_similarity(&item.vector, &c.centroid), c))
+            .filter(|(sim, _)| *sim >= CLUSTER_THRESHOLD)
+            .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        match best {
+            Some((_, c)) => {
+                c.members.push(i);
+                for (s, v) in c.sum.iter_mut().zip(&item.vector) {
+                    *s += v;
+                }
+                c.centroid = normalize(&c.sum);
+            }
+            None => clusters.push(Cluster {
+                members: vec![i],
+                sum: item.vector.clone(),
+                centroid: normalize(&item.vector),
+            }),
+        }
+    }
+
+    clusters
+}
+
+fn normalize(v: &[f32]) -> Vec<f32> {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        v.iter().map(|x| x / norm).collect()
+    } else {
+        v.to_vec()
+    }
+}
+
+fn dominant<'a>(values: impl Iterator<Item = &'a str>) -> Option<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for v in values {
+        *counts.entry(v).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(a.0)))
+        .map(|(v, _)| v.to_string())
+}
+
+/// Rule-based summary: the item closest to the centroid plus outcome counts.
+fn summarize(decision_maker: &str, domain: &str, members: &[&GroupItem], centroid: &[f32]) -> String {
+    let representative = members
+        .iter()
+        .max_by(|a, b| {
+            cosine_similarity(&a.vector, centroid)
+                .partial_cmp(&cosine_similarity(&b.vector, centroid))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .map(|m| m.content.as_str())
+        .unwrap_or_default();
+
+    let confirmed = members.iter().filter(|m| m.outcome.as_deref() == Some("confirmed")).count();
+    let rejected = members.iter().filter(|m| m.outcome.as_deref() == Some("rejected")).count();
+    let mut outcomes = String::new();
+    if confirmed > 0 {
+        outcomes.push_str(&format!(", 확정 {}건", confirmed));
+    }
+    if rejected > 0 {
+        outcomes.push_str(&format!(", 기각 {}건", rejected));
+    }
+
+    format!(
+        "{}의 {} 판단 패턴 (근거 {}건{}): {}",
+        decision_maker,
+        domain,
+        members.len(),
+        outcomes,
+        representative
+    )
+}
+
+/// Groups that must be re-mined after `knowledge_id` changed.
+fn refresh_for_item(db: &RagDb, knowledge_id: &str) -> Result<(), String> {
+    let mut groups: BTreeSet<(String, String)> = {
+        let conn = db.conn();
+        let mut stmt = conn
+            .prepare(
+                "SELECT DISTINCT decision_maker, knowledge_domain
+                 FROM decision_patterns
+                 WHERE EXISTS (SELECT 1 FROM json_each(evidence_item_ids) WHERE value = ?1)",
+            )
+            .map_err(|e| format!("Prepare evidence lookup failed: {}", e))?;
+        let rows = stmt
+            .query_map([knowledge_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| format!("Evidence lookup failed: {}", e))?;
+        rows.filter_map(|r| r.ok()).collect()
+    };
+
+    if let Some(item) = knowledge::get_knowledge_item(db, knowledge_id)? {
+        let ontology = Ontology::load(&db.conn())?;
+        if let Some(maker) = item.decision_maker.filter(|_| {
+            item.is_active && ontology.has_trait(&item.knowledge_type, TermTrait::PatternSource)
+        }) {
+            let domain = item.scope_layer.unwrap_or_else(|| GENERAL_DOMAIN.to_string());
+            groups.insert((maker, domain));
+        }
+    }
+
+    for (maker, domain) in groups {
+        mine_group(db, &maker, &domain)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::knowledge::KnowledgeItem;
+    use std::sync::Arc;
+
+    fn setup() -> Arc<RagDb> {
+        let dir = std::env::temp_dir().join(format!("rag_patterns_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Arc::new(RagDb::open(&dir.join("test.db")).unwrap());
+        let weak = Arc::downgrade(&db);
+        db.subscribe(move |event| {
+            if let Some(db) = weak.upgrade() {
+                on_event(&db, event);
+            }
+        });
+        db
+    }
+
+    /// Unit vector along `axis`, nudged toward `axis + 1` for variation
+    fn vector(axis: usize, nudge: f32) -> Vec<f32> {
+        let mut v = vec![0.0; EMBEDDING_DIM];
+        v[axis] = 1.0;
+        v[axis + 1] = nudge;
+        normalize(&v)
+    }
+
+    fn create(db: &RagDb, content: &str, layer: &str, outcome: &str, embedding: &[f32]) -> String {
+        let item = KnowledgeItem {
+            scope: "global".to_string(),
+            scope_layer: Some(layer.to_string()),
+            confidence: 0.8,
+            decision_maker: Some("김경신".to_string()),
+            outcome: Some(outcome.to_string()),
+            ..KnowledgeItem::for_test(content)
+        };
+        knowledge::create_knowledge_item(db, &item, embedding).unwrap()
+    }
+
+    #[test]
+    fn test_incremental_mining() {
+        let db = setup();
+        let a = create(&db, "업체 선정은 포트폴리오 우선", "operations", "confirmed", &vector(0, 0.1));
+        assert!(list_patterns(&db, Some("김경신"), None).unwrap().is_empty());
+
+        // Second similar decision forms a pattern; an unrelated one does not join it
+        let b = create(&db, "업체는 레퍼런스 확인 후 선정", "operations", "confirmed", &vector(0, 0.2));
+        create(&db, "촬영 일정은 여유 있게", "operations", "confirmed", &vector(10, 0.0));
+        // Same topic in another domain is a separate group
+        create(&db, "업체 선정 기준", "creative", "confirmed", &vector(0, 0.1));
+
+        let patterns = list_patterns(&db, Some("김경신"), Some("operations")).unwrap();
+        assert_eq!(patterns.len(), 1);
+        let pattern = &patterns[0];
+        assert_eq!(pattern.sample_count, 2);
+        assert_eq!(pattern.evidence_item_ids, vec![a.clone(), b.clone()]);
+        assert!((pattern.confidence - 0.8 * 2.0 / 4.0).abs() < 1e-9);
+        assert!(pattern.pattern_summary.as_deref().unwrap().contains("확정 2건"));
+
+        // Deactivating evidence dissolves the pattern
+        knowledge::deactivate_knowledge_item(&db, &b, None).unwrap();
+        assert!(list_patterns(&db, Some("김경신"), Some("operations")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_find_patterns_ranks_by_question() {
+        let db = setup();
+        create(&db, "업체 선정은 포트폴리오 우선", "operations", "confirmed", &vector(0, 0.1));
+        create(&db, "업체는 레퍼런스 확인 후 선정", "operations", "rejected", &vector(0, 0.2));
+        create(&db, "예산은 10% 버퍼", "operations", "confirmed", &vector(20, 0.1));
+        create(&db, "예산 초과 시 보고", "operations", "confirmed", &vector(20, 0.2));
+
+        let stable_id = list_patterns(&db, None, None).unwrap()[0].id.clone();
+        let report = mine_all(&db, Some("김경신")).unwrap();
+        assert_eq!((report.groups, report.created, report.updated), (1, 0, 2));
+        assert!(list_patterns(&db, None, None).unwrap().iter().any(|p| p.id == stable_id));
+
+        let matches = find_patterns(&db, Some("김경신"), &vector(0, 0.15), 1).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].evidence.len(), 2);
+        assert!(matches[0].evidence[0].content.contains("업체"));
+        assert!(find_patterns(&db, Some("다른 사람"), &vector(0, 0.15), 3).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,313 @@
+/// Persona Engine — Answer as a role (CEO, CD, PD…) grounded in local RAG
+///
+/// A persona binds a `role_tag`, a system prompt and retrieval settings.
+/// `ask_persona` runs the 정/반/개인 retrieval with the persona's settings
+/// (items tagged with its role are boosted), calls the LLM with the cited
+/// context, and logs query, context and answer to `persona_query_log` so
+/// answers can be rated afterwards.
+
+use crate::rag::context::{self, BuiltContext, ContextFormat, ContextOptions, RetrievalSettings};
+use crate::rag::db::RagDb;
+use crate::rag::embedding::EmbeddingEngine;
+use crate::rag::knowledge;
+use crate::rag::llm::{LlmClient, LlmTask};
+use crate::rag::query;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Max tokens for a persona answer
+const PERSONA_MAX_TOKENS: u32 = 1024;
+
+/// Persona definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersonaDefinition {
+    pub id: String,
+    pub name: String,
+    pub role_tag: String,
+    pub description: String,
+    pub system_prompt: String,
+    pub retrieval: RetrievalSettings,
+    /// Token budget for the injected context
+    pub context_max_tokens: usize,
+}
+
+/// Answer returned by `ask_persona`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersonaAnswer {
+    pub query_log_id: String,
+    pub persona_id: String,
+    pub answer: String,
+    pub context: BuiltContext,
+}
+
+/// Row of `persona_query_log`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersonaQueryLog {
+    pub id: String,
+    pub persona_id: String,
+    pub query: String,
+    pub rag_context: Option<String>,
+    pub response: Option<String>,
+    pub was_helpful: Option<bool>,
+    pub created_at: String,
+}
+
+/// Built-in personas.
+pub fn builtin_personas() -> Vec<PersonaDefinition> {
+    vec![
+        PersonaDefinition {
+            id: "ceo".to_string(),
+            name: "CEO 김경신".to_string(),
+            role_tag: "CEO".to_string(),
+            description: "예산·수익성·클라이언트 관계를 기준으로 최종 판단".to_string(),
+            system_prompt: "당신은 크리에이티브 에이전시의 대표(CEO) 김경신입니다. \
+                축적된 판단 기록에 근거해 수익성, 리스크, 클라이언트 관계를 기준으로 결론부터 명확히 답하세요. \
+                기록에 없는 내용은 추측이라고 밝히세요."
+                .to_string(),
+            retrieval: RetrievalSettings {
+                thesis_limit: 6,
+                role_boost: 0.15,
+                ..Default::default()
+            },
+            context_max_tokens: 800,
+        },
+        PersonaDefinition {
+            id: "cd".to_string(),
+            name: "Creative Director".to_string(),
+            role_tag: "CD".to_string(),
+            description: "크리에이티브 방향과 품질 기준 중심의 조언".to_string(),
+            system_prompt: "당신은 크리에이티브 디렉터(CD)입니다. \
+                축적된 크리에이티브 판단과 피드백 패턴을 근거로 콘셉트, 톤앤매너, 품질 기준에 대해 답하세요. \
+                대안이 있다면 함께 제시하세요."
+                .to_string(),
+            retrieval: RetrievalSettings {
+                thesis_threshold: 0.28,
+                role_boost: 0.15,
+                ..Default::default()
+            },
+            context_max_tokens: 600,
+        },
+        PersonaDefinition {
+            id: "pd".to_string(),
+            name: "Producer".to_string(),
+            role_tag: "PD".to_string(),
+            description: "일정·제작·협업 실행 관점의 조언".to_string(),
+            system_prompt: "당신은 프로듀서(PD)입니다. \
+                축적된 제작·일정·협업 기록을 근거로 실행 가능한 단계와 일정 리스크를 중심으로 답하세요."
+                .to_string(),
+            retrieval: RetrievalSettings {
+                antithesis_limit: 4,
+                role_boost: 0.15,
+                ..Default::default()
+            },
+            context_max_tokens: 600,
+        },
+        PersonaDefinition {
+            id: "budget_manager".to_string(),
+            name: "Budget Manager".to_string(),
+            role_tag: "BUDGET_MANAGER".to_string(),
+            description: "예산 배분과 비용 통제 관점의 조언".to_string(),
+            system_prompt: "당신은 예산 관리자입니다. \
+                축적된 예산 판단 기록을 근거로 금액, 비율, 승인 기준을 구체적으로 답하세요."
+                .to_string(),
+            retrieval: RetrievalSettings {
+                role_boost: 0.2,
+                ..Default::default()
+            },
+            context_max_tokens: 600,
+        },
+    ]
+}
+
+/// Look up a persona by id or role tag (case-insensitive).
+pub fn get_persona(id: &str) -> Result<PersonaDefinition, String> {
+    builtin_personas()
+        .into_iter()
+        .find(|p| p.id.eq_ignore_ascii_case(id) || p.role_tag.eq_ignore_ascii_case(id))
+        .ok_or_else(|| format!("Unknown persona: {}", id))
+}
+
+/// Retrieve and build the cited context a persona answers from.
+pub fn build_persona_context(
+    db: &RagDb,
+    engine: &EmbeddingEngine,
+    persona: &PersonaDefinition,
+    query: &str,
+    user_id: Option<&str>,
+    project_id: Option<&str>,
+) -> Result<BuiltContext, String> {
+    let embedding = engine.embed(query)?;
+    let base = query::SearchParams {
+        query_embedding: embedding.vector,
+        scope: "all".to_string(),
+        user_id: user_id.map(String::from),
+        project_id: project_id.map(String::from),
+        role_tag: Some(persona.role_tag.clone()),
+        ..Default::default()
+    };
+    let sections = context::retrieve(db, &base, &persona.retrieval)?;
+
+    let options = ContextOptions {
+        max_tokens: persona.context_max_tokens,
+        max_chars: None,
+        format: ContextFormat::Markdown,
+    };
+    Ok(context::build_context(&sections, &options, &|text| engine.count_tokens(text)))
+}
+
+/// Full system prompt: persona instructions followed by the retrieved context.
+pub fn system_prompt(persona: &PersonaDefinition, context: &BuiltContext) -> String {
+    if context.text.is_empty() {
+        format!(
+            "{}\n\n(관련된 축적 지식이 없습니다. 일반적인 원칙으로 답하되 근거가 없음을 밝히세요.)",
+            persona.system_prompt
+        )
+    } else {
+        format!("{}\n\n{}", persona.system_prompt, context.text)
+    }
+}
+
+/// Ask the persona task's LLM to answer `query` as the persona.
+pub async fn generate_answer(system: &str, query: &str, llm: &LlmClient) -> Result<String, String> {
+    llm.complete(LlmTask::Persona, system, query, PERSONA_MAX_TOKENS).await
+}
+
+/// Record a persona query with its context and answer. Returns the log id.
+pub fn log_query(
+    db: &RagDb,
+    persona_id: &str,
+    query: &str,
+    context: &BuiltContext,
+    response: &str,
+) -> Result<String, String> {
+    let id = Uuid::new_v4().to_string();
+    let context_json = serde_json::to_string(context).map_err(|e| format!("Serialize context failed: {}", e))?;
+
+    let conn = db.conn();
+    conn.execute(
+        "INSERT INTO persona_query_log (id, persona_id, query, rag_context, response)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![id, persona_id, query, context_json, response],
+    )
+    .map_err(|e| format!("Log persona query failed: {}", e))?;
+
+    Ok(id)
+}
+
+/// Rate a persona answer; the cited knowledge items get the same relevance
+/// adjustment as `rag_feedback`.
+pub fn record_feedback(db: &RagDb, query_log_id: &str, was_helpful: bool) -> Result<(), String> {
+    let context_json: Option<String> = {
+        let conn = db.conn();
+        let updated = conn
+            .execute(
+                "UPDATE persona_query_log SET was_helpful = ?1 WHERE id = ?2",
+                rusqlite::params![was_helpful as i32, query_log_id],
+            )
+            .map_err(|e| format!("Persona feedback failed: {}", e))?;
+        if updated == 0 {
+            return Err(format!("Persona query not found: {}", query_log_id));
+        }
+        conn.query_row(
+            "SELECT rag_context FROM persona_query_log WHERE id = ?1",
+            [query_log_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Persona feedback failed: {}", e))?
+    };
+
+    if let Some(context) = context_json.and_then(|c| serde_json::from_str::<BuiltContext>(&c).ok()) {
+        for citation in context.citations {
+            let _ = knowledge::update_feedback(db, &citation.knowledge_id, was_helpful);
+        }
+    }
+    Ok(())
+}
+
+/// Recent persona queries, newest first.
+pub fn list_queries(db: &RagDb, persona_id: Option<&str>, limit: usize) -> Result<Vec<PersonaQueryLog>, String> {
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, persona_id, query, rag_context, response, was_helpful, created_at
+             FROM persona_query_log
+             WHERE (?1 IS NULL OR persona_id = ?1)
+             ORDER BY created_at DESC, rowid DESC
+             LIMIT ?2",
+        )
+        .map_err(|e| format!("Prepare persona log failed: {}", e))?;
+
+    let rows = stmt
+        .query_map(rusqlite::params![persona_id, limit as i64], |row| {
+            Ok(PersonaQueryLog {
+                id: row.get(0)?,
+                persona_id: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                query: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                rag_context: row.get(3)?,
+                response: row.get(4)?,
+                was_helpful: row.get::<_, Option<i32>>(5)?.map(|v| v != 0),
+                created_at: row.get::<_, Option<String>>(6)?.unwrap_or_default(),
+            })
+        })
+        .map_err(|e| format!("Query persona log failed: {}", e))?;
+
+    Ok(rows.filter_map(|r| r.ok()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::knowledge::KnowledgeItem;
+
+    fn setup() -> (RagDb, EmbeddingEngine) {
+        let dir = std::env::temp_dir().join(format!("rag_persona_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        (RagDb::open(&dir.join("test.db")).unwrap(), EmbeddingEngine::new(dir.join("models")))
+    }
+
+    fn create(db: &RagDb, engine: &EmbeddingEngine, content: &str, role_tag: Option<&str>) -> String {
+        let item = KnowledgeItem {
+            scope: "global".to_string(),
+            role_tag: role_tag.map(String::from),
+            confidence: 0.8,
+            ..KnowledgeItem::for_test(content)
+        };
+        let vector = engine.embed(content).unwrap().vector;
+        knowledge::create_knowledge_item(db, &item, &vector).unwrap()
+    }
+
+    #[test]
+    fn test_persona_lookup() {
+        assert_eq!(get_persona("CEO").unwrap().id, "ceo");
+        assert_eq!(get_persona("budget_manager").unwrap().role_tag, "BUDGET_MANAGER");
+        assert!(get_persona("intern").is_err());
+    }
+
+    #[test]
+    fn test_context_prefers_role_and_log_feedback() {
+        let (db, engine) = setup();
+        let ceo_item = create(&db, &engine, "예산 결정 기준 정리", Some("CEO"));
+        create(&db, &engine, "예산 결정 기준 정리", Some("CD"));
+
+        let mut persona = get_persona("ceo").unwrap();
+        persona.retrieval.thesis_threshold = -1.0;
+        persona.retrieval.thesis_limit = 1;
+        let built = build_persona_context(&db, &engine, &persona, "예산 결정 기준 정리", None, None).unwrap();
+        assert_eq!(built.citations[0].knowledge_id, ceo_item);
+        assert!(system_prompt(&persona, &built).contains("[K1]"));
+
+        let before = knowledge::get_knowledge_item(&db, &ceo_item).unwrap().unwrap().relevance_score;
+        let log_id = log_query(&db, &persona.id, "예산 기준?", &built, "답변").unwrap();
+        record_feedback(&db, &log_id, true).unwrap();
+        let after = knowledge::get_knowledge_item(&db, &ceo_item).unwrap().unwrap().relevance_score;
+        assert!(after > before);
+        assert!(record_feedback(&db, "missing", true).is_err());
+
+        let logs = list_queries(&db, Some("ceo"), 10).unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].was_helpful, Some(true));
+        let stored: BuiltContext = serde_json::from_str(logs[0].rag_context.as_deref().unwrap()).unwrap();
+        assert_eq!(stored.citations.len(), built.citations.len());
+        assert!(list_queries(&db, Some("cd"), 10).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,601 @@
+/// Outbound Policy — what may leave the device, enforced in the backend
+///
+/// Every LLM call (`LlmClient`) and every sync export is checked against the
+/// rules in `outbound_rules` before anything is sent:
+/// - Conditions: channel (llm / sync / any), scope, project, knowledge_type,
+///   provider (`anthropic` / `openai_compatible` / `mock`, as in the LLM
+///   settings) — unset conditions match everything
+/// - Effects: `block` (never send) or `local_only` (send only to a provider
+///   running on this machine, e.g. Ollama on localhost)
+///
+/// Examples: "local providers only" = `{channel: llm, effect: local_only}`,
+/// "no external LLM for project X" = `{channel: llm, project_id: X, effect:
+/// local_only}`, "sync disabled for personal" = `{channel: sync, scope:
+/// personal, effect: block}`. Local-only mode (`set_local_only`) is the
+/// built-in rule `local-only` covering every channel.
+///
+/// A violation fails the call with a `PolicyViolation` (recoverable from the
+/// error string with `PolicyViolation::parse`). Every outbound call, allowed
+/// or blocked, is logged in `outbound_log` with a SHA-256 of the content sent
+/// — never the content itself.
+
+use crate::rag::context::BuiltContext;
+use crate::rag::db::RagDb;
+use crate::rag::llm::ProviderKind;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use uuid::Uuid;
+
+/// Id of the built-in rule behind local-only mode
+pub const LOCAL_ONLY_RULE: &str = "local-only";
+
+/// Prefix of the error string of a blocked call (followed by the violation as JSON)
+pub const VIOLATION_PREFIX: &str = "Blocked by outbound policy: ";
+
+/// Hosts treated as this machine
+const LOCAL_HOSTS: &[&str] = &["localhost", "127.0.0.1", "::1", "[::1]", "0.0.0.0"];
+
+// ── Types ──────────────────────────────────────────────
+
+/// Kind of outbound call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Channel {
+    Llm,
+    Sync,
+    /// Rules only: both channels
+    Any,
+}
+
+impl Channel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Channel::Llm => "llm",
+            Channel::Sync => "sync",
+            Channel::Any => "any",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "llm" => Ok(Channel::Llm),
+            "sync" => Ok(Channel::Sync),
+            "any" => Ok(Channel::Any),
+            _ => Err(format!("Unknown outbound channel: {}", s)),
+        }
+    }
+}
+
+/// What a matching rule does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Effect {
+    Block,
+    LocalOnly,
+}
+
+impl Effect {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Effect::Block => "block",
+            Effect::LocalOnly => "local_only",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "block" => Ok(Effect::Block),
+            "local_only" => Ok(Effect::LocalOnly),
+            _ => Err(format!("Unknown policy effect: {}", s)),
+        }
+    }
+}
+
+/// One outbound rule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyRule {
+    /// Empty = generated on save
+    #[serde(default)]
+    pub id: String,
+    pub channel: Channel,
+    pub effect: Effect,
+    #[serde(default)]
+    pub scope: Option<String>,
+    #[serde(default)]
+    pub project_id: Option<String>,
+    #[serde(default)]
+    pub knowledge_type: Option<String>,
+    /// Provider kind of LLM calls ("anthropic", "openai_compatible", "mock")
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub description: String,
+}
+
+impl PolicyRule {
+    fn matches(&self, call: &Outbound) -> bool {
+        let condition = |rule: &Option<String>, value: Option<&str>| rule.as_deref().map_or(true, |r| Some(r) == value);
+        let labelled = |rule: &Option<String>, labels: &[String]| rule.as_ref().map_or(true, |r| labels.contains(r));
+        (self.channel == Channel::Any || self.channel == call.channel)
+            && condition(&self.project_id, call.project_id)
+            && labelled(&self.scope, &call.labels.scopes)
+            && labelled(&self.knowledge_type, &call.labels.knowledge_types)
+            && condition(&self.provider, call.provider)
+    }
+}
+
+/// Scopes and knowledge types of the data in a call
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DataLabels {
+    pub scopes: Vec<String>,
+    pub knowledge_types: Vec<String>,
+}
+
+impl DataLabels {
+    pub fn add(&mut self, scope: &str, knowledge_type: &str) {
+        if !self.scopes.iter().any(|s| s == scope) {
+            self.scopes.push(scope.to_string());
+        }
+        if !self.knowledge_types.iter().any(|t| t == knowledge_type) {
+            self.knowledge_types.push(knowledge_type.to_string());
+        }
+    }
+
+    /// Labels of the knowledge items cited in a built context.
+    pub fn of_context(db: &RagDb, context: &BuiltContext) -> Result<Self, String> {
+        let ids: Vec<&str> = context.citations.iter().map(|c| c.knowledge_id.as_str()).collect();
+        Self::of_items(db, &ids)
+    }
+
+    /// Labels of the given knowledge items; unknown ids are skipped.
+    pub fn of_items(db: &RagDb, ids: &[&str]) -> Result<Self, String> {
+        let conn = db.conn();
+        let mut stmt = conn
+            .prepare("SELECT scope, knowledge_type FROM knowledge_items WHERE id = ?1")
+            .map_err(|e| format!("Prepare data labels failed: {}", e))?;
+        let mut labels = Self::default();
+        for id in ids {
+            let row = stmt.query_row([id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)));
+            match row {
+                Ok((scope, knowledge_type)) => labels.add(&scope, &knowledge_type),
+                Err(rusqlite::Error::QueryReturnedNoRows) => {}
+                Err(e) => return Err(format!("Load data labels failed: {}", e)),
+            }
+        }
+        Ok(labels)
+    }
+}
+
+/// A call about to leave the device
+#[derive(Debug, Clone)]
+pub struct Outbound<'a> {
+    pub channel: Channel,
+    /// Provider name, or "sync"
+    pub destination: &'a str,
+    /// `ProviderKind::as_str` of LLM calls
+    pub provider: Option<&'a str>,
+    /// Destination runs on this machine
+    pub local: bool,
+    pub purpose: &'a str,
+    pub project_id: Option<&'a str>,
+    pub room_id: Option<&'a str>,
+    pub labels: &'a DataLabels,
+}
+
+/// Why a call was refused
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyViolation {
+    pub rule_id: String,
+    pub effect: Effect,
+    pub channel: Channel,
+    pub destination: String,
+    pub reason: String,
+}
+
+impl PolicyViolation {
+    /// The violation behind an error string, if the error is one.
+    pub fn parse(error: &str) -> Option<Self> {
+        serde_json::from_str(error.strip_prefix(VIOLATION_PREFIX)?).ok()
+    }
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let json = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+        write!(f, "{}{}", VIOLATION_PREFIX, json)
+    }
+}
+
+impl From<PolicyViolation> for String {
+    fn from(violation: PolicyViolation) -> Self {
+        violation.to_string()
+    }
+}
+
+/// One logged outbound call
+#[derive(Debug, Clone, Serialize)]
+pub struct OutboundLogEntry {
+    pub id: i64,
+    pub created_at: String,
+    pub channel: String,
+    pub destination: String,
+    pub local: bool,
+    pub purpose: String,
+    pub project_id: Option<String>,
+    pub room_id: Option<String>,
+    pub scopes: Vec<String>,
+    pub knowledge_types: Vec<String>,
+    /// SHA-256 (hex) of the content sent, or of the content refused
+    pub content_hash: String,
+    pub bytes: usize,
+    pub allowed: bool,
+    /// Rule that blocked the call
+    pub rule_id: Option<String>,
+}
+
+// ── Evaluation ─────────────────────────────────────────
+
+/// First rule the call violates, if any.
+pub fn evaluate(rules: &[PolicyRule], call: &Outbound) -> Option<PolicyViolation> {
+    let rule = rules
+        .iter()
+        .filter(|rule| rule.matches(call))
+        .find(|rule| rule.effect == Effect::Block || !call.local)?;
+    let reason = match rule.effect {
+        Effect::Block => format!("{} calls are blocked", call.channel.as_str()),
+        Effect::LocalOnly => format!("only local destinations are allowed, {} is external", call.destination),
+    };
+    let reason = if rule.description.is_empty() { reason } else { format!("{} ({})", reason, rule.description) };
+    Some(PolicyViolation {
+        rule_id: rule.id.clone(),
+        effect: rule.effect,
+        channel: call.channel,
+        destination: call.destination.to_string(),
+        reason,
+    })
+}
+
+/// Check a call against the stored rules; Err = blocked (see `PolicyViolation::parse`).
+/// Blocked calls are logged here; log allowed ones with `record` once they are sent.
+pub fn check(db: &RagDb, call: &Outbound, content: &str) -> Result<(), String> {
+    let Some(violation) = evaluate(&list_rules(db)?, call) else {
+        return Ok(());
+    };
+    record(db, call, content, Some(&violation))?;
+    log::warn!("Outbound {} to {} blocked by rule {}", call.channel.as_str(), call.destination, violation.rule_id);
+    Err(violation.into())
+}
+
+/// Whether `url` points at this machine.
+pub fn is_local_url(url: &str) -> bool {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    let host = if authority.starts_with('[') {
+        authority.split_inclusive(']').next().unwrap_or(authority)
+    } else {
+        authority.split(':').next().unwrap_or(authority)
+    };
+    let host = host.to_lowercase();
+    LOCAL_HOSTS.contains(&host.as_str()) || host.starts_with("127.") || host.ends_with(".localhost")
+}
+
+// ── Rules ──────────────────────────────────────────────
+
+pub fn list_rules(db: &RagDb) -> Result<Vec<PolicyRule>, String> {
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, channel, effect, scope, project_id, knowledge_type, provider, description
+             FROM outbound_rules ORDER BY created_at, id",
+        )
+        .map_err(|e| format!("Prepare outbound rules failed: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, Option<String>>(5)?,
+                row.get::<_, Option<String>>(6)?,
+                row.get::<_, String>(7)?,
+            ))
+        })
+        .map_err(|e| format!("Query outbound rules failed: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Read outbound rules failed: {}", e))?;
+    // An unreadable rule must not silently allow traffic
+    rows.into_iter()
+        .map(|(id, channel, effect, scope, project_id, knowledge_type, provider, description)| {
+            Ok(PolicyRule {
+                id,
+                channel: Channel::parse(&channel)?,
+                effect: Effect::parse(&effect)?,
+                scope,
+                project_id,
+                knowledge_type,
+                provider,
+                description,
+            })
+        })
+        .collect()
+}
+
+/// Create or replace a rule (an empty id creates a new one).
+pub fn set_rule(db: &RagDb, rule: &PolicyRule) -> Result<PolicyRule, String> {
+    let non_empty = |v: &Option<String>| v.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(String::from);
+    let rule = PolicyRule {
+        id: if rule.id.trim().is_empty() { Uuid::new_v4().to_string() } else { rule.id.trim().to_string() },
+        scope: non_empty(&rule.scope),
+        project_id: non_empty(&rule.project_id),
+        knowledge_type: non_empty(&rule.knowledge_type),
+        provider: non_empty(&rule.provider),
+        description: rule.description.trim().to_string(),
+        ..rule.clone()
+    };
+    if let Some(provider) = &rule.provider {
+        ProviderKind::parse(provider)?;
+    }
+    let conn = db.conn();
+    conn.execute(
+        "INSERT INTO outbound_rules (id, channel, effect, scope, project_id, knowledge_type, provider, description)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+         ON CONFLICT(id) DO UPDATE SET
+            channel = excluded.channel,
+            effect = excluded.effect,
+            scope = excluded.scope,
+            project_id = excluded.project_id,
+            knowledge_type = excluded.knowledge_type,
+            provider = excluded.provider,
+            description = excluded.description",
+        rusqlite::params![
+            rule.id,
+            rule.channel.as_str(),
+            rule.effect.as_str(),
+            rule.scope,
+            rule.project_id,
+            rule.knowledge_type,
+            rule.provider,
+            rule.description,
+        ],
+    )
+    .map_err(|e| format!("Save outbound rule failed: {}", e))?;
+    Ok(rule)
+}
+
+pub fn remove_rule(db: &RagDb, id: &str) -> Result<bool, String> {
+    let conn = db.conn();
+    let removed = conn
+        .execute("DELETE FROM outbound_rules WHERE id = ?1", [id])
+        .map_err(|e| format!("Remove outbound rule failed: {}", e))?;
+    Ok(removed > 0)
+}
+
+/// Turn local-only mode on or off: nothing goes to an external LLM and sync is off.
+pub fn set_local_only(db: &RagDb, enabled: bool) -> Result<(), String> {
+    if !enabled {
+        remove_rule(db, LOCAL_ONLY_RULE)?;
+        return Ok(());
+    }
+    set_rule(
+        db,
+        &PolicyRule {
+            id: LOCAL_ONLY_RULE.to_string(),
+            channel: Channel::Any,
+            effect: Effect::LocalOnly,
+            scope: None,
+            project_id: None,
+            knowledge_type: None,
+            provider: None,
+            description: "local-only mode".to_string(),
+        },
+    )?;
+    log::info!("Local-only mode enabled");
+    Ok(())
+}
+
+pub fn is_local_only(db: &RagDb) -> Result<bool, String> {
+    Ok(list_rules(db)?.iter().any(|r| r.id == LOCAL_ONLY_RULE))
+}
+
+// ── Log ────────────────────────────────────────────────
+
+/// Log an outbound call (callers that evaluate rules themselves, e.g. sync per item).
+pub fn record(db: &RagDb, call: &Outbound, content: &str, violation: Option<&PolicyViolation>) -> Result<(), String> {
+    let hash = hex::encode(Sha256::digest(content.as_bytes()));
+    let conn = db.conn();
+    conn.execute(
+        "INSERT INTO outbound_log
+            (channel, destination, local, purpose, project_id, room_id, scopes, knowledge_types,
+             content_hash, bytes, allowed, rule_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        rusqlite::params![
+            call.channel.as_str(),
+            call.destination,
+            call.local,
+            call.purpose,
+            call.project_id,
+            call.room_id,
+            serde_json::to_string(&call.labels.scopes).unwrap_or_else(|_| "[]".into()),
+            serde_json::to_string(&call.labels.knowledge_types).unwrap_or_else(|_| "[]".into()),
+            hash,
+            content.len() as i64,
+            violation.is_none(),
+            violation.map(|v| v.rule_id.as_str()),
+        ],
+    )
+    .map_err(|e| format!("Record outbound call failed: {}", e))?;
+    Ok(())
+}
+
+/// Recent outbound calls, newest first (one channel or all).
+pub fn outbound_log(db: &RagDb, channel: Option<Channel>, limit: usize) -> Result<Vec<OutboundLogEntry>, String> {
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, created_at, channel, destination, local, purpose, project_id, room_id,
+                    scopes, knowledge_types, content_hash, bytes, allowed, rule_id
+             FROM outbound_log
+             WHERE ?1 IS NULL OR channel = ?1
+             ORDER BY id DESC
+             LIMIT ?2",
+        )
+        .map_err(|e| format!("Prepare outbound log failed: {}", e))?;
+    let entries = stmt
+        .query_map(rusqlite::params![channel.map(|c| c.as_str()), limit as i64], |row| {
+            Ok(OutboundLogEntry {
+                id: row.get(0)?,
+                created_at: row.get(1)?,
+                channel: row.get(2)?,
+                destination: row.get(3)?,
+                local: row.get(4)?,
+                purpose: row.get(5)?,
+                project_id: row.get(6)?,
+                room_id: row.get(7)?,
+                scopes: serde_json::from_str(&row.get::<_, String>(8)?).unwrap_or_default(),
+                knowledge_types: serde_json::from_str(&row.get::<_, String>(9)?).unwrap_or_default(),
+                content_hash: row.get(10)?,
+                bytes: row.get::<_, i64>(11)? as usize,
+                allowed: row.get(12)?,
+                rule_id: row.get(13)?,
+            })
+        })
+        .map_err(|e| format!("Query outbound log failed: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::llm::{LlmClient, LlmTask, MockProvider};
+    use std::sync::Arc;
+
+    fn setup() -> Arc<RagDb> {
+        let dir = std::env::temp_dir().join(format!("rag_policy_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        Arc::new(RagDb::open(&dir.join("test.db")).unwrap())
+    }
+
+    fn rule(channel: Channel, effect: Effect) -> PolicyRule {
+        PolicyRule {
+            id: String::new(),
+            channel,
+            effect,
+            scope: None,
+            project_id: None,
+            knowledge_type: None,
+            provider: None,
+            description: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_rules_match_scope_project_and_provider() {
+        let rules = vec![
+            PolicyRule { id: "no-personal-sync".into(), scope: Some("personal".into()), ..rule(Channel::Sync, Effect::Block) },
+            PolicyRule { id: "x-local".into(), project_id: Some("x".into()), ..rule(Channel::Llm, Effect::LocalOnly) },
+            PolicyRule { id: "no-anthropic-decisions".into(), knowledge_type: Some("decision_pattern".into()), provider: Some("anthropic".into()), ..rule(Channel::Any, Effect::Block) },
+        ];
+        let mut personal = DataLabels::default();
+        personal.add("personal", "context");
+        let mut decisions = DataLabels::default();
+        decisions.add("team", "decision_pattern");
+        let unlabelled = DataLabels::default();
+        let call = |channel, provider: Option<&'static str>, local, project_id, labels| Outbound {
+            channel,
+            destination: provider.unwrap_or("sync"),
+            provider,
+            local,
+            purpose: "test",
+            project_id,
+            room_id: None,
+            labels,
+        };
+
+        let (anthropic, openai) = (Some("anthropic"), Some("openai_compatible"));
+        let blocked = evaluate(&rules, &call(Channel::Sync, None, false, None, &personal)).unwrap();
+        assert_eq!((blocked.rule_id.as_str(), blocked.effect), ("no-personal-sync", Effect::Block));
+        assert!(evaluate(&rules, &call(Channel::Llm, anthropic, false, None, &personal)).is_none());
+        assert!(evaluate(&rules, &call(Channel::Sync, None, false, None, &decisions)).is_none());
+
+        assert!(evaluate(&rules, &call(Channel::Llm, anthropic, false, Some("x"), &unlabelled)).is_some());
+        assert!(evaluate(&rules, &call(Channel::Llm, openai, true, Some("x"), &unlabelled)).is_none());
+        assert!(evaluate(&rules, &call(Channel::Llm, openai, false, Some("y"), &decisions)).is_none());
+        assert_eq!(
+            evaluate(&rules, &call(Channel::Llm, anthropic, false, Some("y"), &decisions)).map(|v| v.rule_id),
+            Some("no-anthropic-decisions".to_string())
+        );
+
+        let error: String = blocked.clone().into();
+        assert_eq!(PolicyViolation::parse(&error), Some(blocked));
+        assert!(is_local_url("http://localhost:11434/v1") && is_local_url("http://127.0.0.1:8080") && is_local_url("http://[::1]:8000/v1"));
+        assert!(!is_local_url("https://api.openai.com/v1") && !is_local_url("http://localhost.evil.com/v1"));
+    }
+
+    #[tokio::test]
+    async fn test_local_only_mode_blocks_and_logs() {
+        let db = setup();
+        set_local_only(&db, true).unwrap();
+        assert!(is_local_only(&db).unwrap());
+
+        // The mock provider counts as local; a remote-looking provider does not
+        let local = LlmClient::new(Box::new(MockProvider::with_reply("ok")), "mock").with_ledger(db.clone());
+        assert_eq!(local.complete(LlmTask::Persona, "system", "hello", 10).await.unwrap(), "ok");
+
+        struct Remote;
+        impl crate::rag::llm::LlmProvider for Remote {
+            fn name(&self) -> &'static str {
+                "Remote"
+            }
+            fn kind(&self) -> ProviderKind {
+                ProviderKind::OpenaiCompatible
+            }
+            fn complete<'a>(&'a self, _: &'a str, _: &'a crate::rag::llm::LlmRequest) -> crate::rag::llm::LlmFuture<'a> {
+                panic!("must not be called")
+            }
+        }
+        let remote = LlmClient::new(Box::new(Remote), "remote")
+            .with_ledger(db.clone())
+            .with_usage("ask_persona", Some("p1"), None);
+        let error = remote.complete(LlmTask::Persona, "system", "hello", 10).await.unwrap_err();
+        let violation = PolicyViolation::parse(&error).unwrap();
+        assert_eq!((violation.rule_id.as_str(), violation.destination.as_str()), (LOCAL_ONLY_RULE, "Remote"));
+
+        let log = outbound_log(&db, Some(Channel::Llm), 10).unwrap();
+        assert_eq!(log.len(), 2);
+        assert!(!log[0].allowed && log[0].rule_id.as_deref() == Some(LOCAL_ONLY_RULE) && log[0].project_id.as_deref() == Some("p1"));
+        assert!(log[1].allowed && log[1].local);
+        assert_eq!(log[0].content_hash, log[1].content_hash);
+        assert_eq!(log[0].content_hash.len(), 64);
+
+        set_local_only(&db, false).unwrap();
+        assert!(list_rules(&db).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_provider_rule_blocks_configured_client() {
+        use crate::rag::http::ResilientHttp;
+
+        let db = setup();
+        let http = Arc::new(ResilientHttp::new(Default::default()).unwrap());
+        // Rules name providers the way the LLM settings do
+        let by_name = PolicyRule { provider: Some("Anthropic".into()), ..rule(Channel::Llm, Effect::Block) };
+        assert!(set_rule(&db, &by_name).is_err());
+        let saved = set_rule(&db, &PolicyRule { provider: Some("anthropic".into()), ..by_name }).unwrap();
+
+        // The digest task defaults to Anthropic; the call is refused before anything is sent
+        let llm = LlmClient::for_task(&db, &http, LlmTask::Digest, Some("sk-test")).unwrap();
+        let error = llm.complete(LlmTask::Digest, "system", "hello", 10).await.unwrap_err();
+        let violation = PolicyViolation::parse(&error).unwrap();
+        assert_eq!(violation.rule_id, saved.id);
+
+        // Other providers are not affected
+        let mock = LlmClient::new(Box::new(MockProvider::with_reply("ok")), "mock").with_ledger(db.clone());
+        assert_eq!(mock.complete(LlmTask::Digest, "system", "hello", 10).await.unwrap(), "ok");
+    }
+}
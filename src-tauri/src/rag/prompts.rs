@@ -0,0 +1,730 @@
+/// Prompt Registry — versioned LLM prompt templates in several languages
+///
+/// Every prompt exists as a built-in template per language pack (ko / en / th).
+/// A template may contain `{variable}` placeholders filled at call time
+/// (`Template::render`), e.g. the extraction prompt's vocabulary lists.
+///
+/// Overrides: `<app data>/prompts/<prompt>.<lang>.txt` (e.g. `digest.th.txt`)
+/// replaces the built-in template without a release. Overrides missing a
+/// required variable are rejected and the built-in stays in use.
+///
+/// Versions: built-ins are `<prompt>.<lang>.v<revision>`; overrides are
+/// `<prompt>.<lang>.custom-<sha256 prefix>`, so every edit gets a new id.
+/// Digests and extracted items record the version that produced them.
+///
+/// Language: set per room or per project (`prompt_languages`); a room's
+/// setting wins over its project's, and Korean is the default.
+
+use crate::rag::db::RagDb;
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// Language pack used when neither the room nor the project sets one
+pub const DEFAULT_LANGUAGE: Language = Language::Ko;
+
+/// System prompt for chat digest analysis (exact Korean port from llm-digest.ts)
+const DIGEST_KO: &str = r#"You are "Re-Be Brain", an AI assistant analyzing Korean project management chat conversations.
+Your job is to analyze a batch of messages and extract structured intelligence.
+
+## Analysis Categories
+
+1. **DECISIONS** — Agreements, approvals, choices made by the team
+   - Look for: consensus phrases ("그렇게 하죠", "알겠습니다", "확정", "결정", "합의")
+   - Include: WHO decided, WHAT was decided, implied confidence level
+
+2. **ACTION ITEMS** — Tasks or commitments mentioned but not yet formalized
+   - Look for: explicit assignments ("~가 ~하기로", "~에게 ~요청")
+   - Also: implicit assignments ("이거 누가 해야 하는데...", "~해야 할 것 같은데")
+   - Include: assignee if identifiable, deadline if mentioned
+
+3. **RISKS / BLOCKERS** — Problems, delays, dependencies, concerns
+   - Look for: problem signals ("문제가", "걱정", "지연", "어려움", "빡빡해")
+   - Include: severity (low/medium/high), what's affected
+
+4. **SUMMARY** — 2-3 sentence Korean summary of the conversation batch
+
+## Korean Language Notes
+- Recognize indirect agreement patterns ("그렇게 하죠" = decision)
+- Extract assignees from honorific context ("김 대표님이 확인해주신다고" = 김 대표님 has action)
+- Detect urgency signals ("급합니다", "ASAP", "긴급" = high priority)
+- "ㅇㅇ", "ㄴㄴ" are casual yes/no
+
+## Response Format (JSON only, no markdown fences)
+{
+  "decisions": [
+    { "text": "description in Korean", "confidence": 0.0-1.0, "relatedUserIds": ["uuid"], "priority": "low|medium|high" }
+  ],
+  "actionItems": [
+    { "text": "description in Korean", "confidence": 0.0-1.0, "relatedUserIds": ["uuid"], "priority": "low|medium|high" }
+  ],
+  "risks": [
+    { "text": "description in Korean", "confidence": 0.0-1.0, "priority": "low|medium|high" }
+  ],
+  "summary": "2-3 sentence summary in Korean"
+}
+
+If there are no items for a category, return an empty array.
+Always respond with valid JSON only."#;
+
+const DIGEST_EN: &str = r#"You are "Re-Be Brain", an AI assistant analyzing project management chat conversations.
+Your job is to analyze a batch of messages and extract structured intelligence.
+
+## Analysis Categories
+
+1. **DECISIONS** — Agreements, approvals, choices made by the team
+   - Look for: consensus phrases ("let's do that", "agreed", "confirmed", "decided", "sounds good")
+   - Include: WHO decided, WHAT was decided, implied confidence level
+
+2. **ACTION ITEMS** — Tasks or commitments mentioned but not yet formalized
+   - Look for: explicit assignments ("X will handle ...", "can you ... by Friday")
+   - Also: implicit assignments ("someone needs to ...", "we should probably ...")
+   - Include: assignee if identifiable, deadline if mentioned
+
+3. **RISKS / BLOCKERS** — Problems, delays, dependencies, concerns
+   - Look for: problem signals ("issue", "worried", "delay", "blocked", "tight schedule")
+   - Include: severity (low/medium/high), what's affected
+
+4. **SUMMARY** — 2-3 sentence English summary of the conversation batch
+
+## Language Notes
+- Short replies ("ok", "yep", "👍") to a proposal count as agreement with it
+- Detect urgency signals ("urgent", "ASAP", "today" = high priority)
+
+## Response Format (JSON only, no markdown fences)
+{
+  "decisions": [
+    { "text": "description in English", "confidence": 0.0-1.0, "relatedUserIds": ["uuid"], "priority": "low|medium|high" }
+  ],
+  "actionItems": [
+    { "text": "description in English", "confidence": 0.0-1.0, "relatedUserIds": ["uuid"], "priority": "low|medium|high" }
+  ],
+  "risks": [
+    { "text": "description in English", "confidence": 0.0-1.0, "priority": "low|medium|high" }
+  ],
+  "summary": "2-3 sentence summary in English"
+}
+
+If there are no items for a category, return an empty array.
+Always respond with valid JSON only."#;
+
+const DIGEST_TH: &str = r#"You are "Re-Be Brain", an AI assistant analyzing Thai project management chat conversations.
+Your job is to analyze a batch of messages and extract structured intelligence.
+
+## Analysis Categories
+
+1. **DECISIONS** — Agreements, approvals, choices made by the team
+   - Look for: consensus phrases ("ตกลง", "โอเค", "เอาตามนี้", "ยืนยัน", "สรุปว่า")
+   - Include: WHO decided, WHAT was decided, implied confidence level
+
+2. **ACTION ITEMS** — Tasks or commitments mentioned but not yet formalized
+   - Look for: explicit assignments ("ฝาก...ด้วย", "...รับผิดชอบ", "ขอให้...")
+   - Also: implicit assignments ("ต้องมีคนทำ...", "น่าจะต้อง...")
+   - Include: assignee if identifiable, deadline if mentioned ("ภายใน...")
+
+3. **RISKS / BLOCKERS** — Problems, delays, dependencies, concerns
+   - Look for: problem signals ("ปัญหา", "กังวล", "ล่าช้า", "ติดขัด", "ไม่ทัน")
+   - Include: severity (low/medium/high), what's affected
+
+4. **SUMMARY** — 2-3 sentence Thai summary of the conversation batch
+
+## Thai Language Notes
+- Polite particles ("ครับ", "ค่ะ", "นะคะ") carry no meaning of their own
+- Names often follow a kinship/title prefix ("พี่", "น้อง", "คุณ") — the name is the assignee
+- "ได้ครับ" / "ได้ค่ะ" in reply to a proposal = agreement
+- Detect urgency signals ("ด่วน", "ด่วนมาก", "ASAP" = high priority)
+- "555" is laughter, not a number
+
+## Response Format (JSON only, no markdown fences)
+{
+  "decisions": [
+    { "text": "description in Thai", "confidence": 0.0-1.0, "relatedUserIds": ["uuid"], "priority": "low|medium|high" }
+  ],
+  "actionItems": [
+    { "text": "description in Thai", "confidence": 0.0-1.0, "relatedUserIds": ["uuid"], "priority": "low|medium|high" }
+  ],
+  "risks": [
+    { "text": "description in Thai", "confidence": 0.0-1.0, "priority": "low|medium|high" }
+  ],
+  "summary": "2-3 sentence summary in Thai"
+}
+
+If there are no items for a category, return an empty array.
+Always respond with valid JSON only."#;
+
+/// User message of a digest call (one window of messages)
+const DIGEST_REQUEST_KO: &str = "다음 채팅 메시지들을 분석해주세요 ({message_count} messages):\n\n{messages}";
+const DIGEST_REQUEST_EN: &str = "Analyze the following chat messages ({message_count} messages):\n\n{messages}";
+const DIGEST_REQUEST_TH: &str = "กรุณาวิเคราะห์ข้อความแชทต่อไปนี้ ({message_count} messages):\n\n{messages}";
+
+/// System prompt for merging per-window summaries
+const DIGEST_REDUCE_KO: &str = r#"You merge partial summaries of ONE Korean project chat room, given in chronological order.
+Write a single 2-3 sentence Korean summary of the whole conversation: keep decisions and open issues, drop repetition.
+
+Respond ONLY with JSON: {"summary": "..."}"#;
+
+const DIGEST_REDUCE_EN: &str = r#"You merge partial summaries of ONE project chat room, given in chronological order.
+Write a single 2-3 sentence English summary of the whole conversation: keep decisions and open issues, drop repetition.
+
+Respond ONLY with JSON: {"summary": "..."}"#;
+
+const DIGEST_REDUCE_TH: &str = r#"You merge partial summaries of ONE Thai project chat room, given in chronological order.
+Write a single 2-3 sentence Thai summary of the whole conversation: keep decisions and open issues, drop repetition.
+
+Respond ONLY with JSON: {"summary": "..."}"#;
+
+/// One labelled window summary in the merge request (one line per window)
+const DIGEST_REDUCE_WINDOW_KO: &str = "[구간 {window}] {summary}";
+const DIGEST_REDUCE_WINDOW_EN: &str = "[Part {window}] {summary}";
+const DIGEST_REDUCE_WINDOW_TH: &str = "[ช่วงที่ {window}] {summary}";
+
+/// System prompt for deep knowledge extraction from digest.
+/// The `{…}` vocabulary lists are filled from the ontology.
+const EXTRACTION_KO: &str = r#"You are a knowledge extraction engine for a Korean creative agency project management system.
+
+Given a chat digest (decisions, action items, risks, summary), extract reusable knowledge patterns.
+
+## Extraction Rules
+1. Focus on REUSABLE patterns, not one-time facts
+2. Each item should be a general principle or recurring pattern
+3. Include context about WHY this knowledge matters
+4. Tag with appropriate knowledge_type and role_tag
+5. Maximum 5 items per digest (quality over quantity)
+
+## Available knowledge_types:
+{knowledge_types}
+
+## Available role_tags:
+{role_tags}
+
+## Available dialectic_tags:
+{dialectic_tags}
+
+## Available scope_layers:
+{scope_layers}
+
+## Response Format (JSON only, no markdown fences)
+{
+  "items": [
+    {
+      "content": "한국어로 작성된 재사용 가능한 지식 패턴",
+      "knowledge_type": "decision_pattern",
+      "role_tag": "CEO",
+      "dialectic_tag": null,
+      "scope_layer": "operations",
+      "confidence": 0.7
+    }
+  ]
+}
+
+If no reusable knowledge can be extracted, return: {"items": []}
+Always respond with valid JSON only."#;
+
+const EXTRACTION_EN: &str = r#"You are a knowledge extraction engine for a creative agency project management system.
+
+Given a chat digest (decisions, action items, risks, summary), extract reusable knowledge patterns.
+
+## Extraction Rules
+1. Focus on REUSABLE patterns, not one-time facts
+2. Each item should be a general principle or recurring pattern
+3. Include context about WHY this knowledge matters
+4. Tag with appropriate knowledge_type and role_tag
+5. Maximum 5 items per digest (quality over quantity)
+6. Write "content" in English
+
+## Available knowledge_types:
+{knowledge_types}
+
+## Available role_tags:
+{role_tags}
+
+## Available dialectic_tags:
+{dialectic_tags}
+
+## Available scope_layers:
+{scope_layers}
+
+## Response Format (JSON only, no markdown fences)
+{
+  "items": [
+    {
+      "content": "A reusable knowledge pattern written in English",
+      "knowledge_type": "decision_pattern",
+      "role_tag": "CEO",
+      "dialectic_tag": null,
+      "scope_layer": "operations",
+      "confidence": 0.7
+    }
+  ]
+}
+
+If no reusable knowledge can be extracted, return: {"items": []}
+Always respond with valid JSON only."#;
+
+const EXTRACTION_TH: &str = r#"You are a knowledge extraction engine for a Thai creative agency project management system.
+
+Given a chat digest (decisions, action items, risks, summary), extract reusable knowledge patterns.
+
+## Extraction Rules
+1. Focus on REUSABLE patterns, not one-time facts
+2. Each item should be a general principle or recurring pattern
+3. Include context about WHY this knowledge matters
+4. Tag with appropriate knowledge_type and role_tag
+5. Maximum 5 items per digest (quality over quantity)
+6. Write "content" in Thai; keep tag values exactly as listed below
+
+## Available knowledge_types:
+{knowledge_types}
+
+## Available role_tags:
+{role_tags}
+
+## Available dialectic_tags:
+{dialectic_tags}
+
+## Available scope_layers:
+{scope_layers}
+
+## Response Format (JSON only, no markdown fences)
+{
+  "items": [
+    {
+      "content": "รูปแบบความรู้ที่นำกลับมาใช้ซ้ำได้ เขียนเป็นภาษาไทย",
+      "knowledge_type": "decision_pattern",
+      "role_tag": "CEO",
+      "dialectic_tag": null,
+      "scope_layer": "operations",
+      "confidence": 0.7
+    }
+  ]
+}
+
+If no reusable knowledge can be extracted, return: {"items": []}
+Always respond with valid JSON only."#;
+
+/// Built-in templates: (prompt, language, revision, text).
+/// Bump the revision whenever a text changes.
+const BUILTINS: &[(PromptId, Language, u32, &str)] = &[
+    (PromptId::Digest, Language::Ko, 1, DIGEST_KO),
+    (PromptId::Digest, Language::En, 1, DIGEST_EN),
+    (PromptId::Digest, Language::Th, 1, DIGEST_TH),
+    (PromptId::DigestRequest, Language::Ko, 1, DIGEST_REQUEST_KO),
+    (PromptId::DigestRequest, Language::En, 1, DIGEST_REQUEST_EN),
+    (PromptId::DigestRequest, Language::Th, 1, DIGEST_REQUEST_TH),
+    (PromptId::DigestReduce, Language::Ko, 1, DIGEST_REDUCE_KO),
+    (PromptId::DigestReduce, Language::En, 1, DIGEST_REDUCE_EN),
+    (PromptId::DigestReduce, Language::Th, 1, DIGEST_REDUCE_TH),
+    (PromptId::DigestReduceWindow, Language::Ko, 1, DIGEST_REDUCE_WINDOW_KO),
+    (PromptId::DigestReduceWindow, Language::En, 1, DIGEST_REDUCE_WINDOW_EN),
+    (PromptId::DigestReduceWindow, Language::Th, 1, DIGEST_REDUCE_WINDOW_TH),
+    (PromptId::Extraction, Language::Ko, 1, EXTRACTION_KO),
+    (PromptId::Extraction, Language::En, 1, EXTRACTION_EN),
+    (PromptId::Extraction, Language::Th, 1, EXTRACTION_TH),
+];
+
+// ── Types ──────────────────────────────────────────────
+
+/// A prompt the pipeline sends to the LLM
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptId {
+    /// Digest system prompt
+    Digest,
+    /// Digest user message wrapping the chat messages
+    DigestRequest,
+    /// Merge of per-window digest summaries
+    DigestReduce,
+    /// Merge request line labelling one window's summary
+    DigestReduceWindow,
+    /// Knowledge extraction from a digest
+    Extraction,
+}
+
+impl PromptId {
+    pub const ALL: [PromptId; 5] = [
+        Self::Digest,
+        Self::DigestRequest,
+        Self::DigestReduce,
+        Self::DigestReduceWindow,
+        Self::Extraction,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Digest => "digest",
+            Self::DigestRequest => "digest_request",
+            Self::DigestReduce => "digest_reduce",
+            Self::DigestReduceWindow => "digest_reduce_window",
+            Self::Extraction => "extraction",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|p| p.as_str() == s)
+            .ok_or_else(|| format!("Unknown prompt: {}", s))
+    }
+
+    /// Placeholders every template of this prompt must contain
+    pub fn variables(&self) -> &'static [&'static str] {
+        match self {
+            Self::Digest | Self::DigestReduce => &[],
+            Self::DigestRequest => &["message_count", "messages"],
+            Self::DigestReduceWindow => &["window", "summary"],
+            Self::Extraction => &["knowledge_types", "role_tags", "dialectic_tags", "scope_layers"],
+        }
+    }
+}
+
+/// Language pack
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Language {
+    Ko,
+    En,
+    Th,
+}
+
+impl Language {
+    pub const ALL: [Language; 3] = [Self::Ko, Self::En, Self::Th];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ko => "ko",
+            Self::En => "en",
+            Self::Th => "th",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|l| l.as_str() == s)
+            .ok_or_else(|| format!("Unsupported language: {} (expected ko, en or th)", s))
+    }
+}
+
+/// Where a room's or project's language is set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Room,
+    Project,
+}
+
+impl Scope {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "room" => Ok(Self::Room),
+            "project" => Ok(Self::Project),
+            _ => Err(format!("Invalid scope_type: {} (expected room or project)", s)),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Room => "room",
+            Self::Project => "project",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateSource {
+    Builtin,
+    Override,
+}
+
+/// One prompt in one language
+#[derive(Debug, Clone, Serialize)]
+pub struct Template {
+    pub prompt: PromptId,
+    pub language: Language,
+    pub version: String,
+    pub source: TemplateSource,
+    pub text: String,
+}
+
+impl Template {
+    /// Fill `{name}` placeholders.
+    pub fn render(&self, vars: &[(&str, &str)]) -> String {
+        vars.iter()
+            .fold(self.text.clone(), |text, (name, value)| text.replace(&format!("{{{}}}", name), value))
+    }
+}
+
+/// Override file that could not be used
+#[derive(Debug, Clone, Serialize)]
+pub struct RejectedOverride {
+    pub file: String,
+    pub reason: String,
+}
+
+/// Templates for one language, resolved once per pipeline run
+#[derive(Debug, Clone)]
+pub struct PromptSet {
+    pub language: Language,
+    templates: Vec<Template>,
+}
+
+impl PromptSet {
+    pub fn get(&self, prompt: PromptId) -> &Template {
+        self.templates
+            .iter()
+            .find(|t| t.prompt == prompt)
+            .expect("prompt set covers every prompt")
+    }
+}
+
+/// Every template in effect, plus rejected overrides
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptCatalog {
+    pub override_dir: Option<String>,
+    pub templates: Vec<Template>,
+    pub rejected: Vec<RejectedOverride>,
+}
+
+/// Built-in templates and the overrides loaded from disk
+#[derive(Debug, Default)]
+pub struct PromptRegistry {
+    dir: Option<PathBuf>,
+    overrides: Vec<Template>,
+    rejected: Vec<RejectedOverride>,
+}
+
+impl PromptRegistry {
+    /// Built-ins plus the overrides in `dir` (a missing dir means no overrides).
+    pub fn load(dir: &Path) -> Self {
+        let mut registry = Self {
+            dir: Some(dir.to_path_buf()),
+            ..Self::default()
+        };
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return registry,
+        };
+        let mut paths: Vec<PathBuf> = entries.filter_map(|e| e.ok().map(|e| e.path())).collect();
+        paths.sort();
+        for path in paths.into_iter().filter(|p| p.extension().is_some_and(|e| e == "txt")) {
+            let file = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            match read_override(&path) {
+                Ok(template) => {
+                    log::info!("Prompt override {} loaded ({})", file, template.version);
+                    registry.overrides.push(template);
+                }
+                Err(reason) => {
+                    log::warn!("Prompt override {} rejected: {}", file, reason);
+                    registry.rejected.push(RejectedOverride { file, reason });
+                }
+            }
+        }
+        registry
+    }
+
+    /// Override if present, else the built-in.
+    pub fn template(&self, prompt: PromptId, language: Language) -> Template {
+        if let Some(t) = self.overrides.iter().find(|t| t.prompt == prompt && t.language == language) {
+            return t.clone();
+        }
+        let (_, _, revision, text) = BUILTINS
+            .iter()
+            .find(|(p, l, _, _)| *p == prompt && *l == language)
+            .expect("built-in template for every prompt and language");
+        Template {
+            prompt,
+            language,
+            version: format!("{}.{}.v{}", prompt.as_str(), language.as_str(), revision),
+            source: TemplateSource::Builtin,
+            text: text.to_string(),
+        }
+    }
+
+    pub fn set(&self, language: Language) -> PromptSet {
+        PromptSet {
+            language,
+            templates: PromptId::ALL.iter().map(|p| self.template(*p, language)).collect(),
+        }
+    }
+
+    pub fn catalog(&self) -> PromptCatalog {
+        let templates = PromptId::ALL
+            .iter()
+            .flat_map(|p| Language::ALL.iter().map(|l| self.template(*p, *l)))
+            .collect();
+        PromptCatalog {
+            override_dir: self.dir.as_ref().map(|d| d.to_string_lossy().to_string()),
+            templates,
+            rejected: self.rejected.clone(),
+        }
+    }
+}
+
+/// `<prompt>.<lang>.txt` → validated override template
+fn read_override(path: &Path) -> Result<Template, String> {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let (prompt, language) = stem
+        .rsplit_once('.')
+        .ok_or_else(|| "file name must be <prompt>.<lang>.txt".to_string())?;
+    let prompt = PromptId::parse(prompt)?;
+    let language = Language::parse(language)?;
+
+    let text = std::fs::read_to_string(path).map_err(|e| format!("read failed: {}", e))?;
+    if text.trim().is_empty() {
+        return Err("template is empty".to_string());
+    }
+    let missing: Vec<&str> = prompt
+        .variables()
+        .iter()
+        .copied()
+        .filter(|v| !text.contains(&format!("{{{}}}", v)))
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("missing variables: {}", missing.join(", ")));
+    }
+
+    let hash = hex::encode(Sha256::digest(text.as_bytes()));
+    Ok(Template {
+        prompt,
+        language,
+        version: format!("{}.{}.custom-{}", prompt.as_str(), language.as_str(), &hash[..8]),
+        source: TemplateSource::Override,
+        text,
+    })
+}
+
+// ── Registry access ────────────────────────────────────
+
+/// Load overrides from `dir` into the database's registry.
+pub fn init(db: &RagDb, dir: &Path) -> PromptCatalog {
+    let registry = PromptRegistry::load(dir);
+    let catalog = registry.catalog();
+    db.set_prompts(registry);
+    catalog
+}
+
+/// Re-read the override directory (after the user edited a template).
+pub fn reload(db: &RagDb) -> PromptCatalog {
+    let dir = db.prompts().dir.clone();
+    match dir {
+        Some(dir) => init(db, &dir),
+        None => db.prompts().catalog(),
+    }
+}
+
+pub fn catalog(db: &RagDb) -> PromptCatalog {
+    db.prompts().catalog()
+}
+
+/// Templates in the language of the room (or else its project).
+pub fn for_scope(db: &RagDb, room_id: Option<&str>, project_id: Option<&str>) -> Result<PromptSet, String> {
+    let language = language_for(db, room_id, project_id)?;
+    Ok(db.prompts().set(language))
+}
+
+// ── Languages ──────────────────────────────────────────
+
+/// Room setting, else project setting, else `DEFAULT_LANGUAGE`.
+pub fn language_for(db: &RagDb, room_id: Option<&str>, project_id: Option<&str>) -> Result<Language, String> {
+    let conn = db.conn();
+    let lookup = |scope: Scope, id: Option<&str>| -> Result<Option<String>, String> {
+        let Some(id) = id else { return Ok(None) };
+        conn.query_row(
+            "SELECT language FROM prompt_languages WHERE scope_type = ?1 AND scope_id = ?2",
+            rusqlite::params![scope.as_str(), id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Load prompt language failed: {}", e))
+    };
+    match lookup(Scope::Room, room_id)?.or(lookup(Scope::Project, project_id)?) {
+        Some(language) => Language::parse(&language),
+        None => Ok(DEFAULT_LANGUAGE),
+    }
+}
+
+/// Set (or with `None`, clear) the language of a room or project.
+pub fn set_language(db: &RagDb, scope: Scope, scope_id: &str, language: Option<Language>) -> Result<(), String> {
+    let conn = db.conn();
+    match language {
+        Some(language) => conn.execute(
+            "INSERT INTO prompt_languages (scope_type, scope_id, language) VALUES (?1, ?2, ?3)
+             ON CONFLICT(scope_type, scope_id) DO UPDATE SET
+                language = excluded.language,
+                updated_at = datetime('now')",
+            rusqlite::params![scope.as_str(), scope_id, language.as_str()],
+        ),
+        None => conn.execute(
+            "DELETE FROM prompt_languages WHERE scope_type = ?1 AND scope_id = ?2",
+            rusqlite::params![scope.as_str(), scope_id],
+        ),
+    }
+    .map_err(|e| format!("Save prompt language failed: {}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rag_prompts_{}_{}", name, Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_overrides_and_versions() {
+        let dir = temp_dir("overrides");
+        std::fs::write(dir.join("digest.th.txt"), "Summarize this Thai chat as JSON.").unwrap();
+        std::fs::write(dir.join("extraction.en.txt"), "Extract knowledge. Types: {knowledge_types}").unwrap();
+        std::fs::write(dir.join("greeting.ko.txt"), "안녕하세요").unwrap();
+        std::fs::write(dir.join("notes.md"), "ignored").unwrap();
+
+        let registry = PromptRegistry::load(&dir);
+        let thai = registry.set(Language::Th);
+        assert_eq!(thai.get(PromptId::Digest).source, TemplateSource::Override);
+        assert!(thai.get(PromptId::Digest).version.starts_with("digest.th.custom-"));
+        assert_eq!(thai.get(PromptId::Extraction).version, "extraction.th.v1");
+
+        // Missing variables and unknown prompts are rejected; built-ins stay in use
+        let english = registry.template(PromptId::Extraction, Language::En);
+        assert_eq!(english.source, TemplateSource::Builtin);
+        let rejected: Vec<&str> = registry.rejected.iter().map(|r| r.reason.as_str()).collect();
+        assert_eq!(rejected, vec!["missing variables: role_tags, dialectic_tags, scope_layers", "Unknown prompt: greeting"]);
+
+        // Editing an override changes its version
+        std::fs::write(dir.join("digest.th.txt"), "Summarize this Thai chat as JSON, briefly.").unwrap();
+        let edited = PromptRegistry::load(&dir).template(PromptId::Digest, Language::Th);
+        assert_ne!(edited.version, thai.get(PromptId::Digest).version);
+
+        let request = registry.template(PromptId::DigestRequest, Language::Ko);
+        let rendered = request.render(&[("message_count", "2"), ("messages", "a\nb")]);
+        assert_eq!(rendered, "다음 채팅 메시지들을 분석해주세요 (2 messages):\n\na\nb");
+        assert_eq!(registry.catalog().templates.len(), PromptId::ALL.len() * Language::ALL.len());
+    }
+
+    #[test]
+    fn test_language_per_room_and_project() {
+        let db = RagDb::open(&temp_dir("languages").join("test.db")).unwrap();
+        assert_eq!(language_for(&db, Some("r1"), Some("p1")).unwrap(), Language::Ko);
+
+        set_language(&db, Scope::Project, "p1", Some(Language::Th)).unwrap();
+        assert_eq!(language_for(&db, Some("r1"), Some("p1")).unwrap(), Language::Th);
+        set_language(&db, Scope::Room, "r1", Some(Language::En)).unwrap();
+        assert_eq!(language_for(&db, Some("r1"), Some("p1")).unwrap(), Language::En);
+        assert_eq!(language_for(&db, Some("r2"), Some("p1")).unwrap(), Language::Th);
+
+        set_language(&db, Scope::Room, "r1", None).unwrap();
+        let set = for_scope(&db, Some("r1"), Some("p1")).unwrap();
+        assert_eq!(set.language, Language::Th);
+        assert_eq!(set.get(PromptId::DigestReduce).version, "digest_reduce.th.v1");
+
+        // Window labels come from the language pack too
+        let en = PromptRegistry::default().set(Language::En);
+        let line = en.get(PromptId::DigestReduceWindow).render(&[("window", "2"), ("summary", "Budget agreed")]);
+        assert_eq!(line, "[Part 2] Budget agreed");
+    }
+}
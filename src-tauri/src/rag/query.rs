@@ -5,9 +5,12 @@
 ///
 /// Scoring formula (identical to Supabase):
 ///   hybrid_score = similarity * 0.70 + relevance_score * 0.20 + min(usage/20, 1.0) * 0.10
+///
+/// Items superseded by an active item are down-ranked (see `relations`).
 
 use crate::rag::db::RagDb;
 use crate::rag::embedding::{cosine_similarity, blob_to_vector, vector_to_blob, EMBEDDING_DIM};
+use crate::rag::relations;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +30,9 @@ pub struct SearchResult {
     pub hybrid_score: f64,
     pub project_id: Option<String>,
     pub user_id: Option<String>,
+    /// Set on results added by relation expansion: the hit this one is related to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub related_via: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +48,9 @@ pub struct SearchParams {
     pub vector_weight: f32,
     pub relevance_weight: f32,
     pub usage_weight: f32,
+    /// Append related items (see `relations::expand_results`)
+    #[serde(default)]
+    pub expand_relations: bool,
 }
 
 impl Default for SearchParams {
@@ -58,6 +67,7 @@ impl Default for SearchParams {
             vector_weight: 0.70,
             relevance_weight: 0.20,
             usage_weight: 0.10,
+            expand_relations: false,
         }
     }
 }
@@ -95,13 +105,19 @@ impl Default for DialecticParams {
 /// Falls back to in-memory scan if vec_knowledge table has issues.
 pub fn hybrid_search(db: &RagDb, params: &SearchParams) -> Result<Vec<SearchResult>, String> {
     // Try sqlite-vec first, fall back to legacy approach
-    match hybrid_search_vec(db, params) {
-        Ok(results) => Ok(results),
+    let mut results = match hybrid_search_vec(db, params) {
+        Ok(results) => results,
         Err(e) => {
             log::warn!("sqlite-vec search failed ({}), using legacy in-memory scan", e);
-            hybrid_search_legacy(db, params)
+            hybrid_search_legacy(db, params)?
         }
+    };
+
+    if params.expand_relations {
+        let related = relations::expand_results(db, &results, params)?;
+        results.extend(related);
     }
+    Ok(results)
 }
 
 /// sqlite-vec powered search: uses KNN to get top-N candidates, then score/filter
@@ -193,9 +209,11 @@ fn hybrid_search_vec(db: &RagDb, params: &SearchParams) -> Result<Vec<SearchResu
             hybrid_score: hybrid_score as f64,
             project_id: row.project_id,
             user_id: row.user_id,
+            related_via: None,
         });
     }
 
+    relations::apply_supersede_penalty(&conn, &mut results);
     results.sort_by(|a, b| b.hybrid_score.partial_cmp(&a.hybrid_score).unwrap_or(std::cmp::Ordering::Equal));
     results.truncate(params.limit);
 
@@ -294,9 +312,11 @@ fn hybrid_search_legacy(db: &RagDb, params: &SearchParams) -> Result<Vec<SearchR
             hybrid_score: hybrid_score as f64,
             project_id: row.project_id,
             user_id: row.user_id,
+            related_via: None,
         });
     }
 
+    relations::apply_supersede_penalty(&conn, &mut results);
     results.sort_by(|a, b| b.hybrid_score.partial_cmp(&a.hybrid_score).unwrap_or(std::cmp::Ordering::Equal));
     results.truncate(params.limit);
 
@@ -404,49 +424,18 @@ pub fn dialectic_search(db: &RagDb, params: &DialecticParams) -> Result<Vec<Sear
             hybrid_score: similarity as f64,
             project_id: row.project_id,
             user_id: row.user_id,
+            related_via: None,
         });
     }
 
-    results.sort_by(|a, b| b.similarity.partial_cmp(&a.similarity).unwrap_or(std::cmp::Ordering::Equal));
+    // hybrid_score == similarity here, minus the supersede penalty
+    relations::apply_supersede_penalty(&conn, &mut results);
+    results.sort_by(|a, b| b.hybrid_score.partial_cmp(&a.hybrid_score).unwrap_or(std::cmp::Ordering::Equal));
     results.truncate(params.limit);
 
     Ok(results)
 }
 
-/// Build RAG context string for LLM injection.
-pub fn build_rag_context(items: &[SearchResult], max_chars: usize) -> String {
-    if items.is_empty() {
-        return String::new();
-    }
-
-    let mut output = String::from(
-        "## 참고 지식 (Knowledge Base)\n\
-         아래는 이 조직에서 축적된 실제 판단 기록입니다. 반드시 이 내용을 바탕으로 구체적으로 답변하세요.\n\n"
-    );
-    let mut chars_used = output.len();
-
-    for item in items {
-        let confidence_pct = (item.confidence * 100.0) as i32;
-        let header = format!("### {} (신뢰도: {}%)\n", item.knowledge_type, confidence_pct);
-        let body = item.summary.as_deref().unwrap_or(&item.content);
-        let entry = format!("{}{}\n\n", header, body);
-
-        if chars_used + entry.len() > max_chars {
-            let remaining = max_chars.saturating_sub(chars_used + header.len() + 5);
-            if remaining > 50 {
-                let truncated: String = body.chars().take(remaining).collect();
-                output.push_str(&format!("{}{}…\n\n", header, truncated));
-            }
-            break;
-        }
-
-        output.push_str(&entry);
-        chars_used += entry.len();
-    }
-
-    output
-}
-
 // ── Internal types ──────────────────────────────────────
 
 struct VecRow {
@@ -490,22 +479,13 @@ fn matches_scope_vec(
     project_id: &Option<String>,
     role_tag: &Option<String>,
 ) -> bool {
-    match search_scope {
-        "personal" => row.scope == "personal" && user_id.as_deref() == row.user_id.as_deref(),
-        "team" => (row.scope == "team" || row.scope == "global")
-            && (project_id.as_deref() == row.project_id.as_deref() || row.project_id.is_none()),
-        "role" => (row.scope == "role" || row.scope == "global")
-            && (role_tag.as_deref() == row.role_tag.as_deref() || row.role_tag.is_none()),
-        "all" => {
-            let is_own = user_id.as_deref() == row.user_id.as_deref() && row.user_id.is_some();
-            let is_team = project_id.as_deref() == row.project_id.as_deref()
-                && row.project_id.is_some()
-                && (row.scope == "team" || row.scope == "global");
-            let is_global = row.scope == "role" || row.scope == "global";
-            is_own || is_team || is_global
-        }
-        _ => false,
-    }
+    matches_scope(
+        (row.scope.as_str(), &row.user_id, &row.project_id, &row.role_tag),
+        search_scope,
+        user_id,
+        project_id,
+        role_tag,
+    )
 }
 
 fn matches_scope_legacy(
@@ -514,19 +494,53 @@ fn matches_scope_legacy(
     user_id: &Option<String>,
     project_id: &Option<String>,
     role_tag: &Option<String>,
+) -> bool {
+    matches_scope(
+        (row.scope.as_str(), &row.user_id, &row.project_id, &row.role_tag),
+        search_scope,
+        user_id,
+        project_id,
+        role_tag,
+    )
+}
+
+/// Whether a result loaded outside the search (e.g. by relation expansion)
+/// would have passed the search's scope and knowledge type filters.
+pub(crate) fn is_visible(result: &SearchResult, params: &SearchParams) -> bool {
+    if let Some(ref kt) = params.knowledge_type {
+        if result.knowledge_type != *kt {
+            return false;
+        }
+    }
+    matches_scope(
+        (result.scope.as_str(), &result.user_id, &result.project_id, &result.role_tag),
+        &params.scope,
+        &params.user_id,
+        &params.project_id,
+        &params.role_tag,
+    )
+}
+
+/// Row fields: (scope, user_id, project_id, role_tag)
+fn matches_scope(
+    (scope, row_user, row_project, row_role): (&str, &Option<String>, &Option<String>, &Option<String>),
+    search_scope: &str,
+    user_id: &Option<String>,
+    project_id: &Option<String>,
+    role_tag: &Option<String>,
 ) -> bool {
     match search_scope {
-        "personal" => row.scope == "personal" && user_id.as_deref() == row.user_id.as_deref(),
-        "team" => (row.scope == "team" || row.scope == "global")
-            && (project_id.as_deref() == row.project_id.as_deref() || row.project_id.is_none()),
-        "role" => (row.scope == "role" || row.scope == "global")
-            && (role_tag.as_deref() == row.role_tag.as_deref() || row.role_tag.is_none()),
+        "personal" => scope == "personal" && user_id.as_deref() == row_user.as_deref(),
+        "team" => (scope == "team" || scope == "global")
+            && (project_id.as_deref() == row_project.as_deref() || row_project.is_none()),
+        "role" => (scope == "role" || scope == "global")
+            && (role_tag.as_deref() == row_role.as_deref() || row_role.is_none()),
         "all" => {
-            let is_own = user_id.as_deref() == row.user_id.as_deref() && row.user_id.is_some();
-            let is_team = project_id.as_deref() == row.project_id.as_deref()
-                && row.project_id.is_some()
-                && (row.scope == "team" || row.scope == "global");
-            let is_global = row.scope == "role" || row.scope == "global";
+            let is_own = user_id.as_deref() == row_user.as_deref() && row_user.is_some();
+            let is_team = project_id.as_deref() == row_project.as_deref()
+                && row_project.is_some()
+                && (scope == "team" || scope == "global");
+            let is_global = scope == "role" || scope == "global";
             is_own || is_team || is_global
         }
         _ => false,
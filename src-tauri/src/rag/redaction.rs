@@ -0,0 +1,608 @@
+/// PII Redaction — nothing personal leaves the device in an LLM prompt
+///
+/// `LlmClient` runs every prompt through a `Redactor` before the provider
+/// call and restores the reply afterwards:
+/// - Korean phone numbers (010-1234-5678, 02-123-4567, +82 10 …)
+/// - 주민등록번호 (YYMMDD-NNNNNNN)
+/// - Email addresses
+/// - Bank account numbers (3+ hyphenated groups, or digits next to "계좌" / a bank name)
+/// - Client names configured per project
+/// - Participant names and user ids of digested messages (`SensitiveTerm`)
+///
+/// Each distinct value becomes a numbered placeholder (`[PHONE_1]`,
+/// `[PERSON_2]`, …); the mapping lives only in memory for the call.
+/// Settings are per project (`redaction_settings`, all detectors on by
+/// default) and each redacting call is audited in `redaction_audit` with
+/// counts per kind — never the values themselves.
+
+use crate::rag::db::RagDb;
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Words that mark a nearby digit run as a bank account
+const ACCOUNT_CONTEXT: &[&str] = &[
+    "계좌", "입금", "송금", "account", "국민", "신한", "우리", "하나", "농협", "기업", "카카오뱅크", "토스", "케이뱅크",
+];
+
+/// Characters before a digit run searched for `ACCOUNT_CONTEXT`
+const ACCOUNT_CONTEXT_CHARS: usize = 20;
+
+/// Shortest term (name, client) that is redacted; shorter ones match too much
+const MIN_TERM_CHARS: usize = 2;
+
+// ── Types ──────────────────────────────────────────────
+
+/// What was redacted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiKind {
+    Phone,
+    Rrn,
+    Email,
+    BankAccount,
+    ClientName,
+    Person,
+    UserId,
+}
+
+impl PiiKind {
+    pub const ALL: [PiiKind; 7] = [
+        PiiKind::Phone,
+        PiiKind::Rrn,
+        PiiKind::Email,
+        PiiKind::BankAccount,
+        PiiKind::ClientName,
+        PiiKind::Person,
+        PiiKind::UserId,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PiiKind::Phone => "phone",
+            PiiKind::Rrn => "rrn",
+            PiiKind::Email => "email",
+            PiiKind::BankAccount => "bank_account",
+            PiiKind::ClientName => "client_name",
+            PiiKind::Person => "person",
+            PiiKind::UserId => "user_id",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|k| k.as_str() == s)
+            .ok_or_else(|| format!("Unknown PII kind: {}", s))
+    }
+
+    fn placeholder_label(&self) -> &'static str {
+        match self {
+            PiiKind::Phone => "PHONE",
+            PiiKind::Rrn => "RRN",
+            PiiKind::Email => "EMAIL",
+            PiiKind::BankAccount => "ACCOUNT",
+            PiiKind::ClientName => "CLIENT",
+            PiiKind::Person => "PERSON",
+            PiiKind::UserId => "USER",
+        }
+    }
+}
+
+/// A known value to redact (participant name / id), in addition to the detectors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SensitiveTerm {
+    pub kind: PiiKind,
+    pub value: String,
+}
+
+impl SensitiveTerm {
+    pub fn person(name: &str) -> Self {
+        Self { kind: PiiKind::Person, value: name.trim().to_string() }
+    }
+
+    pub fn user_id(id: &str) -> Self {
+        Self { kind: PiiKind::UserId, value: id.trim().to_string() }
+    }
+}
+
+/// Redaction settings of a project
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RedactionSettings {
+    pub project_id: Option<String>,
+    pub enabled: bool,
+    /// Detectors in use
+    pub kinds: Vec<PiiKind>,
+    pub client_names: Vec<String>,
+    /// false = defaults (no stored row)
+    pub is_custom: bool,
+}
+
+impl RedactionSettings {
+    pub fn default_for(project_id: Option<&str>) -> Self {
+        Self {
+            project_id: project_id.map(String::from),
+            enabled: true,
+            kinds: PiiKind::ALL.to_vec(),
+            client_names: vec![],
+            is_custom: false,
+        }
+    }
+}
+
+/// One audited LLM call
+#[derive(Debug, Clone, Serialize)]
+pub struct RedactionAudit {
+    pub id: i64,
+    pub created_at: String,
+    pub project_id: Option<String>,
+    pub room_id: Option<String>,
+    pub task: String,
+    pub purpose: String,
+    /// Values redacted per kind
+    pub counts: BTreeMap<String, usize>,
+    pub total: usize,
+}
+
+/// Redacted text with its placeholders (preview / tests)
+#[derive(Debug, Clone, Serialize)]
+pub struct RedactionPreview {
+    pub text: String,
+    pub counts: BTreeMap<String, usize>,
+}
+
+// ── Redactor ───────────────────────────────────────────
+
+/// Replaces PII with placeholders and puts it back into replies
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    settings: RedactionSettings,
+    terms: Vec<SensitiveTerm>,
+    /// (placeholder, original, kind) in order of first appearance
+    mapping: Vec<(String, String, PiiKind)>,
+}
+
+impl Redactor {
+    pub fn new(settings: RedactionSettings, terms: &[SensitiveTerm]) -> Self {
+        let mut all: Vec<SensitiveTerm> = settings
+            .client_names
+            .iter()
+            .map(|name| SensitiveTerm { kind: PiiKind::ClientName, value: name.trim().to_string() })
+            .chain(terms.iter().cloned())
+            .filter(|t| settings.kinds.contains(&t.kind) && t.value.chars().count() >= MIN_TERM_CHARS)
+            .collect();
+        // Longest first so "김민수 대표" wins over "김민수"
+        all.sort_by_key(|t| std::cmp::Reverse(t.value.chars().count()));
+        all.dedup_by(|a, b| a.value == b.value);
+        Self { settings, terms: all, mapping: vec![] }
+    }
+
+    /// Text with every detected value replaced by its placeholder.
+    pub fn redact(&mut self, text: &str) -> String {
+        if !self.settings.enabled {
+            return text.to_string();
+        }
+        let mut out = text.to_string();
+        for term in self.terms.clone() {
+            if out.contains(&term.value) {
+                let placeholder = self.placeholder(term.kind, &term.value);
+                out = out.replace(&term.value, &placeholder);
+            }
+        }
+
+        let spans = detect(&out, &self.settings.kinds);
+        if spans.is_empty() {
+            return out;
+        }
+        let chars: Vec<char> = out.chars().collect();
+        let mut redacted = String::with_capacity(out.len());
+        let mut cursor = 0;
+        for (start, end, kind) in spans {
+            redacted.extend(&chars[cursor..start]);
+            let original: String = chars[start..end].iter().collect();
+            redacted.push_str(&self.placeholder(kind, &original));
+            cursor = end;
+        }
+        redacted.extend(&chars[cursor..]);
+        redacted
+    }
+
+    /// Put the originals back into a reply.
+    pub fn restore(&self, text: &str) -> String {
+        self.mapping
+            .iter()
+            .fold(text.to_string(), |text, (placeholder, original, _)| text.replace(placeholder, original))
+    }
+
+    /// Restore every string inside a JSON reply.
+    pub fn restore_value(&self, value: &mut Value) {
+        match value {
+            Value::String(s) => *s = self.restore(s),
+            Value::Array(items) => items.iter_mut().for_each(|v| self.restore_value(v)),
+            Value::Object(fields) => fields.values_mut().for_each(|v| self.restore_value(v)),
+            _ => {}
+        }
+    }
+
+    /// Distinct values redacted so far, per kind.
+    pub fn counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for (_, _, kind) in &self.mapping {
+            *counts.entry(kind.as_str().to_string()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    pub fn total(&self) -> usize {
+        self.mapping.len()
+    }
+
+    fn placeholder(&mut self, kind: PiiKind, original: &str) -> String {
+        if let Some((placeholder, _, _)) = self.mapping.iter().find(|(_, o, k)| o == original && *k == kind) {
+            return placeholder.clone();
+        }
+        let n = self.mapping.iter().filter(|(_, _, k)| *k == kind).count() + 1;
+        let placeholder = format!("[{}_{}]", kind.placeholder_label(), n);
+        self.mapping.push((placeholder.clone(), original.to_string(), kind));
+        placeholder
+    }
+}
+
+// ── Detectors ──────────────────────────────────────────
+
+/// Non-overlapping (start, end, kind) spans in char indices, in order.
+fn detect(text: &str, kinds: &[PiiKind]) -> Vec<(usize, usize, PiiKind)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut spans = Vec::new();
+    if kinds.contains(&PiiKind::Email) {
+        spans.extend(emails(&chars));
+    }
+    for (start, end) in digit_runs(&chars) {
+        if spans.iter().any(|(s, e, _)| start < *e && *s < end) {
+            continue;
+        }
+        if let Some(kind) = classify_number(&chars, start, end).filter(|k| kinds.contains(k)) {
+            spans.push((start, end, kind));
+        }
+    }
+    spans.sort_by_key(|(start, _, _)| *start);
+    spans
+}
+
+fn emails(chars: &[char]) -> Vec<(usize, usize, PiiKind)> {
+    let local = |c: char| c.is_ascii_alphanumeric() || "._%+-".contains(c);
+    let domain = |c: char| c.is_ascii_alphanumeric() || ".-".contains(c);
+    let mut spans = Vec::new();
+    for (at, _) in chars.iter().enumerate().filter(|(_, c)| **c == '@') {
+        let mut start = at;
+        while start > 0 && local(chars[start - 1]) {
+            start -= 1;
+        }
+        let mut end = at + 1;
+        while end < chars.len() && domain(chars[end]) {
+            end += 1;
+        }
+        while end > at + 1 && chars[end - 1] == '.' {
+            end -= 1;
+        }
+        let host: String = chars[at + 1..end].iter().collect();
+        if start < at && host.contains('.') && !host.starts_with('.') {
+            spans.push((start, end, PiiKind::Email));
+        }
+    }
+    spans
+}
+
+/// Runs of digits joined by single separators (`-`, `.`, space), starting
+/// with a digit or `+`, not glued to Latin letters or digits on either side
+/// (Korean particles may follow directly: "010-1234-5678로").
+fn digit_runs(chars: &[char]) -> Vec<(usize, usize)> {
+    let mut runs = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let starts = chars[i].is_ascii_digit() || (chars[i] == '+' && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()));
+        if !starts || (i > 0 && chars[i - 1].is_ascii_alphanumeric()) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        let mut end = i + 1;
+        while end < chars.len() {
+            if chars[end].is_ascii_digit() {
+                end += 1;
+            } else if "-. ".contains(chars[end]) && chars.get(end + 1).is_some_and(|c| c.is_ascii_digit()) {
+                end += 2;
+            } else {
+                break;
+            }
+        }
+        if !chars.get(end).is_some_and(|c| c.is_ascii_alphanumeric()) {
+            runs.push((start, end));
+        }
+        i = end;
+    }
+    runs
+}
+
+fn classify_number(chars: &[char], start: usize, end: usize) -> Option<PiiKind> {
+    let run: String = chars[start..end].iter().collect();
+    let groups: Vec<&str> = run.trim_start_matches('+').split(['-', '.', ' ']).collect();
+    let digits: String = run.chars().filter(|c| c.is_ascii_digit()).collect();
+    let sizes: Vec<usize> = groups.iter().map(|g| g.len()).collect();
+
+    if is_rrn(&digits, &sizes, run.contains('-')) {
+        return Some(PiiKind::Rrn);
+    }
+    let international = run.starts_with('+');
+    let national = if international {
+        digits.strip_prefix("82").map(|rest| format!("0{}", rest.trim_start_matches('0')))?
+    } else {
+        digits.clone()
+    };
+    // Area / carrier code first, then groups of 3+ digits (not dates like 2026.03.05)
+    let code_groups = if international { 2 } else { 1 };
+    if is_phone(&national) && (sizes.len() == 1 || sizes.iter().skip(code_groups).all(|s| *s >= 3)) {
+        return Some(PiiKind::Phone);
+    }
+    let account_len = (10..=16).contains(&digits.len());
+    let hyphenated = sizes.len() >= 3 && run.contains('-');
+    if account_len && (hyphenated || account_context(chars, start)) {
+        return Some(PiiKind::BankAccount);
+    }
+    None
+}
+
+/// YYMMDD-GNNNNNN with a valid month, day and gender digit
+fn is_rrn(digits: &str, sizes: &[usize], hyphen: bool) -> bool {
+    let shaped = (sizes == [6, 7] && hyphen) || sizes == [13];
+    if !shaped {
+        return false;
+    }
+    let num = |range: std::ops::Range<usize>| digits[range].parse::<u32>().unwrap_or(0);
+    (1..=12).contains(&num(2..4)) && (1..=31).contains(&num(4..6)) && (1..=8).contains(&num(6..7))
+}
+
+fn is_phone(digits: &str) -> bool {
+    let len = digits.len();
+    let mobile = ["010", "011", "016", "017", "018", "019"].iter().any(|p| digits.starts_with(p));
+    let internet = digits.starts_with("070") || digits.starts_with("050");
+    let seoul = digits.starts_with("02");
+    let area = digits.len() > 2 && digits.starts_with('0') && ('3'..='6').contains(&digits[1..].chars().next().unwrap_or('0'));
+    ((mobile || internet || area) && (10..=12).contains(&len)) || (seoul && (9..=10).contains(&len))
+}
+
+fn account_context(chars: &[char], start: usize) -> bool {
+    let from = start.saturating_sub(ACCOUNT_CONTEXT_CHARS);
+    let before: String = chars[from..start].iter().collect::<String>().to_lowercase();
+    ACCOUNT_CONTEXT.iter().any(|w| before.contains(w))
+}
+
+// ── Settings ───────────────────────────────────────────
+
+/// Effective settings for a project (defaults when none are stored).
+pub fn get_settings(db: &RagDb, project_id: Option<&str>) -> Result<RedactionSettings, String> {
+    let Some(id) = project_id else {
+        return Ok(RedactionSettings::default_for(None));
+    };
+    let conn = db.conn();
+    let row = conn
+        .query_row(
+            "SELECT enabled, kinds, client_names FROM redaction_settings WHERE project_id = ?1",
+            [id],
+            |row| Ok((row.get::<_, bool>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Load redaction settings failed: {}", e))?;
+    let Some((enabled, kinds, client_names)) = row else {
+        return Ok(RedactionSettings::default_for(project_id));
+    };
+    let kinds: Vec<String> = serde_json::from_str(&kinds).unwrap_or_default();
+    Ok(RedactionSettings {
+        project_id: project_id.map(String::from),
+        enabled,
+        kinds: kinds.iter().filter_map(|k| PiiKind::parse(k).ok()).collect(),
+        client_names: serde_json::from_str(&client_names).unwrap_or_default(),
+        is_custom: true,
+    })
+}
+
+/// Store a project's settings.
+pub fn set_settings(
+    db: &RagDb,
+    project_id: &str,
+    enabled: bool,
+    kinds: &[PiiKind],
+    client_names: &[String],
+) -> Result<RedactionSettings, String> {
+    let kinds: Vec<&str> = kinds.iter().map(|k| k.as_str()).collect();
+    let client_names: Vec<&str> = client_names.iter().map(|n| n.trim()).filter(|n| !n.is_empty()).collect();
+    {
+        let conn = db.conn();
+        conn.execute(
+            "INSERT INTO redaction_settings (project_id, enabled, kinds, client_names) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(project_id) DO UPDATE SET
+                enabled = excluded.enabled,
+                kinds = excluded.kinds,
+                client_names = excluded.client_names,
+                updated_at = datetime('now')",
+            rusqlite::params![
+                project_id,
+                enabled,
+                serde_json::to_string(&kinds).unwrap_or_else(|_| "[]".into()),
+                serde_json::to_string(&client_names).unwrap_or_else(|_| "[]".into()),
+            ],
+        )
+        .map_err(|e| format!("Save redaction settings failed: {}", e))?;
+    }
+    if !enabled {
+        log::warn!("PII redaction disabled for project {}", project_id);
+    }
+    get_settings(db, Some(project_id))
+}
+
+/// Back to the defaults (everything redacted, no client names).
+pub fn reset_settings(db: &RagDb, project_id: &str) -> Result<bool, String> {
+    let conn = db.conn();
+    let removed = conn
+        .execute("DELETE FROM redaction_settings WHERE project_id = ?1", [project_id])
+        .map_err(|e| format!("Reset redaction settings failed: {}", e))?;
+    Ok(removed > 0)
+}
+
+/// Redact `text` with a project's settings without calling anything.
+pub fn preview(db: &RagDb, project_id: Option<&str>, text: &str) -> Result<RedactionPreview, String> {
+    let mut redactor = Redactor::new(get_settings(db, project_id)?, &[]);
+    let text = redactor.redact(text);
+    Ok(RedactionPreview { text, counts: redactor.counts() })
+}
+
+// ── Audit ──────────────────────────────────────────────
+
+/// Record what a call redacted (kinds and counts only).
+pub fn audit(
+    db: &RagDb,
+    redactor: &Redactor,
+    project_id: Option<&str>,
+    room_id: Option<&str>,
+    task: &str,
+    purpose: &str,
+) -> Result<(), String> {
+    if redactor.total() == 0 {
+        return Ok(());
+    }
+    let counts = serde_json::to_string(&redactor.counts()).unwrap_or_else(|_| "{}".into());
+    let conn = db.conn();
+    conn.execute(
+        "INSERT INTO redaction_audit (project_id, room_id, task, purpose, counts, total)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        rusqlite::params![project_id, room_id, task, purpose, counts, redactor.total() as i64],
+    )
+    .map_err(|e| format!("Record redaction audit failed: {}", e))?;
+    Ok(())
+}
+
+/// Recent audited calls, newest first (one project or all).
+pub fn audit_log(db: &RagDb, project_id: Option<&str>, limit: usize) -> Result<Vec<RedactionAudit>, String> {
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, created_at, project_id, room_id, task, purpose, counts, total
+             FROM redaction_audit
+             WHERE ?1 IS NULL OR project_id = ?1
+             ORDER BY id DESC
+             LIMIT ?2",
+        )
+        .map_err(|e| format!("Prepare redaction audit failed: {}", e))?;
+    let entries = stmt
+        .query_map(rusqlite::params![project_id, limit as i64], |row| {
+            Ok(RedactionAudit {
+                id: row.get(0)?,
+                created_at: row.get(1)?,
+                project_id: row.get(2)?,
+                room_id: row.get(3)?,
+                task: row.get(4)?,
+                purpose: row.get(5)?,
+                counts: serde_json::from_str(&row.get::<_, String>(6)?).unwrap_or_default(),
+                total: row.get::<_, i64>(7)? as usize,
+            })
+        })
+        .map_err(|e| format!("Query redaction audit failed: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::llm::{LlmClient, LlmFuture, LlmProvider, LlmReply, LlmRequest, LlmTask};
+    use std::sync::{Arc, Mutex};
+    use uuid::Uuid;
+
+    #[test]
+    fn test_detectors_and_restore() {
+        let settings = RedactionSettings {
+            client_names: vec!["삼성전자".to_string()],
+            ..RedactionSettings::default_for(Some("p1"))
+        };
+        let terms = [SensitiveTerm::person("김민수"), SensitiveTerm::user_id("u-42")];
+        let mut redactor = Redactor::new(settings, &terms);
+
+        let text = "[2026-03-01T10:00:00Z] 김민수 (u-42): 삼성전자 담당자 010-1234-5678 / +82 10 9876 5432, \
+                    mail kim.ms@example.co.kr, 주민번호 900101-1234567, 국민은행 계좌 123456789012 \
+                    또는 110-123-456789로 입금. 예산 3,000만원, 2026.03.05 촬영, 김민수 확인";
+        let redacted = redactor.redact(text);
+        for secret in ["010-1234-5678", "9876 5432", "kim.ms@", "900101", "123456789012", "110-123-456789", "삼성전자", "김민수", "u-42"] {
+            assert!(!redacted.contains(secret), "{} leaked: {}", secret, redacted);
+        }
+        assert!(redacted.contains("2026-03-01T10:00:00Z") && redacted.contains("2026.03.05") && redacted.contains("3,000만원"));
+        assert!(redacted.starts_with("[2026-03-01T10:00:00Z] [PERSON_1] ([USER_1]): [CLIENT_1]"));
+        assert!(redacted.ends_with("[PERSON_1] 확인"));
+
+        let counts = redactor.counts();
+        assert_eq!(counts.get("phone"), Some(&2));
+        assert_eq!(counts.get("bank_account"), Some(&2));
+        assert_eq!((counts.get("rrn"), counts.get("email")), (Some(&1), Some(&1)));
+        assert_eq!(redactor.restore(&redacted), text);
+
+        let mut reply = serde_json::json!({"decisions": [{"text": "[PERSON_1]님이 [PHONE_1]로 연락", "relatedUserIds": ["[USER_1]"]}]});
+        redactor.restore_value(&mut reply);
+        assert_eq!(reply["decisions"][0]["text"], "김민수님이 010-1234-5678로 연락");
+        assert_eq!(reply["decisions"][0]["relatedUserIds"][0], "u-42");
+    }
+
+    /// Answers with the prompt it received, remembering it
+    struct EchoProvider(Arc<Mutex<String>>);
+
+    impl LlmProvider for EchoProvider {
+        fn name(&self) -> &'static str {
+            "Echo"
+        }
+
+        fn kind(&self) -> crate::rag::llm::ProviderKind {
+            crate::rag::llm::ProviderKind::Mock
+        }
+
+        fn complete<'a>(&'a self, _model: &'a str, request: &'a LlmRequest) -> LlmFuture<'a> {
+            *self.0.lock().unwrap() = request.user.clone();
+            let text = serde_json::json!({ "summary": request.user }).to_string();
+            Box::pin(async move { Ok(LlmReply { text, usage: None }) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_redacts_per_project_and_audits() {
+        let dir = std::env::temp_dir().join(format!("rag_redaction_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Arc::new(RagDb::open(&dir.join("test.db")).unwrap());
+        let sent = Arc::new(Mutex::new(String::new()));
+        let client = |project: &str| {
+            LlmClient::new(Box::new(EchoProvider(sent.clone())), "echo")
+                .with_ledger(db.clone())
+                .with_usage("test", Some(project), Some("room1"))
+        };
+        let schema = serde_json::json!({"type": "object"});
+        let message = "한빛엔터 이수진 010-2222-3333";
+
+        set_settings(&db, "p1", true, &PiiKind::ALL, &["한빛엔터".to_string()]).unwrap();
+        let terms = [SensitiveTerm::person("이수진")];
+        let reply = client("p1")
+            .complete_json_redacting(LlmTask::Digest, "system", message, 100, schema.clone(), &terms)
+            .await
+            .unwrap();
+        assert_eq!(*sent.lock().unwrap(), "[CLIENT_1] [PERSON_1] [PHONE_1]");
+        assert_eq!(reply["summary"], message);
+
+        // Disabled per project: sent as is, nothing audited
+        set_settings(&db, "p2", false, &PiiKind::ALL, &[]).unwrap();
+        client("p2").complete_json(LlmTask::Digest, "system", message, 100, schema).await.unwrap();
+        assert_eq!(*sent.lock().unwrap(), message);
+
+        let log = audit_log(&db, None, 10).unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!((log[0].project_id.as_deref(), log[0].total), (Some("p1"), 3));
+        assert_eq!(log[0].counts.get("client_name"), Some(&1));
+        assert_eq!(preview(&db, Some("p3"), "010-2222-3333").unwrap().text, "[PHONE_1]");
+    }
+}
@@ -0,0 +1,516 @@
+/// Knowledge Relations — Typed graph between knowledge items
+///
+/// A relation reads `source --relation_type--> target`:
+/// - supersedes    newer decision replaces an older one (target is down-ranked)
+/// - contradicts   e.g. a risk that contradicts a CEO pattern
+/// - supports      evidence for the target
+/// - derived_from  source was extracted/synthesized from target
+/// - duplicate_of  source was merged into target (see `dedup`)
+///
+/// Search uses the graph twice: items superseded by an active item get their
+/// hybrid score multiplied by `SUPERSEDED_PENALTY`, and `expand_results` pulls
+/// in related items (the newer version, supporting and contradicting items).
+
+use crate::rag::db::RagDb;
+use crate::rag::embedding::{blob_to_vector, cosine_similarity, EMBEDDING_DIM};
+use crate::rag::events::ChangeKind;
+use crate::rag::knowledge;
+use crate::rag::query::{self, SearchParams, SearchResult};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use uuid::Uuid;
+
+/// Score multiplier for items superseded by an active item
+pub const SUPERSEDED_PENALTY: f64 = 0.5;
+
+/// Default traversal depth
+pub const DEFAULT_MAX_DEPTH: usize = 2;
+
+/// Relation type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelationType {
+    Supersedes,
+    Contradicts,
+    Supports,
+    DerivedFrom,
+    DuplicateOf,
+}
+
+impl RelationType {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "supersedes" => Ok(Self::Supersedes),
+            "contradicts" => Ok(Self::Contradicts),
+            "supports" => Ok(Self::Supports),
+            "derived_from" => Ok(Self::DerivedFrom),
+            "duplicate_of" => Ok(Self::DuplicateOf),
+            other => Err(format!("Unknown relation type: {}", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Supersedes => "supersedes",
+            Self::Contradicts => "contradicts",
+            Self::Supports => "supports",
+            Self::DerivedFrom => "derived_from",
+            Self::DuplicateOf => "duplicate_of",
+        }
+    }
+}
+
+/// Stored relation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Relation {
+    pub id: String,
+    pub source_id: String,
+    pub target_id: String,
+    pub relation_type: RelationType,
+    pub note: Option<String>,
+    pub created_by: Option<String>,
+    pub created_at: String,
+}
+
+/// Direction of an edge as seen from the item being traversed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Outgoing,
+    Incoming,
+}
+
+/// Item reached while traversing the graph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelatedItem {
+    pub knowledge_id: String,
+    pub relation_type: RelationType,
+    pub direction: Direction,
+    /// Item this one was reached from
+    pub via_id: String,
+    pub depth: usize,
+}
+
+/// Link two knowledge items (idempotent: returns the existing relation if present).
+pub fn link(
+    db: &RagDb,
+    source_id: &str,
+    target_id: &str,
+    relation_type: RelationType,
+    note: Option<&str>,
+    created_by: Option<&str>,
+) -> Result<Relation, String> {
+    if source_id == target_id {
+        return Err("Cannot relate a knowledge item to itself".to_string());
+    }
+    let target = knowledge::get_knowledge_item(db, target_id)?
+        .ok_or_else(|| format!("Knowledge item not found: {}", target_id))?;
+    if knowledge::get_knowledge_item(db, source_id)?.is_none() {
+        return Err(format!("Knowledge item not found: {}", source_id));
+    }
+
+    let relation = {
+        let conn = db.conn();
+        conn.execute(
+            "INSERT OR IGNORE INTO knowledge_relations
+                (id, source_id, target_id, relation_type, note, created_by)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                Uuid::new_v4().to_string(),
+                source_id,
+                target_id,
+                relation_type.as_str(),
+                note,
+                created_by,
+            ],
+        )
+        .map_err(|e| format!("Insert relation failed: {}", e))?;
+
+        conn.query_row(
+            &format!("{} WHERE source_id = ?1 AND target_id = ?2 AND relation_type = ?3", SELECT_RELATION),
+            rusqlite::params![source_id, target_id, relation_type.as_str()],
+            row_to_relation,
+        )
+        .map_err(|e| format!("Read relation failed: {}", e))?
+    };
+
+    log::info!("Linked {} --{}--> {}", source_id, relation_type.as_str(), target_id);
+
+    // Superseding changes how the target ranks
+    if relation_type == RelationType::Supersedes {
+        knowledge::notify_changed(db, &target, ChangeKind::Updated);
+    }
+    Ok(relation)
+}
+
+/// Remove a relation by id. Returns false if it did not exist.
+pub fn unlink(db: &RagDb, relation_id: &str) -> Result<bool, String> {
+    let removed: Option<Relation> = {
+        let conn = db.conn();
+        let relation = conn
+            .query_row(
+                &format!("{} WHERE id = ?1", SELECT_RELATION),
+                [relation_id],
+                row_to_relation,
+            )
+            .ok();
+        conn.execute("DELETE FROM knowledge_relations WHERE id = ?1", [relation_id])
+            .map_err(|e| format!("Delete relation failed: {}", e))?;
+        relation
+    };
+
+    let Some(relation) = removed else {
+        return Ok(false);
+    };
+    if relation.relation_type == RelationType::Supersedes {
+        if let Some(target) = knowledge::get_knowledge_item(db, &relation.target_id)? {
+            knowledge::notify_changed(db, &target, ChangeKind::Updated);
+        }
+    }
+    Ok(true)
+}
+
+/// All relations touching an item (both directions).
+pub fn list_relations(db: &RagDb, knowledge_id: &str) -> Result<Vec<Relation>, String> {
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} WHERE source_id = ?1 OR target_id = ?1 ORDER BY created_at DESC",
+            SELECT_RELATION
+        ))
+        .map_err(|e| format!("Prepare relations failed: {}", e))?;
+
+    let rows = stmt
+        .query_map([knowledge_id], row_to_relation)
+        .map_err(|e| format!("Query relations failed: {}", e))?;
+
+    Ok(rows.filter_map(|r| r.ok()).collect())
+}
+
+/// Breadth-first traversal from `start_id` in both directions.
+///
+/// `types` limits which relation types are followed (all when empty).
+pub fn traverse(
+    db: &RagDb,
+    start_id: &str,
+    types: &[RelationType],
+    max_depth: usize,
+) -> Result<Vec<RelatedItem>, String> {
+    let mut visited: HashSet<String> = HashSet::from([start_id.to_string()]);
+    let mut queue: VecDeque<(String, usize)> = VecDeque::from([(start_id.to_string(), 0)]);
+    let mut found = Vec::new();
+
+    while let Some((current, depth)) = queue.pop_front() {
+        if depth >= max_depth {
+            continue;
+        }
+        for relation in list_relations(db, &current)? {
+            if !types.is_empty() && !types.contains(&relation.relation_type) {
+                continue;
+            }
+            let (next, direction) = if relation.source_id == current {
+                (relation.target_id, Direction::Outgoing)
+            } else {
+                (relation.source_id, Direction::Incoming)
+            };
+            if !visited.insert(next.clone()) {
+                continue;
+            }
+            found.push(RelatedItem {
+                knowledge_id: next.clone(),
+                relation_type: relation.relation_type,
+                direction,
+                via_id: current.clone(),
+                depth: depth + 1,
+            });
+            queue.push_back((next, depth + 1));
+        }
+    }
+
+    Ok(found)
+}
+
+/// Down-rank results that are superseded by an active item (call before sorting).
+pub fn apply_supersede_penalty(conn: &Connection, results: &mut [SearchResult]) {
+    if results.is_empty() {
+        return;
+    }
+    let ids: Vec<&str> = results.iter().map(|r| r.id.as_str()).collect();
+    let superseded = match superseded_among(conn, &ids) {
+        Ok(set) => set,
+        Err(e) => {
+            log::warn!("Supersede lookup failed: {}", e);
+            return;
+        }
+    };
+
+    for result in results.iter_mut().filter(|r| superseded.contains(&r.id)) {
+        result.hybrid_score *= SUPERSEDED_PENALTY;
+    }
+}
+
+/// Related items for a result set, scored against the same query.
+///
+/// Follows one hop: the newer item that supersedes a result, and items that
+/// support, contradict or were derived from/into it. Superseded and duplicate
+/// items are never pulled in. Returns at most `params.limit` extra results.
+pub fn expand_results(
+    db: &RagDb,
+    results: &[SearchResult],
+    params: &SearchParams,
+) -> Result<Vec<SearchResult>, String> {
+    let mut seen: HashSet<String> = results.iter().map(|r| r.id.clone()).collect();
+    let mut candidates: Vec<(String, String)> = Vec::new(); // (related id, via id)
+
+    for result in results {
+        for relation in list_relations(db, &result.id)? {
+            let outgoing = relation.source_id == result.id;
+            let wanted = match relation.relation_type {
+                RelationType::Supersedes => !outgoing, // only the newer version
+                RelationType::DuplicateOf => false,
+                _ => true,
+            };
+            let other = if outgoing { relation.target_id } else { relation.source_id };
+            if wanted && seen.insert(other.clone()) {
+                candidates.push((other, result.id.clone()));
+            }
+        }
+    }
+
+    let conn = db.conn();
+    let mut expanded = Vec::new();
+    for (id, via) in candidates {
+        if expanded.len() >= params.limit {
+            break;
+        }
+        if let Some(mut result) = load_related(&conn, &id, params)? {
+            result.related_via = Some(via);
+            expanded.push(result);
+        }
+    }
+    apply_supersede_penalty(&conn, &mut expanded);
+
+    Ok(expanded)
+}
+
+// ── Internal helpers ────────────────────────────────────
+
+const SELECT_RELATION: &str = "SELECT id, source_id, target_id, relation_type, note, created_by, created_at
+     FROM knowledge_relations";
+
+fn row_to_relation(row: &rusqlite::Row) -> rusqlite::Result<Relation> {
+    let relation_type: String = row.get(3)?;
+    Ok(Relation {
+        id: row.get(0)?,
+        source_id: row.get(1)?,
+        target_id: row.get(2)?,
+        relation_type: RelationType::parse(&relation_type).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, e.into())
+        })?,
+        note: row.get(4)?,
+        created_by: row.get(5)?,
+        created_at: row.get(6)?,
+    })
+}
+
+/// Ids (among `ids`) that are the target of a `supersedes` relation from an active item.
+fn superseded_among(conn: &Connection, ids: &[&str]) -> Result<HashSet<String>, String> {
+    let placeholders = (1..=ids.len()).map(|i| format!("?{}", i)).collect::<Vec<_>>().join(", ");
+    let sql = format!(
+        "SELECT DISTINCT r.target_id
+         FROM knowledge_relations r
+         JOIN knowledge_items s ON s.id = r.source_id
+         WHERE r.relation_type = 'supersedes'
+           AND s.is_active = 1
+           AND r.target_id IN ({})",
+        placeholders
+    );
+
+    let mut stmt = conn.prepare(&sql).map_err(|e| format!("Prepare supersede lookup failed: {}", e))?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(ids.iter()), |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Supersede lookup failed: {}", e))?;
+
+    Ok(rows.filter_map(|r| r.ok()).collect())
+}
+
+/// Load an active, visible related item and score it like a hybrid search hit.
+fn load_related(conn: &Connection, id: &str, params: &SearchParams) -> Result<Option<SearchResult>, String> {
+    let row = conn.query_row(
+        "SELECT ki.id, ki.content, ki.summary, ki.knowledge_type, ki.source_type,
+                ki.scope, ki.role_tag, ki.dialectic_tag, ki.confidence,
+                ki.relevance_score, ki.usage_count, ki.project_id, ki.user_id,
+                e.vector
+         FROM knowledge_items ki
+         JOIN embeddings e ON e.knowledge_id = ki.id
+         WHERE ki.id = ?1
+           AND ki.is_active = 1
+           AND (ki.expires_at IS NULL OR ki.expires_at > datetime('now'))",
+        [id],
+        |row| {
+            Ok((
+                SearchResult {
+                    id: row.get(0)?,
+                    content: row.get(1)?,
+                    summary: row.get(2)?,
+                    knowledge_type: row.get(3)?,
+                    source_type: row.get(4)?,
+                    scope: row.get(5)?,
+                    role_tag: row.get(6)?,
+                    dialectic_tag: row.get(7)?,
+                    confidence: row.get(8)?,
+                    relevance_score: row.get(9)?,
+                    usage_count: row.get(10)?,
+                    similarity: 0.0,
+                    hybrid_score: 0.0,
+                    project_id: row.get(11)?,
+                    user_id: row.get(12)?,
+                    related_via: None,
+                },
+                row.get::<_, Vec<u8>>(13)?,
+            ))
+        },
+    );
+
+    let (mut result, blob) = match row {
+        Ok(r) => r,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+        Err(e) => return Err(format!("Load related item failed: {}", e)),
+    };
+
+    // Same visibility as the search itself: no other users' personal items,
+    // other projects' team items, or items outside the requested scope/type
+    let vector = blob_to_vector(&blob);
+    if !query::is_visible(&result, params) || vector.len() != EMBEDDING_DIM {
+        return Ok(None);
+    }
+
+    let similarity = cosine_similarity(&params.query_embedding, &vector);
+    let usage_factor = (result.usage_count as f32 / 20.0).min(1.0);
+    result.similarity = similarity as f64;
+    result.hybrid_score = (similarity * params.vector_weight
+        + result.relevance_score as f32 * params.relevance_weight
+        + usage_factor * params.usage_weight) as f64;
+
+    Ok(Some(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::embedding::EmbeddingEngine;
+    use crate::rag::knowledge::KnowledgeItem;
+
+    fn setup() -> (RagDb, EmbeddingEngine) {
+        let dir = std::env::temp_dir().join(format!("rag_relations_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        (RagDb::open(&dir.join("test.db")).unwrap(), EmbeddingEngine::new(dir.join("models")))
+    }
+
+    fn create(db: &RagDb, engine: &EmbeddingEngine, content: &str) -> String {
+        let item = KnowledgeItem {
+            scope: "global".to_string(),
+            ..KnowledgeItem::for_test(content)
+        };
+        let vector = engine.embed(content).unwrap().vector;
+        knowledge::create_knowledge_item(db, &item, &vector).unwrap()
+    }
+
+    #[test]
+    fn test_link_unlink_and_traverse() {
+        let (db, engine) = setup();
+        let a = create(&db, &engine, "A");
+        let b = create(&db, &engine, "B");
+        let c = create(&db, &engine, "C");
+
+        let ab = link(&db, &a, &b, RelationType::Supersedes, None, Some("u1")).unwrap();
+        // Idempotent
+        assert_eq!(link(&db, &a, &b, RelationType::Supersedes, None, None).unwrap().id, ab.id);
+        link(&db, &c, &b, RelationType::Contradicts, Some("예산 충돌"), None).unwrap();
+        assert!(link(&db, &a, &a, RelationType::Supports, None, None).is_err());
+
+        let reached = traverse(&db, &a, &[], DEFAULT_MAX_DEPTH).unwrap();
+        let ids: Vec<(&str, usize)> = reached.iter().map(|r| (r.knowledge_id.as_str(), r.depth)).collect();
+        assert_eq!(ids, vec![(b.as_str(), 1), (c.as_str(), 2)]);
+        assert_eq!(reached[1].direction, Direction::Incoming);
+
+        // Filtered traversal only follows supersedes
+        assert_eq!(traverse(&db, &a, &[RelationType::Supersedes], 5).unwrap().len(), 1);
+
+        assert!(unlink(&db, &ab.id).unwrap());
+        assert!(!unlink(&db, &ab.id).unwrap());
+        assert_eq!(list_relations(&db, &b).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_superseded_is_down_ranked_and_expansion() {
+        let (db, engine) = setup();
+        let text = "촬영 장소는 스튜디오 A";
+        let old = create(&db, &engine, text);
+        let new = create(&db, &engine, "촬영 장소를 스튜디오 B로 변경");
+        let support = create(&db, &engine, "스튜디오 B 대관 확정 메일");
+
+        let params = query::SearchParams {
+            query_embedding: engine.embed(text).unwrap().vector,
+            threshold: -1.0,
+            limit: 1,
+            ..Default::default()
+        };
+        let before = query::hybrid_search(&db, &params).unwrap()[0].hybrid_score;
+
+        link(&db, &new, &old, RelationType::Supersedes, None, None).unwrap();
+        link(&db, &support, &new, RelationType::Supports, None, None).unwrap();
+
+        let mut hits = query::hybrid_search(&db, &SearchParams { limit: 3, ..params.clone() }).unwrap();
+        let old_hit = hits.iter().find(|r| r.id == old).unwrap();
+        assert!(old_hit.hybrid_score < before * 0.6, "{} vs {}", old_hit.hybrid_score, before);
+
+        // Expanding the old item pulls in its replacement, not further hops
+        hits.retain(|r| r.id == old);
+        let expanded = expand_results(&db, &hits, &params).unwrap();
+        assert_eq!(expanded.len(), 1);
+        assert_eq!(expanded[0].id, new);
+        assert_eq!(expanded[0].related_via.as_deref(), Some(old.as_str()));
+    }
+
+    #[test]
+    fn test_expansion_respects_search_visibility() {
+        let (db, engine) = setup();
+        let hit = create(&db, &engine, "촬영 장소는 스튜디오 A");
+        let team_item = KnowledgeItem {
+            project_id: Some("p2".to_string()),
+            ..KnowledgeItem::for_test("p2 스튜디오 대관 확정")
+        };
+        let vector = engine.embed(&team_item.content).unwrap().vector;
+        let team = knowledge::create_knowledge_item(&db, &team_item, &vector).unwrap();
+        let risk_item = KnowledgeItem {
+            scope: "global".to_string(),
+            knowledge_type: "recurring_risk".to_string(),
+            ..KnowledgeItem::for_test("스튜디오 A 소음 민원")
+        };
+        let vector = engine.embed(&risk_item.content).unwrap().vector;
+        let risk = knowledge::create_knowledge_item(&db, &risk_item, &vector).unwrap();
+        link(&db, &team, &hit, RelationType::Supports, None, None).unwrap();
+        link(&db, &risk, &hit, RelationType::Contradicts, None, None).unwrap();
+
+        let params = query::SearchParams {
+            query_embedding: engine.embed("스튜디오").unwrap().vector,
+            threshold: -1.0,
+            ..Default::default()
+        };
+        let hits = query::hybrid_search(&db, &SearchParams { limit: 10, ..params.clone() }).unwrap();
+        let hits: Vec<SearchResult> = hits.into_iter().filter(|r| r.id == hit).collect();
+
+        // No project given: other projects' team items stay hidden, as in the search
+        let expanded = expand_results(&db, &hits, &params).unwrap();
+        let ids: Vec<&str> = expanded.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec![risk.as_str()]);
+
+        let in_p2 = SearchParams { project_id: Some("p2".to_string()), ..params.clone() };
+        assert_eq!(expand_results(&db, &hits, &in_p2).unwrap().len(), 2);
+
+        let decisions_only = SearchParams { knowledge_type: Some("decision_pattern".to_string()), ..params };
+        assert!(expand_results(&db, &hits, &decisions_only).unwrap().is_empty());
+    }
+}
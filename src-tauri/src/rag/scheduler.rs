@@ -0,0 +1,670 @@
+/// Digest Scheduler — incremental per-room digests from a persisted message queue
+///
+/// The frontend streams each room's messages into `enqueue`. Only messages
+/// newer than the room's watermark (last digested `created_at`, then message
+/// id) are queued, and each message is queued once. `digest_room` consumes the
+/// queue, stores the digest with its message range and advances the
+/// watermark, so the same messages are never digested twice.
+///
+/// The background scheduler (`run_due`, every `TICK_SECS`) picks rooms whose
+/// queue reached `min_messages` or has been idle for `idle_secs`. It digests
+/// at most `max_rooms_per_tick` rooms per tick, and each room at most once
+/// per `min_interval_secs` — counted from the last digest or the last failed
+/// attempt, so a failing room is not retried every tick. If the digest provider needs the caller's API key
+/// (Anthropic), the room is announced with `RagEvent::DigestDue` instead, and
+/// the frontend calls `rag_digest_room` with its key.
+///
+/// Timestamps are compared as strings: send `created_at` in one ISO-8601 format.
+
+use crate::rag::db::RagDb;
+use crate::rag::digest::{self, ChatMessage, DigestResult, MessageRange, WhenUnreachable};
+use crate::rag::events::RagEvent;
+use crate::rag::http::ResilientHttp;
+use crate::rag::llm::{LlmClient, LlmTask};
+use crate::rag::prompts;
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+/// How often the background scheduler looks for due rooms
+pub const TICK_SECS: u64 = 60;
+
+/// A digest claim older than this is treated as abandoned (crash mid-digest)
+const STALE_CLAIM_SECS: i64 = 900;
+
+// ── Types ──────────────────────────────────────────────
+
+/// When a room's queue is digested automatically
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SchedulerPolicy {
+    /// Digest once this many messages are queued
+    pub min_messages: usize,
+    /// …or once no message arrived for this long (and at least one is queued)
+    pub idle_secs: i64,
+    /// Never digest a room more often than this
+    pub min_interval_secs: i64,
+    /// Rooms digested per scheduler tick
+    pub max_rooms_per_tick: usize,
+}
+
+impl Default for SchedulerPolicy {
+    fn default() -> Self {
+        Self {
+            min_messages: 30,
+            idle_secs: 600,
+            min_interval_secs: 900,
+            max_rooms_per_tick: 2,
+        }
+    }
+}
+
+/// Why a room is due
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DueReason {
+    MessageCount,
+    Idle,
+}
+
+/// Queue and watermark of one room
+#[derive(Debug, Clone, Serialize)]
+pub struct RoomQueueStatus {
+    pub room_id: String,
+    pub project_id: Option<String>,
+    pub pending_count: usize,
+    pub oldest_pending_at: Option<String>,
+    pub newest_pending_at: Option<String>,
+    /// Seconds since the last message was queued
+    pub idle_secs: Option<i64>,
+    /// Watermark: `created_at` / id of the last digested message
+    pub last_message_at: Option<String>,
+    pub last_message_id: Option<String>,
+    pub digested_count: i64,
+    pub last_digest_at: Option<String>,
+    /// Last failed scheduled digest (cleared by the next successful one)
+    pub last_failed_at: Option<String>,
+    pub due: Option<DueReason>,
+    /// Due, but digested or failed less than `min_interval_secs` ago
+    pub rate_limited: bool,
+}
+
+/// Outcome of `enqueue`
+#[derive(Debug, Clone, Serialize)]
+pub struct EnqueueResult {
+    pub queued: usize,
+    /// At or before the watermark
+    pub already_digested: usize,
+    /// Already in the queue
+    pub duplicates: usize,
+    pub pending_count: usize,
+}
+
+/// A stored digest of a room's queue
+#[derive(Debug, Clone, Serialize)]
+pub struct RoomDigest {
+    pub room_id: String,
+    pub digest: DigestResult,
+    pub stored_ids: Vec<String>,
+    pub range: MessageRange,
+}
+
+// ── Queue ──────────────────────────────────────────────
+
+/// Queue the room's messages that are newer than its watermark.
+pub fn enqueue(
+    db: &RagDb,
+    room_id: &str,
+    project_id: Option<&str>,
+    messages: &[ChatMessage],
+) -> Result<EnqueueResult, String> {
+    let conn = db.conn();
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Begin enqueue failed: {}", e))?;
+
+    tx.execute(
+        "INSERT INTO digest_watermarks (room_id, project_id) VALUES (?1, ?2)
+         ON CONFLICT(room_id) DO UPDATE SET
+            project_id = COALESCE(excluded.project_id, project_id),
+            updated_at = datetime('now')",
+        rusqlite::params![room_id, project_id],
+    )
+    .map_err(|e| format!("Save watermark failed: {}", e))?;
+    let (last_at, last_id): (Option<String>, Option<String>) = tx
+        .query_row(
+            "SELECT last_message_at, last_message_id FROM digest_watermarks WHERE room_id = ?1",
+            [room_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("Load watermark failed: {}", e))?;
+
+    let (mut queued, mut already_digested, mut duplicates) = (0, 0, 0);
+    for message in messages {
+        if is_digested(message, last_at.as_deref(), last_id.as_deref()) {
+            already_digested += 1;
+            continue;
+        }
+        let inserted = tx
+            .execute(
+                "INSERT OR IGNORE INTO digest_queue
+                    (room_id, message_key, message_id, user_id, user_name, content, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                rusqlite::params![
+                    room_id,
+                    message_key(message),
+                    message.id,
+                    message.user_id,
+                    message.user_name,
+                    message.content,
+                    message.created_at,
+                ],
+            )
+            .map_err(|e| format!("Queue message failed: {}", e))?;
+        if inserted > 0 {
+            queued += 1;
+        } else {
+            duplicates += 1;
+        }
+    }
+
+    let pending_count: i64 = tx
+        .query_row("SELECT COUNT(*) FROM digest_queue WHERE room_id = ?1", [room_id], |row| row.get(0))
+        .map_err(|e| format!("Count queue failed: {}", e))?;
+    tx.commit().map_err(|e| format!("Commit enqueue failed: {}", e))?;
+
+    Ok(EnqueueResult {
+        queued,
+        already_digested,
+        duplicates,
+        pending_count: pending_count as usize,
+    })
+}
+
+/// At or before the watermark (`created_at`, then id when both sides have one)
+fn is_digested(message: &ChatMessage, last_at: Option<&str>, last_id: Option<&str>) -> bool {
+    let Some(last_at) = last_at else { return false };
+    match message.created_at.as_str().cmp(last_at) {
+        Ordering::Less => true,
+        Ordering::Greater => false,
+        Ordering::Equal => match (message.id.as_deref(), last_id) {
+            (Some(id), Some(last)) => id <= last,
+            _ => true,
+        },
+    }
+}
+
+/// Dedupe key: the message id, or a hash of time + author + text
+fn message_key(message: &ChatMessage) -> String {
+    match &message.id {
+        Some(id) => format!("id:{}", id),
+        None => {
+            let mut hasher = Sha256::new();
+            for part in [&message.created_at, &message.user_id, &message.content] {
+                hasher.update(part.as_bytes());
+                hasher.update([0u8]);
+            }
+            format!("h:{}", &hex::encode(hasher.finalize())[..32])
+        }
+    }
+}
+
+/// Queued messages of a room in chat order, with their queue row ids.
+fn pending_messages(conn: &Connection, room_id: &str) -> Result<Vec<(i64, ChatMessage)>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, message_id, user_id, user_name, content, created_at
+             FROM digest_queue WHERE room_id = ?1
+             ORDER BY created_at, message_id, id",
+        )
+        .map_err(|e| format!("Prepare queue failed: {}", e))?;
+    let rows = stmt
+        .query_map([room_id], |row| {
+            Ok((
+                row.get(0)?,
+                ChatMessage {
+                    id: row.get(1)?,
+                    user_id: row.get(2)?,
+                    user_name: row.get(3)?,
+                    content: row.get(4)?,
+                    created_at: row.get(5)?,
+                },
+            ))
+        })
+        .map_err(|e| format!("Query queue failed: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(rows)
+}
+
+// ── Status ─────────────────────────────────────────────
+
+/// Queue status of one room (or all rooms).
+pub fn room_status(db: &RagDb, room_id: Option<&str>, policy: &SchedulerPolicy) -> Result<Vec<RoomQueueStatus>, String> {
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare(
+            "SELECT w.room_id, w.project_id, COUNT(q.id), MIN(q.created_at), MAX(q.created_at),
+                    CAST((julianday('now') - julianday(MAX(q.queued_at))) * 86400 AS INTEGER),
+                    w.last_message_at, w.last_message_id, w.digested_count, w.last_digest_at,
+                    CAST((julianday('now') - julianday(w.last_digest_at)) * 86400 AS INTEGER),
+                    w.last_failed_at,
+                    CAST((julianday('now') - julianday(w.last_failed_at)) * 86400 AS INTEGER)
+             FROM digest_watermarks w
+             LEFT JOIN digest_queue q ON q.room_id = w.room_id
+             WHERE ?1 IS NULL OR w.room_id = ?1
+             GROUP BY w.room_id
+             ORDER BY w.room_id",
+        )
+        .map_err(|e| format!("Prepare room status failed: {}", e))?;
+    let rows = stmt
+        .query_map([room_id], |row| {
+            let pending_count = row.get::<_, i64>(2)? as usize;
+            let idle_secs: Option<i64> = row.get(5)?;
+            let since_digest: Option<i64> = row.get(10)?;
+            let since_failure: Option<i64> = row.get(12)?;
+
+            let due = if pending_count == 0 {
+                None
+            } else if pending_count >= policy.min_messages {
+                Some(DueReason::MessageCount)
+            } else if idle_secs.is_some_and(|idle| idle >= policy.idle_secs) {
+                Some(DueReason::Idle)
+            } else {
+                None
+            };
+            let rate_limited = due.is_some()
+                && [since_digest, since_failure]
+                    .iter()
+                    .any(|since| since.is_some_and(|s| s < policy.min_interval_secs));
+
+            Ok(RoomQueueStatus {
+                room_id: row.get(0)?,
+                project_id: row.get(1)?,
+                pending_count,
+                oldest_pending_at: row.get(3)?,
+                newest_pending_at: row.get(4)?,
+                idle_secs,
+                last_message_at: row.get(6)?,
+                last_message_id: row.get(7)?,
+                digested_count: row.get(8)?,
+                last_digest_at: row.get(9)?,
+                last_failed_at: row.get(11)?,
+                due,
+                rate_limited,
+            })
+        })
+        .map_err(|e| format!("Query room status failed: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(rows)
+}
+
+/// Rooms to digest now: due, not rate limited, fullest queues first.
+pub fn due_rooms(db: &RagDb, policy: &SchedulerPolicy) -> Result<Vec<RoomQueueStatus>, String> {
+    let mut rooms: Vec<RoomQueueStatus> = room_status(db, None, policy)?
+        .into_iter()
+        .filter(|r| r.due.is_some() && !r.rate_limited)
+        .collect();
+    rooms.sort_by_key(|r| std::cmp::Reverse(r.pending_count));
+    rooms.truncate(policy.max_rooms_per_tick);
+    Ok(rooms)
+}
+
+// ── Digest ─────────────────────────────────────────────
+
+/// Digest everything queued for a room, store it and advance the watermark.
+///
+/// Returns `None` when nothing is queued. Messages queued while the LLM call
+/// runs stay in the queue for the next digest. Without a usable `llm`, or
+/// with an unreachable one and `unreachable` = `Fallback`, the rule-based
+/// digest is stored instead (see `digest::analyze_or_fallback`); a failed call
+/// leaves the queue and watermark untouched.
+pub async fn digest_room(
+    db: &RagDb,
+    room_id: &str,
+    llm: Result<&LlmClient, &str>,
+    unreachable: WhenUnreachable,
+) -> Result<Option<RoomDigest>, String> {
+    if !claim(db, room_id)? {
+        let known = {
+            let conn = db.conn();
+            conn.query_row("SELECT 1 FROM digest_watermarks WHERE room_id = ?1", [room_id], |_| Ok(()))
+                .optional()
+                .map_err(|e| format!("Load watermark failed: {}", e))?
+                .is_some()
+        };
+        return if known {
+            Err(format!("Room {} is already being digested", room_id))
+        } else {
+            Ok(None)
+        };
+    }
+
+    let result = digest_claimed(db, room_id, llm, unreachable).await;
+    let conn = db.conn();
+    conn.execute("UPDATE digest_watermarks SET digest_started_at = NULL WHERE room_id = ?1", [room_id])
+        .map_err(|e| format!("Release room failed: {}", e))?;
+    result
+}
+
+async fn digest_claimed(
+    db: &RagDb,
+    room_id: &str,
+    llm: Result<&LlmClient, &str>,
+    unreachable: WhenUnreachable,
+) -> Result<Option<RoomDigest>, String> {
+    let (rows, project_id) = {
+        let conn = db.conn();
+        let project_id: Option<String> = conn
+            .query_row("SELECT project_id FROM digest_watermarks WHERE room_id = ?1", [room_id], |row| row.get(0))
+            .map_err(|e| format!("Load watermark failed: {}", e))?;
+        (pending_messages(&conn, room_id)?, project_id)
+    };
+    let (row_ids, messages): (Vec<i64>, Vec<ChatMessage>) = rows.into_iter().unzip();
+    let Some(range) = MessageRange::of(&messages) else {
+        return Ok(None);
+    };
+
+    let prompts = prompts::for_scope(db, Some(room_id), project_id.as_deref())?;
+    let digest = digest::analyze_or_fallback(&messages, llm, &prompts, unreachable).await?;
+    let stored_ids = digest::store_digest(db, room_id, project_id.as_deref(), &digest, &range)?;
+
+    let last = messages.last().expect("non-empty range");
+    {
+        let conn = db.conn();
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(|e| format!("Begin watermark update failed: {}", e))?;
+        tx.execute(
+            "UPDATE digest_watermarks SET
+                last_message_at = ?2,
+                last_message_id = ?3,
+                digested_count = digested_count + ?4,
+                last_digest_at = datetime('now'),
+                last_failed_at = NULL,
+                due_notified_at = NULL,
+                updated_at = datetime('now')
+             WHERE room_id = ?1",
+            rusqlite::params![room_id, last.created_at, last.id, messages.len() as i64],
+        )
+        .map_err(|e| format!("Advance watermark failed: {}", e))?;
+        for id in &row_ids {
+            tx.execute("DELETE FROM digest_queue WHERE id = ?1", [id])
+                .map_err(|e| format!("Dequeue failed: {}", e))?;
+        }
+        tx.commit().map_err(|e| format!("Commit watermark update failed: {}", e))?;
+    }
+
+    log::info!("Digested {} queued messages of room {}", messages.len(), room_id);
+    Ok(Some(RoomDigest {
+        room_id: room_id.to_string(),
+        digest,
+        stored_ids,
+        range,
+    }))
+}
+
+/// Mark the room as being digested (false if another digest holds it or the room is unknown).
+fn claim(db: &RagDb, room_id: &str) -> Result<bool, String> {
+    let conn = db.conn();
+    let claimed = conn
+        .execute(
+            "UPDATE digest_watermarks SET digest_started_at = datetime('now')
+             WHERE room_id = ?1
+               AND (digest_started_at IS NULL
+                    OR (julianday('now') - julianday(digest_started_at)) * 86400 > ?2)",
+            rusqlite::params![room_id, STALE_CLAIM_SECS],
+        )
+        .map_err(|e| format!("Claim room failed: {}", e))?;
+    Ok(claimed > 0)
+}
+
+// ── Scheduler ──────────────────────────────────────────
+
+/// One scheduler tick: digest due rooms, or announce them when the digest
+/// provider needs the frontend's API key. Returns the number of rooms digested.
+pub async fn run_due(db: &Arc<RagDb>, http: &Arc<ResilientHttp>, policy: &SchedulerPolicy) -> Result<usize, String> {
+    let due = due_rooms(db, policy)?;
+    if due.is_empty() {
+        return Ok(0);
+    }
+
+    let mut digested = 0;
+    for room in due {
+        let llm = LlmClient::for_task(db, http, LlmTask::Digest, None)
+            .map(|llm| llm.with_usage("scheduled_digest", room.project_id.as_deref(), Some(&room.room_id)));
+        match &llm {
+            // An unreachable provider is retried later rather than digested by the rules
+            Ok(llm) => match digest_room(db, &room.room_id, Ok(llm), WhenUnreachable::Fail).await {
+                Ok(Some(_)) => digested += 1,
+                Ok(None) => {}
+                Err(e) => {
+                    log::warn!("Scheduled digest of room {} failed: {}", room.room_id, e);
+                    mark_failed(db, &room.room_id)?;
+                }
+            },
+            Err(_) => {
+                if mark_notified(db, &room.room_id, policy.min_interval_secs)? {
+                    db.emit(RagEvent::DigestDue(room));
+                }
+            }
+        }
+    }
+    Ok(digested)
+}
+
+/// Record a failed scheduled digest; the room waits `min_interval_secs` before the next try.
+fn mark_failed(db: &RagDb, room_id: &str) -> Result<(), String> {
+    let conn = db.conn();
+    conn.execute(
+        "UPDATE digest_watermarks SET last_failed_at = datetime('now') WHERE room_id = ?1",
+        [room_id],
+    )
+    .map_err(|e| format!("Record digest failure failed: {}", e))?;
+    Ok(())
+}
+
+/// Record a DigestDue announcement; false if the room was announced recently.
+fn mark_notified(db: &RagDb, room_id: &str, interval_secs: i64) -> Result<bool, String> {
+    let conn = db.conn();
+    let updated = conn
+        .execute(
+            "UPDATE digest_watermarks SET due_notified_at = datetime('now')
+             WHERE room_id = ?1
+               AND (due_notified_at IS NULL
+                    OR (julianday('now') - julianday(due_notified_at)) * 86400 >= ?2)",
+            rusqlite::params![room_id, interval_secs],
+        )
+        .map_err(|e| format!("Mark digest due failed: {}", e))?;
+    Ok(updated > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::llm::MockProvider;
+    use std::sync::Mutex;
+    use uuid::Uuid;
+
+    fn setup() -> RagDb {
+        let dir = std::env::temp_dir().join(format!("rag_scheduler_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        RagDb::open(&dir.join("test.db")).unwrap()
+    }
+
+    fn message(id: Option<&str>, minute: u32, content: &str) -> ChatMessage {
+        ChatMessage {
+            id: id.map(String::from),
+            user_id: "u1".to_string(),
+            user_name: "김PD".to_string(),
+            content: content.to_string(),
+            created_at: format!("2026-03-02T10:{:02}:00Z", minute),
+        }
+    }
+
+    fn mock_llm() -> LlmClient {
+        let reply = r#"{"decisions": [{"text": "콘티 금요일 공유", "confidence": 0.8, "priority": "medium"}],
+                        "actionItems": [], "risks": [], "summary": "콘티 일정 논의"}"#;
+        LlmClient::new(Box::new(MockProvider::with_reply(reply)), "mock")
+    }
+
+    #[tokio::test]
+    async fn test_watermark_digests_only_new_messages() {
+        let db = setup();
+        let llm = mock_llm();
+        let first = vec![
+            message(Some("m1"), 0, "콘티는 금요일까지"),
+            message(Some("m2"), 1, "네 알겠습니다"),
+            message(None, 2, "아이디 없는 메시지"),
+        ];
+
+        let result = enqueue(&db, "room1", Some("p1"), &first).unwrap();
+        assert_eq!((result.queued, result.pending_count), (3, 3));
+        // Re-sending the same stream queues nothing new
+        let again = enqueue(&db, "room1", Some("p1"), &first).unwrap();
+        assert_eq!((again.queued, again.duplicates), (0, 3));
+
+        let done = digest_room(&db, "room1", Ok(&llm), WhenUnreachable::Fail).await.unwrap().unwrap();
+        assert_eq!(done.range.message_count, 3);
+        assert_eq!(done.range.end, "2026-03-02T10:02:00Z");
+        assert_eq!(done.stored_ids.len(), 4);
+        assert!(digest_room(&db, "room1", Ok(&llm), WhenUnreachable::Fail).await.unwrap().is_none());
+        assert!(digest_room(&db, "unknown", Ok(&llm), WhenUnreachable::Fail).await.unwrap().is_none());
+
+        // The whole stream again plus one new message: only the new one is queued
+        let mut stream = first.clone();
+        stream.push(message(Some("m4"), 3, "촬영은 다음 주"));
+        let result = enqueue(&db, "room1", None, &stream).unwrap();
+        assert_eq!((result.queued, result.already_digested), (1, 3));
+
+        let status = &room_status(&db, Some("room1"), &SchedulerPolicy::default()).unwrap()[0];
+        assert_eq!(status.project_id.as_deref(), Some("p1"));
+        assert_eq!(status.pending_count, 1);
+        assert_eq!(status.digested_count, 3);
+        assert_eq!(status.last_message_at.as_deref(), Some("2026-03-02T10:02:00Z"));
+        assert_eq!(status.due, None);
+
+        let done = digest_room(&db, "room1", Ok(&llm), WhenUnreachable::Fail).await.unwrap().unwrap();
+        assert_eq!(done.range.message_count, 1);
+        let stored = digest::get_recent_digests(&db, "room1", 10).unwrap();
+        assert_eq!(stored.len(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_failed_call_keeps_queue() {
+        let db = setup();
+        enqueue(&db, "room1", Some("p1"), &[message(Some("m1"), 0, "콘티는 금요일까지")]).unwrap();
+
+        // A reply that cannot be parsed is an error, not a rule-based digest
+        let broken = LlmClient::new(Box::new(MockProvider::with_reply("서버 오류")), "mock");
+        assert!(digest_room(&db, "room1", Ok(&broken), WhenUnreachable::Fail).await.is_err());
+        assert!(digest::get_recent_digests(&db, "room1", 10).unwrap().is_empty());
+        let status = &room_status(&db, Some("room1"), &SchedulerPolicy::default()).unwrap()[0];
+        assert_eq!((status.pending_count, status.digested_count), (1, 0));
+
+        let done = digest_room(&db, "room1", Ok(&mock_llm()), WhenUnreachable::Fail).await.unwrap().unwrap();
+        assert_eq!(done.range.message_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_thresholds_and_rate_limit() {
+        let db = Arc::new(setup());
+        let http = Arc::new(ResilientHttp::new(Default::default()).unwrap());
+        let policy = SchedulerPolicy {
+            min_messages: 3,
+            idle_secs: 600,
+            min_interval_secs: 900,
+            max_rooms_per_tick: 1,
+        };
+        let due_events = Arc::new(Mutex::new(Vec::new()));
+        let sink = due_events.clone();
+        db.subscribe(move |event| {
+            if let RagEvent::DigestDue(room) = event {
+                sink.lock().unwrap().push(room.room_id.clone());
+            }
+        });
+
+        let messages: Vec<ChatMessage> = (0..3).map(|m| message(None, m, "메시지")).collect();
+        enqueue(&db, "busy", None, &messages).unwrap();
+        enqueue(&db, "quiet", None, &messages[..1]).unwrap();
+        enqueue(&db, "fresh", None, &messages[..1]).unwrap();
+        {
+            let conn = db.conn();
+            conn.execute(
+                "UPDATE digest_queue SET queued_at = datetime('now', '-20 minutes') WHERE room_id = 'quiet'",
+                [],
+            )
+            .unwrap();
+        }
+
+        let statuses = room_status(&db, None, &policy).unwrap();
+        let due: Vec<(&str, Option<DueReason>)> = statuses.iter().map(|s| (s.room_id.as_str(), s.due)).collect();
+        assert_eq!(
+            due,
+            vec![("busy", Some(DueReason::MessageCount)), ("fresh", None), ("quiet", Some(DueReason::Idle))]
+        );
+        // One room per tick, fullest first
+        assert_eq!(due_rooms(&db, &policy).unwrap()[0].room_id, "busy");
+
+        // Default provider (Anthropic) needs the frontend's key: announce, once per interval
+        assert_eq!(run_due(&db, &http, &policy).await.unwrap(), 0);
+        assert_eq!(run_due(&db, &http, &policy).await.unwrap(), 0);
+        assert_eq!(*due_events.lock().unwrap(), vec!["busy"]);
+
+        // With a keyless provider the scheduler digests by itself
+        crate::rag::llm::set_settings(&db, LlmTask::Digest, crate::rag::llm::ProviderKind::Mock, "", None, None)
+            .unwrap();
+        assert_eq!(run_due(&db, &http, &policy).await.unwrap(), 1);
+        let busy = &room_status(&db, Some("busy"), &policy).unwrap()[0];
+        assert_eq!((busy.pending_count, busy.digested_count), (0, 3));
+
+        // Freshly digested rooms are rate limited
+        enqueue(&db, "busy", None, &[message(None, 10, "a"), message(None, 11, "b"), message(None, 12, "c")])
+            .unwrap();
+        let busy = &room_status(&db, Some("busy"), &policy).unwrap()[0];
+        assert!(busy.due.is_some() && busy.rate_limited);
+        assert_eq!(due_rooms(&db, &policy).unwrap()[0].room_id, "quiet");
+    }
+
+    #[tokio::test]
+    async fn test_failed_scheduled_digest_backs_off() {
+        let db = Arc::new(setup());
+        let http = Arc::new(
+            ResilientHttp::new(crate::rag::http::HttpPolicy { max_retries: 0, ..Default::default() }).unwrap(),
+        );
+        let policy = SchedulerPolicy { min_messages: 1, ..SchedulerPolicy::default() };
+        // A local server that is not running: every call fails to connect
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        crate::rag::llm::set_settings(
+            &db,
+            LlmTask::Digest,
+            crate::rag::llm::ProviderKind::OpenaiCompatible,
+            "llama3",
+            Some(&format!("http://{}/v1", closed)),
+            None,
+        )
+        .unwrap();
+        enqueue(&db, "room1", None, &[message(Some("m1"), 0, "콘티는 금요일까지")]).unwrap();
+
+        assert_eq!(run_due(&db, &http, &policy).await.unwrap(), 0);
+        let status = &room_status(&db, Some("room1"), &policy).unwrap()[0];
+        assert_eq!((status.pending_count, status.digested_count), (1, 0));
+        assert!(status.last_failed_at.is_some() && status.rate_limited);
+        assert!(due_rooms(&db, &policy).unwrap().is_empty());
+
+        // Once the interval has passed the room is tried again, and a success clears the failure
+        {
+            let conn = db.conn();
+            conn.execute("UPDATE digest_watermarks SET last_failed_at = datetime('now', '-20 minutes')", [])
+                .unwrap();
+        }
+        crate::rag::llm::set_settings(&db, LlmTask::Digest, crate::rag::llm::ProviderKind::Mock, "", None, None)
+            .unwrap();
+        assert_eq!(run_due(&db, &http, &policy).await.unwrap(), 1);
+        let status = &room_status(&db, Some("room1"), &policy).unwrap()[0];
+        assert_eq!(status.last_failed_at, None);
+    }
+}
@@ -0,0 +1,646 @@
+/// Project Context Snapshots — Precompiled project state for chat turns
+///
+/// Compiles a project's current decisions, open risks, action items, recent
+/// digest summaries and key knowledge into one JSON document stored in
+/// `context_snapshots` with a TTL. `rag_get_context` prepends it whenever a
+/// `project_id` is given, instead of rediscovering project state by search.
+///
+/// A snapshot is dropped as soon as the project is written to (knowledge
+/// change events, stored digests) and regenerated on the next read.
+
+use crate::rag::context::{BuiltContext, ContextFormat, ContextOptions};
+use crate::rag::db::RagDb;
+use crate::rag::events::RagEvent;
+use crate::rag::ontology::TermTrait;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Snapshot lifetime
+pub const DEFAULT_TTL_SECS: i64 = 15 * 60;
+
+/// Maximum entries per snapshot section
+const MAX_SECTION_ITEMS: usize = 5;
+
+/// Entry text is cut to this many characters
+const MAX_ENTRY_CHARS: usize = 160;
+
+/// One line of a snapshot section
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    /// "knowledge" or "digest"
+    pub source: String,
+    /// Knowledge item or chat digest id
+    pub source_id: String,
+    pub text: String,
+    pub confidence: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decision_maker: Option<String>,
+}
+
+/// Compiled project state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectSnapshot {
+    pub project_id: String,
+    pub decisions: Vec<SnapshotEntry>,
+    pub open_risks: Vec<SnapshotEntry>,
+    pub action_items: Vec<SnapshotEntry>,
+    pub recent_digests: Vec<SnapshotEntry>,
+    pub key_knowledge: Vec<SnapshotEntry>,
+    pub generated_at: String,
+    pub expires_at: String,
+}
+
+impl ProjectSnapshot {
+    pub fn is_empty(&self) -> bool {
+        self.decisions.is_empty()
+            && self.open_risks.is_empty()
+            && self.action_items.is_empty()
+            && self.recent_digests.is_empty()
+            && self.key_knowledge.is_empty()
+    }
+
+    /// Drop the last entry of the least important non-empty section.
+    fn drop_last_entry(&mut self) {
+        for section in [
+            &mut self.key_knowledge,
+            &mut self.recent_digests,
+            &mut self.action_items,
+            &mut self.open_risks,
+            &mut self.decisions,
+        ] {
+            if section.pop().is_some() {
+                return;
+            }
+        }
+    }
+
+    fn sections(&self) -> [(&'static str, &'static str, &[SnapshotEntry]); 5] {
+        [
+            ("decisions", "주요 결정", &self.decisions),
+            ("open_risks", "미해결 리스크", &self.open_risks),
+            ("action_items", "액션 아이템", &self.action_items),
+            ("recent_digests", "최근 대화 요약", &self.recent_digests),
+            ("key_knowledge", "핵심 지식", &self.key_knowledge),
+        ]
+    }
+}
+
+/// Stored snapshot if still valid, otherwise a freshly generated (and stored) one.
+pub fn get_or_generate(db: &RagDb, project_id: &str, ttl_secs: i64) -> Result<ProjectSnapshot, String> {
+    if let Some(snapshot) = load(db, project_id)? {
+        return Ok(snapshot);
+    }
+    generate(db, project_id, ttl_secs)
+}
+
+/// Compile and store a new snapshot, replacing any previous one.
+pub fn generate(db: &RagDb, project_id: &str, ttl_secs: i64) -> Result<ProjectSnapshot, String> {
+    let conn = db.conn();
+    let (generated_at, expires_at): (String, String) = conn
+        .query_row(
+            "SELECT datetime('now'), datetime('now', ?1)",
+            [format!("{:+} seconds", ttl_secs)],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("Snapshot clock failed: {}", e))?;
+
+    let decisions = knowledge_entries(
+        &conn,
+        project_id,
+        &format!(
+            "k.knowledge_type IN ({}) AND COALESCE(k.outcome, '') != 'rejected'",
+            TermTrait::Decision.types_sql()
+        ),
+        "julianday(k.updated_at) DESC",
+        MAX_SECTION_ITEMS,
+    )?;
+    let mut open_risks = knowledge_entries(
+        &conn,
+        project_id,
+        "(k.knowledge_type = 'recurring_risk' OR k.dialectic_tag = 'risk')
+         AND COALESCE(k.outcome, 'pending') IN ('pending', 'escalated')",
+        "k.confidence DESC, julianday(k.updated_at) DESC",
+        MAX_SECTION_ITEMS,
+    )?;
+    let mut action_items = knowledge_entries(
+        &conn,
+        project_id,
+        "k.knowledge_type = 'workflow' AND COALESCE(k.outcome, 'pending') IN ('pending', 'escalated')",
+        "julianday(k.updated_at) DESC",
+        MAX_SECTION_ITEMS,
+    )?;
+    fill_from_digests(&conn, project_id, "risks", &mut open_risks)?;
+    fill_from_digests(&conn, project_id, "action_items", &mut action_items)?;
+    let recent_digests = digest_summaries(&conn, project_id)?;
+
+    let listed: HashSet<String> = decisions
+        .iter()
+        .chain(&open_risks)
+        .chain(&action_items)
+        .map(|e| e.source_id.clone())
+        .collect();
+    // Over-fetch so items already listed above can be skipped
+    let key_knowledge: Vec<SnapshotEntry> = knowledge_entries(
+        &conn,
+        project_id,
+        "1 = 1",
+        "k.confidence * k.relevance_score DESC, k.usage_count DESC",
+        MAX_SECTION_ITEMS * 4,
+    )?
+    .into_iter()
+    .filter(|e| !listed.contains(&e.source_id))
+    .take(MAX_SECTION_ITEMS)
+    .collect();
+
+    let snapshot = ProjectSnapshot {
+        project_id: project_id.to_string(),
+        decisions,
+        open_risks,
+        action_items,
+        recent_digests,
+        key_knowledge,
+        generated_at,
+        expires_at,
+    };
+
+    let data = serde_json::to_string(&snapshot).map_err(|e| format!("Serialize snapshot failed: {}", e))?;
+    conn.execute(
+        "INSERT INTO context_snapshots (project_id, snapshot_data, generated_at, expires_at)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(project_id) DO UPDATE SET
+            snapshot_data = excluded.snapshot_data,
+            generated_at = excluded.generated_at,
+            expires_at = excluded.expires_at",
+        rusqlite::params![project_id, data, snapshot.generated_at, snapshot.expires_at],
+    )
+    .map_err(|e| format!("Store snapshot failed: {}", e))?;
+
+    Ok(snapshot)
+}
+
+/// Drop a project's snapshot (next read regenerates it) and emit
+/// `RagEvent::SnapshotInvalidated` so cached contexts embedding it are dropped too.
+pub fn invalidate(db: &RagDb, project_id: &str) -> Result<(), String> {
+    invalidate_with(&db.conn(), project_id)?;
+    db.emit(RagEvent::SnapshotInvalidated(project_id.to_string()));
+    Ok(())
+}
+
+/// `invalidate` for callers already holding the connection; they must emit
+/// `RagEvent::SnapshotInvalidated` themselves once the connection is released.
+pub(crate) fn invalidate_with(conn: &Connection, project_id: &str) -> Result<(), String> {
+    conn.execute("DELETE FROM context_snapshots WHERE project_id = ?1", [project_id])
+        .map_err(|e| format!("Invalidate snapshot failed: {}", e))?;
+    Ok(())
+}
+
+/// Listener entry point for `RagDb::subscribe`: drop snapshots of changed projects.
+pub fn on_event(db: &RagDb, event: &RagEvent) {
+    if let RagEvent::KnowledgeChanged(change) = event {
+        if let Some(project_id) = &change.project_id {
+            if let Err(e) = invalidate(db, project_id) {
+                log::warn!("{}", e);
+            }
+        }
+    }
+}
+
+/// Render a snapshot for prompt injection in the given format.
+pub fn render(snapshot: &ProjectSnapshot, format: ContextFormat) -> String {
+    match format {
+        ContextFormat::Markdown => {
+            let mut out = format!("## 프로젝트 현황 (기준 {})\n", snapshot.generated_at);
+            for (_, title, entries) in snapshot.sections() {
+                if entries.is_empty() {
+                    continue;
+                }
+                out.push_str(&format!("### {}\n", title));
+                for e in entries {
+                    out.push_str(&format!("- {}\n", e.text));
+                }
+            }
+            out
+        }
+        ContextFormat::Xml => {
+            let mut out = format!(
+                "<project_snapshot project_id=\"{}\" generated_at=\"{}\">\n",
+                xml_escape(&snapshot.project_id),
+                snapshot.generated_at
+            );
+            for (key, _, entries) in snapshot.sections() {
+                if entries.is_empty() {
+                    continue;
+                }
+                out.push_str(&format!("<{}>\n", key));
+                for e in entries {
+                    out.push_str(&format!("<item>{}</item>\n", xml_escape(&e.text)));
+                }
+                out.push_str(&format!("</{}>\n", key));
+            }
+            out.push_str("</project_snapshot>");
+            out
+        }
+        ContextFormat::Json => serde_json::to_string(snapshot).unwrap_or_default(),
+    }
+}
+
+/// Budget left for the knowledge sections once the snapshot is paid for.
+pub fn knowledge_options(
+    snapshot: &ProjectSnapshot,
+    options: &ContextOptions,
+    count_tokens: &dyn Fn(&str) -> usize,
+) -> ContextOptions {
+    if snapshot.is_empty() {
+        return options.clone();
+    }
+    let rendered = render(snapshot, options.format);
+    ContextOptions {
+        max_tokens: options.max_tokens.saturating_sub(count_tokens(&rendered)),
+        // +1 for the separating newline
+        max_chars: options.max_chars.map(|c| c.saturating_sub(rendered.chars().count() + 1)),
+        format: options.format,
+    }
+}
+
+/// Prepend a rendered snapshot to a built context, within the budget of `options`.
+///
+/// Entries are dropped from the end (least important section first) until the
+/// result fits; a snapshot with nothing left that fits is skipped.
+/// JSON contexts get a `project_snapshot` field so the output stays one document.
+pub fn prepend(
+    built: &mut BuiltContext,
+    snapshot: &ProjectSnapshot,
+    options: &ContextOptions,
+    count_tokens: &dyn Fn(&str) -> usize,
+) {
+    let char_cap = options.max_chars.unwrap_or(usize::MAX);
+    let mut snapshot = snapshot.clone();
+    while !snapshot.is_empty() {
+        let text = with_snapshot(&built.text, built.format, &snapshot);
+        let tokens = count_tokens(&text);
+        if tokens <= options.max_tokens && text.chars().count() <= char_cap {
+            built.text = text;
+            built.tokens_used = tokens;
+            return;
+        }
+        snapshot.drop_last_entry();
+    }
+}
+
+fn with_snapshot(text: &str, format: ContextFormat, snapshot: &ProjectSnapshot) -> String {
+    match format {
+        ContextFormat::Json => {
+            let mut doc: serde_json::Value = serde_json::from_str(text)
+                .unwrap_or_else(|_| serde_json::Value::Object(Default::default()));
+            if let Some(obj) = doc.as_object_mut() {
+                obj.insert(
+                    "project_snapshot".to_string(),
+                    serde_json::to_value(snapshot).unwrap_or_default(),
+                );
+            }
+            doc.to_string()
+        }
+        format if text.is_empty() => render(snapshot, format),
+        format => format!("{}\n{}", render(snapshot, format), text),
+    }
+}
+
+// ── Internal helpers ────────────────────────────────────
+
+fn load(db: &RagDb, project_id: &str) -> Result<Option<ProjectSnapshot>, String> {
+    let conn = db.conn();
+    let result = conn.query_row(
+        "SELECT snapshot_data FROM context_snapshots
+         WHERE project_id = ?1 AND expires_at > datetime('now')",
+        [project_id],
+        |row| row.get::<_, String>(0),
+    );
+
+    match result {
+        // Unreadable (older shape) snapshots are simply regenerated
+        Ok(data) => Ok(serde_json::from_str(&data).ok()),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(format!("Load snapshot failed: {}", e)),
+    }
+}
+
+fn knowledge_entries(
+    conn: &Connection,
+    project_id: &str,
+    condition: &str,
+    order_by: &str,
+    limit: usize,
+) -> Result<Vec<SnapshotEntry>, String> {
+    let sql = format!(
+        "SELECT k.id, COALESCE(k.summary, k.content), k.confidence, k.outcome, k.decision_maker
+         FROM knowledge_items k
+         WHERE k.is_active = 1
+           AND k.project_id = ?1
+           AND (k.expires_at IS NULL OR k.expires_at > datetime('now'))
+           AND {}
+         ORDER BY {}
+         LIMIT {}",
+        condition, order_by, limit
+    );
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| format!("Prepare snapshot query failed: {}", e))?;
+
+    let rows = stmt
+        .query_map([project_id], |row| {
+            Ok(SnapshotEntry {
+                source: "knowledge".to_string(),
+                source_id: row.get(0)?,
+                text: clip(&row.get::<_, String>(1)?),
+                confidence: row.get(2)?,
+                outcome: row.get(3)?,
+                decision_maker: row.get(4)?,
+            })
+        })
+        .map_err(|e| format!("Snapshot query failed: {}", e))?;
+
+    Ok(rows.filter_map(|r| r.ok()).collect())
+}
+
+/// Top up a section with items from the project's latest digests of `digest_type`.
+fn fill_from_digests(
+    conn: &Connection,
+    project_id: &str,
+    digest_type: &str,
+    entries: &mut Vec<SnapshotEntry>,
+) -> Result<(), String> {
+    if entries.len() >= MAX_SECTION_ITEMS {
+        return Ok(());
+    }
+
+    #[derive(Deserialize)]
+    struct DigestLine {
+        text: String,
+        #[serde(default)]
+        confidence: f64,
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, content FROM chat_digests
+             WHERE project_id = ?1 AND digest_type = ?2
+             ORDER BY julianday(created_at) DESC
+             LIMIT 3",
+        )
+        .map_err(|e| format!("Prepare digest query failed: {}", e))?;
+    let rows: Vec<(String, String)> = stmt
+        .query_map(rusqlite::params![project_id, digest_type], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Digest query failed: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    for (digest_id, content) in rows {
+        let lines: Vec<DigestLine> = serde_json::from_str(&content).unwrap_or_default();
+        for line in lines {
+            if entries.len() >= MAX_SECTION_ITEMS {
+                return Ok(());
+            }
+            let text = clip(&line.text);
+            if entries.iter().any(|e| e.text == text) {
+                continue;
+            }
+            entries.push(SnapshotEntry {
+                source: "digest".to_string(),
+                source_id: digest_id.clone(),
+                text,
+                confidence: line.confidence,
+                outcome: None,
+                decision_maker: None,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn digest_summaries(conn: &Connection, project_id: &str) -> Result<Vec<SnapshotEntry>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, content, confidence FROM chat_digests
+             WHERE project_id = ?1 AND digest_type = 'summary'
+             ORDER BY julianday(created_at) DESC
+             LIMIT 3",
+        )
+        .map_err(|e| format!("Prepare digest summaries failed: {}", e))?;
+
+    let rows = stmt
+        .query_map([project_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<f64>>(2)?))
+        })
+        .map_err(|e| format!("Digest summaries failed: {}", e))?;
+
+    Ok(rows
+        .filter_map(|r| r.ok())
+        .filter_map(|(id, content, confidence)| {
+            let value: serde_json::Value = serde_json::from_str(&content).ok()?;
+            let text = value.get("text")?.as_str()?.trim().to_string();
+            (!text.is_empty()).then(|| SnapshotEntry {
+                source: "digest".to_string(),
+                source_id: id,
+                text: clip(&text),
+                confidence: confidence.unwrap_or(0.8),
+                outcome: None,
+                decision_maker: None,
+            })
+        })
+        .collect())
+}
+
+fn clip(text: &str) -> String {
+    let text = text.trim();
+    if text.chars().count() <= MAX_ENTRY_CHARS {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(MAX_ENTRY_CHARS).collect();
+    format!("{}…", cut.trim_end())
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::digest::{self, DigestItem, DigestResult};
+    use crate::rag::embedding::EmbeddingEngine;
+    use crate::rag::knowledge::{self, KnowledgeItem};
+    use crate::rag::ontology;
+    use std::sync::{Arc, Mutex};
+    use uuid::Uuid;
+
+    fn setup() -> (Arc<RagDb>, EmbeddingEngine) {
+        let dir = std::env::temp_dir().join(format!("rag_snapshot_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Arc::new(RagDb::open(&dir.join("test.db")).unwrap());
+        let weak = Arc::downgrade(&db);
+        db.subscribe(move |event| {
+            if let Some(db) = weak.upgrade() {
+                on_event(&db, event);
+            }
+        });
+        (db, EmbeddingEngine::new(dir.join("models")))
+    }
+
+    fn create(db: &RagDb, engine: &EmbeddingEngine, content: &str, knowledge_type: &str, project: &str) -> String {
+        let item = KnowledgeItem {
+            knowledge_type: knowledge_type.to_string(),
+            confidence: 0.8,
+            user_id: Some("u1".to_string()),
+            project_id: Some(project.to_string()),
+            ..KnowledgeItem::for_test(content)
+        };
+        let vector = engine.embed(content).unwrap().vector;
+        knowledge::create_knowledge_item(db, &item, &vector).unwrap()
+    }
+
+    #[test]
+    fn test_snapshot_sections_and_invalidation() {
+        let (db, engine) = setup();
+        create(&db, &engine, "촬영지는 부산으로 확정", "decision_pattern", "p1");
+        create(&db, &engine, "우천 시 일정 지연 가능", "recurring_risk", "p1");
+        create(&db, &engine, "다른 프로젝트 결정", "decision_pattern", "p2");
+
+        let first = get_or_generate(&db, "p1", DEFAULT_TTL_SECS).unwrap();
+        assert_eq!(first.decisions.len(), 1);
+        assert_eq!(first.open_risks[0].text, "우천 시 일정 지연 가능");
+        assert!(first.key_knowledge.is_empty());
+
+        // Served from storage while valid
+        assert_eq!(get_or_generate(&db, "p1", DEFAULT_TTL_SECS).unwrap().generated_at, first.generated_at);
+
+        // Writes to another project leave it alone; writes to p1 drop it
+        create(&db, &engine, "p2 리스크", "recurring_risk", "p2");
+        assert!(load(&db, "p1").unwrap().is_some());
+        create(&db, &engine, "편집 일정 확인", "workflow", "p1");
+        assert!(load(&db, "p1").unwrap().is_none());
+
+        let digest = DigestResult {
+            decisions: vec![],
+            action_items: vec![DigestItem {
+                text: "콘티 공유".to_string(),
+                confidence: 0.9,
+                related_user_ids: vec![],
+                priority: "high".to_string(),
+            }],
+            risks: vec![],
+            summary: "촬영 준비 회의".to_string(),
+            windows: vec![],
+            discarded: vec![],
+            fallback: None,
+            prompt_version: None,
+        };
+        get_or_generate(&db, "p1", DEFAULT_TTL_SECS).unwrap();
+        let range = digest::MessageRange {
+            start: "2026-03-02T09:00:00Z".to_string(),
+            end: "2026-03-02T18:00:00Z".to_string(),
+            message_count: 12,
+        };
+        let invalidated: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let sink = invalidated.clone();
+        db.subscribe(move |event| {
+            if let RagEvent::SnapshotInvalidated(project_id) = event {
+                sink.lock().unwrap().push(project_id.clone());
+            }
+        });
+        digest::store_digest(&db, "room1", Some("p1"), &digest, &range).unwrap();
+        assert!(load(&db, "p1").unwrap().is_none());
+        assert_eq!(*invalidated.lock().unwrap(), vec!["p1".to_string()]);
+
+        let second = get_or_generate(&db, "p1", DEFAULT_TTL_SECS).unwrap();
+        let actions: Vec<&str> = second.action_items.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(actions, vec!["편집 일정 확인", "콘티 공유"]);
+        assert_eq!(second.recent_digests[0].text, "촬영 준비 회의");
+
+        // Organisation decision types count as decisions too
+        let kt = ontology::Dimension::KnowledgeType;
+        ontology::add_term(&db, kt, "music_licensing", "음원 라이선스", None, &[], &[TermTrait::Decision]).unwrap();
+        create(&db, &engine, "BGM은 라이선스 음원으로 확정", "music_licensing", "p3");
+        assert_eq!(get_or_generate(&db, "p3", DEFAULT_TTL_SECS).unwrap().decisions.len(), 1);
+    }
+
+    #[test]
+    fn test_expired_snapshot_is_regenerated_and_prepended() {
+        let (db, engine) = setup();
+        create(&db, &engine, "예산 상한 5천만원", "budget_decision", "p1");
+
+        generate(&db, "p1", -1).unwrap();
+        assert!(load(&db, "p1").unwrap().is_none());
+        let snapshot = get_or_generate(&db, "p1", DEFAULT_TTL_SECS).unwrap();
+
+        let count = |t: &str| t.chars().count();
+        let mut built = BuiltContext {
+            text: "{\"sections\":{}}".to_string(),
+            format: ContextFormat::Json,
+            citations: vec![],
+            tokens_used: 0,
+            omitted_count: 0,
+        };
+        prepend(&mut built, &snapshot, &ContextOptions::default(), &count);
+        let doc: serde_json::Value = serde_json::from_str(&built.text).unwrap();
+        assert_eq!(doc["project_snapshot"]["decisions"][0]["text"], "예산 상한 5천만원");
+        assert!(doc.get("sections").is_some());
+
+        let mut built = BuiltContext { text: String::new(), format: ContextFormat::Markdown, ..built };
+        prepend(&mut built, &snapshot, &ContextOptions::default(), &count);
+        assert!(built.text.starts_with("## 프로젝트 현황"));
+        assert!(built.text.contains("- 예산 상한 5천만원"));
+        assert_eq!(built.tokens_used, count(&built.text));
+    }
+
+    #[test]
+    fn test_snapshot_stays_within_budget() {
+        let (db, engine) = setup();
+        create(&db, &engine, "촬영지는 부산으로 확정", "decision_pattern", "p1");
+        create(&db, &engine, "예산 상한 5천만원", "budget_decision", "p1");
+        create(&db, &engine, "우천 시 일정 지연 가능", "recurring_risk", "p1");
+        create(&db, &engine, "편집 일정 확인", "workflow", "p1");
+        let snapshot = get_or_generate(&db, "p1", DEFAULT_TTL_SECS).unwrap();
+        let count = |t: &str| t.chars().count();
+        let full = count(&render(&snapshot, ContextFormat::Markdown));
+
+        // Knowledge fills whatever the snapshot leaves; the total never exceeds the caller's budget
+        let build = |options: &ContextOptions| {
+            let knowledge_options = knowledge_options(&snapshot, options, &count);
+            let text = "k".repeat(knowledge_options.max_tokens.min(knowledge_options.max_chars.unwrap_or(usize::MAX)).min(40));
+            let mut built = BuiltContext {
+                tokens_used: count(&text),
+                text,
+                format: ContextFormat::Markdown,
+                citations: vec![],
+                omitted_count: 0,
+            };
+            prepend(&mut built, &snapshot, options, &count);
+            built
+        };
+        for budget in [full + 41, full, full / 2, 20] {
+            let by_tokens = ContextOptions::with_limits(None, Some(budget), ContextFormat::Markdown);
+            let built = build(&by_tokens);
+            assert!(built.tokens_used <= by_tokens.max_tokens, "{} > {}", built.tokens_used, budget);
+
+            let by_chars = ContextOptions::with_limits(Some(budget), None, ContextFormat::Markdown);
+            let built = build(&by_chars);
+            assert!(built.text.chars().count() <= budget, "{} > {}", built.text.chars().count(), budget);
+        }
+
+        // Room for everything: whole snapshot, then the knowledge
+        let built = build(&ContextOptions::with_limits(None, Some(full + 41), ContextFormat::Markdown));
+        assert_eq!(built.text, format!("{}\n{}", render(&snapshot, ContextFormat::Markdown), "k".repeat(40)));
+        // Too big for the budget: clipped to the leading decisions
+        let built = build(&ContextOptions::with_limits(Some(full / 2), None, ContextFormat::Markdown));
+        assert!(built.text.starts_with("## 프로젝트 현황") && built.text.contains("### 주요 결정"));
+        assert!(!built.text.contains("### 액션 아이템"));
+        // Not even the header fits: skipped
+        assert!(!build(&ContextOptions::with_limits(None, Some(20), ContextFormat::Markdown)).text.contains("프로젝트 현황"));
+    }
+}
@@ -0,0 +1,452 @@
+/// Standing Queries — Saved searches that fire when matching knowledge arrives
+///
+/// A standing query stores its text, embedding, optional filters
+/// (project, knowledge_type, dialectic_tag, role_tag) and a similarity threshold.
+/// Every new knowledge item — from ingest, digest extraction or sync import —
+/// is evaluated against the active standing queries:
+///
+///   match = filters pass AND (cosine(query, item) >= threshold OR content contains query text)
+///
+/// Matches are recorded in `standing_query_matches` (once per query/item pair)
+/// and published as `RagEvent::StandingQueryMatched` for the frontend.
+
+use crate::rag::db::RagDb;
+use crate::rag::embedding::{blob_to_vector, cosine_similarity, vector_to_blob};
+use crate::rag::events::RagEvent;
+use crate::rag::knowledge::KnowledgeItem;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Default similarity threshold for new standing queries
+pub const DEFAULT_THRESHOLD: f64 = 0.55;
+
+/// Saved standing query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StandingQuery {
+    pub id: String,
+    pub name: String,
+    pub query_text: String,
+    pub user_id: Option<String>,
+    pub project_id: Option<String>,
+    pub knowledge_type: Option<String>,
+    pub dialectic_tag: Option<String>,
+    pub role_tag: Option<String>,
+    pub threshold: f64,
+    /// Also show a native notification on match
+    pub notify: bool,
+    pub is_active: bool,
+    pub match_count: i64,
+    pub last_matched_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Input for creating a standing query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewStandingQuery {
+    pub name: String,
+    pub query_text: String,
+    pub user_id: Option<String>,
+    pub project_id: Option<String>,
+    pub knowledge_type: Option<String>,
+    pub dialectic_tag: Option<String>,
+    pub role_tag: Option<String>,
+    pub threshold: f64,
+    pub notify: bool,
+}
+
+/// A knowledge item that matched a standing query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StandingMatch {
+    pub standing_query_id: String,
+    pub standing_query_name: String,
+    pub user_id: Option<String>,
+    pub knowledge_id: String,
+    pub content: String,
+    pub knowledge_type: String,
+    pub dialectic_tag: Option<String>,
+    pub project_id: Option<String>,
+    pub similarity: f64,
+    pub notify: bool,
+    pub matched_at: String,
+}
+
+const SELECT_COLUMNS: &str = "id, name, query_text, user_id, project_id, knowledge_type,
+        dialectic_tag, role_tag, threshold, notify, is_active, match_count,
+        last_matched_at, created_at, updated_at";
+
+fn row_to_standing_query(row: &rusqlite::Row) -> rusqlite::Result<StandingQuery> {
+    Ok(StandingQuery {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        query_text: row.get(2)?,
+        user_id: row.get(3)?,
+        project_id: row.get(4)?,
+        knowledge_type: row.get(5)?,
+        dialectic_tag: row.get(6)?,
+        role_tag: row.get(7)?,
+        threshold: row.get(8)?,
+        notify: row.get::<_, i32>(9)? != 0,
+        is_active: row.get::<_, i32>(10)? != 0,
+        match_count: row.get(11)?,
+        last_matched_at: row.get(12)?,
+        created_at: row.get(13)?,
+        updated_at: row.get(14)?,
+    })
+}
+
+/// Create a standing query with its query embedding.
+pub fn create_standing_query(
+    db: &RagDb,
+    query: &NewStandingQuery,
+    embedding: &[f32],
+) -> Result<StandingQuery, String> {
+    if query.query_text.trim().is_empty() {
+        return Err("Standing query text is empty".to_string());
+    }
+
+    let id = Uuid::new_v4().to_string();
+    {
+        let conn = db.conn();
+        conn.execute(
+            "INSERT INTO standing_queries (
+                id, name, query_text, embedding, user_id, project_id,
+                knowledge_type, dialectic_tag, role_tag, threshold, notify
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            rusqlite::params![
+                id,
+                query.name,
+                query.query_text,
+                vector_to_blob(embedding),
+                query.user_id,
+                query.project_id,
+                query.knowledge_type,
+                query.dialectic_tag,
+                query.role_tag,
+                query.threshold,
+                query.notify as i32,
+            ],
+        )
+        .map_err(|e| format!("Insert standing query failed: {}", e))?;
+    }
+
+    log::info!("Created standing query {} ({})", id, query.name);
+    get_standing_query(db, &id)?.ok_or_else(|| "Standing query not found after insert".to_string())
+}
+
+/// Get a standing query by ID.
+pub fn get_standing_query(db: &RagDb, id: &str) -> Result<Option<StandingQuery>, String> {
+    let conn = db.conn();
+    let result = conn.query_row(
+        &format!("SELECT {} FROM standing_queries WHERE id = ?1", SELECT_COLUMNS),
+        [id],
+        row_to_standing_query,
+    );
+
+    match result {
+        Ok(query) => Ok(Some(query)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(format!("Get standing query failed: {}", e)),
+    }
+}
+
+/// List standing queries, optionally only those owned by `user_id`.
+pub fn list_standing_queries(
+    db: &RagDb,
+    user_id: Option<&str>,
+) -> Result<Vec<StandingQuery>, String> {
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM standing_queries
+             WHERE (?1 IS NULL OR user_id = ?1)
+             ORDER BY created_at DESC",
+            SELECT_COLUMNS
+        ))
+        .map_err(|e| format!("List standing queries failed: {}", e))?;
+
+    let results: Vec<StandingQuery> = stmt
+        .query_map([user_id], row_to_standing_query)
+        .map_err(|e| format!("List standing queries failed: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(results)
+}
+
+/// Pause or resume a standing query.
+pub fn set_standing_query_active(db: &RagDb, id: &str, active: bool) -> Result<(), String> {
+    let conn = db.conn();
+    conn.execute(
+        "UPDATE standing_queries SET is_active = ?1, updated_at = datetime('now') WHERE id = ?2",
+        rusqlite::params![active as i32, id],
+    )
+    .map_err(|e| format!("Update standing query failed: {}", e))?;
+    Ok(())
+}
+
+/// Delete a standing query and its match log.
+pub fn delete_standing_query(db: &RagDb, id: &str) -> Result<(), String> {
+    let conn = db.conn();
+    conn.execute("DELETE FROM standing_query_matches WHERE standing_query_id = ?1", [id])
+        .map_err(|e| format!("Delete standing query matches failed: {}", e))?;
+    conn.execute("DELETE FROM standing_queries WHERE id = ?1", [id])
+        .map_err(|e| format!("Delete standing query failed: {}", e))?;
+    Ok(())
+}
+
+/// Recent matches for a standing query (newest first).
+pub fn get_matches(
+    db: &RagDb,
+    standing_query_id: &str,
+    limit: usize,
+) -> Result<Vec<StandingMatch>, String> {
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare(
+            "SELECT sq.id, sq.name, sq.user_id, ki.id, ki.content, ki.knowledge_type,
+                    ki.dialectic_tag, ki.project_id, m.similarity, sq.notify, m.created_at
+             FROM standing_query_matches m
+             JOIN standing_queries sq ON sq.id = m.standing_query_id
+             JOIN knowledge_items ki ON ki.id = m.knowledge_id
+             WHERE m.standing_query_id = ?1
+             ORDER BY m.created_at DESC
+             LIMIT ?2",
+        )
+        .map_err(|e| format!("Query standing matches failed: {}", e))?;
+
+    let results: Vec<StandingMatch> = stmt
+        .query_map(rusqlite::params![standing_query_id, limit as i64], |row| {
+            Ok(StandingMatch {
+                standing_query_id: row.get(0)?,
+                standing_query_name: row.get(1)?,
+                user_id: row.get(2)?,
+                knowledge_id: row.get(3)?,
+                content: row.get(4)?,
+                knowledge_type: row.get(5)?,
+                dialectic_tag: row.get(6)?,
+                project_id: row.get(7)?,
+                similarity: row.get(8)?,
+                notify: row.get::<_, i32>(9)? != 0,
+                matched_at: row.get(10)?,
+            })
+        })
+        .map_err(|e| format!("Query standing matches failed: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(results)
+}
+
+/// Evaluate a freshly stored knowledge item against all active standing queries.
+///
+/// Records new matches and emits `RagEvent::StandingQueryMatched` for each one.
+/// Must be called without holding the connection guard.
+pub fn evaluate_item(
+    db: &RagDb,
+    item: &KnowledgeItem,
+    embedding: &[f32],
+) -> Result<Vec<StandingMatch>, String> {
+    if !item.is_active || item.id.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut matches = Vec::new();
+    {
+        let conn = db.conn();
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {}, embedding FROM standing_queries WHERE is_active = 1",
+                SELECT_COLUMNS
+            ))
+            .map_err(|e| format!("Load standing queries failed: {}", e))?;
+
+        let queries: Vec<(StandingQuery, Vec<u8>)> = stmt
+            .query_map([], |row| Ok((row_to_standing_query(row)?, row.get(15)?)))
+            .map_err(|e| format!("Load standing queries failed: {}", e))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        let content_lower = item.content.to_lowercase();
+
+        for (query, embedding_blob) in queries {
+            if !passes_filters(&query, item) {
+                continue;
+            }
+
+            let query_vec = blob_to_vector(&embedding_blob);
+            let similarity = if query_vec.len() == embedding.len() && !embedding.is_empty() {
+                cosine_similarity(&query_vec, embedding) as f64
+            } else {
+                0.0
+            };
+
+            let needle = query.query_text.trim().to_lowercase();
+            let keyword_hit = !needle.is_empty() && content_lower.contains(&needle);
+
+            if similarity < query.threshold && !keyword_hit {
+                continue;
+            }
+
+            let inserted = conn
+                .execute(
+                    "INSERT OR IGNORE INTO standing_query_matches (id, standing_query_id, knowledge_id, similarity)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    rusqlite::params![Uuid::new_v4().to_string(), query.id, item.id, similarity],
+                )
+                .map_err(|e| format!("Record standing match failed: {}", e))?;
+
+            if inserted == 0 {
+                continue; // Already matched (e.g. re-imported via sync)
+            }
+
+            conn.execute(
+                "UPDATE standing_queries
+                 SET match_count = match_count + 1, last_matched_at = datetime('now')
+                 WHERE id = ?1",
+                [&query.id],
+            )
+            .map_err(|e| format!("Update standing query failed: {}", e))?;
+
+            matches.push(StandingMatch {
+                standing_query_id: query.id,
+                standing_query_name: query.name,
+                user_id: query.user_id,
+                knowledge_id: item.id.clone(),
+                content: item.content.clone(),
+                knowledge_type: item.knowledge_type.clone(),
+                dialectic_tag: item.dialectic_tag.clone(),
+                project_id: item.project_id.clone(),
+                similarity,
+                notify: query.notify,
+                matched_at: chrono::Utc::now().to_rfc3339(),
+            });
+        }
+    }
+
+    for m in &matches {
+        log::info!(
+            "Standing query '{}' matched knowledge item {} (sim {:.2})",
+            m.standing_query_name,
+            m.knowledge_id,
+            m.similarity
+        );
+        db.emit(RagEvent::StandingQueryMatched(m.clone()));
+    }
+
+    Ok(matches)
+}
+
+/// Filter + visibility check: personal items only match their owner's queries.
+fn passes_filters(query: &StandingQuery, item: &KnowledgeItem) -> bool {
+    if item.scope == "personal" && item.user_id.is_some() && item.user_id != query.user_id {
+        return false;
+    }
+    if query.project_id.is_some() && item.project_id != query.project_id {
+        return false;
+    }
+    if let Some(ref kt) = query.knowledge_type {
+        if item.knowledge_type != *kt {
+            return false;
+        }
+    }
+    if query.dialectic_tag.is_some() && item.dialectic_tag != query.dialectic_tag {
+        return false;
+    }
+    if query.role_tag.is_some() && item.role_tag != query.role_tag {
+        return false;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::embedding::EmbeddingEngine;
+    use crate::rag::knowledge;
+    use std::sync::{Arc, Mutex};
+
+    fn setup() -> (RagDb, EmbeddingEngine) {
+        let dir = std::env::temp_dir().join(format!("rag_standing_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        (RagDb::open(&dir.join("test.db")).unwrap(), EmbeddingEngine::new(dir.join("models")))
+    }
+
+    fn test_item(content: &str, project_id: Option<&str>, dialectic_tag: Option<&str>) -> KnowledgeItem {
+        KnowledgeItem {
+            knowledge_type: "recurring_risk".to_string(),
+            source_type: "test".to_string(),
+            dialectic_tag: dialectic_tag.map(|s| s.to_string()),
+            project_id: project_id.map(|s| s.to_string()),
+            ..KnowledgeItem::for_test(content)
+        }
+    }
+
+    #[test]
+    fn test_new_item_triggers_standing_query() {
+        let (db, embedding) = setup();
+        let received: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        db.subscribe(move |event| {
+            if let RagEvent::StandingQueryMatched(m) = event {
+                sink.lock().unwrap().push(m.knowledge_id.clone());
+            }
+        });
+
+        let query = NewStandingQuery {
+            name: "촬영 일정 리스크".to_string(),
+            query_text: "촬영 일정".to_string(),
+            user_id: Some("u1".to_string()),
+            project_id: Some("p1".to_string()),
+            knowledge_type: None,
+            dialectic_tag: Some("risk".to_string()),
+            role_tag: None,
+            threshold: 0.99,
+            notify: true,
+        };
+        let sq = create_standing_query(&db, &query, &embedding.embed(&query.query_text).unwrap().vector).unwrap();
+
+        // Matches: right project, risk tag, contains query text
+        let item = test_item("촬영 일정이 비 때문에 지연될 수 있음", Some("p1"), Some("risk"));
+        let vec = embedding.embed(&item.content).unwrap().vector;
+        let id = knowledge::create_knowledge_item(&db, &item, &vec).unwrap();
+
+        // Filtered out: other project / no risk tag
+        let other = test_item("촬영 일정 확정", Some("p2"), Some("risk"));
+        knowledge::create_knowledge_item(&db, &other, &embedding.embed(&other.content).unwrap().vector).unwrap();
+        let untagged = test_item("촬영 일정 공유", Some("p1"), None);
+        knowledge::create_knowledge_item(&db, &untagged, &embedding.embed(&untagged.content).unwrap().vector).unwrap();
+
+        assert_eq!(*received.lock().unwrap(), vec![id.clone()]);
+
+        let matches = get_matches(&db, &sq.id, 10).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].knowledge_id, id);
+        assert_eq!(get_standing_query(&db, &sq.id).unwrap().unwrap().match_count, 1);
+    }
+
+    #[test]
+    fn test_inactive_query_and_delete() {
+        let (db, embedding) = setup();
+        let query = NewStandingQuery {
+            name: "client".to_string(),
+            query_text: "현대자동차".to_string(),
+            user_id: None,
+            project_id: None,
+            knowledge_type: None,
+            dialectic_tag: None,
+            role_tag: None,
+            threshold: DEFAULT_THRESHOLD,
+            notify: false,
+        };
+        let sq = create_standing_query(&db, &query, &embedding.embed(&query.query_text).unwrap().vector).unwrap();
+        set_standing_query_active(&db, &sq.id, false).unwrap();
+
+        let item = test_item("현대자동차 캠페인 예산 변경", None, None);
+        knowledge::create_knowledge_item(&db, &item, &embedding.embed(&item.content).unwrap().vector).unwrap();
+        assert!(get_matches(&db, &sq.id, 10).unwrap().is_empty());
+
+        delete_standing_query(&db, &sq.id).unwrap();
+        assert!(list_standing_queries(&db, None).unwrap().is_empty());
+    }
+}
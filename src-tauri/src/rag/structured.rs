@@ -0,0 +1,378 @@
+/// Structured LLM Output — tolerant JSON parsing and per-item validation
+///
+/// Providers are asked for schema-shaped output (Anthropic tool use,
+/// OpenAI-compatible `response_format`), but local models and plain-text
+/// replies still drift. Parsing here never gives up on the whole reply for
+/// one bad item:
+/// - `parse_value` — strict parse first, then: take the first balanced JSON
+///   object out of surrounding prose / markdown fences and repair it
+///   (trailing commas, raw newlines inside strings, smart quotes)
+/// - `ItemValidator` — walks the arrays of a reply, keeps valid items,
+///   coerces numeric strings, and records every dropped item with the reason
+///   (`Discarded`), which is surfaced to the UI next to the result.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// An LLM output item rejected during validation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Discarded {
+    /// Location in the reply, e.g. `decisions[2]`
+    pub path: String,
+    pub reason: String,
+}
+
+// ── Parsing ────────────────────────────────────────────
+
+/// Parse the first JSON object in an LLM reply. `label` prefixes errors.
+pub fn parse_value(text: &str, label: &str) -> Result<Value, String> {
+    let trimmed = text.trim();
+    if let Ok(value @ Value::Object(_)) = serde_json::from_str::<Value>(trimmed) {
+        return Ok(value);
+    }
+
+    let candidate = extract_object(trimmed).unwrap_or(trimmed);
+    let repaired = repair(candidate);
+    match serde_json::from_str::<Value>(&repaired) {
+        Ok(value @ Value::Object(_)) => {
+            log::debug!("Repaired {} JSON from a {}-char reply", label, trimmed.len());
+            Ok(value)
+        }
+        Ok(_) => Err(format!("{} reply is not a JSON object — raw: {}", label, preview(trimmed))),
+        Err(e) => Err(format!("Failed to parse {} JSON: {} — raw: {}", label, e, preview(trimmed))),
+    }
+}
+
+/// The first balanced `{ … }` in `text` (string-aware). An unterminated object
+/// (reply cut off by max_tokens) returns everything from its opening brace.
+pub fn extract_object(text: &str) -> Option<&str> {
+    let start = text.find('{')?;
+    let mut depth = 0usize;
+    let mut open_quote: Option<char> = None;
+    let mut escaped = false;
+    for (i, c) in text[start..].char_indices() {
+        if let Some(quote) = open_quote {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                _ if closes_string(quote, c) => open_quote = None,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' | '\u{201C}' | '\u{201D}' => open_quote = Some(c),
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&text[start..start + i + 1]);
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(&text[start..])
+}
+
+/// Fix the mistakes models make most often. Unterminated strings, arrays and
+/// objects are closed so a truncated reply keeps its complete items.
+pub fn repair(json: &str) -> String {
+    let mut out = String::with_capacity(json.len() + 8);
+    let mut closers: Vec<char> = Vec::new();
+    let mut open_quote: Option<char> = None;
+    let mut escaped = false;
+
+    for c in json.chars() {
+        if let Some(quote) = open_quote {
+            match c {
+                _ if escaped => {
+                    escaped = false;
+                    out.push(c);
+                }
+                '\\' => {
+                    escaped = true;
+                    out.push(c);
+                }
+                _ if closes_string(quote, c) => {
+                    open_quote = None;
+                    out.push('"');
+                }
+                '\n' => out.push_str("\\n"),
+                '\r' => {}
+                '\t' => out.push_str("\\t"),
+                _ => out.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' | '\u{201C}' | '\u{201D}' => {
+                open_quote = Some(c);
+                out.push('"');
+            }
+            '{' => {
+                closers.push('}');
+                out.push(c);
+            }
+            '[' => {
+                closers.push(']');
+                out.push(c);
+            }
+            '}' | ']' => {
+                strip_trailing_comma(&mut out);
+                closers.pop();
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+
+    if open_quote.is_some() {
+        out.push('"');
+    }
+    // Truncated: drop a dangling key/value fragment, then close what is open
+    while let Some(closer) = closers.pop() {
+        drop_incomplete_tail(&mut out, closer);
+        strip_trailing_comma(&mut out);
+        out.push(closer);
+    }
+    out
+}
+
+/// Whether `c` ends a string opened with `quote`. Only `"` ends an ASCII-quoted
+/// string, so smart quotes in its text (“확정”) stay part of the value.
+fn closes_string(quote: char, c: char) -> bool {
+    match quote {
+        '"' => c == '"',
+        _ => matches!(c, '"' | '\u{201D}'),
+    }
+}
+
+fn strip_trailing_comma(out: &mut String) {
+    let trimmed = out.trim_end().len();
+    if out[..trimmed].ends_with(',') {
+        out.truncate(trimmed - 1);
+    }
+}
+
+/// After a cut-off: remove a dangling `"key":` or `"key` (object) fragment.
+fn drop_incomplete_tail(out: &mut String, closer: char) {
+    let trimmed = out.trim_end();
+    if let Some(before_colon) = trimmed.strip_suffix(':') {
+        if let Some(key_start) = before_colon.trim_end().rfind([',', '{']) {
+            out.truncate(key_start + 1);
+        }
+        return;
+    }
+    if closer != '}' {
+        return;
+    }
+    let Some(body) = trimmed.strip_suffix('"') else { return };
+    let mut search = body.len();
+    let open = loop {
+        match body[..search].rfind('"') {
+            Some(i) if body[..i].ends_with('\\') => search = i,
+            found => break found,
+        }
+    };
+    if let Some(open) = open {
+        let before = body[..open].trim_end();
+        if before.ends_with(',') || before.ends_with('{') {
+            out.truncate(before.len());
+        }
+    }
+}
+
+fn preview(text: &str) -> String {
+    let cut: String = text.chars().take(300).collect();
+    if cut.len() < text.len() {
+        format!("{}…", cut)
+    } else {
+        cut
+    }
+}
+
+// ── Validation ─────────────────────────────────────────
+
+/// Collects valid items from a parsed reply and records the rest.
+#[derive(Debug, Default)]
+pub struct ItemValidator {
+    pub discarded: Vec<Discarded>,
+}
+
+impl ItemValidator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse each element of `reply[key]`; invalid elements are recorded, not fatal.
+    /// A missing or null array is empty.
+    pub fn items<T>(&mut self, reply: &Value, key: &str, parse: impl Fn(&Value) -> Result<T, String>) -> Vec<T> {
+        let elements = match &reply[key] {
+            Value::Array(elements) => elements.as_slice(),
+            Value::Null => return Vec::new(),
+            // A lone object where a list was expected
+            single @ Value::Object(_) => std::slice::from_ref(single),
+            other => {
+                self.discard(key.to_string(), format!("expected an array, got {}", type_name(other)));
+                return Vec::new();
+            }
+        };
+        elements
+            .iter()
+            .enumerate()
+            .filter_map(|(i, element)| match parse(element) {
+                Ok(item) => Some(item),
+                Err(reason) => {
+                    self.discard(format!("{}[{}]", key, i), reason);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    pub fn discard(&mut self, path: String, reason: String) {
+        log::debug!("Discarded LLM output {}: {}", path, reason);
+        self.discarded.push(Discarded { path, reason });
+    }
+}
+
+/// Non-empty string field.
+pub fn required_str(item: &Value, key: &str) -> Result<String, String> {
+    match &item[key] {
+        Value::String(s) if !s.trim().is_empty() => Ok(s.trim().to_string()),
+        Value::String(_) => Err(format!("{} is empty", key)),
+        Value::Null => Err(format!("{} is missing", key)),
+        other => Err(format!("{} must be a string, got {}", key, type_name(other))),
+    }
+}
+
+/// Optional string field (empty / non-string → None).
+pub fn optional_str(item: &Value, key: &str) -> Option<String> {
+    item[key].as_str().map(str::trim).filter(|s| !s.is_empty()).map(String::from)
+}
+
+/// Array of strings (non-strings are skipped).
+pub fn str_list(item: &Value, key: &str) -> Vec<String> {
+    match &item[key] {
+        Value::Array(values) => values.iter().filter_map(|v| v.as_str()).map(String::from).collect(),
+        Value::String(s) if !s.is_empty() => vec![s.clone()],
+        _ => Vec::new(),
+    }
+}
+
+/// Number or numeric string ("0.8", "85%").
+pub fn coerce_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => {
+            let s = s.trim();
+            match s.strip_suffix('%') {
+                Some(pct) => pct.trim().parse::<f64>().ok().map(|p| p / 100.0),
+                None => s.parse::<f64>().ok(),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Confidence in 0..=1; percentages (1 < x ≤ 100) are scaled down.
+pub fn confidence(item: &Value, key: &str) -> Result<f64, String> {
+    let value = coerce_f64(&item[key]).ok_or_else(|| match &item[key] {
+        Value::Null => format!("{} is missing", key),
+        other => format!("{} is not a number: {}", key, other),
+    })?;
+    let value = if value > 1.0 && value <= 100.0 { value / 100.0 } else { value };
+    if !(0.0..=1.0).contains(&value) {
+        return Err(format!("{} out of range: {}", key, value));
+    }
+    Ok(value)
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_value_repairs_common_mistakes() {
+        // Prose + fences + trailing commas
+        let reply = "네, 분석 결과입니다:\n```json\n{\"items\": [{\"a\": 1,}, {\"a\": 2},],}\n```\n도움이 되길 바랍니다.";
+        assert_eq!(parse_value(reply, "test").unwrap()["items"][1]["a"], 2);
+
+        // Raw newline inside a string, smart quotes
+        let reply = "{\"summary\": \"첫 줄\n둘째 줄\", \u{201C}risks\u{201D}: []}";
+        let value = parse_value(reply, "test").unwrap();
+        assert_eq!(value["summary"], "첫 줄\n둘째 줄");
+        assert!(value["risks"].is_array());
+
+        // Smart quotes inside an ASCII-quoted string are text, not delimiters
+        let reply = "{\"decisions\": [{\"text\": \"예산 \u{201C}확정\u{201D} 처리\",}], \"summary\": \"\u{201D}끝\"}";
+        let value = parse_value(reply, "test").unwrap();
+        assert_eq!(value["decisions"][0]["text"], "예산 \u{201C}확정\u{201D} 처리");
+        assert_eq!(value["summary"], "\u{201D}끝");
+
+        // Truncated by max_tokens: complete items survive
+        let reply = r#"{"items": [{"content": "완성된 항목", "confidence": 0.9}, {"content": "잘린 항"#;
+        let value = parse_value(reply, "test").unwrap();
+        assert_eq!(value["items"][0]["content"], "완성된 항목");
+        let reply = r#"{"decisions": [{"text": "A"}], "summary":"#;
+        assert_eq!(parse_value(reply, "test").unwrap()["decisions"][0]["text"], "A");
+        let reply = r#"{"decisions": [{"text": "B", "confid"#;
+        assert_eq!(parse_value(reply, "test").unwrap()["decisions"][0], serde_json::json!({"text": "B"}));
+
+        // Braces inside strings do not end the object
+        let reply = r#"결과: {"text": "괄호 } 포함", "n": 1} 끝 {"other": true}"#;
+        assert_eq!(parse_value(reply, "test").unwrap()["n"], 1);
+
+        assert!(parse_value("JSON을 만들 수 없습니다", "test").is_err());
+        assert!(parse_value("[1, 2]", "test").is_err());
+    }
+
+    #[test]
+    fn test_item_validator_keeps_valid_items() {
+        let reply = serde_json::json!({
+            "items": [
+                {"content": "좋은 항목", "confidence": 0.8},
+                {"content": "문자열 숫자", "confidence": "0.7"},
+                {"content": "퍼센트", "confidence": "85%"},
+                {"content": "", "confidence": 0.9},
+                {"content": "범위 밖", "confidence": 7000},
+                {"confidence": 0.5},
+                "not an object"
+            ],
+            "risks": "none"
+        });
+
+        let mut validator = ItemValidator::new();
+        let items = validator.items(&reply, "items", |item| {
+            Ok((required_str(item, "content")?, confidence(item, "confidence")?))
+        });
+        assert_eq!(
+            items,
+            vec![
+                ("좋은 항목".to_string(), 0.8),
+                ("문자열 숫자".to_string(), 0.7),
+                ("퍼센트".to_string(), 0.85),
+            ]
+        );
+        let risks: Vec<String> = validator.items(&reply, "risks", |item| required_str(item, "text"));
+        assert!(risks.is_empty());
+        assert!(validator.items(&reply, "missing", |item| required_str(item, "text")).is_empty());
+
+        let paths: Vec<&str> = validator.discarded.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(paths, vec!["items[3]", "items[4]", "items[5]", "items[6]", "risks"]);
+        assert_eq!(validator.discarded[0].reason, "content is empty");
+        assert!(validator.discarded[1].reason.contains("out of range"));
+    }
+}
@@ -0,0 +1,380 @@
+/// Dialectic Synthesis (정반합) — From retrieved thesis/antithesis to a decision report
+///
+/// 1. 정 thesis: knowledge supporting the proposal (hybrid search)
+/// 2. 반 antithesis: risks, constraints and client concerns (dialectic search)
+/// 3. 합 synthesis: the LLM reconciles both into cited points + a recommendation
+///
+/// Every point carries the citation ids ([K1], …) it relies on, mapped back to
+/// knowledge ids. A synthesis can be kept as a `lesson_learned` item linked
+/// `derived_from` each cited source.
+
+use crate::rag::context::{self, BuiltContext, Citation, ContextFormat, ContextOptions, RetrievalSettings};
+use crate::rag::db::RagDb;
+use crate::rag::embedding::EmbeddingEngine;
+use crate::rag::knowledge::{self, KnowledgeItem};
+use crate::rag::llm::{LlmClient, LlmTask};
+use crate::rag::query;
+use crate::rag::relations::{self, RelationType};
+use crate::rag::structured::{self, ItemValidator};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Max tokens for the synthesis response
+const SYNTHESIS_MAX_TOKENS: u32 = 2048;
+
+/// Token budget for the two passes in the prompt
+const SYNTHESIS_CONTEXT_TOKENS: usize = 1200;
+
+/// Upper bound for the confidence of a stored synthesis
+const MAX_LESSON_CONFIDENCE: f64 = 0.8;
+
+const SYNTHESIS_SYSTEM_PROMPT: &str = r#"You are "Re-Be Brain", performing a 정반합 (thesis-antithesis-synthesis) review of a proposal for a Korean creative agency.
+
+You receive the proposal and the organization's recorded knowledge, split into 정 (supporting) and 반 (opposing: risks, constraints, client concerns) sections. Each knowledge item has a citation id like [K1].
+
+Respond ONLY with JSON (Korean text values):
+{
+  "thesis": [{"text": "지지 근거", "cites": ["K1"]}],
+  "antithesis": [{"text": "반대 근거/리스크", "cites": ["K3"]}],
+  "synthesis": [{"text": "두 관점을 조율한 결론", "cites": ["K1", "K3"]}],
+  "recommendation": "한 문장 권고"
+}
+
+Rules:
+1. Every point must cite at least one id from the provided knowledge; never invent ids
+2. If a side has no recorded knowledge, return an empty array for it
+3. Synthesis points must address the antithesis, not just restate the thesis
+4. Maximum 5 points per section"#;
+
+/// One cited point of the report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SynthesisPoint {
+    pub text: String,
+    pub cite_ids: Vec<String>,
+    pub knowledge_ids: Vec<String>,
+}
+
+/// Structured 정반합 report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SynthesisReport {
+    pub proposal: String,
+    pub thesis: Vec<SynthesisPoint>,
+    pub antithesis: Vec<SynthesisPoint>,
+    pub synthesis: Vec<SynthesisPoint>,
+    pub recommendation: String,
+    pub citations: Vec<Citation>,
+    /// Id of the stored `lesson_learned` item, if the synthesis was kept
+    pub lesson_id: Option<String>,
+}
+
+/// Retrieve the thesis and antithesis passes for a proposal.
+pub fn gather_passes(
+    db: &RagDb,
+    engine: &EmbeddingEngine,
+    proposal: &str,
+    base: query::SearchParams,
+) -> Result<BuiltContext, String> {
+    let embedding = engine.embed(proposal)?;
+    let params = query::SearchParams {
+        query_embedding: embedding.vector,
+        ..base
+    };
+    let settings = RetrievalSettings {
+        personal_limit: 0,
+        ..Default::default()
+    };
+    let sections = context::retrieve(db, &params, &settings)?;
+
+    let options = ContextOptions {
+        max_tokens: SYNTHESIS_CONTEXT_TOKENS,
+        max_chars: None,
+        format: ContextFormat::Markdown,
+    };
+    Ok(context::build_context(&sections, &options, &|text| engine.count_tokens(text)))
+}
+
+/// User prompt: the proposal followed by both passes.
+pub fn build_prompt(proposal: &str, passes: &BuiltContext) -> String {
+    let knowledge = if passes.text.is_empty() { "(축적된 관련 지식 없음)" } else { &passes.text };
+    format!("## 제안 (Proposal)\n{}\n\n{}", proposal.trim(), knowledge)
+}
+
+/// Ask the synthesis task's LLM for the report and parse it.
+pub async fn synthesize(proposal: &str, passes: &BuiltContext, llm: &LlmClient) -> Result<SynthesisReport, String> {
+    let reply = llm
+        .complete_json(
+            LlmTask::Synthesis,
+            SYNTHESIS_SYSTEM_PROMPT,
+            &build_prompt(proposal, passes),
+            SYNTHESIS_MAX_TOKENS,
+            report_schema(),
+        )
+        .await?;
+
+    Ok(parse_report(proposal, &reply, passes))
+}
+
+/// JSON schema of the synthesis reply (requested as structured output)
+fn report_schema() -> Value {
+    let points = serde_json::json!({
+        "type": "array",
+        "items": {
+            "type": "object",
+            "properties": {
+                "text": { "type": "string" },
+                "cites": { "type": "array", "items": { "type": "string" } }
+            },
+            "required": ["text", "cites"]
+        }
+    });
+    serde_json::json!({
+        "type": "object",
+        "properties": {
+            "thesis": points,
+            "antithesis": points,
+            "synthesis": points,
+            "recommendation": { "type": "string" }
+        },
+        "required": ["thesis", "antithesis", "synthesis", "recommendation"]
+    })
+}
+
+/// Build the report from the model's JSON, mapping citation ids to knowledge ids.
+///
+/// Points without text are dropped; unknown citation ids are dropped; points
+/// left without any citation are kept but carry no knowledge ids.
+pub fn parse_report(proposal: &str, reply: &Value, passes: &BuiltContext) -> SynthesisReport {
+    let by_cite: HashMap<&str, &str> = passes
+        .citations
+        .iter()
+        .map(|c| (c.cite_id.as_str(), c.knowledge_id.as_str()))
+        .collect();
+
+    let point = |raw: &Value| -> Result<SynthesisPoint, String> {
+        let text = structured::required_str(raw, "text")?;
+        let mut cite_ids = Vec::new();
+        let mut knowledge_ids = Vec::new();
+        for cite in structured::str_list(raw, "cites") {
+            let cite = cite.trim_matches(|c| c == '[' || c == ']').to_string();
+            if let Some(id) = by_cite.get(cite.as_str()) {
+                if !cite_ids.contains(&cite) {
+                    knowledge_ids.push(id.to_string());
+                    cite_ids.push(cite);
+                }
+            }
+        }
+        Ok(SynthesisPoint { text, cite_ids, knowledge_ids })
+    };
+
+    let mut validator = ItemValidator::new();
+    let report = SynthesisReport {
+        proposal: proposal.to_string(),
+        thesis: validator.items(reply, "thesis", point),
+        antithesis: validator.items(reply, "antithesis", point),
+        synthesis: validator.items(reply, "synthesis", point),
+        recommendation: structured::optional_str(reply, "recommendation").unwrap_or_default(),
+        citations: passes.citations.clone(),
+        lesson_id: None,
+    };
+    if !validator.discarded.is_empty() {
+        log::debug!("Synthesis: dropped {} point(s)", validator.discarded.len());
+    }
+    report
+}
+
+/// Store the synthesis as a `lesson_learned` item linked `derived_from` its sources.
+///
+/// Returns the new knowledge id (also set on `report.lesson_id`).
+pub fn store_as_lesson(
+    db: &RagDb,
+    engine: &EmbeddingEngine,
+    report: &mut SynthesisReport,
+    user_id: Option<&str>,
+    project_id: Option<&str>,
+) -> Result<String, String> {
+    if report.synthesis.is_empty() && report.recommendation.is_empty() {
+        return Err("Synthesis is empty — nothing to store".to_string());
+    }
+
+    let mut lines = Vec::new();
+    if !report.recommendation.is_empty() {
+        lines.push(report.recommendation.clone());
+    }
+    lines.extend(report.synthesis.iter().map(|p| format!("- {}", p.text)));
+    let content = lines.join("\n");
+
+    // Sources in citation order, each once
+    let mut sources: Vec<&Citation> = Vec::new();
+    for point in report.thesis.iter().chain(&report.antithesis).chain(&report.synthesis) {
+        for id in &point.knowledge_ids {
+            if !sources.iter().any(|c| &c.knowledge_id == id) {
+                if let Some(c) = report.citations.iter().find(|c| &c.knowledge_id == id) {
+                    sources.push(c);
+                }
+            }
+        }
+    }
+    let confidence = if sources.is_empty() {
+        0.5
+    } else {
+        (sources.iter().map(|c| c.confidence).sum::<f64>() / sources.len() as f64).min(MAX_LESSON_CONFIDENCE)
+    };
+
+    let source_context = serde_json::json!({
+        "proposal": report.proposal,
+        "sources": sources.iter().map(|c| &c.knowledge_id).collect::<Vec<_>>(),
+    });
+
+    let embed_result = engine.embed(&content)?;
+    let item = KnowledgeItem {
+        id: String::new(),
+        content,
+        summary: (!report.recommendation.is_empty()).then(|| report.recommendation.clone()),
+        knowledge_type: "lesson_learned".to_string(),
+        source_type: "dialectic_synthesis".to_string(),
+        scope: if project_id.is_some() { "team".to_string() } else { "personal".to_string() },
+        scope_layer: None,
+        role_tag: None,
+        dialectic_tag: None,
+        confidence,
+        relevance_score: 0.5,
+        usage_count: 0,
+        decision_maker: None,
+        outcome: None,
+        financial_impact_krw: None,
+        source_id: None,
+        source_context: Some(source_context.to_string()),
+        user_id: user_id.map(String::from),
+        project_id: project_id.map(String::from),
+        did_author: None,
+        is_active: true,
+        expires_at: None,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        updated_at: chrono::Utc::now().to_rfc3339(),
+    };
+    let id = knowledge::create_knowledge_item(db, &item, &embed_result.vector)?;
+
+    for source in &sources {
+        relations::link(db, &id, &source.knowledge_id, RelationType::DerivedFrom, Some("정반합 synthesis"), user_id)?;
+    }
+
+    report.lesson_id = Some(id.clone());
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn setup() -> (RagDb, EmbeddingEngine) {
+        let dir = std::env::temp_dir().join(format!("rag_synthesis_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        (RagDb::open(&dir.join("test.db")).unwrap(), EmbeddingEngine::new(dir.join("models")))
+    }
+
+    fn create(db: &RagDb, engine: &EmbeddingEngine, content: &str, dialectic_tag: Option<&str>) -> String {
+        let item = KnowledgeItem {
+            scope: "global".to_string(),
+            dialectic_tag: dialectic_tag.map(String::from),
+            confidence: 0.9,
+            ..KnowledgeItem::for_test(content)
+        };
+        let vector = engine.embed(content).unwrap().vector;
+        knowledge::create_knowledge_item(db, &item, &vector).unwrap()
+    }
+
+    fn citation(cite_id: &str, knowledge_id: &str, section: context::ContextSection) -> Citation {
+        Citation {
+            cite_id: cite_id.to_string(),
+            knowledge_id: knowledge_id.to_string(),
+            section,
+            knowledge_type: "decision_pattern".to_string(),
+            confidence: 0.9,
+            truncated: false,
+        }
+    }
+
+    #[test]
+    fn test_parse_report_maps_citations() {
+        let passes = BuiltContext {
+            text: "...".to_string(),
+            format: ContextFormat::Markdown,
+            citations: vec![
+                citation("K1", "a", context::ContextSection::Thesis),
+                citation("K2", "b", context::ContextSection::Antithesis),
+            ],
+            tokens_used: 0,
+            omitted_count: 0,
+        };
+        let raw = r#"```json
+        {"thesis": [{"text": "해외 촬영 경험 있음", "cites": ["K1", "K9"]}],
+         "antithesis": [{"text": "환율 리스크", "cites": ["[K2]"]}],
+         "synthesis": [{"text": "환율 버퍼 10% 확보 후 진행", "cites": ["K1", "K2", "K1"]}, {"text": " "}],
+         "recommendation": "조건부 진행"}
+        ```"#;
+
+        let reply = structured::parse_value(raw, "synthesis").unwrap();
+        let report = parse_report("해외 촬영", &reply, &passes);
+        assert_eq!(report.thesis[0].knowledge_ids, vec!["a"]);
+        assert_eq!(report.antithesis[0].cite_ids, vec!["K2"]);
+        assert_eq!(report.synthesis.len(), 1);
+        assert_eq!(report.synthesis[0].knowledge_ids, vec!["a", "b"]);
+        assert_eq!(report.recommendation, "조건부 진행");
+        assert!(structured::parse_value("not json", "synthesis").is_err());
+    }
+
+    #[test]
+    fn test_store_as_lesson_links_sources() {
+        let (db, engine) = setup();
+        let pro = create(&db, &engine, "해외 촬영은 현지 코디네이터와 함께", None);
+        let con = create(&db, &engine, "해외 촬영 시 환율 변동으로 예산 초과", Some("risk"));
+
+        let passes = gather_passes(
+            &db,
+            &engine,
+            "해외 촬영 진행",
+            query::SearchParams {
+                threshold: -1.0,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(build_prompt("해외 촬영 진행", &passes).starts_with("## 제안"));
+
+        let mut report = SynthesisReport {
+            proposal: "해외 촬영 진행".to_string(),
+            thesis: vec![],
+            antithesis: vec![],
+            synthesis: vec![SynthesisPoint {
+                text: "환율 버퍼를 두고 진행".to_string(),
+                cite_ids: vec!["K1".to_string(), "K2".to_string()],
+                knowledge_ids: vec![pro.clone(), con.clone()],
+            }],
+            recommendation: "조건부 진행".to_string(),
+            citations: vec![
+                citation("K1", &pro, context::ContextSection::Thesis),
+                citation("K2", &con, context::ContextSection::Antithesis),
+            ],
+            lesson_id: None,
+        };
+        let id = store_as_lesson(&db, &engine, &mut report, Some("u1"), None).unwrap();
+        assert_eq!(report.lesson_id.as_deref(), Some(id.as_str()));
+
+        let lesson = knowledge::get_knowledge_item(&db, &id).unwrap().unwrap();
+        assert_eq!(lesson.knowledge_type, "lesson_learned");
+        assert_eq!(lesson.confidence, MAX_LESSON_CONFIDENCE);
+
+        let mut targets: Vec<String> = relations::list_relations(&db, &id)
+            .unwrap()
+            .into_iter()
+            .filter(|r| r.relation_type == RelationType::DerivedFrom)
+            .map(|r| r.target_id)
+            .collect();
+        targets.sort();
+        let mut expected = vec![pro, con];
+        expected.sort();
+        assert_eq!(targets, expected);
+    }
+}
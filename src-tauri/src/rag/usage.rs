@@ -0,0 +1,584 @@
+/// LLM Usage Ledger — tokens, latency and cost of every provider call
+///
+/// `LlmClient` records one `llm_usage` row per provider call: provider,
+/// model, task (the feature), purpose (the calling command), project / room,
+/// input and output tokens, latency and status. Token counts come from the
+/// provider's `usage` block; servers that omit it get an estimate
+/// (`tokens_estimated`).
+///
+/// Cost is in USD from per-model prices: built-in Claude / OpenAI prices,
+/// overridden or extended by `llm_prices`. Unpriced models (local servers,
+/// mock) have no cost.
+///
+/// Budgets: `llm_budgets` holds a monthly USD limit per project. Before a
+/// call the projected spend (month so far + this call at `max_tokens`) is
+/// checked; `block` budgets refuse calls that would exceed the limit,
+/// `warn` budgets let them through. Crossing `BUDGET_WARN_RATIO` or the
+/// limit publishes `RagEvent::BudgetAlert`.
+
+use crate::rag::db::RagDb;
+use crate::rag::events::RagEvent;
+use crate::rag::llm::LlmTask;
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+
+/// Share of the monthly limit at which a warning is published
+pub const BUDGET_WARN_RATIO: f64 = 0.8;
+
+/// Built-in prices: (model prefix, USD per million input tokens, per million output tokens).
+/// First match wins, so more specific prefixes come first.
+const BUILTIN_PRICES: &[(&str, f64, f64)] = &[
+    ("claude-haiku-4", 1.0, 5.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("claude-3-haiku", 0.25, 1.25),
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-opus-4-5", 5.0, 25.0),
+    ("claude-opus-4", 15.0, 75.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4o", 2.5, 10.0),
+];
+
+// ── Types ──────────────────────────────────────────────
+
+/// Who a call is made for (set on the client with `LlmClient::with_usage`)
+#[derive(Debug, Clone, Default)]
+pub struct UsageContext {
+    /// Calling command, e.g. `digest`, `scheduled_digest`, `extract_from_digest`
+    pub purpose: String,
+    pub project_id: Option<String>,
+    pub room_id: Option<String>,
+}
+
+/// Tokens of one call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TokenUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    /// Not reported by the provider; estimated from the text
+    pub estimated: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallStatus {
+    Ok,
+    Error,
+    Cancelled,
+    /// Refused by a `block` budget (never sent)
+    Blocked,
+}
+
+impl CallStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CallStatus::Ok => "ok",
+            CallStatus::Error => "error",
+            CallStatus::Cancelled => "cancelled",
+            CallStatus::Blocked => "blocked",
+        }
+    }
+}
+
+/// One provider call to record
+#[derive(Debug, Clone)]
+pub struct CallRecord<'a> {
+    pub context: &'a UsageContext,
+    pub task: LlmTask,
+    pub provider: &'a str,
+    pub model: &'a str,
+    pub tokens: TokenUsage,
+    pub latency_ms: u64,
+    pub status: CallStatus,
+    pub error: Option<&'a str>,
+}
+
+/// Aggregation key for `summarize`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsageGroup {
+    Day,
+    Project,
+    Feature,
+}
+
+impl UsageGroup {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "day" => Ok(Self::Day),
+            "project" => Ok(Self::Project),
+            "feature" => Ok(Self::Feature),
+            _ => Err(format!("Invalid group_by: {} (expected day, project or feature)", s)),
+        }
+    }
+
+    fn column(&self) -> &'static str {
+        match self {
+            Self::Day => "date(created_at)",
+            Self::Project => "project_id",
+            Self::Feature => "task",
+        }
+    }
+}
+
+/// Aggregated usage of one day / project / feature
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageBucket {
+    /// Day (YYYY-MM-DD), project id or task; null = calls without a project
+    pub key: Option<String>,
+    pub calls: i64,
+    pub failed: i64,
+    pub blocked: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cost_usd: f64,
+    pub avg_latency_ms: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetMode {
+    Warn,
+    Block,
+}
+
+impl BudgetMode {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "warn" => Ok(Self::Warn),
+            "block" => Ok(Self::Block),
+            _ => Err(format!("Invalid budget mode: {} (expected warn or block)", s)),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Warn => "warn",
+            Self::Block => "block",
+        }
+    }
+}
+
+/// A project's monthly budget and this month's spend
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetStatus {
+    pub project_id: String,
+    /// YYYY-MM (UTC)
+    pub month: String,
+    pub monthly_limit_usd: f64,
+    pub mode: BudgetMode,
+    pub spent_usd: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertLevel {
+    /// Projected spend crossed `BUDGET_WARN_RATIO` of the limit
+    Warning,
+    /// Projected spend crossed the limit (the call was blocked for `block` budgets)
+    Exceeded,
+}
+
+/// Published when a call pushes a project's projected spend over a threshold
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetAlert {
+    pub budget: BudgetStatus,
+    pub level: AlertLevel,
+    /// Spend including the upcoming call (at its token limit)
+    pub projected_usd: f64,
+    pub task: LlmTask,
+    pub blocked: bool,
+}
+
+/// Price of a model in USD per million tokens
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelPrice {
+    pub model: String,
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+    /// false = built-in price
+    pub is_custom: bool,
+}
+
+// ── Recording ──────────────────────────────────────────
+
+/// Append a call to the ledger.
+pub fn record(db: &RagDb, call: &CallRecord) -> Result<(), String> {
+    let conn = db.conn();
+    let cost = cost_usd(&conn, call.model, call.tokens.input_tokens, call.tokens.output_tokens)?;
+    conn.execute(
+        "INSERT INTO llm_usage
+            (provider, model, task, purpose, project_id, room_id, input_tokens, output_tokens,
+             tokens_estimated, cost_usd, latency_ms, status, error)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        rusqlite::params![
+            call.provider,
+            call.model,
+            call.task.as_str(),
+            call.context.purpose,
+            call.context.project_id,
+            call.context.room_id,
+            call.tokens.input_tokens,
+            call.tokens.output_tokens,
+            call.tokens.estimated,
+            cost,
+            call.latency_ms as i64,
+            call.status.as_str(),
+            call.error,
+        ],
+    )
+    .map_err(|e| format!("Record LLM usage failed: {}", e))?;
+    Ok(())
+}
+
+/// Check the project's budget before a call of up to `input_tokens` +
+/// `max_output_tokens`. Errors when a `block` budget would be exceeded
+/// (the refusal is recorded in the ledger).
+pub fn check_budget(
+    db: &RagDb,
+    context: &UsageContext,
+    task: LlmTask,
+    provider: &str,
+    model: &str,
+    input_tokens: u32,
+    max_output_tokens: u32,
+) -> Result<(), String> {
+    let Some(project_id) = context.project_id.as_deref() else {
+        return Ok(());
+    };
+    let (budget, call_cost) = {
+        let conn = db.conn();
+        let Some(budget) = load_budget(&conn, project_id)? else {
+            return Ok(());
+        };
+        let call_cost = cost_usd(&conn, model, input_tokens, max_output_tokens)?.unwrap_or(0.0);
+        (budget, call_cost)
+    };
+
+    let projected = budget.spent_usd + call_cost;
+    let limit = budget.monthly_limit_usd;
+    let exceeded = projected > limit;
+    let blocked = exceeded && budget.mode == BudgetMode::Block;
+    let level = if exceeded && (blocked || budget.spent_usd <= limit) {
+        Some(AlertLevel::Exceeded)
+    } else if !exceeded && budget.spent_usd <= limit * BUDGET_WARN_RATIO && projected > limit * BUDGET_WARN_RATIO {
+        Some(AlertLevel::Warning)
+    } else {
+        None
+    };
+
+    if blocked {
+        let tokens = TokenUsage { input_tokens: 0, output_tokens: 0, estimated: false };
+        let reason = format!(
+            "Monthly LLM budget of project {} would be exceeded (${:.4} spent + ${:.4} for this call > ${:.2})",
+            project_id, budget.spent_usd, call_cost, limit
+        );
+        record(
+            db,
+            &CallRecord {
+                context,
+                task,
+                provider,
+                model,
+                tokens,
+                latency_ms: 0,
+                status: CallStatus::Blocked,
+                error: Some(&reason),
+            },
+        )?;
+        log::warn!("{}", reason);
+        db.emit(RagEvent::BudgetAlert(BudgetAlert { budget, level: AlertLevel::Exceeded, projected_usd: projected, task, blocked }));
+        return Err(reason);
+    }
+    if let Some(level) = level {
+        log::warn!("LLM budget of project {}: {:?} (${:.4} of ${:.2})", project_id, level, projected, limit);
+        db.emit(RagEvent::BudgetAlert(BudgetAlert { budget, level, projected_usd: projected, task, blocked }));
+    }
+    Ok(())
+}
+
+// ── Reports ────────────────────────────────────────────
+
+/// Usage grouped by day, project or feature, optionally since a date and for one project.
+pub fn summarize(
+    db: &RagDb,
+    group: UsageGroup,
+    since: Option<&str>,
+    project_id: Option<&str>,
+) -> Result<Vec<UsageBucket>, String> {
+    let conn = db.conn();
+    let sql = format!(
+        "SELECT {key}, COUNT(*),
+                SUM(status IN ('error', 'cancelled')), SUM(status = 'blocked'),
+                SUM(input_tokens), SUM(output_tokens), COALESCE(SUM(cost_usd), 0),
+                COALESCE(AVG(CASE WHEN status != 'blocked' THEN latency_ms END), 0)
+         FROM llm_usage
+         WHERE (?1 IS NULL OR created_at >= ?1) AND (?2 IS NULL OR project_id = ?2)
+         GROUP BY {key}
+         ORDER BY {key}",
+        key = group.column()
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| format!("Prepare usage summary failed: {}", e))?;
+    let buckets = stmt
+        .query_map(rusqlite::params![since, project_id], |row| {
+            Ok(UsageBucket {
+                key: row.get(0)?,
+                calls: row.get(1)?,
+                failed: row.get(2)?,
+                blocked: row.get(3)?,
+                input_tokens: row.get(4)?,
+                output_tokens: row.get(5)?,
+                cost_usd: row.get(6)?,
+                avg_latency_ms: row.get(7)?,
+            })
+        })
+        .map_err(|e| format!("Query usage summary failed: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(buckets)
+}
+
+// ── Budgets ────────────────────────────────────────────
+
+/// Set a project's monthly budget.
+pub fn set_budget(db: &RagDb, project_id: &str, monthly_limit_usd: f64, mode: BudgetMode) -> Result<BudgetStatus, String> {
+    if !monthly_limit_usd.is_finite() || monthly_limit_usd < 0.0 {
+        return Err("monthly_limit_usd must be zero or more".to_string());
+    }
+    let conn = db.conn();
+    conn.execute(
+        "INSERT INTO llm_budgets (project_id, monthly_limit_usd, mode) VALUES (?1, ?2, ?3)
+         ON CONFLICT(project_id) DO UPDATE SET
+            monthly_limit_usd = excluded.monthly_limit_usd,
+            mode = excluded.mode,
+            updated_at = datetime('now')",
+        rusqlite::params![project_id, monthly_limit_usd, mode.as_str()],
+    )
+    .map_err(|e| format!("Save LLM budget failed: {}", e))?;
+    load_budget(&conn, project_id)?.ok_or_else(|| "Budget not found after save".to_string())
+}
+
+pub fn remove_budget(db: &RagDb, project_id: &str) -> Result<bool, String> {
+    let conn = db.conn();
+    let removed = conn
+        .execute("DELETE FROM llm_budgets WHERE project_id = ?1", [project_id])
+        .map_err(|e| format!("Remove LLM budget failed: {}", e))?;
+    Ok(removed > 0)
+}
+
+/// Budgets with this month's spend (one project or all).
+pub fn budgets(db: &RagDb, project_id: Option<&str>) -> Result<Vec<BudgetStatus>, String> {
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare("SELECT project_id FROM llm_budgets WHERE ?1 IS NULL OR project_id = ?1 ORDER BY project_id")
+        .map_err(|e| format!("Prepare LLM budgets failed: {}", e))?;
+    let ids: Vec<String> = stmt
+        .query_map([project_id], |row| row.get(0))
+        .map_err(|e| format!("Query LLM budgets failed: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    let mut statuses = Vec::with_capacity(ids.len());
+    for id in ids {
+        statuses.extend(load_budget(&conn, &id)?);
+    }
+    Ok(statuses)
+}
+
+fn load_budget(conn: &Connection, project_id: &str) -> Result<Option<BudgetStatus>, String> {
+    conn.query_row(
+        "SELECT b.monthly_limit_usd, b.mode, strftime('%Y-%m', 'now'),
+                (SELECT COALESCE(SUM(u.cost_usd), 0) FROM llm_usage u
+                 WHERE u.project_id = b.project_id AND u.status != 'blocked'
+                   AND strftime('%Y-%m', u.created_at) = strftime('%Y-%m', 'now'))
+         FROM llm_budgets b WHERE b.project_id = ?1",
+        [project_id],
+        |row| {
+            Ok((
+                row.get::<_, f64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, f64>(3)?,
+            ))
+        },
+    )
+    .optional()
+    .map_err(|e| format!("Load LLM budget failed: {}", e))?
+    .map(|(limit, mode, month, spent)| {
+        Ok(BudgetStatus {
+            project_id: project_id.to_string(),
+            month,
+            monthly_limit_usd: limit,
+            mode: BudgetMode::parse(&mode)?,
+            spent_usd: spent,
+        })
+    })
+    .transpose()
+}
+
+// ── Prices ─────────────────────────────────────────────
+
+/// USD cost of a call, or `None` for unpriced models.
+fn cost_usd(conn: &Connection, model: &str, input_tokens: u32, output_tokens: u32) -> Result<Option<f64>, String> {
+    Ok(price_for(conn, model)?
+        .map(|(input, output)| (input_tokens as f64 * input + output_tokens as f64 * output) / 1_000_000.0))
+}
+
+/// Custom price for the exact model, else the first built-in prefix match.
+fn price_for(conn: &Connection, model: &str) -> Result<Option<(f64, f64)>, String> {
+    let custom = conn
+        .query_row(
+            "SELECT input_per_mtok, output_per_mtok FROM llm_prices WHERE model = ?1",
+            [model],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Load LLM price failed: {}", e))?;
+    Ok(custom.or_else(|| {
+        BUILTIN_PRICES
+            .iter()
+            .find(|(prefix, _, _)| model.starts_with(prefix))
+            .map(|(_, input, output)| (*input, *output))
+    }))
+}
+
+/// Built-in and custom prices.
+pub fn list_prices(db: &RagDb) -> Result<Vec<ModelPrice>, String> {
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare("SELECT model, input_per_mtok, output_per_mtok FROM llm_prices ORDER BY model")
+        .map_err(|e| format!("Prepare LLM prices failed: {}", e))?;
+    let mut prices: Vec<ModelPrice> = stmt
+        .query_map([], |row| {
+            Ok(ModelPrice {
+                model: row.get(0)?,
+                input_per_mtok: row.get(1)?,
+                output_per_mtok: row.get(2)?,
+                is_custom: true,
+            })
+        })
+        .map_err(|e| format!("Query LLM prices failed: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+    prices.extend(BUILTIN_PRICES.iter().map(|(model, input, output)| ModelPrice {
+        model: model.to_string(),
+        input_per_mtok: *input,
+        output_per_mtok: *output,
+        is_custom: false,
+    }));
+    Ok(prices)
+}
+
+/// Price a model (exact name), e.g. an OpenAI-compatible one; `None` removes the custom price.
+pub fn set_price(db: &RagDb, model: &str, price: Option<(f64, f64)>) -> Result<(), String> {
+    let model = model.trim();
+    if model.is_empty() {
+        return Err("model is required".to_string());
+    }
+    let conn = db.conn();
+    match price {
+        Some((input, output)) => {
+            if !(input >= 0.0 && output >= 0.0) {
+                return Err("Prices must be zero or more".to_string());
+            }
+            conn.execute(
+                "INSERT INTO llm_prices (model, input_per_mtok, output_per_mtok) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(model) DO UPDATE SET
+                    input_per_mtok = excluded.input_per_mtok,
+                    output_per_mtok = excluded.output_per_mtok,
+                    updated_at = datetime('now')",
+                rusqlite::params![model, input, output],
+            )
+        }
+        None => conn.execute("DELETE FROM llm_prices WHERE model = ?1", [model]),
+    }
+    .map_err(|e| format!("Save LLM price failed: {}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::llm::{LlmClient, MockProvider};
+    use std::sync::{Arc, Mutex};
+    use uuid::Uuid;
+
+    const HAIKU: &str = "claude-haiku-4-5-20251001";
+
+    fn setup() -> Arc<RagDb> {
+        let dir = std::env::temp_dir().join(format!("rag_usage_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        Arc::new(RagDb::open(&dir.join("test.db")).unwrap())
+    }
+
+    fn mock_client(db: &Arc<RagDb>, model: &str, project_id: &str) -> LlmClient {
+        LlmClient::new(Box::new(MockProvider::with_reply("요약입니다")), model)
+            .with_ledger(db.clone())
+            .with_usage("test", Some(project_id), Some("room1"))
+    }
+
+    #[tokio::test]
+    async fn test_ledger_and_summaries() {
+        let db = setup();
+        mock_client(&db, HAIKU, "p1").complete(LlmTask::Digest, "system", "대화 내용", 100).await.unwrap();
+        mock_client(&db, HAIKU, "p1").complete(LlmTask::Extraction, "system", "다이제스트", 100).await.unwrap();
+        mock_client(&db, "llama3.1", "p2").complete(LlmTask::Digest, "system", "대화", 100).await.unwrap();
+
+        let by_feature = summarize(&db, UsageGroup::Feature, None, None).unwrap();
+        let features: Vec<(Option<&str>, i64)> = by_feature.iter().map(|b| (b.key.as_deref(), b.calls)).collect();
+        assert_eq!(features, vec![(Some("digest"), 2), (Some("extraction"), 1)]);
+
+        let by_project = summarize(&db, UsageGroup::Project, None, None).unwrap();
+        assert_eq!(by_project.len(), 2);
+        assert!(by_project[0].cost_usd > 0.0 && by_project[0].input_tokens > 0);
+        // Unpriced (local) model: tokens counted, no cost
+        assert_eq!(by_project[1].cost_usd, 0.0);
+        assert!(by_project[1].output_tokens > 0);
+
+        let by_day = summarize(&db, UsageGroup::Day, None, Some("p1")).unwrap();
+        assert_eq!(by_day.len(), 1);
+        assert_eq!(by_day[0].calls, 2);
+
+        // Custom prices override built-ins
+        set_price(&db, "llama3.1", Some((0.5, 0.5))).unwrap();
+        mock_client(&db, "llama3.1", "p2").complete(LlmTask::Digest, "system", "대화", 100).await.unwrap();
+        let p2 = &summarize(&db, UsageGroup::Project, None, Some("p2")).unwrap()[0];
+        assert!(p2.cost_usd > 0.0);
+        assert!(list_prices(&db).unwrap().iter().any(|p| p.model == "llama3.1" && p.is_custom));
+    }
+
+    #[tokio::test]
+    async fn test_budgets_warn_and_block() {
+        let db = setup();
+        let alerts = Arc::new(Mutex::new(Vec::new()));
+        let sink = alerts.clone();
+        db.subscribe(move |event| {
+            if let RagEvent::BudgetAlert(alert) = event {
+                sink.lock().unwrap().push((alert.level, alert.blocked));
+            }
+        });
+
+        // 1000 output tokens of Haiku cost $0.005: over a $0.004 limit
+        set_budget(&db, "p1", 0.004, BudgetMode::Warn).unwrap();
+        mock_client(&db, HAIKU, "p1").complete(LlmTask::Digest, "system", "대화", 1000).await.unwrap();
+        assert_eq!(*alerts.lock().unwrap(), vec![(AlertLevel::Exceeded, false)]);
+
+        set_budget(&db, "p1", 0.004, BudgetMode::Block).unwrap();
+        let err = mock_client(&db, HAIKU, "p1").complete(LlmTask::Digest, "system", "대화", 1000).await.unwrap_err();
+        assert!(err.contains("budget"));
+        assert_eq!(alerts.lock().unwrap().last(), Some(&(AlertLevel::Exceeded, true)));
+        // Only the call that was sent is in the outbound log
+        let sent = crate::rag::policy::outbound_log(&db, Some(crate::rag::policy::Channel::Llm), 10).unwrap();
+        assert!(sent.len() == 1 && sent[0].allowed);
+
+        // Small calls still fit
+        mock_client(&db, HAIKU, "p2").complete(LlmTask::Digest, "system", "대화", 1000).await.unwrap();
+        set_budget(&db, "p1", 1.0, BudgetMode::Block).unwrap();
+        mock_client(&db, HAIKU, "p1").complete(LlmTask::Digest, "system", "대화", 100).await.unwrap();
+
+        let p1 = &summarize(&db, UsageGroup::Project, None, Some("p1")).unwrap()[0];
+        assert_eq!((p1.calls, p1.blocked), (3, 1));
+        let status = &budgets(&db, None).unwrap()[0];
+        assert!(status.spent_usd > 0.0 && status.spent_usd < 0.004);
+        assert!(remove_budget(&db, "p1").unwrap());
+    }
+}
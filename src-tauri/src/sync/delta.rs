@@ -5,8 +5,14 @@
 ///
 /// Delta format: JSON array of KnowledgeItem + embedding pairs.
 
+use crate::rag::conflicts;
 use crate::rag::db::RagDb;
 use crate::rag::embedding::blob_to_vector;
+use crate::rag::events::ChangeKind;
+use crate::rag::history;
+use crate::rag::knowledge::{self, KnowledgeItem};
+use crate::rag::ontology::{Dimension, Ontology, FALLBACK_KNOWLEDGE_TYPE};
+use crate::rag::standing;
 use serde::{Deserialize, Serialize};
 
 /// A single knowledge item with its embedding, ready for sync
@@ -159,24 +165,19 @@ pub fn apply_delta(
     db: &RagDb,
     delta: &SyncDelta,
 ) -> Result<(usize, usize), String> {
-    let conn = db.conn();
     let mut upserted = 0;
     let mut skipped = 0;
+    let mut applied: Vec<(SyncItem, Option<KnowledgeItem>)> = Vec::new();
+    let ontology = Ontology::load(&db.conn())?;
 
     for item in &delta.items {
         // Check if item exists locally
-        let local_updated: Option<String> = conn
-            .query_row(
-                "SELECT updated_at FROM knowledge_items WHERE id = ?1",
-                [&item.id],
-                |row| row.get(0),
-            )
-            .ok();
+        let local = knowledge::get_knowledge_item(db, &item.id)?;
 
-        let should_upsert = match &local_updated {
-            Some(local_ts) => {
+        let should_upsert = match &local {
+            Some(local) => {
                 // Last-Write-Wins: incoming is newer
-                item.updated_at > *local_ts
+                item.updated_at > local.updated_at
             }
             None => true, // New item
         };
@@ -186,9 +187,15 @@ pub fn apply_delta(
             continue;
         }
 
-        // Upsert knowledge item
+        if let Some(local) = &local {
+            history::ensure_baseline(db, local)?;
+        }
+        let item = localize_vocabulary(&ontology, item, local.as_ref());
+
+        // Upsert knowledge item (ON CONFLICT keeps the row, so embeddings/history don't cascade away)
+        let conn = db.conn();
         conn.execute(
-            "INSERT OR REPLACE INTO knowledge_items (
+            "INSERT INTO knowledge_items (
                 id, content, summary, knowledge_type, source_type,
                 scope, scope_layer, role_tag, dialectic_tag,
                 confidence, relevance_score, usage_count,
@@ -198,7 +205,20 @@ pub fn apply_delta(
             ) VALUES (
                 ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12,
                 ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24
-            )",
+            )
+            ON CONFLICT(id) DO UPDATE SET
+                content = excluded.content, summary = excluded.summary,
+                knowledge_type = excluded.knowledge_type, source_type = excluded.source_type,
+                scope = excluded.scope, scope_layer = excluded.scope_layer,
+                role_tag = excluded.role_tag, dialectic_tag = excluded.dialectic_tag,
+                confidence = excluded.confidence, relevance_score = excluded.relevance_score,
+                usage_count = excluded.usage_count, decision_maker = excluded.decision_maker,
+                outcome = excluded.outcome, financial_impact_krw = excluded.financial_impact_krw,
+                source_id = excluded.source_id, source_context = excluded.source_context,
+                user_id = excluded.user_id, project_id = excluded.project_id,
+                did_author = excluded.did_author, is_active = excluded.is_active,
+                expires_at = excluded.expires_at, created_at = excluded.created_at,
+                updated_at = excluded.updated_at",
             rusqlite::params![
                 item.id,
                 item.content,
@@ -237,10 +257,42 @@ pub fn apply_delta(
             )
             .map_err(|e| format!("Upsert embedding failed: {}", e))?;
         }
+        drop(conn);
 
+        applied.push((item, local));
         upserted += 1;
     }
 
+    // Version + notify listeners; new items from other devices are evaluated against
+    // standing queries too, and every incoming item is checked for conflicts locally
+    for (item, previous) in applied {
+        let knowledge_item = to_knowledge_item(&item);
+
+        match &previous {
+            Some(previous) => {
+                let mut fields = knowledge::changed_fields(previous, &knowledge_item);
+                if previous.is_active != knowledge_item.is_active {
+                    fields.push("is_active");
+                }
+                if !fields.is_empty() {
+                    history::record_version(db, &knowledge_item, "update", &fields, Some("sync"))?;
+                }
+                knowledge::notify_changed(db, &knowledge_item, ChangeKind::Updated);
+            }
+            None => {
+                knowledge::notify_changed(db, &knowledge_item, ChangeKind::Created);
+                if let Err(e) = standing::evaluate_item(db, &knowledge_item, &item.embedding) {
+                    log::warn!("Standing query evaluation failed for {}: {}", item.id, e);
+                }
+            }
+        }
+        if !item.embedding.is_empty() {
+            if let Err(e) = conflicts::check_item(db, &knowledge_item, &item.embedding) {
+                log::warn!("Conflict check failed for {}: {}", item.id, e);
+            }
+        }
+    }
+
     log::info!(
         "Applied sync delta: {} upserted, {} skipped (LWW)",
         upserted,
@@ -250,6 +302,66 @@ pub fn apply_delta(
     Ok((upserted, skipped))
 }
 
+/// Map synced vocabulary onto the local ontology so the v11 triggers accept it.
+///
+/// Another device may carry custom terms this one lacks, or role tags written
+/// before the vocabulary existed: unknown knowledge types fall back to
+/// `FALLBACK_KNOWLEDGE_TYPE`, unknown role tags are dropped. Values equal to
+/// the local row pass unchanged, as in `Ontology::canonicalize_update`.
+fn localize_vocabulary(ontology: &Ontology, item: &SyncItem, local: Option<&KnowledgeItem>) -> SyncItem {
+    let mut item = item.clone();
+    let closest = |dimension: Dimension, raw: &str| {
+        ontology.validate(dimension, raw).ok().or_else(|| ontology.resolve(dimension, raw))
+    };
+
+    if local.map(|l| &l.knowledge_type) != Some(&item.knowledge_type) {
+        let resolved = closest(Dimension::KnowledgeType, &item.knowledge_type)
+            .unwrap_or_else(|| FALLBACK_KNOWLEDGE_TYPE.to_string());
+        if resolved != item.knowledge_type {
+            log::warn!("Sync item {}: knowledge_type '{}' stored as '{}'", item.id, item.knowledge_type, resolved);
+        }
+        item.knowledge_type = resolved;
+    }
+    if local.map(|l| &l.role_tag) != Some(&item.role_tag) {
+        let resolved = item.role_tag.as_deref().and_then(|tag| closest(Dimension::RoleTag, tag));
+        if resolved != item.role_tag {
+            log::warn!("Sync item {}: role_tag {:?} stored as {:?}", item.id, item.role_tag, resolved);
+        }
+        item.role_tag = resolved;
+    }
+    item
+}
+
+/// Convert a sync item back to a knowledge item (without its embedding).
+fn to_knowledge_item(item: &SyncItem) -> KnowledgeItem {
+    KnowledgeItem {
+        id: item.id.clone(),
+        content: item.content.clone(),
+        summary: item.summary.clone(),
+        knowledge_type: item.knowledge_type.clone(),
+        source_type: item.source_type.clone(),
+        scope: item.scope.clone(),
+        scope_layer: item.scope_layer.clone(),
+        role_tag: item.role_tag.clone(),
+        dialectic_tag: item.dialectic_tag.clone(),
+        confidence: item.confidence,
+        relevance_score: item.relevance_score,
+        usage_count: item.usage_count,
+        decision_maker: item.decision_maker.clone(),
+        outcome: item.outcome.clone(),
+        financial_impact_krw: item.financial_impact_krw,
+        source_id: item.source_id.clone(),
+        source_context: item.source_context.clone(),
+        user_id: item.user_id.clone(),
+        project_id: item.project_id.clone(),
+        did_author: item.did_author.clone(),
+        is_active: item.is_active,
+        expires_at: item.expires_at.clone(),
+        created_at: item.created_at.clone(),
+        updated_at: item.updated_at.clone(),
+    }
+}
+
 /// Get the count of items changed since a timestamp.
 pub fn count_changes(db: &RagDb, since: Option<&str>) -> Result<i64, String> {
     let conn = db.conn();
@@ -6,6 +6,10 @@
 /// 3. Encrypt with AES-256-GCM (key derived from DID private key)
 /// 4. Return base64 blob (for Supabase Storage or file transfer)
 ///
+/// Items an outbound policy rule keeps on this device (e.g. "sync disabled
+/// for personal") are withheld from the export; the export is logged in
+/// `outbound_log` with the hash of the blob.
+///
 /// Import is the reverse:
 /// 1. Receive base64 blob
 /// 2. Decrypt with DID-derived key
@@ -14,6 +18,7 @@
 
 use crate::did::identity::DidIdentity;
 use crate::rag::db::RagDb;
+use crate::rag::policy::{self, Channel, DataLabels, Outbound, PolicyViolation};
 use crate::sync::delta::{self, SyncDelta, SyncMeta};
 use crate::sync::encryption;
 use serde::{Deserialize, Serialize};
@@ -37,6 +42,8 @@ pub struct ExportResult {
     pub raw_size_bytes: usize,
     /// Encrypted blob size in bytes
     pub blob_size_bytes: usize,
+    /// Changed items withheld by outbound policy rules
+    pub withheld_count: usize,
 }
 
 /// Result of an import operation
@@ -85,8 +92,9 @@ pub fn export_encrypted(
     let signing_key = identity.get_signing_key()?;
     let sync_key = encryption::derive_sync_key(&signing_key.to_bytes())?;
 
-    // 2. Extract delta (or full export)
-    let delta = delta::get_delta(db, since)?;
+    // 2. Extract delta (or full export), minus items the outbound policy keeps local
+    let mut delta = delta::get_delta(db, since)?;
+    let withheld_count = withhold_by_policy(db, &mut delta)?;
     let item_count = delta.items.len();
     let total_count = delta.total_count;
     let is_delta = since.is_some();
@@ -101,6 +109,7 @@ pub fn export_encrypted(
             exported_at: chrono::Utc::now().to_rfc3339(),
             raw_size_bytes: 0,
             blob_size_bytes: 0,
+            withheld_count,
         });
     }
 
@@ -112,6 +121,11 @@ pub fn export_encrypted(
     // 4. Encrypt
     let blob = encryption::encrypt_json(&sync_key, &json)?;
     let blob_size = blob.len();
+    let mut labels = DataLabels::default();
+    for item in &delta.items {
+        labels.add(&item.scope, &item.knowledge_type);
+    }
+    policy::record(db, &sync_call(None, &labels), &blob, None)?;
 
     log::info!(
         "Exported {} items ({} bytes → {} bytes encrypted, delta={})",
@@ -130,9 +144,58 @@ pub fn export_encrypted(
         exported_at: chrono::Utc::now().to_rfc3339(),
         raw_size_bytes: raw_size,
         blob_size_bytes: blob_size,
+        withheld_count,
     })
 }
 
+fn sync_call<'a>(project_id: Option<&'a str>, labels: &'a DataLabels) -> Outbound<'a> {
+    Outbound {
+        channel: Channel::Sync,
+        destination: "sync",
+        provider: None,
+        local: false,
+        purpose: "sync_export",
+        project_id,
+        room_id: None,
+        labels,
+    }
+}
+
+/// Drop the items a policy rule blocks from syncing; returns how many.
+/// Errors when there were changes but every one of them is withheld.
+fn withhold_by_policy(db: &RagDb, delta: &mut SyncDelta) -> Result<usize, String> {
+    let rules = policy::list_rules(db)?;
+    if rules.is_empty() {
+        return Ok(0);
+    }
+    let mut withheld: Vec<(String, PolicyViolation)> = Vec::new();
+    let mut withheld_labels = DataLabels::default();
+    delta.items.retain(|item| {
+        let mut labels = DataLabels::default();
+        labels.add(&item.scope, &item.knowledge_type);
+        match policy::evaluate(&rules, &sync_call(item.project_id.as_deref(), &labels)) {
+            Some(violation) => {
+                withheld.push((item.id.clone(), violation));
+                withheld_labels.add(&item.scope, &item.knowledge_type);
+                false
+            }
+            None => true,
+        }
+    });
+    let Some((_, first)) = withheld.first() else {
+        return Ok(0);
+    };
+
+    let ids: Vec<&str> = withheld.iter().map(|(id, _)| id.as_str()).collect();
+    policy::record(db, &sync_call(None, &withheld_labels), &ids.join("\n"), Some(first))?;
+    log::info!("Sync export: {} item(s) withheld by outbound policy", withheld.len());
+
+    if delta.items.is_empty() {
+        return Err(first.clone().into());
+    }
+    Ok(withheld.len())
+}
+
 /// Import an encrypted blob and apply to local database.
 ///
 /// Decrypts the blob, parses the delta, and applies Last-Write-Wins merge.
@@ -434,6 +497,29 @@ mod tests {
         assert!(export.is_delta);
     }
 
+    #[test]
+    fn test_delta_export_includes_same_day_edits() {
+        let (db, identity, embedding) = setup_test_env();
+        let edited = ingest_test_item(&db, &embedding, "초기 예산 논의");
+        let removed = ingest_test_item(&db, &embedding, "임시 메모");
+
+        let sync_time = chrono::Utc::now().to_rfc3339();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+
+        let update = knowledge::KnowledgeUpdate {
+            summary: Some("예산 논의".to_string()),
+            ..Default::default()
+        };
+        knowledge::update_knowledge_item(&db, &embedding, &edited, &update, None).unwrap();
+        knowledge::deactivate_knowledge_item(&db, &removed, None).unwrap();
+
+        // Edits are stamped in the same RFC3339 format the watermark uses
+        let export = export_encrypted(&db, &identity, Some(&sync_time)).unwrap();
+        assert_eq!(export.item_count, 2);
+        let delta = delta::get_delta(&db, Some(&sync_time)).unwrap();
+        assert!(delta.items.iter().all(|item| item.updated_at > sync_time));
+    }
+
     #[test]
     fn test_sync_status() {
         let (db, identity, _embedding) = setup_test_env();
@@ -522,4 +608,95 @@ mod tests {
             assert_eq!(content, "수정된 내용");
         }
     }
+
+    #[test]
+    fn test_export_withholds_policy_blocked_items() {
+        let (db, identity, embedding) = setup_test_env();
+        ingest_test_item(&db, &embedding, "개인 메모");
+        let team = ingest_test_item(&db, &embedding, "팀 결정");
+        {
+            let conn = db.conn();
+            conn.execute("UPDATE knowledge_items SET scope = 'team' WHERE id = ?1", [&team]).unwrap();
+        }
+        policy::set_rule(
+            &db,
+            &policy::PolicyRule {
+                id: "no-personal-sync".to_string(),
+                channel: Channel::Sync,
+                effect: policy::Effect::Block,
+                scope: Some("personal".to_string()),
+                project_id: None,
+                knowledge_type: None,
+                provider: None,
+                description: String::new(),
+            },
+        )
+        .unwrap();
+
+        let export = export_encrypted(&db, &identity, None).unwrap();
+        assert_eq!((export.item_count, export.withheld_count), (1, 1));
+        let log = policy::outbound_log(&db, Some(Channel::Sync), 10).unwrap();
+        assert_eq!(log.len(), 2);
+        assert!(log[0].allowed && log[0].scopes == vec!["team"]);
+        assert_eq!(log[1].rule_id.as_deref(), Some("no-personal-sync"));
+
+        // Nothing left to send once every item is personal
+        {
+            let conn = db.conn();
+            conn.execute("UPDATE knowledge_items SET scope = 'personal' WHERE id = ?1", [&team]).unwrap();
+        }
+        let error = export_encrypted(&db, &identity, None).unwrap_err();
+        assert_eq!(PolicyViolation::parse(&error).unwrap().rule_id, "no-personal-sync");
+    }
+
+    #[test]
+    fn test_apply_delta_maps_unknown_vocabulary() {
+        use crate::rag::ontology;
+
+        let (source, _identity, embedding) = setup_test_env();
+        ingest_test_item(&source, &embedding, "드론 촬영 허가 필요");
+        ingest_test_item(&source, &embedding, "현장 PD 확정");
+        let mut incoming = delta::get_delta(&source, None).unwrap();
+        // A custom term from another device and a free-form legacy role tag
+        incoming.items[0].knowledge_type = "xqzvw".to_string();
+        incoming.items[0].role_tag = Some("qxzvw".to_string());
+        incoming.items[1].role_tag = Some("producer".to_string());
+
+        let (db, _identity, _embedding) = setup_test_env();
+        assert_eq!(delta::apply_delta(&db, &incoming).unwrap(), (2, 0));
+
+        let first = knowledge::get_knowledge_item(&db, &incoming.items[0].id).unwrap().unwrap();
+        assert_eq!(first.knowledge_type, ontology::FALLBACK_KNOWLEDGE_TYPE);
+        assert_eq!(first.role_tag, None);
+        let second = knowledge::get_knowledge_item(&db, &incoming.items[1].id).unwrap().unwrap();
+        assert_eq!(second.role_tag.as_deref(), Some("PD"));
+    }
+
+    #[test]
+    fn test_import_checks_conflicts_with_local_items() {
+        use crate::rag::conflicts;
+        use crate::rag::embedding::EMBEDDING_DIM;
+
+        let project_item = |content: &str| knowledge::KnowledgeItem {
+            knowledge_type: "budget_decision".to_string(),
+            project_id: Some("p1".to_string()),
+            ..knowledge::KnowledgeItem::for_test(content)
+        };
+        let mut vector = vec![0.0; EMBEDDING_DIM];
+        vector[0] = 1.0;
+
+        let (teammate_db, identity, _embedding) = setup_test_env();
+        knowledge::create_knowledge_item(&teammate_db, &project_item("촬영 예산 5000만원으로 증액"), &vector).unwrap();
+        let export = export_encrypted(&teammate_db, &identity, None).unwrap();
+
+        let (db, _identity, _embedding) = setup_test_env();
+        knowledge::create_knowledge_item(&db, &project_item("촬영 예산 3000만원으로 고정"), &vector).unwrap();
+        assert!(conflicts::list_conflicts(&db, Some("p1"), None).unwrap().is_empty());
+
+        let import = import_encrypted(&db, &identity, &export.blob).unwrap();
+        assert_eq!(import.upserted, 1);
+        let found = conflicts::list_conflicts(&db, Some("p1"), Some("open")).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].conflict_kind, "amount");
+    }
 }
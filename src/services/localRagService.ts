@@ -20,6 +20,8 @@ import { invokeTauri, isTauriApp } from '@/lib/platform';
 
 export interface SearchResult {
   id: string;
+  /** Set on results added by relation expansion */
+  related_via?: string;
   content: string;
   summary?: string;
   knowledge_type: string;
@@ -52,6 +54,8 @@ export interface IngestResult {
 }
 
 export interface ChatMessage {
+  /** Stable message id; used to deduplicate the digest queue */
+  id?: string;
   user_id: string;
   user_name: string;
   content: string;
@@ -63,6 +67,25 @@ export interface DigestResult {
   actionItems: DigestItem[];
   risks: RiskItem[];
   summary: string;
+  /** Message windows analyzed; long conversations are digested per window and merged */
+  windows?: MessageRange[];
+  /** Reply items dropped during validation (absent when none) */
+  discarded?: DiscardedItem[];
+  /** Set when the offline rule-based digester ran instead of the LLM (why); items are low-confidence */
+  fallback?: string;  /** Digest template version, e.g. `digest.ko.v1` (absent for rule-based digests) */
+  prompt_version?: string;
+}
+
+export interface MessageRange {
+  start: string;
+  end: string;
+  message_count: number;
+}
+
+/** An LLM output item rejected during validation, e.g. `decisions[2]` */
+export interface DiscardedItem {
+  path: string;
+  reason: string;
 }
 
 export interface DigestItem {
@@ -85,8 +108,12 @@ export interface DigestResponse {
 
 export interface ExtractionResult {
   created_ids: string[];
+  /** Existing items that absorbed a near-duplicate instead of an insert */
+  merged_ids: string[];
   skipped_count: number;
   is_pseudo_embedding: boolean;
+  /** Extracted items dropped as invalid (absent when none) */
+  discarded?: DiscardedItem[];
 }
 
 export interface StoredDigest {
@@ -95,11 +122,54 @@ export interface StoredDigest {
   project_id?: string;
   digest_type: string;
   content: string;
+  /** created_at of the earliest / latest message covered */
+  message_range_start: string | null;
+  message_range_end: string | null;
   message_count: number;
   confidence: number;
+  prompt_version: string | null;
   created_at: string;
 }
 
+export type DigestDueReason = 'message_count' | 'idle';
+
+/** Digest queue and watermark of one room */
+export interface RoomQueueStatus {
+  room_id: string;
+  project_id: string | null;
+  pending_count: number;
+  oldest_pending_at: string | null;
+  newest_pending_at: string | null;
+  /** Seconds since the last message was queued */
+  idle_secs: number | null;
+  /** Watermark: created_at / id of the last digested message */
+  last_message_at: string | null;
+  last_message_id: string | null;
+  digested_count: number;
+  last_digest_at: string | null;
+  /** Last failed scheduled digest (cleared by the next successful one) */
+  last_failed_at: string | null;
+  due: DigestDueReason | null;
+  /** Due, but digested or failed too recently */
+  rate_limited: boolean;
+}
+
+export interface EnqueueResult {
+  queued: number;
+  /** At or before the watermark */
+  already_digested: number;
+  /** Already in the queue */
+  duplicates: number;
+  pending_count: number;
+}
+
+export interface RoomDigest {
+  room_id: string;
+  digest: DigestResult;
+  stored_ids: string[];
+  range: MessageRange;
+}
+
 export interface SeedResult {
   seeded: number;
   already_seeded: boolean;
@@ -120,6 +190,8 @@ export async function ragSearch(params: {
   knowledgeType?: string;
   threshold?: number;
   limit?: number;
+  /** Append related items (newer versions, supporting/contradicting items) */
+  expandRelations?: boolean;
 }): Promise<SearchResult[]> {
   if (!isTauriApp()) return [];
 
@@ -132,6 +204,7 @@ export async function ragSearch(params: {
     knowledge_type: params.knowledgeType,
     threshold: params.threshold,
     limit: params.limit,
+    expand_relations: params.expandRelations,
   });
 
   return result ? JSON.parse(result) : [];
@@ -165,9 +238,29 @@ export async function ragDialecticSearch(params: {
   return result ? JSON.parse(result) : [];
 }
 
+export type ContextFormat = 'markdown' | 'xml' | 'json';
+
+export interface ContextCitation {
+  cite_id: string;
+  knowledge_id: string;
+  section: 'thesis' | 'antithesis' | 'personal';
+  knowledge_type: string;
+  confidence: number;
+  truncated: boolean;
+}
+
+export interface CitedContext {
+  text: string;
+  format: ContextFormat;
+  citations: ContextCitation[];
+  tokens_used: number;
+  omitted_count: number;
+}
+
 /**
  * Get RAG context string for LLM injection.
- * Performs 3-pass search (thesis → antithesis → personal) and builds markdown.
+ * Performs 3-pass search (thesis → antithesis → personal) and builds a
+ * token-budgeted context with [K1]-style citations.
  */
 export async function ragGetContext(params: {
   query: string;
@@ -175,7 +268,10 @@ export async function ragGetContext(params: {
   userId?: string;
   projectId?: string;
   roleTag?: string;
+  /** @deprecated use maxTokens */
   maxChars?: number;
+  maxTokens?: number;
+  format?: ContextFormat;
 }): Promise<string> {
   if (!isTauriApp()) return '';
 
@@ -186,11 +282,40 @@ export async function ragGetContext(params: {
     project_id: params.projectId,
     role_tag: params.roleTag,
     max_chars: params.maxChars,
+    max_tokens: params.maxTokens,
+    format: params.format,
   });
 
   return result || '';
 }
 
+/**
+ * Same as ragGetContext, plus the citation map ([K1] → knowledge id) for the UI.
+ */
+export async function ragGetCitedContext(params: {
+  query: string;
+  scope?: string;
+  userId?: string;
+  projectId?: string;
+  roleTag?: string;
+  maxTokens?: number;
+  format?: ContextFormat;
+}): Promise<CitedContext | null> {
+  if (!isTauriApp()) return null;
+
+  const result = await invokeTauri<string>('rag_get_cited_context', {
+    query: params.query,
+    scope: params.scope,
+    user_id: params.userId,
+    project_id: params.projectId,
+    role_tag: params.roleTag,
+    max_tokens: params.maxTokens,
+    format: params.format,
+  });
+
+  return result ? JSON.parse(result) : null;
+}
+
 // ─── Ingest ─────────────────────────────────────────────
 
 /**
@@ -231,52 +356,1214 @@ export async function ragIngest(params: {
   return result ? JSON.parse(result) : null;
 }
 
-// ─── Phase 3: Digest & Extract ──────────────────────────
+export interface DedupeReport {
+  scanned: number;
+  merged: number;
+  groups: { kept_id: string; merged_ids: string[] }[];
+  dry_run: boolean;
+}
 
 /**
- * Analyze chat messages using Claude Haiku.
- * Extracts decisions, action items, risks, and summary.
- *
- * Privacy: Messages are sent to Claude API for analysis only.
- * Anthropic does NOT train on API data. Results stored locally.
- *
- * @param messages - Array of chat messages to analyze
- * @param roomId - Chat room ID for storage
- * @param projectId - Optional project ID
- * @param apiKey - Anthropic API key
+ * Merge near-duplicate knowledge already stored (oldest item survives).
+ * Use dryRun to preview the groups without changing anything.
  */
-export async function ragDigest(params: {
-  messages: ChatMessage[];
-  roomId: string;
+export async function ragDedupe(params: {
   projectId?: string;
-  apiKey: string;
-}): Promise<DigestResponse | null> {
+  threshold?: number;
+  dryRun?: boolean;
+} = {}): Promise<DedupeReport | null> {
   if (!isTauriApp()) return null;
 
-  const result = await invokeTauri<string>('rag_digest', {
-    messages: params.messages,
-    room_id: params.roomId,
+  const result = await invokeTauri<string>('rag_dedupe', {
+    project_id: params.projectId,
+    threshold: params.threshold,
+    dry_run: params.dryRun,
+  });
+
+  return result ? JSON.parse(result) : null;
+}
+
+// ─── Knowledge CRUD & History ───────────────────────────
+
+/** Editable fields; omitted fields stay unchanged, '' clears an optional field. */
+export interface KnowledgeUpdate {
+  content?: string;
+  summary?: string;
+  knowledge_type?: string;
+  scope?: string;
+  scope_layer?: string;
+  role_tag?: string;
+  dialectic_tag?: string;
+  confidence?: number;
+  decision_maker?: string;
+  outcome?: string;
+  financial_impact_krw?: number;
+  project_id?: string;
+  expires_at?: string;
+}
+
+export interface KnowledgeVersion {
+  id: string;
+  knowledge_id: string;
+  version: number;
+  change_type: 'baseline' | 'update' | 'deactivate' | 'restore' | 'revert' | 'merge';
+  changed_fields: string[];
+  snapshot: Record<string, unknown>;
+  changed_by?: string;
+  created_at: string;
+}
+
+export async function ragGetKnowledge(id: string): Promise<Record<string, unknown> | null> {
+  if (!isTauriApp()) return null;
+  const result = await invokeTauri<string>('rag_get_knowledge', { id });
+  return result ? JSON.parse(result) : null;
+}
+
+/**
+ * Edit a knowledge item. Content changes are re-embedded; every change is versioned.
+ */
+export async function ragUpdateKnowledge(
+  id: string,
+  update: KnowledgeUpdate,
+  changedBy?: string,
+): Promise<Record<string, unknown> | null> {
+  if (!isTauriApp()) return null;
+  const result = await invokeTauri<string>('rag_update_knowledge', {
+    id,
+    update,
+    changed_by: changedBy,
+  });
+  return result ? JSON.parse(result) : null;
+}
+
+export async function ragDeactivateKnowledge(id: string, changedBy?: string): Promise<void> {
+  if (!isTauriApp()) return;
+  await invokeTauri<void>('rag_deactivate_knowledge', { id, changed_by: changedBy });
+}
+
+export async function ragRestoreKnowledge(id: string, changedBy?: string): Promise<void> {
+  if (!isTauriApp()) return;
+  await invokeTauri<void>('rag_restore_knowledge', { id, changed_by: changedBy });
+}
+
+/** Permanently delete an item, its embedding and its history. */
+export async function ragDeleteKnowledge(id: string): Promise<void> {
+  if (!isTauriApp()) return;
+  await invokeTauri<void>('rag_delete_knowledge', { id });
+}
+
+export async function ragKnowledgeHistory(id: string): Promise<KnowledgeVersion[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('rag_knowledge_history', { id });
+  return result ? JSON.parse(result) : [];
+}
+
+export async function ragRevertKnowledge(
+  id: string,
+  version: number,
+  changedBy?: string,
+): Promise<Record<string, unknown> | null> {
+  if (!isTauriApp()) return null;
+  const result = await invokeTauri<string>('rag_revert_knowledge', {
+    id,
+    version,
+    changed_by: changedBy,
+  });
+  return result ? JSON.parse(result) : null;
+}
+
+// ─── Knowledge Relations ────────────────────────────────
+
+export type RelationType = 'supersedes' | 'contradicts' | 'supports' | 'derived_from' | 'duplicate_of';
+
+export interface KnowledgeRelation {
+  id: string;
+  source_id: string;
+  target_id: string;
+  relation_type: RelationType;
+  note?: string;
+  created_by?: string;
+  created_at: string;
+}
+
+export interface RelatedItem {
+  knowledge_id: string;
+  relation_type: RelationType;
+  direction: 'outgoing' | 'incoming';
+  via_id: string;
+  depth: number;
+}
+
+/** Link source → target, e.g. a new decision `supersedes` an older one. */
+export async function ragLinkKnowledge(params: {
+  sourceId: string;
+  targetId: string;
+  relationType: RelationType;
+  note?: string;
+  createdBy?: string;
+}): Promise<KnowledgeRelation | null> {
+  if (!isTauriApp()) return null;
+  const result = await invokeTauri<string>('rag_link_knowledge', {
+    source_id: params.sourceId,
+    target_id: params.targetId,
+    relation_type: params.relationType,
+    note: params.note,
+    created_by: params.createdBy,
+  });
+  return result ? JSON.parse(result) : null;
+}
+
+export async function ragUnlinkKnowledge(relationId: string): Promise<boolean> {
+  if (!isTauriApp()) return false;
+  return (await invokeTauri<boolean>('rag_unlink_knowledge', { relation_id: relationId })) ?? false;
+}
+
+export async function ragKnowledgeRelations(id: string): Promise<KnowledgeRelation[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('rag_knowledge_relations', { id });
+  return result ? JSON.parse(result) : [];
+}
+
+export async function ragTraverseRelations(
+  id: string,
+  relationTypes?: RelationType[],
+  maxDepth?: number,
+): Promise<RelatedItem[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('rag_traverse_relations', {
+    id,
+    relation_types: relationTypes,
+    max_depth: maxDepth,
+  });
+  return result ? JSON.parse(result) : [];
+}
+
+// ─── Project Snapshots ──────────────────────────────────
+
+export interface SnapshotEntry {
+  source: 'knowledge' | 'digest';
+  source_id: string;
+  text: string;
+  confidence: number;
+  outcome?: string;
+  decision_maker?: string;
+}
+
+export interface ProjectSnapshot {
+  project_id: string;
+  decisions: SnapshotEntry[];
+  open_risks: SnapshotEntry[];
+  action_items: SnapshotEntry[];
+  recent_digests: SnapshotEntry[];
+  key_knowledge: SnapshotEntry[];
+  generated_at: string;
+  expires_at: string;
+}
+
+/**
+ * Compiled project state. `ragGetContext` already prepends this when a
+ * projectId is given; use `refresh` to rebuild before the TTL runs out.
+ */
+export async function ragProjectSnapshot(
+  projectId: string,
+  refresh?: boolean,
+): Promise<ProjectSnapshot | null> {
+  if (!isTauriApp()) return null;
+  const result = await invokeTauri<string>('rag_project_snapshot', {
+    project_id: projectId,
+    refresh,
+  });
+  return result ? JSON.parse(result) : null;
+}
+
+// ─── Contradiction Detection ────────────────────────────
+
+export type ConflictStatus = 'open' | 'resolved' | 'dismissed';
+
+/** Also emitted as the `rag:conflict-detected` Tauri event when found on ingest. */
+export interface KnowledgeConflict {
+  id: string;
+  item_a: string;
+  item_b: string;
+  content_a: string;
+  content_b: string;
+  project_id: string | null;
+  similarity: number;
+  conflict_kind: 'amount' | 'date' | 'percentage' | 'outcome' | 'polarity' | 'semantic';
+  reason: string;
+  detected_by: 'heuristic' | 'llm';
+  status: ConflictStatus;
+  resolution_note: string | null;
+  detected_at: string;
+  resolved_at: string | null;
+}
+
+/**
+ * Scan one project (or all) for contradicting knowledge. Passing an API key
+ * lets Claude confirm heuristic hits and review very similar pairs.
+ */
+export async function ragDetectConflicts(params: {
+  projectId?: string;
+  threshold?: number;
+  apiKey?: string;
+  /** Ask the conflict-task LLM to confirm pairs (defaults to true when apiKey is set) */
+  llmReview?: boolean;
+  /** Pass to `ragCancelLlm` to abort the review */
+  requestId?: string;
+} = {}): Promise<KnowledgeConflict[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('rag_detect_conflicts', {
     project_id: params.projectId,
+    threshold: params.threshold,
     api_key: params.apiKey,
+    llm_review: params.llmReview,
+    request_id: params.requestId,
+  });
+  return result ? JSON.parse(result) : [];
+}
+
+export async function ragListConflicts(
+  projectId?: string,
+  status?: ConflictStatus,
+): Promise<KnowledgeConflict[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('rag_list_conflicts', {
+    project_id: projectId,
+    status,
+  });
+  return result ? JSON.parse(result) : [];
+}
+
+export async function ragResolveConflict(
+  id: string,
+  status: ConflictStatus,
+  note?: string,
+): Promise<KnowledgeConflict | null> {
+  if (!isTauriApp()) return null;
+  const result = await invokeTauri<string>('rag_resolve_conflict', { id, status, note });
+  return result ? JSON.parse(result) : null;
+}
+
+// ─── Decision Outcome Lifecycle ─────────────────────────
+
+export type DecisionOutcome = 'pending' | 'escalated' | 'confirmed' | 'rejected';
+
+export interface DecisionOutcomeChange {
+  id: string;
+  knowledge_id: string;
+  from_outcome: DecisionOutcome | null;
+  to_outcome: DecisionOutcome;
+  reason: string | null;
+  changed_by: string | null;
+  changed_at: string;
+}
+
+/** Also emitted as the `rag:decision-reminder` Tauri event for stale decisions. */
+export interface TrackedDecision {
+  knowledge_id: string;
+  content: string;
+  knowledge_type: string;
+  outcome: DecisionOutcome;
+  decision_maker: string | null;
+  project_id: string | null;
+  financial_impact_krw: number | null;
+  outcome_since: string;
+  days_in_outcome: number;
+}
+
+/** Move a decision to another outcome. Reversing confirmed/rejected requires a reason. */
+export async function ragSetDecisionOutcome(
+  id: string,
+  outcome: DecisionOutcome,
+  reason?: string,
+  changedBy?: string,
+): Promise<DecisionOutcomeChange | null> {
+  if (!isTauriApp()) return null;
+  const result = await invokeTauri<string>('rag_set_decision_outcome', {
+    id,
+    outcome,
+    reason,
+    changed_by: changedBy,
+  });
+  return result ? JSON.parse(result) : null;
+}
+
+export async function ragDecisionOutcomeHistory(id: string): Promise<DecisionOutcomeChange[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('rag_decision_outcome_history', { id });
+  return result ? JSON.parse(result) : [];
+}
+
+/** Decisions by outcome (default pending + escalated), longest-waiting first. */
+export async function ragDecisionsByOutcome(params: {
+  outcomes?: DecisionOutcome[];
+  projectId?: string;
+  decisionMaker?: string;
+  minDays?: number;
+  limit?: number;
+} = {}): Promise<TrackedDecision[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('rag_decisions_by_outcome', {
+    filter: {
+      outcomes: params.outcomes ?? ['pending', 'escalated'],
+      project_id: params.projectId,
+      decision_maker: params.decisionMaker,
+      min_days: params.minDays,
+      limit: params.limit ?? 50,
+    },
+  });
+  return result ? JSON.parse(result) : [];
+}
+
+/** Fire `rag:decision-reminder` for decisions waiting longer than `days` (default 7). */
+export async function ragRunDecisionReminders(days?: number): Promise<TrackedDecision[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('rag_run_decision_reminders', { days });
+  return result ? JSON.parse(result) : [];
+}
+
+// ─── LLM Providers ──────────────────────────────────────
+
+export type LlmTask = 'digest' | 'extraction' | 'conflict' | 'synthesis' | 'persona';
+export type LlmProviderKind = 'anthropic' | 'openai_compatible' | 'mock';
+
+export interface LlmSettings {
+  task: LlmTask;
+  provider: LlmProviderKind;
+  model: string;
+  base_url: string | null;
+  api_key_env: string | null;
+  is_custom: boolean;
+}
+
+/** Effective provider/model per task (built-in default when not customised). */
+export async function ragLlmSettings(): Promise<LlmSettings[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('rag_llm_settings');
+  return result ? JSON.parse(result) : [];
+}
+
+/**
+ * Route a task to a provider. For 'openai_compatible' (Ollama, vLLM, LM Studio…)
+ * pass `baseUrl` such as `http://localhost:11434/v1`; `apiKeyEnv` names the
+ * environment variable holding its key, if the server needs one.
+ */
+export async function ragSetLlmSettings(params: {
+  task: LlmTask;
+  provider: LlmProviderKind;
+  model: string;
+  baseUrl?: string;
+  apiKeyEnv?: string;
+}): Promise<LlmSettings | null> {
+  if (!isTauriApp()) return null;
+  const result = await invokeTauri<string>('rag_set_llm_settings', {
+    task: params.task,
+    provider: params.provider,
+    model: params.model,
+    base_url: params.baseUrl,
+    api_key_env: params.apiKeyEnv,
+  });
+  return result ? JSON.parse(result) : null;
+}
+
+export interface LlmCircuit {
+  host: string;
+  consecutive_failures: number;
+  open: boolean;
+  retry_in_secs: number;
+}
+
+/**
+ * Abort an LLM call started with the same `requestId` (digest, extraction,
+ * synthesis, persona, conflict review). Returns false if it already finished.
+ */
+export async function ragCancelLlm(requestId: string): Promise<boolean> {
+  if (!isTauriApp()) return false;
+  return (await invokeTauri<boolean>('rag_cancel_llm', { request_id: requestId })) ?? false;
+}
+
+/** Hosts whose circuit breaker tripped after repeated failures (open = calls paused). */
+export async function ragLlmCircuits(): Promise<LlmCircuit[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('rag_llm_circuits');
+  return result ? JSON.parse(result) : [];
+}
+
+/** Drop the custom setting for a task, returning it to the built-in default. */
+export async function ragResetLlmSettings(task: LlmTask): Promise<boolean> {
+  if (!isTauriApp()) return false;
+  return (await invokeTauri<boolean>('rag_reset_llm_settings', { task })) ?? false;
+}
+
+// ─── LLM Usage & Budgets ────────────────────────────────
+
+export type LlmUsageGroup = 'day' | 'project' | 'feature';
+export type LlmBudgetMode = 'warn' | 'block';
+
+export interface LlmUsageBucket {
+  /** Day (YYYY-MM-DD), project id or task; null = calls without a project */
+  key: string | null;
+  calls: number;
+  failed: number;
+  /** Calls refused by a `block` budget */
+  blocked: number;
+  input_tokens: number;
+  output_tokens: number;
+  cost_usd: number;
+  avg_latency_ms: number;
+}
+
+export interface LlmBudget {
+  project_id: string;
+  /** YYYY-MM (UTC) */
+  month: string;
+  monthly_limit_usd: number;
+  mode: LlmBudgetMode;
+  spent_usd: number;
+}
+
+/** Payload of the `rag:llm-budget` Tauri event */
+export interface LlmBudgetAlert {
+  budget: LlmBudget;
+  level: 'warning' | 'exceeded';
+  projected_usd: number;
+  task: LlmTask;
+  blocked: boolean;
+}
+
+export interface LlmPrice {
+  model: string;
+  input_per_mtok: number;
+  output_per_mtok: number;
+  is_custom: boolean;
+}
+
+/** LLM calls aggregated by day, project or feature (task). */
+export async function ragLlmUsage(params: {
+  groupBy: LlmUsageGroup;
+  since?: string;
+  projectId?: string;
+}): Promise<LlmUsageBucket[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('rag_llm_usage', {
+    group_by: params.groupBy,
+    since: params.since,
+    project_id: params.projectId,
   });
+  return result ? JSON.parse(result) : [];
+}
+
+/** Monthly budgets with this month's spend. */
+export async function ragLlmBudgets(projectId?: string): Promise<LlmBudget[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('rag_llm_budgets', { project_id: projectId });
+  return result ? JSON.parse(result) : [];
+}
 
+/** `block` refuses calls that would exceed the limit; `warn` only emits `rag:llm-budget`. */
+export async function ragSetLlmBudget(
+  projectId: string,
+  monthlyLimitUsd: number,
+  mode: LlmBudgetMode,
+): Promise<LlmBudget | null> {
+  if (!isTauriApp()) return null;
+  const result = await invokeTauri<string>('rag_set_llm_budget', {
+    project_id: projectId,
+    monthly_limit_usd: monthlyLimitUsd,
+    mode,
+  });
   return result ? JSON.parse(result) : null;
 }
 
+export async function ragRemoveLlmBudget(projectId: string): Promise<boolean> {
+  if (!isTauriApp()) return false;
+  return (await invokeTauri<boolean>('rag_remove_llm_budget', { project_id: projectId })) ?? false;
+}
+
+/** Model prices in USD per million tokens. */
+export async function ragLlmPrices(): Promise<LlmPrice[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('rag_llm_prices');
+  return result ? JSON.parse(result) : [];
+}
+
+/** Price a model (e.g. an OpenAI-compatible one); pass null to remove the custom price. */
+export async function ragSetLlmPrice(
+  model: string,
+  price: { inputPerMtok: number; outputPerMtok: number } | null,
+): Promise<void> {
+  if (!isTauriApp()) return;
+  await invokeTauri<void>('rag_set_llm_price', {
+    model,
+    input_per_mtok: price?.inputPerMtok,
+    output_per_mtok: price?.outputPerMtok,
+  });
+}
+
+// ─── PII Redaction ──────────────────────────────────────
+
+export type PiiKind = 'phone' | 'rrn' | 'email' | 'bank_account' | 'client_name' | 'person' | 'user_id';
+
+export interface RedactionSettings {
+  project_id: string | null;
+  enabled: boolean;
+  kinds: PiiKind[];
+  client_names: string[];
+  /** false = defaults (everything redacted, no client names) */
+  is_custom: boolean;
+}
+
+export interface RedactionPreview {
+  /** Text with placeholders such as [PHONE_1] */
+  text: string;
+  counts: Partial<Record<PiiKind, number>>;
+}
+
+export interface RedactionAuditEntry {
+  id: number;
+  created_at: string;
+  project_id: string | null;
+  room_id: string | null;
+  task: string;
+  purpose: string;
+  /** Values redacted per kind (the values themselves are never stored) */
+  counts: Partial<Record<PiiKind, number>>;
+  total: number;
+}
+
+export async function ragRedactionSettings(projectId?: string): Promise<RedactionSettings | null> {
+  if (!isTauriApp()) return null;
+  const result = await invokeTauri<string>('rag_redaction_settings', { project_id: projectId });
+  return result ? JSON.parse(result) : null;
+}
+
+export async function ragSetRedactionSettings(
+  projectId: string,
+  settings: { enabled: boolean; kinds: PiiKind[]; clientNames: string[] },
+): Promise<RedactionSettings | null> {
+  if (!isTauriApp()) return null;
+  const result = await invokeTauri<string>('rag_set_redaction_settings', {
+    project_id: projectId,
+    enabled: settings.enabled,
+    kinds: settings.kinds,
+    client_names: settings.clientNames,
+  });
+  return result ? JSON.parse(result) : null;
+}
+
+export async function ragResetRedactionSettings(projectId: string): Promise<boolean> {
+  if (!isTauriApp()) return false;
+  return (await invokeTauri<boolean>('rag_reset_redaction_settings', { project_id: projectId })) ?? false;
+}
+
+/** Redact text with a project's settings without sending it anywhere. */
+export async function ragRedactionPreview(text: string, projectId?: string): Promise<RedactionPreview | null> {
+  if (!isTauriApp()) return null;
+  const result = await invokeTauri<string>('rag_redaction_preview', { text, project_id: projectId });
+  return result ? JSON.parse(result) : null;
+}
+
+/** Recent LLM calls that had something redacted, newest first. */
+export async function ragRedactionAudit(projectId?: string, limit?: number): Promise<RedactionAuditEntry[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('rag_redaction_audit', { project_id: projectId, limit });
+  return result ? JSON.parse(result) : [];
+}
+
+// ─── Outbound Policy ────────────────────────────────────
+
+export type OutboundChannel = 'llm' | 'sync' | 'any';
+export type OutboundEffect = 'block' | 'local_only';
+
+/** Unset conditions match everything. */
+export interface OutboundRule {
+  /** Empty = new rule; 'local-only' is the local-only mode rule */
+  id: string;
+  channel: OutboundChannel;
+  effect: OutboundEffect;
+  scope?: string | null;
+  project_id?: string | null;
+  knowledge_type?: string | null;
+  /** Provider of LLM calls, as in `ragSetLlmSettings` */
+  provider?: LlmProviderKind | null;
+  description?: string;
+}
+
+export interface PolicyViolation {
+  rule_id: string;
+  effect: OutboundEffect;
+  channel: OutboundChannel;
+  destination: string;
+  reason: string;
+}
+
+export interface OutboundLogEntry {
+  id: number;
+  created_at: string;
+  channel: 'llm' | 'sync';
+  destination: string;
+  local: boolean;
+  purpose: string;
+  project_id: string | null;
+  room_id: string | null;
+  scopes: string[];
+  knowledge_types: string[];
+  /** SHA-256 of the content sent (or refused) */
+  content_hash: string;
+  bytes: number;
+  allowed: boolean;
+  rule_id: string | null;
+}
+
+const POLICY_VIOLATION_PREFIX = 'Blocked by outbound policy: ';
+
+/** The policy violation behind a failed LLM / sync call, if that is why it failed. */
+export function parsePolicyViolation(error: unknown): PolicyViolation | null {
+  const message = error instanceof Error ? error.message : String(error);
+  const at = message.indexOf(POLICY_VIOLATION_PREFIX);
+  if (at < 0) return null;
+  try {
+    return JSON.parse(message.slice(at + POLICY_VIOLATION_PREFIX.length));
+  } catch {
+    return null;
+  }
+}
+
+export async function ragOutboundRules(): Promise<OutboundRule[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('rag_outbound_rules');
+  return result ? JSON.parse(result) : [];
+}
+
+export async function ragSetOutboundRule(rule: OutboundRule): Promise<OutboundRule | null> {
+  if (!isTauriApp()) return null;
+  const result = await invokeTauri<string>('rag_set_outbound_rule', { rule });
+  return result ? JSON.parse(result) : null;
+}
+
+export async function ragRemoveOutboundRule(id: string): Promise<boolean> {
+  if (!isTauriApp()) return false;
+  return (await invokeTauri<boolean>('rag_remove_outbound_rule', { id })) ?? false;
+}
+
+/** Local-only mode: no external LLM provider and no sync export. */
+export async function ragSetLocalOnly(enabled: boolean): Promise<void> {
+  if (!isTauriApp()) return;
+  await invokeTauri<void>('rag_set_local_only', { enabled });
+}
+
+export async function ragLocalOnly(): Promise<boolean> {
+  if (!isTauriApp()) return false;
+  return (await invokeTauri<boolean>('rag_local_only')) ?? false;
+}
+
+export async function ragOutboundLog(channel?: 'llm' | 'sync', limit?: number): Promise<OutboundLogEntry[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('rag_outbound_log', { channel, limit });
+  return result ? JSON.parse(result) : [];
+}
+
+// ─── Ontology Vocabulary ────────────────────────────────
+
+export type OntologyDimension = 'knowledge_type' | 'role_tag' | 'scope_layer' | 'dialectic_tag';
+
+/** Knowledge-type behaviour: `decision` → project snapshot decisions, `pattern_source` → pattern mining. */
+export type OntologyTrait = 'decision' | 'pattern_source';
+
+export interface OntologyTerm {
+  dimension: OntologyDimension;
+  value: string;
+  label: string;
+  category: string | null;
+  aliases: string[];
+  traits: OntologyTrait[];
+  is_builtin: boolean;
+}
+
+/** Valid values for knowledge_type / role_tag / scope_layer / dialectic_tag. */
+export async function ragOntology(): Promise<OntologyTerm[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('rag_ontology');
+  return result ? JSON.parse(result).terms : [];
+}
+
+/** Add an organisation-specific term (knowledge_type and role_tag only). */
+export async function ragAddOntologyTerm(params: {
+  dimension: 'knowledge_type' | 'role_tag';
+  value: string;
+  label?: string;
+  category?: string;
+  aliases?: string[];
+  traits?: OntologyTrait[];
+}): Promise<OntologyTerm | null> {
+  if (!isTauriApp()) return null;
+  const result = await invokeTauri<string>('rag_add_ontology_term', { ...params });
+  return result ? JSON.parse(result) : null;
+}
+
+export async function ragRemoveOntologyTerm(
+  dimension: OntologyDimension,
+  value: string,
+): Promise<void> {
+  if (!isTauriApp()) return;
+  await invokeTauri<void>('rag_remove_ontology_term', { dimension, value });
+}
+
+// ─── Prompt Registry ────────────────────────────────────
+
+export type PromptLanguage = 'ko' | 'en' | 'th';
+export type PromptId = 'digest' | 'digest_request' | 'digest_reduce' | 'digest_reduce_window' | 'extraction';
+
+export interface PromptTemplate {
+  prompt: PromptId;
+  language: PromptLanguage;
+  /** `<prompt>.<lang>.v<n>` built-in, `<prompt>.<lang>.custom-<hash>` override */
+  version: string;
+  source: 'builtin' | 'override';
+  text: string;
+}
+
+export interface PromptCatalog {
+  /** Directory scanned for `<prompt>.<lang>.txt` overrides */
+  override_dir: string | null;
+  templates: PromptTemplate[];
+  rejected: { file: string; reason: string }[];
+}
+
+/** Templates in effect for every prompt and language. */
+export async function ragPromptTemplates(): Promise<PromptCatalog | null> {
+  if (!isTauriApp()) return null;
+  const result = await invokeTauri<string>('rag_prompt_templates');
+  return result ? JSON.parse(result) : null;
+}
+
+/** Re-read override files after editing them. */
+export async function ragReloadPrompts(): Promise<PromptCatalog | null> {
+  if (!isTauriApp()) return null;
+  const result = await invokeTauri<string>('rag_reload_prompts');
+  return result ? JSON.parse(result) : null;
+}
+
+/** Set the prompt language of a room or project (null clears it). */
+export async function ragSetPromptLanguage(
+  scopeType: 'room' | 'project',
+  scopeId: string,
+  language: PromptLanguage | null,
+): Promise<void> {
+  if (!isTauriApp()) return;
+  await invokeTauri<void>('rag_set_prompt_language', {
+    scope_type: scopeType,
+    scope_id: scopeId,
+    language,
+  });
+}
+
+/** Language used for a room: room setting, else project setting, else `ko`. */
+export async function ragPromptLanguage(params: {
+  roomId?: string;
+  projectId?: string;
+}): Promise<PromptLanguage> {
+  if (!isTauriApp()) return 'ko';
+  const result = await invokeTauri<string>('rag_prompt_language', {
+    room_id: params.roomId,
+    project_id: params.projectId,
+  });
+  return (result as PromptLanguage) || 'ko';
+}
+
+// ─── Knowledge Aging ────────────────────────────────────
+
+export interface DecayPolicy {
+  knowledge_type: string;
+  /** null = never decays */
+  half_life_days: number | null;
+  /** null = never expires */
+  ttl_days: number | null;
+  source: 'builtin' | 'custom';
+}
+
+export interface AgingRun {
+  id: string;
+  decayed_count: number;
+  expired_count: number;
+  ran_at: string;
+}
+
+export interface AgingChange {
+  run_id: string;
+  knowledge_id: string;
+  content: string;
+  knowledge_type: string;
+  action: 'decayed' | 'expired';
+  confidence_before: number;
+  confidence_after: number;
+  relevance_before: number;
+  relevance_after: number;
+  expires_at: string | null;
+}
+
+/** Run the aging job now (it also runs in the background every few hours). */
+export async function ragRunAging(): Promise<AgingRun | null> {
+  if (!isTauriApp()) return null;
+  const result = await invokeTauri<string>('rag_run_aging');
+  return result ? JSON.parse(result) : null;
+}
+
+export async function ragAgingRuns(limit = 20): Promise<AgingRun[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('rag_aging_runs', { limit });
+  return result ? JSON.parse(result) : [];
+}
+
+export async function ragAgingChanges(params: {
+  runId?: string;
+  knowledgeId?: string;
+  limit?: number;
+} = {}): Promise<AgingChange[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('rag_aging_changes', {
+    run_id: params.runId,
+    knowledge_id: params.knowledgeId,
+    limit: params.limit,
+  });
+  return result ? JSON.parse(result) : [];
+}
+
+export async function ragDecayPolicies(): Promise<DecayPolicy[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('rag_decay_policies');
+  return result ? JSON.parse(result) : [];
+}
+
+export async function ragSetDecayPolicy(
+  knowledgeType: string,
+  halfLifeDays: number | null,
+  ttlDays: number | null,
+): Promise<void> {
+  if (!isTauriApp()) return;
+  await invokeTauri<void>('rag_set_decay_policy', {
+    knowledge_type: knowledgeType,
+    half_life_days: halfLifeDays,
+    ttl_days: ttlDays,
+  });
+}
+
+export async function ragResetDecayPolicy(knowledgeType: string): Promise<boolean> {
+  if (!isTauriApp()) return false;
+  return (await invokeTauri<boolean>('rag_reset_decay_policy', { knowledge_type: knowledgeType })) ?? false;
+}
+
+// ─── Decision Patterns ──────────────────────────────────
+
+export interface DecisionPattern {
+  id: string;
+  user_id: string | null;
+  decision_maker: string;
+  knowledge_domain: string;
+  pattern_type: string | null;
+  pattern_summary: string | null;
+  evidence_item_ids: string[];
+  confidence: number;
+  sample_count: number;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface DecisionPatternMatch {
+  pattern: DecisionPattern;
+  similarity: number;
+  evidence: {
+    knowledge_id: string;
+    content: string;
+    outcome: string | null;
+    confidence: number;
+  }[];
+}
+
+export interface PatternMiningReport {
+  groups: number;
+  created: number;
+  updated: number;
+  removed: number;
+}
+
+/** Re-mine decision patterns (patterns also update automatically on knowledge changes). */
+export async function ragMineDecisionPatterns(
+  decisionMaker?: string,
+): Promise<PatternMiningReport | null> {
+  if (!isTauriApp()) return null;
+  const result = await invokeTauri<string>('rag_mine_decision_patterns', {
+    decision_maker: decisionMaker,
+  });
+  return result ? JSON.parse(result) : null;
+}
+
+export async function ragDecisionPatterns(
+  decisionMaker?: string,
+  domain?: string,
+): Promise<DecisionPattern[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('rag_decision_patterns', {
+    decision_maker: decisionMaker,
+    domain,
+  });
+  return result ? JSON.parse(result) : [];
+}
+
+/** "How does 김경신 usually decide vendor selection?" — patterns with evidence. */
+export async function ragAskDecisionPatterns(
+  query: string,
+  decisionMaker?: string,
+  limit?: number,
+): Promise<DecisionPatternMatch[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('rag_ask_decision_patterns', {
+    query,
+    decision_maker: decisionMaker,
+    limit,
+  });
+  return result ? JSON.parse(result) : [];
+}
+
+// ─── 정반합 Synthesis ───────────────────────────────────
+
+export interface SynthesisPoint {
+  text: string;
+  cite_ids: string[];
+  knowledge_ids: string[];
+}
+
+export interface SynthesisReport {
+  proposal: string;
+  thesis: SynthesisPoint[];
+  antithesis: SynthesisPoint[];
+  synthesis: SynthesisPoint[];
+  recommendation: string;
+  citations: ContextCitation[];
+  lesson_id: string | null;
+}
+
+/**
+ * Review a proposal 정반합-style: supporting knowledge, opposing knowledge,
+ * and an LLM synthesis, each point citing knowledge ids. With `store`, the
+ * synthesis is kept as a lesson_learned item linked to its sources.
+ */
+export async function ragSynthesize(params: {
+  proposal: string;
+  apiKey?: string;
+  requestId?: string;
+  userId?: string;
+  projectId?: string;
+  roleTag?: string;
+  store?: boolean;
+}): Promise<SynthesisReport | null> {
+  if (!isTauriApp()) return null;
+  const result = await invokeTauri<string>('rag_synthesize', {
+    proposal: params.proposal,
+    user_id: params.userId,
+    project_id: params.projectId,
+    role_tag: params.roleTag,
+    store: params.store,
+    api_key: params.apiKey,
+    request_id: params.requestId,
+  });
+  return result ? JSON.parse(result) : null;
+}
+
+// ─── Personas ───────────────────────────────────────────
+
+export interface PersonaDefinition {
+  id: string;
+  name: string;
+  role_tag: string;
+  description: string;
+  system_prompt: string;
+  retrieval: {
+    thesis_threshold: number;
+    thesis_limit: number;
+    antithesis_threshold: number;
+    antithesis_limit: number;
+    personal_threshold: number;
+    personal_limit: number;
+    role_boost: number;
+  };
+  context_max_tokens: number;
+}
+
+export interface PersonaAnswer {
+  query_log_id: string;
+  persona_id: string;
+  answer: string;
+  context: CitedContext;
+}
+
+export interface PersonaQueryLog {
+  id: string;
+  persona_id: string;
+  query: string;
+  rag_context: string | null;
+  response: string | null;
+  was_helpful: boolean | null;
+  created_at: string;
+}
+
+export async function listPersonas(): Promise<PersonaDefinition[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('list_personas');
+  return result ? JSON.parse(result) : [];
+}
+
+/**
+ * Ask a persona (e.g. 'ceo', 'cd', 'pd') a question. The answer is grounded
+ * in local knowledge; cite ids in the answer map to `context.citations`.
+ */
+export async function askPersona(params: {
+  personaId: string;
+  query: string;
+  apiKey?: string;
+  requestId?: string;
+  userId?: string;
+  projectId?: string;
+}): Promise<PersonaAnswer | null> {
+  if (!isTauriApp()) return null;
+  const result = await invokeTauri<string>('ask_persona', {
+    persona_id: params.personaId,
+    query: params.query,
+    user_id: params.userId,
+    project_id: params.projectId,
+    api_key: params.apiKey,
+    request_id: params.requestId,
+  });
+  return result ? JSON.parse(result) : null;
+}
+
+export async function personaFeedback(queryLogId: string, wasHelpful: boolean): Promise<void> {
+  if (!isTauriApp()) return;
+  await invokeTauri<void>('persona_feedback', {
+    query_log_id: queryLogId,
+    was_helpful: wasHelpful,
+  });
+}
+
+export async function personaHistory(personaId?: string, limit?: number): Promise<PersonaQueryLog[]> {
+  if (!isTauriApp()) return [];
+  const result = await invokeTauri<string>('persona_history', {
+    persona_id: personaId,
+    limit,
+  });
+  return result ? JSON.parse(result) : [];
+}
+
+// ─── Phase 3: Digest & Extract ──────────────────────────
+
+/**
+ * Analyze chat messages using Claude Haiku.
+ * Extracts decisions, action items, risks, and summary.
+ * When the provider is not configured (no key, no base URL) or cannot be
+ * reached (connection refused, timeouts after every retry, open circuit) a
+ * rule-based digest is returned instead, with `digest.fallback` set. Other
+ * failures (refusals, unparseable replies, cancellation) reject.
+ *
+ * Privacy: Messages are sent to Claude API for analysis only.
+ * Anthropic does NOT train on API data. Results stored locally.
+ *
+ * @param messages - Array of chat messages to analyze
+ * @param roomId - Chat room ID for storage
+ * @param projectId - Optional project ID
+ * @param apiKey - Anthropic API key (optional when the task uses another provider)
+ */
+export async function ragDigest(params: {
+  messages: ChatMessage[];
+  roomId: string;
+  projectId?: string;
+  apiKey?: string;
+  requestId?: string;
+}): Promise<DigestResponse | null> {
+  if (!isTauriApp()) return null;
+
+  const result = await invokeTauri<string>('rag_digest', {
+    messages: params.messages,
+    room_id: params.roomId,
+    project_id: params.projectId,
+    api_key: params.apiKey,
+    request_id: params.requestId,
+  });
+
+  return result ? JSON.parse(result) : null;
+}
+
+/**
+ * Queue a room's messages for incremental digesting.
+ * Messages at or before the room's watermark are skipped.
+ */
+export async function ragEnqueueMessages(params: {
+  roomId: string;
+  projectId?: string;
+  messages: ChatMessage[];
+}): Promise<EnqueueResult | null> {
+  if (!isTauriApp()) return null;
+
+  const result = await invokeTauri<string>('rag_enqueue_messages', {
+    room_id: params.roomId,
+    project_id: params.projectId,
+    messages: params.messages,
+  });
+
+  return result ? JSON.parse(result) : null;
+}
+
+/**
+ * Digest a room's queued messages now and advance its watermark.
+ * Resolves to null when nothing is queued. Falls back to the rule-based digest
+ * like `ragDigest`; the background scheduler instead keeps the messages
+ * queued while the provider is unreachable.
+ */
+export async function ragDigestRoom(params: {
+  roomId: string;
+  apiKey?: string;
+  requestId?: string;
+}): Promise<RoomDigest | null> {
+  if (!isTauriApp()) return null;
+
+  const result = await invokeTauri<string>('rag_digest_room', {
+    room_id: params.roomId,
+    api_key: params.apiKey,
+    request_id: params.requestId,
+  });
+
+  return result ? JSON.parse(result) : null;
+}
+
+/**
+ * Digest queue status for one room, or all rooms.
+ * The background scheduler emits `rag:digest-due` with a RoomQueueStatus
+ * when a room is due but no key-less provider can digest it.
+ */
+export async function ragDigestQueueStatus(roomId?: string): Promise<RoomQueueStatus[]> {
+  if (!isTauriApp()) return [];
+
+  const result = await invokeTauri<string>('rag_digest_queue_status', {
+    room_id: roomId,
+  });
+
+  return result ? JSON.parse(result) : [];
+}
+
 /**
  * Extract reusable knowledge from a digest using Claude Haiku.
  * Deep analysis that finds patterns, not just one-time facts.
  *
  * @param digestJson - JSON string of DigestResult
  * @param sourceId - Digest ID for duplicate prevention
- * @param apiKey - Anthropic API key
+ * @param apiKey - Anthropic API key (optional when the task uses another provider)
  */
 export async function ragExtractFromDigest(params: {
   digestJson: string;
   userId?: string;
   projectId?: string;
   sourceId: string;
-  apiKey: string;
+  apiKey?: string;
+  requestId?: string;
 }): Promise<ExtractionResult | null> {
   if (!isTauriApp()) return null;
 
@@ -286,6 +1573,7 @@ export async function ragExtractFromDigest(params: {
     project_id: params.projectId,
     source_id: params.sourceId,
     api_key: params.apiKey,
+    request_id: params.requestId,
   });
 
   return result ? JSON.parse(result) : null;